@@ -4,18 +4,80 @@ use anyhow::Context;
 use arrow::array::RecordBatch;
 use async_trait::async_trait;
 use duckdb::Connection;
-use smelt_backend::{Backend, BackendCapabilities, BackendError, SqlDialect};
+use smelt_backend::{Backend, BackendCapabilities, BackendError, Materialization, SqlDialect};
+use std::ops::Deref;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 /// DuckDB backend for smelt.
 ///
-/// Wraps a DuckDB connection and implements the Backend trait.
+/// Wraps a pool of DuckDB connections and implements the Backend trait.
 /// DuckDB operations are synchronous, so they're wrapped in spawn_blocking.
-/// Uses Arc<Mutex<Connection>> since Connection is not Sync.
+/// Each operation checks out its own connection, so models run in parallel
+/// don't queue behind one another.
 pub struct DuckDbBackend {
-    connection: Arc<Mutex<Connection>>,
+    pool: Arc<ConnectionPool>,
     schema: String,
+    /// Whether the engine has MERGE INTO, added in DuckDB 1.4
+    supports_merge: bool,
+}
+
+/// Connections to one database.
+///
+/// Connection is not Sync, but DuckDB runs queries from different connections
+/// to the same database concurrently. Connections are cloned from the first
+/// one when none is idle and kept for reuse.
+struct ConnectionPool {
+    connection: Mutex<Connection>,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl ConnectionPool {
+    fn new(connection: Connection) -> Self {
+        Self {
+            connection: Mutex::new(connection),
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Check out a connection, returned to the pool when dropped.
+    fn get(self: &Arc<Self>) -> Result<PooledConnection, BackendError> {
+        let idle = self.idle.lock().unwrap().pop();
+        let connection = match idle {
+            Some(connection) => connection,
+            None => self
+                .connection
+                .lock()
+                .unwrap()
+                .try_clone()
+                .map_err(|e| BackendError::connection_failed(e.to_string()))?,
+        };
+        Ok(PooledConnection {
+            pool: Arc::clone(self),
+            connection: Some(connection),
+        })
+    }
+}
+
+struct PooledConnection {
+    pool: Arc<ConnectionPool>,
+    connection: Option<Connection>,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection.as_ref().expect("connection is present until drop")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.idle.lock().unwrap().push(connection);
+        }
+    }
 }
 
 impl DuckDbBackend {
@@ -28,7 +90,7 @@ impl DuckDbBackend {
         let schema_for_init = schema.clone();
 
         // Run blocking DuckDB operations in spawn_blocking
-        let connection = tokio::task::spawn_blocking(move || {
+        let (pool, version) = tokio::task::spawn_blocking(move || {
             // Create parent directory if needed
             if let Some(parent) = database_path.parent() {
                 std::fs::create_dir_all(parent).with_context(|| {
@@ -46,43 +108,54 @@ impl DuckDbBackend {
                 .execute(&format!("CREATE SCHEMA IF NOT EXISTS {}", schema_for_init), [])
                 .with_context(|| format!("Failed to create schema: {}", schema_for_init))?;
 
-            Ok::<_, anyhow::Error>(Arc::new(Mutex::new(connection)))
+            let version: String = connection
+                .query_row("SELECT version()", [], |row| row.get(0))
+                .context("Failed to read the DuckDB version")?;
+
+            Ok::<_, anyhow::Error>((Arc::new(ConnectionPool::new(connection)), version))
         })
         .await
         .map_err(|e| BackendError::connection_failed(e.to_string()))?
         .map_err(|e| BackendError::connection_failed(e.to_string()))?;
 
         Ok(Self {
-            connection,
+            pool,
             schema,
+            supports_merge: has_merge(&version),
         })
     }
 
+    /// The schema this backend was opened with.
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
     /// Check if a table exists in the information schema.
     pub async fn table_exists_sync(&self, schema: &str, table_name: &str) -> Result<bool, BackendError> {
         let query = "SELECT COUNT(*) > 0 FROM information_schema.tables WHERE table_schema = ? AND table_name = ?";
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
         let schema = schema.to_string();
         let table_name = table_name.to_string();
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
-            conn.query_row(query, [&schema, &table_name], |row| row.get(0))
-                .unwrap_or(false)
+            let conn = pool.get()?;
+            Ok(conn
+                .query_row(query, [&schema, &table_name], |row| row.get(0))
+                .unwrap_or(false))
         })
         .await
-        .map_err(|e| BackendError::Other(e.into()))
+        .map_err(|e| BackendError::Other(e.into()))?
     }
 }
 
 #[async_trait]
 impl Backend for DuckDbBackend {
     async fn execute_sql(&self, sql: &str) -> Result<Vec<RecordBatch>, BackendError> {
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
         let sql = sql.to_string();
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             let mut stmt = conn.prepare(&sql).map_err(|e| {
                 BackendError::execution_failed("query", e.to_string())
             })?;
@@ -104,11 +177,13 @@ impl Backend for DuckDbBackend {
         sql: &str,
     ) -> Result<(), BackendError> {
         let table_name = format!("{}.{}", schema, name);
-        let create_sql = format!("CREATE TABLE {} AS {}", table_name, sql);
-        let connection = Arc::clone(&self.connection);
+        let create_sql = self
+            .dialect()
+            .create_as_sql(Materialization::Table, &table_name, sql);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&create_sql, []).map_err(|e| {
                 BackendError::execution_failed(table_name.clone(), e.to_string())
             })?;
@@ -125,11 +200,13 @@ impl Backend for DuckDbBackend {
         sql: &str,
     ) -> Result<(), BackendError> {
         let view_name = format!("{}.{}", schema, name);
-        let create_sql = format!("CREATE VIEW {} AS {}", view_name, sql);
-        let connection = Arc::clone(&self.connection);
+        let create_sql = self
+            .dialect()
+            .create_as_sql(Materialization::View, &view_name, sql);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&create_sql, []).map_err(|e| {
                 BackendError::execution_failed(view_name.clone(), e.to_string())
             })?;
@@ -141,11 +218,13 @@ impl Backend for DuckDbBackend {
 
     async fn drop_table_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError> {
         let table_name = format!("{}.{}", schema, name);
-        let drop_sql = format!("DROP TABLE IF EXISTS {}", table_name);
-        let connection = Arc::clone(&self.connection);
+        let drop_sql = self
+            .dialect()
+            .drop_if_exists_sql(Materialization::Table, &table_name);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&drop_sql, []).map_err(|e| {
                 BackendError::execution_failed(table_name.clone(), e.to_string())
             })?;
@@ -157,11 +236,13 @@ impl Backend for DuckDbBackend {
 
     async fn drop_view_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError> {
         let view_name = format!("{}.{}", schema, name);
-        let drop_sql = format!("DROP VIEW IF EXISTS {}", view_name);
-        let connection = Arc::clone(&self.connection);
+        let drop_sql = self
+            .dialect()
+            .drop_if_exists_sql(Materialization::View, &view_name);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&drop_sql, []).map_err(|e| {
                 BackendError::execution_failed(view_name.clone(), e.to_string())
             })?;
@@ -174,10 +255,10 @@ impl Backend for DuckDbBackend {
     async fn get_row_count(&self, schema: &str, name: &str) -> Result<usize, BackendError> {
         let table_name = format!("{}.{}", schema, name);
         let sql = format!("SELECT COUNT(*) FROM {}", table_name);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.query_row(&sql, [], |row| row.get(0))
                 .map_err(|e| BackendError::execution_failed(table_name.clone(), e.to_string()))
         })
@@ -193,10 +274,10 @@ impl Backend for DuckDbBackend {
     ) -> Result<Vec<RecordBatch>, BackendError> {
         let table_name = format!("{}.{}", schema, name);
         let sql = format!("SELECT * FROM {} LIMIT {}", table_name, limit);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             let mut stmt = conn.prepare(&sql).map_err(|e| {
                 BackendError::execution_failed(table_name.clone(), e.to_string())
             })?;
@@ -217,10 +298,10 @@ impl Backend for DuckDbBackend {
 
     async fn ensure_schema(&self, schema: &str) -> Result<(), BackendError> {
         let sql = format!("CREATE SCHEMA IF NOT EXISTS {}", schema);
-        let connection = Arc::clone(&self.connection);
+        let pool = Arc::clone(&self.pool);
 
         tokio::task::spawn_blocking(move || {
-            let conn = connection.lock().unwrap();
+            let conn = pool.get()?;
             conn.execute(&sql, [])
                 .map_err(|e| BackendError::execution_failed("schema", e.to_string()))?;
             Ok(())
@@ -229,19 +310,59 @@ impl Backend for DuckDbBackend {
         .map_err(|e| BackendError::Other(e.into()))?
     }
 
+    /// Runs the statements on one pooled connection; `execute_sql` may take
+    /// a different connection for each call.
+    async fn execute_in_transaction(
+        &self,
+        relation: &str,
+        statements: Vec<String>,
+    ) -> Result<(), BackendError> {
+        let pool = Arc::clone(&self.pool);
+        let table_name = relation.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = pool.get()?;
+            let failed = |e: duckdb::Error| {
+                BackendError::execution_failed(table_name.clone(), e.to_string())
+            };
+            let transaction = conn.unchecked_transaction().map_err(failed)?;
+            for statement in &statements {
+                transaction.execute(statement, []).map_err(failed)?;
+            }
+            transaction.commit().map_err(failed)
+        })
+        .await
+        .map_err(|e| BackendError::Other(e.into()))?
+    }
+
     fn dialect(&self) -> SqlDialect {
         SqlDialect::DuckDB
     }
 
     fn capabilities(&self) -> BackendCapabilities {
-        BackendCapabilities::duckdb()
+        BackendCapabilities {
+            supports_merge: self.supports_merge,
+            ..BackendCapabilities::duckdb()
+        }
     }
 }
 
+/// Whether a DuckDB version such as `v1.2.2` has MERGE INTO
+fn has_merge(version: &str) -> bool {
+    let mut parts = version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let major = parts.next().unwrap_or(0);
+    let minor = parts.next().unwrap_or(0);
+    (major, minor) >= (1, 4)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use smelt_backend::Materialization;
+    use arrow::util::display::array_value_to_string;
+    use smelt_backend::{Materialization, SnapshotStrategy};
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -288,6 +409,84 @@ mod tests {
         assert_eq!(result.row_count, 1);
     }
 
+    #[tokio::test]
+    async fn test_failed_build_keeps_old_table() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model("main", "numbers", "SELECT * FROM range(3)", Materialization::Table, false)
+            .await
+            .unwrap();
+
+        let err = backend
+            .execute_model("main", "numbers", "SELECT * FROM missing_table", Materialization::Table, false)
+            .await;
+        assert!(err.is_err());
+        assert_eq!(backend.get_row_count("main", "numbers").await.unwrap(), 3);
+        assert!(!backend.table_exists("main", "numbers__smelt_staging").await.unwrap());
+
+        // A successful build replaces the table and leaves no staging table
+        let result = backend
+            .execute_model("main", "numbers", "SELECT * FROM range(5)", Materialization::Table, false)
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 5);
+        assert!(!backend.table_exists("main", "numbers__smelt_staging").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_view_is_replaced_in_place() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model("main", "numbers", "SELECT * FROM range(3)", Materialization::View, false)
+            .await
+            .unwrap();
+
+        // CREATE OR REPLACE VIEW swaps the definition without a staging relation
+        let result = backend
+            .execute_model("main", "numbers", "SELECT * FROM range(5)", Materialization::View, false)
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 5);
+        assert!(!backend.table_exists("main", "numbers__smelt_staging").await.unwrap());
+
+        // A broken definition leaves the old view in place
+        let err = backend
+            .execute_model("main", "numbers", "SELECT * FROM missing_table", Materialization::View, false)
+            .await;
+        assert!(err.is_err());
+        assert_eq!(backend.get_row_count("main", "numbers").await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_leftover_staging_table_is_replaced() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        // Left behind by a run that was killed mid-build
+        backend
+            .execute_sql("CREATE TABLE main.numbers__smelt_staging AS SELECT 'stale' AS label")
+            .await
+            .unwrap();
+
+        let result = backend
+            .execute_model("main", "numbers", "SELECT * FROM range(4)", Materialization::Table, false)
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 4);
+        assert!(!backend.table_exists("main", "numbers__smelt_staging").await.unwrap());
+        assert_eq!(
+            backend.table_columns("main", "numbers").await.unwrap(),
+            vec!["range"]
+        );
+    }
+
     #[tokio::test]
     async fn test_execute_with_preview() {
         let temp_dir = TempDir::new().unwrap();
@@ -309,6 +508,301 @@ mod tests {
         assert_eq!(total_rows, 3);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_models() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+
+        // Each model runs on its own connection
+        let (a, b) = tokio::join!(
+            backend.execute_model("main", "a", "SELECT * FROM range(100)", Materialization::Table, false),
+            backend.execute_model("main", "b", "SELECT * FROM range(200)", Materialization::Table, false),
+        );
+        assert_eq!(a.unwrap().row_count, 100);
+        assert_eq!(b.unwrap().row_count, 200);
+
+        // Tables created on one connection are visible on the others
+        let result = backend
+            .execute_model("main", "c", "SELECT * FROM main.a UNION ALL SELECT * FROM main.b", Materialization::View, false)
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 300);
+    }
+
+    #[tokio::test]
+    async fn test_tablesample_clause() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model(
+                "main",
+                "numbers",
+                "SELECT * FROM range(1000) t(n)",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let sql = format!(
+            "SELECT * FROM main.numbers {} WHERE n >= 0",
+            backend.dialect().tablesample_clause(0.5)
+        );
+        let batches = backend.execute_sql(&sql).await.unwrap();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert!(rows < 1000);
+    }
+
+    #[tokio::test]
+    async fn test_table_columns() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model(
+                "main",
+                "customers",
+                "SELECT 1 AS id, 'a' AS \"Status\", TRUE AS is_current",
+                Materialization::Table,
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            backend.table_columns("main", "customers").await.unwrap(),
+            vec!["id", "Status", "is_current"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_incremental() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_sql(
+                "CREATE TABLE main.events AS SELECT * FROM (VALUES \
+                 (DATE '2024-01-01', 1), (DATE '2024-01-02', 2)) t(day, n)",
+            )
+            .await
+            .unwrap();
+        let sql = "SELECT day, n FROM main.events";
+        backend
+            .execute_model("main", "copy", sql, Materialization::Incremental, false)
+            .await
+            .unwrap();
+
+        let since = backend.max_value("main", "copy", "day").await.unwrap();
+        assert_eq!(since.as_deref(), Some("2024-01-02"));
+
+        // A late row for the last day seen, and a new day
+        backend
+            .execute_sql("INSERT INTO main.events VALUES (DATE '2024-01-02', 3), (DATE '2024-01-03', 4)")
+            .await
+            .unwrap();
+        let new_rows = "SELECT day, n FROM main.events WHERE day >= '2024-01-02'";
+        let result = backend
+            .execute_incremental("main", "copy", new_rows, "day", "2024-01-02", false)
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 4);
+
+        let batches = backend
+            .execute_sql("SELECT SUM(n)::INTEGER FROM main.copy")
+            .await
+            .unwrap();
+        assert_eq!(
+            array_value_to_string(batches[0].column(0), 0).unwrap(),
+            "10"
+        );
+
+        // A failed insert leaves the table as it was
+        let err = backend
+            .execute_incremental("main", "copy", "SELECT 1", "day", "2024-01-01", false)
+            .await;
+        assert!(err.is_err());
+        assert_eq!(backend.get_row_count("main", "copy").await.unwrap(), 4);
+
+        let empty = backend
+            .execute_model("main", "empty", "SELECT DATE '2024-01-01' AS day WHERE false", Materialization::Table, false)
+            .await;
+        assert_eq!(empty.unwrap().row_count, 0);
+        assert_eq!(backend.max_value("main", "empty", "day").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_merge_into() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        backend
+            .execute_model(
+                "main",
+                "prices",
+                "SELECT * FROM (VALUES ('eu', 1, 10.0), ('eu', 2, 20.0), ('us', 1, 15.0)) t(region, sku, price)",
+                Materialization::Merge,
+                false,
+            )
+            .await
+            .unwrap();
+
+        // One changed row and one new row, matched on a composite key
+        let unique_key = vec!["region".to_string(), "sku".to_string()];
+        let changes = "SELECT * FROM (VALUES ('eu', 2, 25.0), ('us', 2, 30.0)) t(region, sku, price)";
+        let result = backend
+            .merge_into("main", "prices", changes, &unique_key, false)
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 4);
+
+        let batches = backend
+            .execute_sql("SELECT string_agg(region || sku || ':' || price::INTEGER, ' ' ORDER BY region, sku) FROM main.prices")
+            .await
+            .unwrap();
+        assert_eq!(
+            array_value_to_string(batches[0].column(0), 0).unwrap(),
+            "eu1:10 eu2:25 us1:15 us2:30"
+        );
+
+        // A failed insert leaves the table as it was
+        let err = backend
+            .merge_into("main", "prices", "SELECT 'eu' AS region, 1 AS sku", &unique_key, false)
+            .await;
+        assert!(err.is_err());
+        assert_eq!(backend.get_row_count("main", "prices").await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_by_updated_at() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        let unique_key = vec!["id".to_string()];
+        let strategy = SnapshotStrategy::Timestamp {
+            updated_at: "updated_at".to_string(),
+        };
+        let versions = "SELECT id, status, valid_from::DATE, valid_to::DATE, is_current \
+                        FROM main.customers ORDER BY id, valid_from";
+
+        let result = backend
+            .execute_snapshot(
+                "main",
+                "customers",
+                "SELECT * FROM (VALUES (1, 'new', DATE '2024-01-01'), (2, 'new', DATE '2024-01-01')) t(id, status, updated_at)",
+                &unique_key,
+                &strategy,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 2);
+
+        // Customer 1 is updated, customer 2 is hard deleted and customer 3 is inserted
+        let source = "SELECT * FROM (VALUES (1, 'active', DATE '2024-01-05'), (3, 'new', DATE '2024-01-05')) t(id, status, updated_at)";
+        let result = backend
+            .execute_snapshot("main", "customers", source, &unique_key, &strategy, false)
+            .await
+            .unwrap();
+        assert_eq!(result.row_count, 4);
+
+        let batches = backend.execute_sql(versions).await.unwrap();
+        let rows = (0..batches[0].num_rows())
+            .map(|row| {
+                (0..5)
+                    .map(|column| array_value_to_string(batches[0].column(column), row).unwrap())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows[0], "1 new 2024-01-01 2024-01-05 false");
+        assert_eq!(rows[1], "1 active 2024-01-05  true");
+        assert!(rows[2].starts_with("2 new 2024-01-01 2"));
+        assert!(rows[2].ends_with(" false"));
+        assert_eq!(rows[3], "3 new 2024-01-05  true");
+
+        // Unchanged rows don't get new versions
+        backend
+            .execute_snapshot("main", "customers", source, &unique_key, &strategy, false)
+            .await
+            .unwrap();
+        assert_eq!(backend.get_row_count("main", "customers").await.unwrap(), 4);
+
+        // A failed run leaves the table as it was
+        let err = backend
+            .execute_snapshot("main", "customers", "SELECT 1 AS id", &unique_key, &strategy, false)
+            .await;
+        assert!(err.is_err());
+        assert_eq!(backend.get_row_count("main", "customers").await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_by_check_columns() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.duckdb");
+
+        let backend = DuckDbBackend::new(&db_path, "main").await.unwrap();
+        let unique_key = vec!["id".to_string()];
+        let strategy = SnapshotStrategy::Check {
+            columns: vec!["status".to_string()],
+        };
+
+        backend
+            .execute_snapshot(
+                "main",
+                "orders",
+                "SELECT * FROM (VALUES (1, 'placed', 10), (2, 'placed', 20)) t(id, status, amount)",
+                &unique_key,
+                &strategy,
+                false,
+            )
+            .await
+            .unwrap();
+
+        // Only a change to a checked column makes a new version, including to NULL
+        backend
+            .execute_snapshot(
+                "main",
+                "orders",
+                "SELECT * FROM (VALUES (1, 'placed', 15), (2, NULL, 20)) t(id, status, amount)",
+                &unique_key,
+                &strategy,
+                false,
+            )
+            .await
+            .unwrap();
+
+        let batches = backend
+            .execute_sql(
+                "SELECT string_agg(id || ':' || coalesce(status, 'null') || ':' || amount || ':' || is_current, ' ' \
+                 ORDER BY id, valid_from, is_current) FROM main.orders",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            array_value_to_string(batches[0].column(0), 0).unwrap(),
+            "1:placed:10:true 2:placed:20:false 2:null:20:true"
+        );
+
+        // The closed version ends when the new one starts
+        let batches = backend
+            .execute_sql(
+                "SELECT count(*) FROM main.orders AS closed JOIN main.orders AS current \
+                 ON closed.id = current.id AND closed.valid_to = current.valid_from",
+            )
+            .await
+            .unwrap();
+        assert_eq!(array_value_to_string(batches[0].column(0), 0).unwrap(), "1");
+    }
+
     #[tokio::test]
     async fn test_capabilities() {
         let temp_dir = TempDir::new().unwrap();
@@ -320,7 +814,17 @@ mod tests {
 
         let caps = backend.capabilities();
         assert!(caps.supports_qualify);
-        assert!(caps.supports_merge);
+        // The bundled DuckDB 1.2 has no MERGE INTO
+        assert!(!caps.supports_merge);
         assert!(caps.supports_create_or_replace_table);
     }
+
+    #[test]
+    fn test_has_merge() {
+        assert!(!has_merge("v1.2.2"));
+        assert!(!has_merge("v1.3.0"));
+        assert!(has_merge("v1.4.0"));
+        assert!(has_merge("v1.5.6"));
+        assert!(has_merge("v2.0.0"));
+    }
 }
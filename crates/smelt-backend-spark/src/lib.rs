@@ -16,7 +16,9 @@
 
 use arrow::array::RecordBatch;
 use async_trait::async_trait;
-use smelt_backend::{Backend, BackendCapabilities, BackendError, SqlDialect};
+use smelt_backend::{
+    Backend, BackendCapabilities, BackendError, ExecutionResult, SnapshotStrategy, SqlDialect,
+};
 
 /// Spark Connect backend for smelt (stub implementation).
 ///
@@ -163,6 +165,15 @@ impl Backend for SparkBackend {
         )))
     }
 
+    async fn table_columns(&self, schema: &str, name: &str) -> Result<Vec<String>, BackendError> {
+        let table_name = self.qualified_name(schema, name);
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would list the columns of {}",
+            table_name
+        )))
+    }
+
     async fn ensure_schema(&self, schema: &str) -> Result<(), BackendError> {
         Err(BackendError::Other(anyhow::anyhow!(
             "Spark backend stub: would create schema {}.{}",
@@ -171,6 +182,58 @@ impl Backend for SparkBackend {
         )))
     }
 
+    async fn merge_into(
+        &self,
+        schema: &str,
+        name: &str,
+        sql: &str,
+        unique_key: &[String],
+        _show_preview: bool,
+    ) -> Result<ExecutionResult, BackendError> {
+        // TODO: Execute the MERGE via Spark Connect
+        // Delta Lake tables support MERGE INTO ... UPDATE SET * / INSERT *
+        let table_name = self.qualified_name(schema, name);
+        let statements = self.dialect().merge_statements(
+            self.capabilities().incremental_strategy(),
+            &table_name,
+            unique_key,
+            sql,
+        );
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would run {}",
+            statements.join("; ")
+        )))
+    }
+
+    async fn execute_snapshot(
+        &self,
+        schema: &str,
+        name: &str,
+        sql: &str,
+        unique_key: &[String],
+        strategy: &SnapshotStrategy,
+        _show_preview: bool,
+    ) -> Result<ExecutionResult, BackendError> {
+        // TODO: Build the table on the first run, then MERGE via Spark Connect
+        // Delta Lake tables support WHEN NOT MATCHED BY SOURCE for hard deletes
+        let table_name = self.qualified_name(schema, name);
+        let columns = self.table_columns(schema, name).await?;
+        let statements = self.dialect().snapshot_statements(
+            self.capabilities().incremental_strategy(),
+            &table_name,
+            unique_key,
+            strategy,
+            &columns,
+            sql,
+        );
+
+        Err(BackendError::Other(anyhow::anyhow!(
+            "Spark backend stub: would run {}",
+            statements.join("; ")
+        )))
+    }
+
     fn dialect(&self) -> SqlDialect {
         SqlDialect::SparkSQL
     }
@@ -206,4 +269,45 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("stub"));
     }
+
+    #[tokio::test]
+    async fn test_merge_into_uses_delta_merge() {
+        let backend = SparkBackend::new("sc://localhost:15002", "spark_catalog", "default")
+            .await
+            .unwrap();
+
+        let unique_key = vec!["id".to_string(), "Region".to_string()];
+        let err = backend
+            .merge_into("default", "dim", "SELECT * FROM updates", &unique_key, false)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Spark backend stub: would run MERGE INTO spark_catalog.default.dim AS target \
+             USING (SELECT * FROM updates) AS source \
+             ON target.id = source.id AND target.`Region` = source.`Region` \
+             WHEN MATCHED THEN UPDATE SET * WHEN NOT MATCHED THEN INSERT *"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reads_table_columns() {
+        let backend = SparkBackend::new("sc://localhost:15002", "spark_catalog", "default")
+            .await
+            .unwrap();
+
+        // The MERGE inserts the table's columns by name (see the dialect tests)
+        let unique_key = vec!["id".to_string()];
+        let strategy = SnapshotStrategy::Check {
+            columns: vec!["Status".to_string()],
+        };
+        let err = backend
+            .execute_snapshot("default", "customers", "SELECT * FROM raw", &unique_key, &strategy, false)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Spark backend stub: would list the columns of spark_catalog.default.customers"
+        );
+    }
 }
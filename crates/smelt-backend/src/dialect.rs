@@ -1,5 +1,7 @@
 //! SQL dialect definitions and backend capabilities.
 
+use crate::{IncrementalStrategy, Materialization, SnapshotStrategy};
+
 /// SQL dialect used by a backend.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SqlDialect {
@@ -20,6 +22,425 @@ impl SqlDialect {
             SqlDialect::PostgreSQL => "PostgreSQL",
         }
     }
+
+    /// Get the TABLESAMPLE clause that keeps roughly `fraction` (0, 1] of a table's rows.
+    pub fn tablesample_clause(&self, fraction: f64) -> String {
+        // Round away float noise: 0.1 * 100.0 is 10.000000000000002
+        let percent = format!("{:.6}", fraction * 100.0);
+        let percent = percent.trim_end_matches('0').trim_end_matches('.');
+        match self {
+            SqlDialect::DuckDB => format!("TABLESAMPLE bernoulli({}%)", percent),
+            SqlDialect::SparkSQL => format!("TABLESAMPLE ({} PERCENT)", percent),
+            SqlDialect::PostgreSQL => format!("TABLESAMPLE BERNOULLI ({})", percent),
+        }
+    }
+
+    /// Get the DROP ... IF EXISTS statement for a materialized model.
+    pub fn drop_if_exists_sql(&self, materialization: Materialization, relation: &str) -> String {
+        match materialization {
+            Materialization::Table
+            | Materialization::Incremental
+            | Materialization::Merge
+            | Materialization::Snapshot => {
+                format!("DROP TABLE IF EXISTS {}", relation)
+            }
+            Materialization::View => format!("DROP VIEW IF EXISTS {}", relation),
+        }
+    }
+
+    /// Get the CREATE ... AS statement that materializes a query.
+    pub fn create_as_sql(
+        &self,
+        materialization: Materialization,
+        relation: &str,
+        sql: &str,
+    ) -> String {
+        match materialization {
+            Materialization::Table
+            | Materialization::Incremental
+            | Materialization::Merge
+            | Materialization::Snapshot => {
+                format!("CREATE TABLE {} AS {}", relation, sql)
+            }
+            Materialization::View => format!("CREATE VIEW {} AS {}", relation, sql),
+        }
+    }
+
+    /// Get the CREATE OR REPLACE VIEW statement, which swaps a view's
+    /// definition in one step.
+    pub fn create_or_replace_view_sql(&self, relation: &str, sql: &str) -> String {
+        format!("CREATE OR REPLACE VIEW {} AS {}", relation, sql)
+    }
+
+    /// Get the name a table is built under before it replaces the live one.
+    pub fn staging_name(&self, name: &str) -> String {
+        format!("{}__smelt_staging", name)
+    }
+
+    /// Get the statements that replace `schema.name` with the table built at
+    /// `schema.staging`.
+    ///
+    /// With transactional DDL the old table is dropped and the staging table
+    /// renamed, and the caller runs both in one transaction. Otherwise CREATE
+    /// OR REPLACE swaps the table in one statement where the backend has it;
+    /// failing that, readers may briefly find no table between the DROP and
+    /// the rename.
+    pub fn swap_statements(
+        &self,
+        capabilities: &BackendCapabilities,
+        schema: &str,
+        staging: &str,
+        name: &str,
+    ) -> Vec<String> {
+        let relation = format!("{}.{}", schema, name);
+        let staging = format!("{}.{}", schema, staging);
+        if capabilities.supports_create_or_replace_table && !capabilities.supports_transactional_ddl
+        {
+            return vec![
+                format!(
+                    "CREATE OR REPLACE TABLE {} AS SELECT * FROM {}",
+                    relation, staging
+                ),
+                format!("DROP TABLE {}", staging),
+            ];
+        }
+
+        // Spark renames to a qualified name; the others keep the schema
+        let new_name = match self {
+            SqlDialect::SparkSQL => relation.clone(),
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL => name.to_string(),
+        };
+        vec![
+            self.drop_if_exists_sql(Materialization::Table, &relation),
+            format!("ALTER TABLE {} RENAME TO {}", staging, new_name),
+        ]
+    }
+
+    /// Get the statements `Backend::execute_model` issues to materialize a model.
+    ///
+    /// A table is built under its staging name and swapped in, inside a
+    /// transaction where the backend has transactional DDL.
+    pub fn materialize_statements(
+        &self,
+        capabilities: &BackendCapabilities,
+        schema: &str,
+        name: &str,
+        sql: &str,
+        materialization: Materialization,
+    ) -> Vec<String> {
+        let relation = format!("{}.{}", schema, name);
+        match materialization {
+            Materialization::View if capabilities.supports_create_or_replace_view => {
+                vec![self.create_or_replace_view_sql(&relation, sql)]
+            }
+            Materialization::View => vec![
+                self.drop_if_exists_sql(materialization, &relation),
+                self.create_as_sql(materialization, &relation, sql),
+            ],
+            Materialization::Table
+            | Materialization::Incremental
+            | Materialization::Merge
+            | Materialization::Snapshot => {
+                let staging = self.staging_name(name);
+                let staging_relation = format!("{}.{}", schema, staging);
+                let mut statements = vec![
+                    self.drop_if_exists_sql(materialization, &staging_relation),
+                    self.create_as_sql(materialization, &staging_relation, sql),
+                ];
+                let swap = self.swap_statements(capabilities, schema, &staging, name);
+                if capabilities.supports_transactional_ddl {
+                    statements.push("BEGIN TRANSACTION".to_string());
+                    statements.extend(swap);
+                    statements.push("COMMIT".to_string());
+                } else {
+                    statements.extend(swap);
+                }
+                statements
+            }
+        }
+    }
+
+    /// Get the query returning the largest value of a column as a string
+    /// (NULL for an empty table).
+    pub fn max_value_sql(&self, relation: &str, column: &str) -> String {
+        let string_type = match self {
+            SqlDialect::SparkSQL => "STRING",
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL => "VARCHAR",
+        };
+        format!(
+            "SELECT CAST(MAX({}) AS {}) FROM {}",
+            self.quote_identifier(column),
+            string_type,
+            relation
+        )
+    }
+
+    /// Get the query listing the columns of a table in order, one per row.
+    pub fn table_columns_sql(&self, schema: &str, name: &str) -> String {
+        match self {
+            SqlDialect::SparkSQL => format!("SHOW COLUMNS IN {}.{}", schema, name),
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL => format!(
+                "SELECT column_name FROM information_schema.columns \
+                 WHERE table_schema = {} AND table_name = {} ORDER BY ordinal_position",
+                self.string_literal(schema),
+                self.string_literal(name)
+            ),
+        }
+    }
+
+    /// Get the statements that write the rows of `new_rows_sql` into an
+    /// incremental table, replacing the rows whose `time_column` is at or
+    /// after `since`.
+    ///
+    /// The rows at `since` itself are rebuilt too, as the previous run may
+    /// have seen only some of them. The MERGE form uses `INSERT *` and
+    /// `WHEN NOT MATCHED BY SOURCE`, which Delta Lake and DuckDB 1.4+ accept.
+    pub fn incremental_statements(
+        &self,
+        strategy: IncrementalStrategy,
+        relation: &str,
+        time_column: &str,
+        since: &str,
+        new_rows_sql: &str,
+    ) -> Vec<String> {
+        let column = self.quote_identifier(time_column);
+        let since = self.string_literal(since);
+        match strategy {
+            IncrementalStrategy::Merge => vec![format!(
+                "MERGE INTO {} AS target USING ({}) AS source ON FALSE \
+                 WHEN NOT MATCHED THEN INSERT * \
+                 WHEN NOT MATCHED BY SOURCE AND target.{} >= {} THEN DELETE",
+                relation, new_rows_sql, column, since
+            )],
+            IncrementalStrategy::DeleteInsert => vec![
+                format!("DELETE FROM {} WHERE {} >= {}", relation, column, since),
+                format!("INSERT INTO {} {}", relation, new_rows_sql),
+            ],
+        }
+    }
+
+    /// Get the statements that upsert the rows of `new_rows_sql` into a
+    /// merge table: rows whose `unique_key` columns match a row of the table
+    /// replace it, and the others are inserted.
+    ///
+    /// The MERGE form uses `UPDATE SET *` and `INSERT *`, which Delta Lake and
+    /// DuckDB 1.4+ accept, so the columns need not be known. DELETE + INSERT
+    /// must run in one transaction.
+    pub fn merge_statements(
+        &self,
+        strategy: IncrementalStrategy,
+        relation: &str,
+        unique_key: &[String],
+        new_rows_sql: &str,
+    ) -> Vec<String> {
+        let matches = |target: &str| self.key_matches(unique_key, target, "source");
+        match strategy {
+            IncrementalStrategy::Merge => vec![format!(
+                "MERGE INTO {} AS target USING ({}) AS source ON {} \
+                 WHEN MATCHED THEN UPDATE SET * \
+                 WHEN NOT MATCHED THEN INSERT *",
+                relation,
+                new_rows_sql,
+                matches("target")
+            )],
+            IncrementalStrategy::DeleteInsert => vec![
+                format!(
+                    "DELETE FROM {} WHERE EXISTS (SELECT 1 FROM ({}) AS source WHERE {})",
+                    relation,
+                    new_rows_sql,
+                    matches(relation)
+                ),
+                format!("INSERT INTO {} {}", relation, new_rows_sql),
+            ],
+        }
+    }
+
+    /// Get the query selecting the rows of `sql` as new current versions in a
+    /// snapshot table, with `valid_from`, `valid_to` and `is_current` appended.
+    pub fn snapshot_select_sql(&self, sql: &str, strategy: &SnapshotStrategy) -> String {
+        format!("SELECT {} FROM ({}) AS source", self.snapshot_columns(strategy), sql)
+    }
+
+    /// Get the statements recording the rows of `sql` in an existing snapshot
+    /// table: current versions of changed or deleted rows are closed and new
+    /// versions are inserted for changed and new rows.
+    ///
+    /// `columns` are the snapshot table's columns. The MERGE form inserts
+    /// them by name, leaving out its `smelt_merge_key_N` helper columns;
+    /// DELETE + INSERT inserts by position and doesn't need them.
+    pub fn snapshot_statements(
+        &self,
+        strategy: IncrementalStrategy,
+        relation: &str,
+        unique_key: &[String],
+        snapshot: &SnapshotStrategy,
+        columns: &[String],
+        sql: &str,
+    ) -> Vec<String> {
+        let now = "CAST(CURRENT_TIMESTAMP AS TIMESTAMP)";
+        match strategy {
+            IncrementalStrategy::Merge => {
+                // Changed rows appear twice in the source: once keyed to close
+                // the current version and once with a NULL key so that the new
+                // version is inserted.
+                let keyed = unique_key
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        format!("source.{} AS smelt_merge_key_{}", self.quote_identifier(column), i)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let unkeyed = (0..unique_key.len())
+                    .map(|i| format!("NULL AS smelt_merge_key_{}", i))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let on = unique_key
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        format!("target.{} = changes.smelt_merge_key_{}", self.quote_identifier(column), i)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+                let versions = format!("{} FROM ({}) AS source", self.snapshot_columns(snapshot), sql);
+                let columns: Vec<_> = columns.iter().map(|c| self.quote_identifier(c)).collect();
+                let values = columns
+                    .iter()
+                    .map(|column| format!("changes.{}", column))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                vec![format!(
+                    "MERGE INTO {relation} AS target USING (\
+                     SELECT {keyed}, {versions} \
+                     UNION ALL \
+                     SELECT {unkeyed}, {versions} JOIN {relation} AS current \
+                     ON current.is_current AND {current_matches} WHERE {current_changed}\
+                     ) AS changes ON target.is_current AND {on} \
+                     WHEN MATCHED AND {changed} THEN \
+                     UPDATE SET valid_to = changes.valid_from, is_current = FALSE \
+                     WHEN NOT MATCHED THEN INSERT ({columns}) VALUES ({values}) \
+                     WHEN NOT MATCHED BY SOURCE AND target.is_current THEN \
+                     UPDATE SET valid_to = {now}, is_current = FALSE",
+                    columns = columns.join(", "),
+                    current_matches = self.key_matches(unique_key, "current", "source"),
+                    current_changed = self.snapshot_changed(snapshot, "current", "source"),
+                    changed = self.snapshot_changed(snapshot, "target", "changes"),
+                )]
+            }
+            IncrementalStrategy::DeleteInsert => vec![
+                format!(
+                    "UPDATE {relation} SET valid_to = {time}, is_current = FALSE \
+                     FROM ({sql}) AS source \
+                     WHERE {relation}.is_current AND {matches} AND {changed}",
+                    time = self.snapshot_time(snapshot, "source"),
+                    matches = self.key_matches(unique_key, relation, "source"),
+                    changed = self.snapshot_changed(snapshot, relation, "source"),
+                ),
+                format!(
+                    "UPDATE {relation} SET valid_to = {now}, is_current = FALSE \
+                     WHERE {relation}.is_current \
+                     AND NOT EXISTS (SELECT 1 FROM ({sql}) AS source WHERE {matches})",
+                    matches = self.key_matches(unique_key, relation, "source"),
+                ),
+                format!(
+                    "INSERT INTO {relation} {versions} \
+                     WHERE NOT EXISTS (SELECT 1 FROM {relation} AS target \
+                     WHERE target.is_current AND {matches})",
+                    versions = self.snapshot_select_sql(sql, snapshot),
+                    matches = self.key_matches(unique_key, "target", "source"),
+                ),
+            ],
+        }
+    }
+
+    /// The columns of a new version of a `source` row.
+    fn snapshot_columns(&self, strategy: &SnapshotStrategy) -> String {
+        format!(
+            "source.*, {} AS valid_from, CAST(NULL AS TIMESTAMP) AS valid_to, TRUE AS is_current",
+            self.snapshot_time(strategy, "source")
+        )
+    }
+
+    /// The time a version of a `source` row becomes valid.
+    fn snapshot_time(&self, strategy: &SnapshotStrategy, source: &str) -> String {
+        match strategy {
+            SnapshotStrategy::Timestamp { updated_at } => {
+                format!("CAST({}.{} AS TIMESTAMP)", source, self.quote_identifier(updated_at))
+            }
+            SnapshotStrategy::Check { .. } => "CAST(CURRENT_TIMESTAMP AS TIMESTAMP)".to_string(),
+        }
+    }
+
+    /// Condition under which a `source` row is a new version of `target`.
+    fn snapshot_changed(&self, strategy: &SnapshotStrategy, target: &str, source: &str) -> String {
+        match strategy {
+            SnapshotStrategy::Timestamp { updated_at } => {
+                let column = self.quote_identifier(updated_at);
+                format!("{}.{} > {}.{}", source, column, target, column)
+            }
+            SnapshotStrategy::Check { columns } => {
+                let differs = columns
+                    .iter()
+                    .map(|column| {
+                        let column = self.quote_identifier(column);
+                        match self {
+                            SqlDialect::SparkSQL => {
+                                format!("NOT ({}.{} <=> {}.{})", target, column, source, column)
+                            }
+                            SqlDialect::DuckDB | SqlDialect::PostgreSQL => {
+                                format!("{}.{} IS DISTINCT FROM {}.{}", target, column, source, column)
+                            }
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" OR ");
+                format!("({})", differs)
+            }
+        }
+    }
+
+    /// Condition matching `target` and `source` rows on `unique_key`.
+    fn key_matches(&self, unique_key: &[String], target: &str, source: &str) -> String {
+        unique_key
+            .iter()
+            .map(|column| {
+                let column = self.quote_identifier(column);
+                format!("{}.{} = {}.{}", target, column, source, column)
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+
+    /// Quote a string literal.
+    pub fn string_literal(&self, value: &str) -> String {
+        match self {
+            SqlDialect::SparkSQL => {
+                format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+            }
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL => {
+                format!("'{}'", value.replace('\'', "''"))
+            }
+        }
+    }
+
+    /// Quote an identifier if it isn't a plain lowercase name.
+    pub fn quote_identifier(&self, name: &str) -> String {
+        let mut chars = name.chars();
+        let is_plain = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+            && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if is_plain {
+            return name.to_string();
+        }
+        match self {
+            SqlDialect::SparkSQL => format!("`{}`", name.replace('`', "``")),
+            SqlDialect::DuckDB | SqlDialect::PostgreSQL => {
+                format!("\"{}\"", name.replace('"', "\"\""))
+            }
+        }
+    }
 }
 
 /// Capabilities of a backend.
@@ -53,21 +474,35 @@ pub struct BackendCapabilities {
 
     /// Supports transactional DDL (can rollback CREATE TABLE)
     pub supports_transactional_ddl: bool,
+
+    /// Supports TABLESAMPLE for sampling a percentage of rows
+    pub supports_tablesample: bool,
 }
 
 impl BackendCapabilities {
+    /// How incremental and merge models write into their existing table: a
+    /// single MERGE where the backend has one, DELETE + INSERT otherwise.
+    pub fn incremental_strategy(&self) -> IncrementalStrategy {
+        if self.supports_merge {
+            IncrementalStrategy::Merge
+        } else {
+            IncrementalStrategy::DeleteInsert
+        }
+    }
+
     /// Capabilities for DuckDB
     pub fn duckdb() -> Self {
         Self {
             supports_qualify: true,
             supports_create_or_replace_table: true,
             supports_create_or_replace_view: true,
-            supports_merge: true,
+            supports_merge: false, // MERGE INTO arrived in DuckDB 1.4; the backend checks its engine
             supports_pivot: true,
             supports_date_literal: true,
             supports_concat_operator: true,
             supports_array_literal: true,
             supports_transactional_ddl: true,
+            supports_tablesample: true,
         }
     }
 
@@ -83,6 +518,7 @@ impl BackendCapabilities {
             supports_concat_operator: true,
             supports_array_literal: false, // Uses ARRAY(a, b, c)
             supports_transactional_ddl: false,
+            supports_tablesample: true,
         }
     }
 
@@ -92,12 +528,161 @@ impl BackendCapabilities {
             supports_qualify: false, // Requires subquery rewrite
             supports_create_or_replace_table: false, // DROP + CREATE
             supports_create_or_replace_view: true,
-            supports_merge: true, // PostgreSQL 15+
+            supports_merge: false, // MERGE has no UPDATE SET * / INSERT *
             supports_pivot: false, // Requires crosstab extension
             supports_date_literal: true,
             supports_concat_operator: true,
             supports_array_literal: false, // Uses ARRAY[a, b, c]
             supports_transactional_ddl: true,
+            supports_tablesample: true,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_statements() {
+        let mut capabilities = BackendCapabilities::postgresql();
+        assert_eq!(
+            SqlDialect::PostgreSQL.materialize_statements(
+                &capabilities,
+                "s",
+                "v",
+                "SELECT 1",
+                Materialization::View
+            ),
+            vec!["CREATE OR REPLACE VIEW s.v AS SELECT 1"]
+        );
+
+        // Views never go through a staging name
+        capabilities.supports_create_or_replace_view = false;
+        assert_eq!(
+            SqlDialect::PostgreSQL.materialize_statements(
+                &capabilities,
+                "s",
+                "v",
+                "SELECT 1",
+                Materialization::View
+            ),
+            vec!["DROP VIEW IF EXISTS s.v", "CREATE VIEW s.v AS SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_merge_inserts_table_columns() {
+        let unique_key = vec!["id".to_string()];
+        let snapshot = SnapshotStrategy::Check {
+            columns: vec!["Status".to_string()],
+        };
+        let columns: Vec<_> = ["id", "Status", "valid_from", "valid_to", "is_current"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        let statements = SqlDialect::SparkSQL.snapshot_statements(
+            IncrementalStrategy::Merge,
+            "s.customers",
+            &unique_key,
+            &snapshot,
+            &columns,
+            "SELECT * FROM raw",
+        );
+        assert_eq!(statements.len(), 1);
+        let merge = &statements[0];
+        assert!(merge.starts_with(
+            "MERGE INTO s.customers AS target USING (\
+             SELECT source.id AS smelt_merge_key_0, source.*, CAST(CURRENT_TIMESTAMP AS TIMESTAMP) AS valid_from"
+        ));
+        assert!(merge.contains(
+            "UNION ALL SELECT NULL AS smelt_merge_key_0, source.*, CAST(CURRENT_TIMESTAMP AS TIMESTAMP) AS valid_from"
+        ));
+        assert!(merge.contains(") AS changes ON target.is_current AND target.id = changes.smelt_merge_key_0 "));
+        assert!(merge.contains("WHEN MATCHED AND (NOT (target.`Status` <=> changes.`Status`)) THEN"));
+        // The helper key is matched on but never inserted
+        assert!(merge.contains(
+            "WHEN NOT MATCHED THEN INSERT (id, `Status`, valid_from, valid_to, is_current) \
+             VALUES (changes.id, changes.`Status`, changes.valid_from, changes.valid_to, changes.is_current) "
+        ));
+        assert!(!merge.contains("INSERT *"));
+        assert!(merge.ends_with(
+            "WHEN NOT MATCHED BY SOURCE AND target.is_current THEN \
+             UPDATE SET valid_to = CAST(CURRENT_TIMESTAMP AS TIMESTAMP), is_current = FALSE"
+        ));
+    }
+
+    #[test]
+    fn test_table_columns_sql() {
+        assert_eq!(
+            SqlDialect::DuckDB.table_columns_sql("main", "it's"),
+            "SELECT column_name FROM information_schema.columns \
+             WHERE table_schema = 'main' AND table_name = 'it''s' ORDER BY ordinal_position"
+        );
+        assert_eq!(
+            SqlDialect::SparkSQL.table_columns_sql("default", "customers"),
+            "SHOW COLUMNS IN default.customers"
+        );
+    }
+
+    #[test]
+    fn test_incremental_statements_follow_strategy() {
+        let strategy = BackendCapabilities::postgresql().incremental_strategy();
+        assert_eq!(
+            SqlDialect::PostgreSQL.incremental_statements(
+                strategy,
+                "s.events",
+                "event_time",
+                "2024-01-01",
+                "SELECT * FROM t"
+            ),
+            vec![
+                "DELETE FROM s.events WHERE event_time >= '2024-01-01'",
+                "INSERT INTO s.events SELECT * FROM t",
+            ]
+        );
+
+        let strategy = BackendCapabilities::spark().incremental_strategy();
+        assert_eq!(
+            SqlDialect::SparkSQL.incremental_statements(
+                strategy,
+                "s.events",
+                "event_time",
+                "2024-01-01",
+                "SELECT * FROM t"
+            ),
+            vec![
+                "MERGE INTO s.events AS target USING (SELECT * FROM t) AS source ON FALSE \
+                 WHEN NOT MATCHED THEN INSERT * \
+                 WHEN NOT MATCHED BY SOURCE AND target.event_time >= '2024-01-01' THEN DELETE",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_statements_follow_strategy() {
+        let unique_key = vec!["id".to_string()];
+
+        // PostgreSQL's MERGE has no UPDATE SET * / INSERT *
+        let strategy = BackendCapabilities::postgresql().incremental_strategy();
+        assert_eq!(strategy, IncrementalStrategy::DeleteInsert);
+        assert_eq!(
+            SqlDialect::PostgreSQL.merge_statements(strategy, "s.dim", &unique_key, "SELECT * FROM t"),
+            vec![
+                "DELETE FROM s.dim WHERE EXISTS (SELECT 1 FROM (SELECT * FROM t) AS source \
+                 WHERE s.dim.id = source.id)",
+                "INSERT INTO s.dim SELECT * FROM t",
+            ]
+        );
+
+        let strategy = BackendCapabilities::spark().incremental_strategy();
+        assert_eq!(
+            SqlDialect::SparkSQL.merge_statements(strategy, "s.dim", &unique_key, "SELECT * FROM t"),
+            vec![
+                "MERGE INTO s.dim AS target USING (SELECT * FROM t) AS source ON target.id = source.id \
+                 WHEN MATCHED THEN UPDATE SET * WHEN NOT MATCHED THEN INSERT *",
+            ]
+        );
+    }
+}
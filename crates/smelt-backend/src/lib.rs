@@ -9,9 +9,10 @@ mod types;
 
 pub use dialect::{BackendCapabilities, SqlDialect};
 pub use error::BackendError;
-pub use types::{ExecutionResult, Materialization};
+pub use types::{ExecutionResult, IncrementalStrategy, Materialization, SnapshotStrategy};
 
-use arrow::array::RecordBatch;
+use arrow::array::{Array, RecordBatch};
+use arrow::util::display::array_value_to_string;
 use async_trait::async_trait;
 
 /// Abstract interface for smelt execution backends.
@@ -63,9 +64,14 @@ pub trait Backend: Send + Sync {
     /// Get the capabilities of this backend.
     fn capabilities(&self) -> BackendCapabilities;
 
-    /// Execute a model (drop + create as table or view).
+    /// Execute a model, replacing its table or view.
     ///
-    /// This is a convenience method that combines drop + create operations.
+    /// A table is built under a staging name, checked, and only then swapped
+    /// in with `swap_table`, so readers never find it missing and a failed
+    /// build leaves the old table in place. If the swap fails on a backend
+    /// without transactional DDL, the old table may already be dropped, so
+    /// the staging table is kept and named in the error. A view is replaced
+    /// with CREATE OR REPLACE VIEW where the backend supports it.
     async fn execute_model(
         &self,
         schema: &str,
@@ -77,9 +83,46 @@ pub trait Backend: Send + Sync {
         let start = std::time::Instant::now();
 
         match materialization {
-            Materialization::Table => {
-                self.drop_table_if_exists(schema, name).await?;
-                self.create_table_as(schema, name, sql).await?;
+            Materialization::Table
+            | Materialization::Incremental
+            | Materialization::Merge
+            | Materialization::Snapshot => {
+                let staging = self.dialect().staging_name(name);
+                self.drop_table_if_exists(schema, &staging).await?;
+
+                let built = async {
+                    self.create_table_as(schema, &staging, sql).await?;
+                    self.get_row_count(schema, &staging).await
+                }
+                .await;
+                if let Err(e) = built {
+                    // The old table is untouched; only the staging table goes
+                    let _ = self.drop_table_if_exists(schema, &staging).await;
+                    return Err(e);
+                }
+
+                if let Err(e) = self.swap_table(schema, &staging, name).await {
+                    if self.capabilities().supports_transactional_ddl {
+                        // The swap rolled back, so the old table is still there
+                        let _ = self.drop_table_if_exists(schema, &staging).await;
+                        return Err(e);
+                    }
+                    // The old table may be gone already; keep the new build
+                    return Err(BackendError::execution_failed(
+                        format!("{}.{}", schema, name),
+                        format!(
+                            "{} (the new table is left at {}.{})",
+                            failure_message(e),
+                            schema,
+                            staging
+                        ),
+                    ));
+                }
+            }
+            Materialization::View if self.capabilities().supports_create_or_replace_view => {
+                let relation = format!("{}.{}", schema, name);
+                self.execute_sql(&self.dialect().create_or_replace_view_sql(&relation, sql))
+                    .await?;
             }
             Materialization::View => {
                 self.drop_view_if_exists(schema, name).await?;
@@ -87,20 +130,389 @@ pub trait Backend: Send + Sync {
             }
         }
 
-        let duration = start.elapsed();
-        let row_count = self.get_row_count(schema, name).await?;
+        execution_result(self, schema, name, start.elapsed(), show_preview).await
+    }
 
-        let preview = if show_preview {
-            Some(self.get_preview(schema, name, 10).await?)
-        } else {
-            None
+    /// Replace the table `name` with the table built at `staging`.
+    ///
+    /// Runs the statements from `SqlDialect::swap_statements`, with
+    /// `execute_in_transaction` where the backend has transactional DDL so
+    /// readers see either the old table or the new one.
+    async fn swap_table(
+        &self,
+        schema: &str,
+        staging: &str,
+        name: &str,
+    ) -> Result<(), BackendError> {
+        let capabilities = self.capabilities();
+        let statements = self
+            .dialect()
+            .swap_statements(&capabilities, schema, staging, name);
+        if capabilities.supports_transactional_ddl {
+            return self
+                .execute_in_transaction(&format!("{}.{}", schema, name), statements)
+                .await;
+        }
+        for statement in statements {
+            self.execute_sql(&statement).await?;
+        }
+        Ok(())
+    }
+
+    /// Run statements that must take effect together, in one transaction.
+    ///
+    /// The default issues BEGIN TRANSACTION and COMMIT through `execute_sql`,
+    /// rolling back if a statement fails. Backends whose `execute_sql` may
+    /// use a different session per call must override it.
+    async fn execute_in_transaction(
+        &self,
+        relation: &str,
+        statements: Vec<String>,
+    ) -> Result<(), BackendError> {
+        self.execute_sql("BEGIN TRANSACTION").await?;
+        for statement in &statements {
+            if let Err(e) = self.execute_sql(statement).await {
+                let _ = self.execute_sql("ROLLBACK").await;
+                return Err(BackendError::execution_failed(relation, failure_message(e)));
+            }
+        }
+        self.execute_sql("COMMIT").await?;
+        Ok(())
+    }
+
+    /// Get the names of a table's columns, in order.
+    async fn table_columns(&self, schema: &str, name: &str) -> Result<Vec<String>, BackendError> {
+        let batches = self
+            .execute_sql(&self.dialect().table_columns_sql(schema, name))
+            .await?;
+
+        let mut columns = Vec::new();
+        for values in batches.iter().map(|batch| batch.column(0)) {
+            for row in 0..values.len() {
+                let column = array_value_to_string(values, row).map_err(|e| {
+                    BackendError::execution_failed(format!("{}.{}", schema, name), e.to_string())
+                })?;
+                columns.push(column);
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Get the largest value of a column of a table, as a string.
+    ///
+    /// Returns None if the table is empty or the column is all NULL.
+    async fn max_value(
+        &self,
+        schema: &str,
+        name: &str,
+        column: &str,
+    ) -> Result<Option<String>, BackendError> {
+        let relation = format!("{}.{}", schema, name);
+        let batches = self
+            .execute_sql(&self.dialect().max_value_sql(&relation, column))
+            .await?;
+
+        let Some(values) = batches
+            .iter()
+            .find(|batch| batch.num_rows() > 0)
+            .map(|batch| batch.column(0))
+        else {
+            return Ok(None);
         };
+        if values.is_null(0) {
+            return Ok(None);
+        }
+        array_value_to_string(values, 0)
+            .map(Some)
+            .map_err(|e| BackendError::execution_failed(relation, e.to_string()))
+    }
+
+    /// Execute an incremental run of a model whose table already exists.
+    ///
+    /// `sql` selects the new rows, those with `time_column` at or after
+    /// `since`. They replace the table's rows in that range, using the
+    /// strategy from `BackendCapabilities::incremental_strategy`, in one
+    /// transaction so readers never see the range deleted but not refilled.
+    async fn execute_incremental(
+        &self,
+        schema: &str,
+        name: &str,
+        sql: &str,
+        time_column: &str,
+        since: &str,
+        show_preview: bool,
+    ) -> Result<ExecutionResult, BackendError> {
+        let start = std::time::Instant::now();
+
+        let relation = format!("{}.{}", schema, name);
+        let statements = self.dialect().incremental_statements(
+            self.capabilities().incremental_strategy(),
+            &relation,
+            time_column,
+            since,
+            sql,
+        );
+        self.execute_in_transaction(&relation, statements).await?;
+
+        execution_result(self, schema, name, start.elapsed(), show_preview).await
+    }
+
+    /// Upsert the rows of `sql` into a merge model's existing table.
+    ///
+    /// Rows whose `unique_key` columns match a row of the table replace it;
+    /// the others are inserted. Uses a MERGE where the backend supports one
+    /// and DELETE + INSERT otherwise (see `BackendCapabilities::incremental_strategy`),
+    /// run with `execute_in_transaction` so readers never see the rows missing.
+    async fn merge_into(
+        &self,
+        schema: &str,
+        name: &str,
+        sql: &str,
+        unique_key: &[String],
+        show_preview: bool,
+    ) -> Result<ExecutionResult, BackendError> {
+        let start = std::time::Instant::now();
+
+        let relation = format!("{}.{}", schema, name);
+        let statements = self.dialect().merge_statements(
+            self.capabilities().incremental_strategy(),
+            &relation,
+            unique_key,
+            sql,
+        );
+        self.execute_in_transaction(&relation, statements).await?;
+
+        execution_result(self, schema, name, start.elapsed(), show_preview).await
+    }
+
+    /// Record the rows of `sql` in a snapshot model's table.
+    ///
+    /// Each row of the table is a version of a source row, valid from
+    /// `valid_from` until `valid_to` and flagged `is_current` while
+    /// `valid_to` is NULL. The first run builds the table with every row
+    /// current; later runs close the current versions of rows that changed
+    /// (per `strategy`) or disappeared from `sql`, and insert new versions,
+    /// all in one transaction.
+    async fn execute_snapshot(
+        &self,
+        schema: &str,
+        name: &str,
+        sql: &str,
+        unique_key: &[String],
+        strategy: &SnapshotStrategy,
+        show_preview: bool,
+    ) -> Result<ExecutionResult, BackendError> {
+        if !self.table_exists(schema, name).await? {
+            let versions = self.dialect().snapshot_select_sql(sql, strategy);
+            return self
+                .execute_model(schema, name, &versions, Materialization::Snapshot, show_preview)
+                .await;
+        }
+
+        let start = std::time::Instant::now();
+
+        let relation = format!("{}.{}", schema, name);
+        let columns = self.table_columns(schema, name).await?;
+        let statements = self.dialect().snapshot_statements(
+            self.capabilities().incremental_strategy(),
+            &relation,
+            unique_key,
+            strategy,
+            &columns,
+            sql,
+        );
+        self.execute_in_transaction(&relation, statements).await?;
+
+        execution_result(self, schema, name, start.elapsed(), show_preview).await
+    }
+}
+
+/// Result of a model that has just been materialized
+async fn execution_result<B: Backend + ?Sized>(
+    backend: &B,
+    schema: &str,
+    name: &str,
+    duration: std::time::Duration,
+    show_preview: bool,
+) -> Result<ExecutionResult, BackendError> {
+    let row_count = backend.get_row_count(schema, name).await?;
+
+    let preview = if show_preview {
+        Some(backend.get_preview(schema, name, 10).await?)
+    } else {
+        None
+    };
+
+    Ok(ExecutionResult {
+        model_name: name.to_string(),
+        duration,
+        row_count,
+        preview,
+    })
+}
+
+/// The message of an error, without the relation `ExecutionFailed` names
+fn failure_message(error: BackendError) -> String {
+    match error {
+        BackendError::ExecutionFailed { message, .. } => message,
+        e => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+
+    /// Keeps the names of its tables and fails statements starting with `fail_on`
+    struct MockBackend {
+        capabilities: BackendCapabilities,
+        fail_on: &'static str,
+        tables: Mutex<BTreeSet<String>>,
+    }
+
+    impl MockBackend {
+        fn new(capabilities: BackendCapabilities, fail_on: &'static str) -> Self {
+            Self {
+                capabilities,
+                fail_on,
+                tables: Mutex::new(BTreeSet::from(["s.orders".to_string()])),
+            }
+        }
+
+        fn tables(&self) -> Vec<String> {
+            self.tables.lock().unwrap().iter().cloned().collect()
+        }
+    }
+
+    #[async_trait]
+    impl Backend for MockBackend {
+        async fn execute_sql(&self, sql: &str) -> Result<Vec<RecordBatch>, BackendError> {
+            if sql.starts_with(self.fail_on) {
+                return Err(BackendError::execution_failed("query", "refused"));
+            }
+            let mut tables = self.tables.lock().unwrap();
+            if let Some(relation) = sql.strip_prefix("DROP TABLE IF EXISTS ") {
+                tables.remove(relation);
+            } else if let Some(relation) = sql.strip_prefix("CREATE TABLE ") {
+                tables.insert(relation.split(' ').next().unwrap().to_string());
+            } else if let Some(rename) = sql.strip_prefix("ALTER TABLE ") {
+                let (from, to) = rename.split_once(" RENAME TO ").unwrap();
+                tables.remove(from);
+                tables.insert(to.to_string());
+            }
+            Ok(Vec::new())
+        }
+
+        async fn create_table_as(
+            &self,
+            schema: &str,
+            name: &str,
+            sql: &str,
+        ) -> Result<(), BackendError> {
+            let relation = format!("{}.{}", schema, name);
+            self.execute_sql(
+                &self
+                    .dialect()
+                    .create_as_sql(Materialization::Table, &relation, sql),
+            )
+            .await?;
+            Ok(())
+        }
+
+        async fn create_view_as(
+            &self,
+            _schema: &str,
+            _name: &str,
+            _sql: &str,
+        ) -> Result<(), BackendError> {
+            unimplemented!()
+        }
+
+        async fn drop_table_if_exists(&self, schema: &str, name: &str) -> Result<(), BackendError> {
+            let relation = format!("{}.{}", schema, name);
+            self.execute_sql(
+                &self
+                    .dialect()
+                    .drop_if_exists_sql(Materialization::Table, &relation),
+            )
+            .await?;
+            Ok(())
+        }
+
+        async fn drop_view_if_exists(
+            &self,
+            _schema: &str,
+            _name: &str,
+        ) -> Result<(), BackendError> {
+            unimplemented!()
+        }
+
+        async fn get_row_count(&self, _schema: &str, _name: &str) -> Result<usize, BackendError> {
+            Ok(0)
+        }
+
+        async fn get_preview(
+            &self,
+            _schema: &str,
+            _name: &str,
+            _limit: usize,
+        ) -> Result<Vec<RecordBatch>, BackendError> {
+            Ok(Vec::new())
+        }
+
+        async fn table_exists(&self, schema: &str, name: &str) -> Result<bool, BackendError> {
+            Ok(self
+                .tables
+                .lock()
+                .unwrap()
+                .contains(&format!("{}.{}", schema, name)))
+        }
+
+        async fn ensure_schema(&self, _schema: &str) -> Result<(), BackendError> {
+            Ok(())
+        }
+
+        fn dialect(&self) -> SqlDialect {
+            SqlDialect::SparkSQL
+        }
+
+        fn capabilities(&self) -> BackendCapabilities {
+            self.capabilities.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_rename_keeps_staging_table() {
+        // Spark drops the old table, then renames the staging table
+        let backend = MockBackend::new(BackendCapabilities::spark(), "ALTER TABLE");
+        let err = backend
+            .execute_model("s", "orders", "SELECT 1", Materialization::Table, false)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Execution failed for 's.orders': refused (the new table is left at s.orders__smelt_staging)"
+        );
+        assert_eq!(backend.tables(), vec!["s.orders__smelt_staging"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_build_keeps_old_table() {
+        let backend = MockBackend::new(BackendCapabilities::spark(), "CREATE TABLE");
+        backend
+            .execute_model("s", "orders", "SELECT 1", Materialization::Table, false)
+            .await
+            .unwrap_err();
+        assert_eq!(backend.tables(), vec!["s.orders"]);
 
-        Ok(ExecutionResult {
-            model_name: name.to_string(),
-            duration,
-            row_count,
-            preview,
-        })
+        let backend = MockBackend::new(BackendCapabilities::spark(), "nothing");
+        backend
+            .execute_model("s", "orders", "SELECT 1", Materialization::Table, false)
+            .await
+            .unwrap();
+        assert_eq!(backend.tables(), vec!["s.orders"]);
     }
 }
@@ -28,6 +28,37 @@ pub enum Materialization {
     /// Materialize as a view (computed on query).
     #[default]
     View,
+
+    /// Materialize as a table that later runs extend with new rows only.
+    Incremental,
+
+    /// Materialize as a table that later runs upsert into by unique key.
+    Merge,
+
+    /// Materialize as a table keeping every version of each row.
+    Snapshot,
+}
+
+/// How new rows are written into an existing incremental or merge table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncrementalStrategy {
+    /// A single MERGE that replaces the rows it matches.
+    Merge,
+
+    /// DELETE the rows being replaced, then INSERT the new ones.
+    DeleteInsert,
+}
+
+/// How a snapshot detects that a row has changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotStrategy {
+    /// The row's `updated_at` column is later than the current version's.
+    /// It also dates the versions.
+    Timestamp { updated_at: String },
+
+    /// Any of `columns` differs from the current version. Versions are
+    /// dated by the time of the run.
+    Check { columns: Vec<String> },
 }
 
 impl std::fmt::Display for Materialization {
@@ -35,6 +66,9 @@ impl std::fmt::Display for Materialization {
         match self {
             Materialization::Table => write!(f, "table"),
             Materialization::View => write!(f, "view"),
+            Materialization::Incremental => write!(f, "incremental"),
+            Materialization::Merge => write!(f, "merge"),
+            Materialization::Snapshot => write!(f, "snapshot"),
         }
     }
 }
@@ -46,6 +80,9 @@ impl std::str::FromStr for Materialization {
         match s.to_lowercase().as_str() {
             "table" => Ok(Materialization::Table),
             "view" => Ok(Materialization::View),
+            "incremental" => Ok(Materialization::Incremental),
+            "merge" => Ok(Materialization::Merge),
+            "snapshot" => Ok(Materialization::Snapshot),
             _ => Err(format!("Unknown materialization: {}", s)),
         }
     }
@@ -1,64 +1,446 @@
 use crate::config::{Config, Materialization};
-use crate::discovery::ModelFile;
+use crate::discovery::{
+    extract_refs, extract_sources, ModelFile, RefInfo, RefParam, SourceRefInfo,
+};
 use crate::errors::{extract_snippet, text_range_to_line_col, CliError};
+use crate::incremental::{IncrementalPlan, Upstream};
 use anyhow::Result;
+use rowan::{TextRange, TextSize};
+use smelt_backend::{BackendCapabilities, SqlDialect};
+use smelt_parser::{File as AstFile, LexOptions, LiteralKind};
 
 #[derive(Debug, Clone)]
 pub struct CompiledModel {
     pub name: String,
     pub sql: String,
     pub materialization: Materialization,
+    /// Maps offsets in `sql` back to the model file
+    pub source_map: SourceMap,
+}
+
+/// Maps offsets in compiled SQL back to offsets in the model source.
+///
+/// Only the replaced spans (ref and source calls) are recorded; text between them is
+/// copied unchanged, so its offsets differ by a running delta.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// (compiled range, source range) of each replacement, in order
+    replacements: Vec<(TextRange, TextRange)>,
+}
+
+impl SourceMap {
+    fn push(&mut self, compiled: TextRange, source: TextRange) {
+        self.replacements.push((compiled, source));
+    }
+
+    /// Source offset for an offset in the compiled SQL. Offsets inside a
+    /// replacement map to the start of the call it came from.
+    pub fn source_offset(&self, offset: TextSize) -> TextSize {
+        self.map(offset, false)
+    }
+
+    /// Source range for a range of the compiled SQL. A range touching a
+    /// replacement is widened to cover the whole call.
+    pub fn source_range(&self, range: TextRange) -> TextRange {
+        let start = self.map(range.start(), false);
+        let end = self.map(range.end(), true);
+        TextRange::new(start, end.max(start))
+    }
+
+    fn map(&self, offset: TextSize, is_end: bool) -> TextSize {
+        let mut compiled_end = TextSize::from(0);
+        let mut source_end = TextSize::from(0);
+
+        for &(compiled, source) in &self.replacements {
+            if offset <= compiled.start() {
+                break;
+            }
+            if offset < compiled.end() || (is_end && offset == compiled.end()) {
+                return if is_end { source.end() } else { source.start() };
+            }
+            compiled_end = compiled.end();
+            source_end = source.end();
+        }
+
+        source_end + (offset - compiled_end)
+    }
 }
 
 pub struct SqlCompiler {
     config: Config,
+    dialect: SqlDialect,
+    capabilities: BackendCapabilities,
+}
+
+/// smelt.ref() parameters from DESIGN.md that are recognised but not compiled yet
+const PLANNED_REF_PARAMS: &[&str] = &[
+    "partitions",
+    "max_staleness",
+    "as_of",
+    "version",
+    "prefer_materialized",
+    "allow_approximate",
+    "inline",
+];
+
+/// A smelt.ref() or smelt.source() call to be replaced
+enum Splice<'a> {
+    Ref(&'a RefInfo),
+    Source(&'a SourceRefInfo),
+}
+
+impl Splice<'_> {
+    fn range(&self) -> TextRange {
+        match self {
+            Splice::Ref(ref_info) => ref_info.range,
+            Splice::Source(source) => source.range,
+        }
+    }
+}
+
+/// Validated smelt.ref() parameters
+#[derive(Debug, Default, PartialEq)]
+struct RefParams {
+    filter: Option<String>,
+    limit: Option<u64>,
+    sample: Option<f64>,
 }
 
 impl SqlCompiler {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            dialect: SqlDialect::DuckDB,
+            capabilities: BackendCapabilities::duckdb(),
+        }
+    }
+
+    /// Compile for a specific backend (defaults to DuckDB)
+    pub fn with_dialect(mut self, dialect: SqlDialect, capabilities: BackendCapabilities) -> Self {
+        self.dialect = dialect;
+        self.capabilities = capabilities;
+        self
     }
 
     /// Compile a model's SQL by replacing smelt.ref() calls with table references
+    /// and smelt.source() calls with the source table's qualified name.
+    ///
+    /// Calls are found in the CST and spliced by range, so comments, string
+    /// literals and formatting around them are preserved.
     pub fn compile(&self, model: &ModelFile, schema: &str) -> Result<CompiledModel> {
-        // ERROR if any named parameters detected
-        for ref_info in &model.refs {
-            if ref_info.has_named_params {
-                let (line, col) = text_range_to_line_col(&model.content, ref_info.range);
-                let snippet = extract_snippet(&model.content, ref_info.range, 0);
-
-                return Err(CliError::NamedParametersNotSupported {
-                    model: model.name.clone(),
-                    file: model.path.clone(),
-                    line,
-                    col,
-                    snippet,
-                }
-                .into());
-            }
-        }
+        self.compile_with(model, schema, None)
+    }
 
-        // Replace refs - we'll do simple string replacement for now
-        // For a production implementation, we'd want AST-based rewriting
-        let mut compiled_sql = model.content.clone();
+    /// Compile the new rows of an incremental model: the ref or source its
+    /// time column is read from is filtered to the rows at or after `since`.
+    pub fn compile_incremental(
+        &self,
+        model: &ModelFile,
+        schema: &str,
+        plan: &IncrementalPlan,
+        since: &str,
+    ) -> Result<CompiledModel> {
+        self.compile_with(model, schema, Some((plan, since)))
+    }
 
-        // Collect all unique refs for replacement
-        let unique_refs: std::collections::HashSet<_> =
-            model.refs.iter().map(|r| r.model_name.as_str()).collect();
+    fn compile_with(
+        &self,
+        model: &ModelFile,
+        schema: &str,
+        incremental: Option<(&IncrementalPlan, &str)>,
+    ) -> Result<CompiledModel> {
+        let lex_options = LexOptions {
+            backslash_escapes: self.dialect == SqlDialect::SparkSQL,
+        };
+        let parse = smelt_parser::parse_with(&model.content, lex_options);
+        let (refs, sources) = match AstFile::cast(parse.syntax()) {
+            Some(file) => {
+                if let Some(source) = file.sources().find(|s| !s.is_well_formed()) {
+                    let (line, col) = text_range_to_line_col(&model.content, source.range());
+                    return Err(CliError::InvalidSourceCall {
+                        model: model.name.clone(),
+                        file: model.path.clone(),
+                        line,
+                        col,
+                        snippet: extract_snippet(&model.content, source.range(), 0),
+                    }
+                    .into());
+                }
+                (extract_refs(&file), extract_sources(&file))
+            }
+            None => (Vec::new(), Vec::new()),
+        };
 
-        // Replace each ref pattern
-        for ref_name in unique_refs {
-            let pattern = format!("smelt.ref('{}')", ref_name);
-            let replacement = format!("{}.{}", schema, ref_name);
-            compiled_sql = compiled_sql.replace(&pattern, &replacement);
-        }
+        let mut splices: Vec<Splice> = refs
+            .iter()
+            .map(Splice::Ref)
+            .chain(sources.iter().map(Splice::Source))
+            .collect();
+        splices.sort_by_key(|splice| splice.range().start());
+
+        let mut source_map = SourceMap::default();
+        let compiled_sql = self.splice_calls(
+            model,
+            schema,
+            incremental,
+            &splices,
+            0..model.content.len(),
+            Some(&mut source_map),
+        )?;
 
         Ok(CompiledModel {
             name: model.name.clone(),
             sql: compiled_sql,
-            materialization: self.config.get_materialization(&model.name),
+            materialization: self
+                .config
+                .get_materialization(&model.name, &model.annotations),
+            source_map,
         })
     }
+
+    /// Copy `span` of the model, replacing the ref and source calls in it.
+    /// Calls nested in a ref's filter are compiled into that ref's replacement.
+    fn splice_calls(
+        &self,
+        model: &ModelFile,
+        schema: &str,
+        incremental: Option<(&IncrementalPlan, &str)>,
+        splices: &[Splice],
+        span: std::ops::Range<usize>,
+        mut source_map: Option<&mut SourceMap>,
+    ) -> Result<String> {
+        let time_filter = |upstream: Upstream| {
+            incremental
+                .filter(|(plan, _)| plan.upstream == upstream)
+                .map(|(plan, since)| plan.upstream_filter(self.dialect, since))
+        };
+
+        let mut compiled_sql = String::with_capacity(span.len());
+        let mut copied_to = span.start;
+
+        for splice in splices {
+            let range = std::ops::Range::<usize>::from(splice.range());
+            // Skip calls outside the span, and calls nested in one already replaced
+            if range.start < copied_to || range.end > span.end {
+                continue;
+            }
+
+            let replacement = match splice {
+                Splice::Ref(ref_info) => {
+                    let mut params = self.validate_ref_params(model, ref_info)?;
+                    if let Some(param) = ref_info
+                        .params
+                        .iter()
+                        .find(|p| p.name.eq_ignore_ascii_case("filter"))
+                    {
+                        params.filter = Some(self.splice_calls(
+                            model,
+                            schema,
+                            incremental,
+                            splices,
+                            param.value_range.into(),
+                            None,
+                        )?);
+                    }
+                    let upstream = Upstream::Model(ref_info.model_name.clone());
+                    if let Some(condition) = time_filter(upstream) {
+                        params.filter = Some(match params.filter {
+                            Some(filter) => format!("({}) AND {}", filter, condition),
+                            None => condition,
+                        });
+                    }
+                    if params == RefParams::default() {
+                        format!("{}.{}", schema, ref_info.model_name)
+                    } else {
+                        self.parameterized_ref_sql(ref_info, &params, schema)
+                    }
+                }
+                Splice::Source(source) => {
+                    let table = format!(
+                        "{}.{}",
+                        self.dialect.quote_identifier(&source.schema),
+                        self.dialect.quote_identifier(&source.table)
+                    );
+                    match time_filter(Upstream::Source {
+                        schema: source.schema.clone(),
+                        table: source.table.clone(),
+                    }) {
+                        // Keep the table name usable as a qualifier, as for refs
+                        Some(condition) if source.alias.is_none() => format!(
+                            "(SELECT * FROM {} WHERE {}) AS {}",
+                            table,
+                            condition,
+                            self.dialect.quote_identifier(&source.table)
+                        ),
+                        Some(condition) => format!("(SELECT * FROM {} WHERE {})", table, condition),
+                        None => table,
+                    }
+                }
+            };
+
+            compiled_sql.push_str(&model.content[copied_to..range.start]);
+            let compiled_start = TextSize::of(compiled_sql.as_str());
+            compiled_sql.push_str(&replacement);
+            if let Some(source_map) = source_map.as_deref_mut() {
+                source_map.push(
+                    TextRange::new(compiled_start, TextSize::of(compiled_sql.as_str())),
+                    splice.range(),
+                );
+            }
+            copied_to = range.end;
+        }
+        compiled_sql.push_str(&model.content[copied_to..span.end]);
+
+        Ok(compiled_sql)
+    }
+
+    /// Check parameter names and value types
+    fn validate_ref_params(&self, model: &ModelFile, ref_info: &RefInfo) -> Result<RefParams> {
+        let mut params = RefParams::default();
+
+        for param in &ref_info.params {
+            let name = param.name.to_lowercase();
+            let is_duplicate = match name.as_str() {
+                "filter" => params.filter.is_some(),
+                "limit" => params.limit.is_some(),
+                "sample" => params.sample.is_some(),
+                _ => false,
+            };
+            if is_duplicate {
+                return Err(invalid_param(
+                    model,
+                    param,
+                    format!("'{}' is given more than once", name),
+                ));
+            }
+
+            match name.as_str() {
+                "filter" => {
+                    // Any expression that isn't a plain non-boolean literal can be a condition
+                    if matches!(
+                        param.literal,
+                        Some(LiteralKind::String | LiteralKind::Number | LiteralKind::Null)
+                    ) {
+                        return Err(invalid_param(
+                            model,
+                            param,
+                            format!(
+                                "'filter' must be a boolean condition, found '{}'",
+                                param.value
+                            ),
+                        ));
+                    }
+                    params.filter = Some(param.value.clone());
+                }
+                "limit" => {
+                    let limit = param
+                        .value
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|_| param.literal == Some(LiteralKind::Number));
+                    match limit {
+                        Some(limit) => params.limit = Some(limit),
+                        None => {
+                            return Err(invalid_param(
+                                model,
+                                param,
+                                format!(
+                                    "'limit' must be a non-negative integer, found '{}'",
+                                    param.value
+                                ),
+                            ))
+                        }
+                    }
+                }
+                "sample" => {
+                    let sample = param.value.parse::<f64>().ok().filter(|f| {
+                        param.literal == Some(LiteralKind::Number) && *f > 0.0 && *f <= 1.0
+                    });
+                    match sample {
+                        Some(_) if !self.capabilities.supports_tablesample => {
+                            return Err(invalid_param(
+                                model,
+                                param,
+                                format!("'sample' is not supported by the {} backend", self.dialect.name()),
+                            ))
+                        }
+                        Some(sample) => params.sample = Some(sample),
+                        None => {
+                            return Err(invalid_param(
+                                model,
+                                param,
+                                format!(
+                                    "'sample' must be a fraction greater than 0 and at most 1, found '{}'",
+                                    param.value
+                                ),
+                            ))
+                        }
+                    }
+                }
+                _ if PLANNED_REF_PARAMS.contains(&name.as_str()) => {
+                    let (line, col) = text_range_to_line_col(&model.content, param.range);
+                    return Err(CliError::NamedParametersNotSupported {
+                        model: model.name.clone(),
+                        param: name,
+                        file: model.path.clone(),
+                        line,
+                        col,
+                        snippet: extract_snippet(&model.content, param.range, 0),
+                    }
+                    .into());
+                }
+                _ => {
+                    return Err(invalid_param(
+                        model,
+                        param,
+                        format!("unknown parameter '{}'", param.name),
+                    ));
+                }
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// `(SELECT * FROM schema.model TABLESAMPLE ... WHERE ... LIMIT n) AS model`
+    fn parameterized_ref_sql(
+        &self,
+        ref_info: &RefInfo,
+        params: &RefParams,
+        schema: &str,
+    ) -> String {
+        let mut sql = format!("(SELECT * FROM {}.{}", schema, ref_info.model_name);
+        if let Some(sample) = params.sample {
+            sql.push(' ');
+            sql.push_str(&self.dialect.tablesample_clause(sample));
+        }
+        if let Some(ref filter) = params.filter {
+            sql.push_str(&format!(" WHERE {}", filter));
+        }
+        if let Some(limit) = params.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        sql.push(')');
+
+        // A derived table needs an alias; keep the model name usable as a qualifier
+        if ref_info.alias.is_none() {
+            sql.push_str(&format!(" AS {}", ref_info.model_name));
+        }
+        sql
+    }
+}
+
+fn invalid_param(model: &ModelFile, param: &RefParam, message: String) -> anyhow::Error {
+    let (line, col) = text_range_to_line_col(&model.content, param.range);
+    CliError::InvalidRefParameter {
+        model: model.name.clone(),
+        message,
+        file: model.path.clone(),
+        line,
+        col,
+        snippet: extract_snippet(&model.content, param.range, 0),
+    }
+    .into()
 }
 
 #[cfg(test)]
@@ -111,8 +493,13 @@ GROUP BY user_id
                 model_name: "raw_events".to_string(),
                 has_named_params: false,
                 range: TextRange::default(),
+                params: vec![],
+                alias: None,
             }],
+            sources: vec![],
             parse_errors: Vec::new(),
+            annotations: Default::default(),
+            annotation_errors: Vec::new(),
         };
 
         let config = make_test_config();
@@ -144,14 +531,21 @@ JOIN smelt.ref('model_b') b ON a.id = b.id
                     model_name: "model_a".to_string(),
                     has_named_params: false,
                     range: TextRange::default(),
+                    params: vec![],
+                    alias: None,
                 },
                 RefInfo {
                     model_name: "model_b".to_string(),
                     has_named_params: false,
                     range: TextRange::default(),
+                    params: vec![],
+                    alias: None,
                 },
             ],
+            sources: vec![],
             parse_errors: Vec::new(),
+            annotations: Default::default(),
+            annotation_errors: Vec::new(),
         };
 
         let config = make_test_config();
@@ -164,39 +558,197 @@ JOIN smelt.ref('model_b') b ON a.id = b.id
         assert!(!compiled.sql.contains("smelt.ref"));
     }
 
+    fn compile_sql(sql: &str) -> Result<CompiledModel> {
+        let model = ModelFile::from_sql(
+            "filtered".to_string(),
+            "models/filtered.sql".into(),
+            sql.to_string(),
+        );
+        SqlCompiler::new(make_test_config()).compile(&model, "main")
+    }
+
     #[test]
-    fn test_named_params_error() {
-        use crate::discovery::RefInfo;
-        use rowan::TextRange;
+    fn test_ref_filter_and_limit() {
+        let compiled = compile_sql(
+            "SELECT user_id\nFROM smelt.ref('raw_events', filter => event_type = 'page_view', limit => 10)\n",
+        )
+        .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT user_id\nFROM (SELECT * FROM main.raw_events WHERE event_type = 'page_view' LIMIT 10) AS raw_events\n"
+        );
+
+        // An existing alias is kept, plain refs are still replaced
+        let compiled = compile_sql(
+            "SELECT e.user_id FROM smelt.ref('raw_events', filter => e_date > '2024-01-01') e \
+             JOIN smelt.ref('users') u ON e.user_id = u.user_id",
+        )
+        .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT e.user_id FROM (SELECT * FROM main.raw_events WHERE e_date > '2024-01-01') e \
+             JOIN main.users u ON e.user_id = u.user_id"
+        );
+    }
+
+    #[test]
+    fn test_ref_filter_compiles_nested_calls() {
+        let compiled = compile_sql(
+            "SELECT * FROM smelt.ref('events', filter => user_id IN (SELECT id FROM smelt.ref('users')))",
+        )
+        .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT * FROM (SELECT * FROM main.events WHERE user_id IN (SELECT id FROM main.users)) AS events"
+        );
+
+        let compiled = compile_sql(
+            "SELECT * FROM smelt.ref('events', filter => country IN (SELECT code FROM smelt.source('raw', 'countries')))",
+        )
+        .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT * FROM (SELECT * FROM main.events WHERE country IN (SELECT code FROM raw.countries)) AS events"
+        );
+
+        // Nested calls take their own parameters, and are validated too
+        let compiled = compile_sql(
+            "SELECT * FROM smelt.ref('events', filter => user_id IN \
+             (SELECT id FROM smelt.ref('users', filter => active, limit => 5)))",
+        )
+        .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT * FROM (SELECT * FROM main.events WHERE user_id IN \
+             (SELECT id FROM (SELECT * FROM main.users WHERE active LIMIT 5) AS users)) AS events"
+        );
+        let err_msg = compile_sql(
+            "SELECT * FROM smelt.ref('events', filter => user_id IN (SELECT id FROM smelt.ref('users', limit => -1)))",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err_msg.contains("'limit' must be a non-negative integer"), "{}", err_msg);
+    }
+
+    #[test]
+    fn test_ref_sample_uses_dialect() {
+        let sql = "SELECT * FROM smelt.ref('raw_events', sample => 0.1)";
+        let compiled = compile_sql(sql).unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT * FROM (SELECT * FROM main.raw_events TABLESAMPLE bernoulli(10%)) AS raw_events"
+        );
+
+        let model = ModelFile::from_sql(
+            "sampled".to_string(),
+            "models/sampled.sql".into(),
+            sql.to_string(),
+        );
+        let compiled = SqlCompiler::new(make_test_config())
+            .with_dialect(SqlDialect::SparkSQL, BackendCapabilities::spark())
+            .compile(&model, "main")
+            .unwrap();
+        assert!(compiled
+            .sql
+            .contains("main.raw_events TABLESAMPLE (10 PERCENT)"));
+
+        let mut capabilities = BackendCapabilities::postgresql();
+        capabilities.supports_tablesample = false;
+        let err = SqlCompiler::new(make_test_config())
+            .with_dialect(SqlDialect::PostgreSQL, capabilities)
+            .compile(&model, "main")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not supported by the PostgreSQL backend"));
+    }
+
+    #[test]
+    fn test_invalid_ref_params() {
+        let cases = [
+            ("limit => -1", "'limit' must be a non-negative integer"),
+            ("limit => 'ten'", "'limit' must be a non-negative integer"),
+            ("sample => 1.5", "'sample' must be a fraction"),
+            ("filter => 'yes'", "'filter' must be a boolean condition"),
+            ("limit => 1, limit => 2", "'limit' is given more than once"),
+            ("fliter => x > 1", "unknown parameter 'fliter'"),
+        ];
+        for (params, expected) in cases {
+            let sql = format!("SELECT user_id\nFROM smelt.ref('raw_events', {})\n", params);
+            let err_msg = compile_sql(&sql).unwrap_err().to_string();
+            assert!(err_msg.contains(expected), "{}: {}", params, err_msg);
+            assert!(err_msg.contains("models/filtered.sql:1:"), "{}", err_msg);
+        }
+    }
 
+    #[test]
+    fn test_named_params_error() {
         let sql = r#"
 SELECT user_id
-FROM smelt.ref('raw_events', filter => event_type = 'page_view')
+FROM smelt.ref('raw_events', as_of => '2024-01-01')
 "#;
 
-        let model = ModelFile {
-            name: "filtered".to_string(),
-            path: "models/filtered.sql".into(),
-            content: sql.to_string(),
-            refs: vec![RefInfo {
-                model_name: "raw_events".to_string(),
-                has_named_params: true,
-                range: TextRange::new(0u32.into(), 10u32.into()),
-            }],
-            parse_errors: Vec::new(),
-        };
-
-        let config = make_test_config();
-        let compiler = SqlCompiler::new(config);
-
-        let result = compiler.compile(&model, "main");
+        let result = compile_sql(sql);
         assert!(result.is_err());
 
         let err_msg = result.unwrap_err().to_string();
-        assert!(err_msg.contains("named parameters"));
+        assert!(err_msg.contains("named parameter 'as_of'"));
         assert!(err_msg.contains("not yet supported"));
     }
 
+    #[test]
+    fn test_ref_rewriting_uses_syntax_tree() {
+        let sql = "-- reads smelt.ref('users')\n\
+                   SELECT 'smelt.ref(''users'')' AS label, u.id\n\
+                   FROM SMELT.REF( \"users\" ) u\n\
+                   JOIN smelt.ref(\n  'events'\n) e ON u.id = e.user_id /* smelt.ref('x') */";
+        let compiled = compile_sql(sql).unwrap();
+        assert_eq!(
+            compiled.sql,
+            "-- reads smelt.ref('users')\n\
+             SELECT 'smelt.ref(''users'')' AS label, u.id\n\
+             FROM main.users u\n\
+             JOIN main.events e ON u.id = e.user_id /* smelt.ref('x') */"
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let sql = "SELECT u.id, bad_column\nFROM smelt.ref('users') u\nJOIN smelt.ref('events', limit => 5) e ON u.id = e.id";
+        let compiled = compile_sql(sql).unwrap();
+        let map = &compiled.source_map;
+        let offset = |text: &str, needle: &str| TextSize::from(text.find(needle).unwrap() as u32);
+
+        // Text before, between and after refs maps back to the same text
+        for needle in ["bad_column", " u\nJOIN", "e ON u.id"] {
+            assert_eq!(
+                map.source_offset(offset(&compiled.sql, needle)),
+                offset(sql, needle)
+            );
+        }
+
+        // Anything inside a replacement maps to the ref call it came from
+        let compiled_ref = offset(&compiled.sql, "main.users");
+        let source_ref = offset(sql, "smelt.ref('users')");
+        assert_eq!(
+            map.source_offset(compiled_ref + TextSize::from(5)),
+            source_ref
+        );
+
+        let subquery = TextRange::at(offset(&compiled.sql, "(SELECT"), TextSize::from(7));
+        assert_eq!(
+            &sql[map.source_range(subquery)],
+            "smelt.ref('events', limit => 5)"
+        );
+
+        // Without refs the map is the identity
+        let compiled = compile_sql("SELECT 1").unwrap();
+        assert_eq!(
+            compiled.source_map.source_offset(TextSize::from(7)),
+            TextSize::from(7)
+        );
+    }
+
     #[test]
     fn test_materialization_from_config() {
         let model = ModelFile {
@@ -204,14 +756,18 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
             path: "models/test_model.sql".into(),
             content: "SELECT 1".to_string(),
             refs: vec![],
+            sources: vec![],
             parse_errors: Vec::new(),
+            annotations: Default::default(),
+            annotation_errors: Vec::new(),
         };
 
         let mut config = make_test_config();
         config.models.insert(
             "test_model".to_string(),
             ModelConfig {
-                materialization: Materialization::Table,
+                materialization: Some(Materialization::Table),
+                ..Default::default()
             },
         );
 
@@ -223,4 +779,84 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
             Materialization::Table
         ));
     }
+
+    #[test]
+    fn test_source_compiles_to_qualified_name() {
+        let compiled = compile_sql(
+            "SELECT e.event_id\nFROM smelt.source('raw', 'events') e\nJOIN smelt.ref('users') u ON e.user_id = u.user_id",
+        )
+        .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT e.event_id\nFROM raw.events e\nJOIN main.users u ON e.user_id = u.user_id"
+        );
+
+        // Names that aren't plain lowercase identifiers are quoted for the target
+        let sql = "SELECT * FROM smelt.source('Raw', 'page views')";
+        let model = ModelFile::from_sql(
+            "views".to_string(),
+            "models/views.sql".into(),
+            sql.to_string(),
+        );
+        let duckdb = SqlCompiler::new(make_test_config())
+            .compile(&model, "main")
+            .unwrap();
+        assert_eq!(duckdb.sql, "SELECT * FROM \"Raw\".\"page views\"");
+        let spark = SqlCompiler::new(make_test_config())
+            .with_dialect(SqlDialect::SparkSQL, BackendCapabilities::spark())
+            .compile(&model, "main")
+            .unwrap();
+        assert_eq!(spark.sql, "SELECT * FROM `Raw`.`page views`");
+
+        let err = compile_sql("SELECT * FROM smelt.source('events')").unwrap_err();
+        assert!(err.to_string().contains("invalid smelt.source() call"));
+    }
+
+    #[test]
+    fn test_compile_incremental_filters_time_column_upstream() {
+        let compiler = SqlCompiler::new(make_test_config());
+        let plan = IncrementalPlan {
+            time_column: "day".to_string(),
+            upstream: Upstream::Model("events".to_string()),
+            upstream_column: "day".to_string(),
+        };
+
+        // Only the upstream of the time column is filtered, alongside any existing filter
+        let model = ModelFile::from_sql(
+            "daily".to_string(),
+            "models/daily.sql".into(),
+            "SELECT e.day, u.name FROM smelt.ref('events', filter => kind = 'click') e \
+             JOIN smelt.ref('users') u ON e.user_id = u.id"
+                .to_string(),
+        );
+        let compiled = compiler
+            .compile_incremental(&model, "main", &plan, "2024-01-02")
+            .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT e.day, u.name FROM (SELECT * FROM main.events WHERE (kind = 'click') AND day >= '2024-01-02') e \
+             JOIN main.users u ON e.user_id = u.id"
+        );
+
+        let plan = IncrementalPlan {
+            time_column: "day".to_string(),
+            upstream: Upstream::Source {
+                schema: "raw".to_string(),
+                table: "events".to_string(),
+            },
+            upstream_column: "event_day".to_string(),
+        };
+        let model = ModelFile::from_sql(
+            "events".to_string(),
+            "models/events.sql".into(),
+            "SELECT event_day AS day FROM smelt.source('raw', 'events')".to_string(),
+        );
+        let compiled = compiler
+            .compile_incremental(&model, "main", &plan, "it's")
+            .unwrap();
+        assert_eq!(
+            compiled.sql,
+            "SELECT event_day AS day FROM (SELECT * FROM raw.events WHERE event_day >= 'it''s') AS events"
+        );
+    }
 }
@@ -1,13 +1,23 @@
 use crate::errors::CliError;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use smelt_backend::{BackendCapabilities, SnapshotStrategy, SqlDialect};
+use smelt_db::ProjectSettings;
+use smelt_parser::annotations::{self, ModelAnnotations};
+use smelt_parser::LexOptions;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Materialization {
     Table,
     View,
+    /// A table that runs extend with rows past its `incremental.time_column`
+    Incremental,
+    /// A table that runs upsert into, matching rows on its `unique_key`
+    Merge,
+    /// A table keeping every version of the rows matched on its `unique_key`
+    Snapshot,
 }
 
 impl<'de> Deserialize<'de> for Materialization {
@@ -19,8 +29,11 @@ impl<'de> Deserialize<'de> for Materialization {
         match s.to_lowercase().as_str() {
             "table" => Ok(Materialization::Table),
             "view" => Ok(Materialization::View),
+            "incremental" => Ok(Materialization::Incremental),
+            "merge" => Ok(Materialization::Merge),
+            "snapshot" => Ok(Materialization::Snapshot),
             _ => Err(serde::de::Error::custom(format!(
-                "Invalid materialization type: {}. Must be 'table' or 'view'",
+                "Invalid materialization type: {}. Must be 'table', 'view', 'incremental', 'merge' or 'snapshot'",
                 s
             ))),
         }
@@ -35,6 +48,45 @@ impl Serialize for Materialization {
         match self {
             Materialization::Table => serializer.serialize_str("table"),
             Materialization::View => serializer.serialize_str("view"),
+            Materialization::Incremental => serializer.serialize_str("incremental"),
+            Materialization::Merge => serializer.serialize_str("merge"),
+            Materialization::Snapshot => serializer.serialize_str("snapshot"),
+        }
+    }
+}
+
+impl From<annotations::Materialization> for Materialization {
+    fn from(materialization: annotations::Materialization) -> Self {
+        match materialization {
+            annotations::Materialization::Table => Materialization::Table,
+            annotations::Materialization::View => Materialization::View,
+            annotations::Materialization::Incremental => Materialization::Incremental,
+            annotations::Materialization::Merge => Materialization::Merge,
+            annotations::Materialization::Snapshot => Materialization::Snapshot,
+        }
+    }
+}
+
+impl From<Materialization> for annotations::Materialization {
+    fn from(materialization: Materialization) -> Self {
+        match materialization {
+            Materialization::Table => annotations::Materialization::Table,
+            Materialization::View => annotations::Materialization::View,
+            Materialization::Incremental => annotations::Materialization::Incremental,
+            Materialization::Merge => annotations::Materialization::Merge,
+            Materialization::Snapshot => annotations::Materialization::Snapshot,
+        }
+    }
+}
+
+impl From<Materialization> for smelt_backend::Materialization {
+    fn from(materialization: Materialization) -> Self {
+        match materialization {
+            Materialization::Table => smelt_backend::Materialization::Table,
+            Materialization::View => smelt_backend::Materialization::View,
+            Materialization::Incremental => smelt_backend::Materialization::Incremental,
+            Materialization::Merge => smelt_backend::Materialization::Merge,
+            Materialization::Snapshot => smelt_backend::Materialization::Snapshot,
         }
     }
 }
@@ -84,6 +136,11 @@ impl Target {
             _ => BackendType::DuckDB, // Default to DuckDB for backward compatibility
         }
     }
+
+    /// Lexing rules of the target's SQL dialect
+    pub fn lex_options(&self) -> LexOptions {
+        smelt_db::project::lex_options(&self.target_type)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -92,9 +149,109 @@ pub enum BackendType {
     Spark,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+impl BackendType {
+    /// SQL dialect of the backend, for compiling without a connection
+    pub fn dialect(&self) -> SqlDialect {
+        match self {
+            BackendType::DuckDB => SqlDialect::DuckDB,
+            BackendType::Spark => SqlDialect::SparkSQL,
+        }
+    }
+
+    pub fn capabilities(&self) -> BackendCapabilities {
+        match self {
+            BackendType::DuckDB => BackendCapabilities::duckdb(),
+            BackendType::Spark => BackendCapabilities::spark(),
+        }
+    }
+}
+
+/// Per-model settings from `models:` in smelt.yml. The same settings can be
+/// given in the model file with `-- @key: value` annotations, which take
+/// precedence (see `Config::model_config`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ModelConfig {
-    pub materialization: Materialization,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub materialization: Option<Materialization>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incremental: Option<IncrementalConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partition_by: Vec<String>,
+    /// Columns a merge or snapshot model matches rows on: `unique_key: id`
+    /// or `unique_key: [region, sku]`
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub unique_key: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl ModelConfig {
+    /// How a snapshot model detects changed rows: exactly one of
+    /// `snapshot.updated_at` and `snapshot.check_columns` must be set
+    pub fn snapshot_strategy(&self) -> Result<SnapshotStrategy, String> {
+        let snapshot = self.snapshot.clone().unwrap_or_default();
+        match (snapshot.updated_at, snapshot.check_columns.is_empty()) {
+            (Some(updated_at), true) => Ok(SnapshotStrategy::Timestamp { updated_at }),
+            (None, false) => Ok(SnapshotStrategy::Check {
+                columns: snapshot.check_columns,
+            }),
+            (None, true) => Err("has no way to detect changes (add \
+                 `-- @snapshot.updated_at: <column>` or `-- @snapshot.check_columns: <columns>`)"
+                .to_string()),
+            (Some(_), false) => Err(
+                "sets both `snapshot.updated_at` and `snapshot.check_columns`".to_string(),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IncrementalConfig {
+    #[serde(default = "default_incremental_enabled")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_column: Option<String>,
+}
+
+/// Columns a snapshot model detects changed rows by: `updated_at: col` or
+/// `check_columns: [col1, col2]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SnapshotConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub check_columns: Vec<String>,
+}
+
+fn default_incremental_enabled() -> bool {
+    true
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(column) => vec![column],
+        OneOrMany::Many(columns) => columns,
+    })
 }
 
 impl Config {
@@ -116,11 +273,117 @@ impl Config {
         })
     }
 
-    pub fn get_materialization(&self, model_name: &str) -> Materialization {
-        self.models
-            .get(model_name)
-            .map(|m| m.materialization.clone())
-            .unwrap_or_else(|| self.default_materialization.clone())
+    /// Analysis settings for a target. Without one, the `dev` target (or the
+    /// only target) is used, as in the language server.
+    pub fn project_settings(&self, target: Option<&str>) -> ProjectSettings {
+        let target = match target {
+            Some(name) => self.targets.get(name),
+            None => self.targets.get("dev").or_else(|| {
+                let mut targets = self.targets.values();
+                targets.next().filter(|_| targets.next().is_none())
+            }),
+        };
+        ProjectSettings {
+            lex_options: target.map(Target::lex_options).unwrap_or_default(),
+            default_materialization: Some(self.default_materialization.into()),
+            models: self
+                .models
+                .iter()
+                .map(|(name, config)| {
+                    let settings = ModelAnnotations {
+                        materialize: config.materialization.map(Into::into),
+                        incremental: config.incremental.as_ref().map(|i| {
+                            annotations::IncrementalAnnotation {
+                                enabled: i.enabled,
+                                time_column: i.time_column.clone(),
+                            }
+                        }),
+                        ..Default::default()
+                    };
+                    (name.clone(), settings)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get_materialization(
+        &self,
+        model_name: &str,
+        annotations: &ModelAnnotations,
+    ) -> Materialization {
+        let config = self.model_config(model_name, annotations);
+        let incremental = config.incremental.as_ref().map(|i| i.enabled);
+
+        match config.materialization {
+            // Enabling incremental processing makes a table incremental
+            Some(Materialization::Table) | None if incremental == Some(true) => {
+                Materialization::Incremental
+            }
+            Some(Materialization::Incremental) if incremental == Some(false) => {
+                Materialization::Table
+            }
+            Some(materialization) => materialization,
+            None => self.default_materialization,
+        }
+    }
+
+    /// Unique key and change detection of a snapshot model
+    pub fn snapshot_settings(
+        &self,
+        model_name: &str,
+        annotations: &ModelAnnotations,
+    ) -> Result<(Vec<String>, SnapshotStrategy)> {
+        let config = self.model_config(model_name, annotations);
+        if config.unique_key.is_empty() {
+            anyhow::bail!(
+                "Model '{}' is materialized as snapshot but has no unique key (add `-- @unique_key: <column>`)",
+                model_name
+            );
+        }
+        let strategy = config.snapshot_strategy().map_err(|reason| {
+            anyhow::anyhow!("Model '{}' is materialized as snapshot but {}", model_name, reason)
+        })?;
+        Ok((config.unique_key, strategy))
+    }
+
+    /// Effective settings for a model: smelt.yml `models:` entries, overridden
+    /// field by field by the model's header annotations
+    pub fn model_config(&self, model_name: &str, annotations: &ModelAnnotations) -> ModelConfig {
+        let mut config = self.models.get(model_name).cloned().unwrap_or_default();
+
+        if let Some(materialize) = annotations.materialize {
+            config.materialization = Some(materialize.into());
+        }
+        if let Some(ref incremental) = annotations.incremental {
+            let time_column = incremental.time_column.clone().or_else(|| {
+                config
+                    .incremental
+                    .as_ref()
+                    .and_then(|i| i.time_column.clone())
+            });
+            config.incremental = Some(IncrementalConfig {
+                enabled: incremental.enabled,
+                time_column,
+            });
+        }
+        if !annotations.partition_by.is_empty() {
+            config.partition_by = annotations.partition_by.clone();
+        }
+        if !annotations.unique_key.is_empty() {
+            config.unique_key = annotations.unique_key.clone();
+        }
+        if let Some(ref snapshot) = annotations.snapshot {
+            // Choosing a strategy in the file replaces the one from smelt.yml
+            config.snapshot = Some(SnapshotConfig {
+                updated_at: snapshot.updated_at.clone(),
+                check_columns: snapshot.check_columns.clone(),
+            });
+        }
+        if !annotations.tags.is_empty() {
+            config.tags = annotations.tags.clone();
+        }
+
+        config
     }
 }
 
@@ -234,10 +497,135 @@ models:
         assert_eq!(config.name, "test_project");
         assert_eq!(
             config.models.get("model1").unwrap().materialization,
-            Materialization::Table
+            Some(Materialization::Table)
         );
         assert_eq!(
             config.models.get("model2").unwrap().materialization,
+            Some(Materialization::View)
+        );
+    }
+
+    #[test]
+    fn test_model_config_merges_annotations() {
+        let yaml = r#"
+name: test_project
+version: 1
+targets:
+  dev:
+    type: duckdb
+    database: test.duckdb
+    schema: main
+models:
+  orders:
+    materialization: view
+    incremental:
+      time_column: created_at
+    partition_by: [region]
+  customers:
+    materialization: merge
+    unique_key: customer_id
+  prices:
+    unique_key: [region, sku]
+  customer_history:
+    materialization: snapshot
+    unique_key: customer_id
+    snapshot:
+      check_columns: status
+"#;
+
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let none = ModelAnnotations::default();
+        let orders = config.model_config("orders", &none);
+        assert_eq!(
+            orders.incremental,
+            Some(IncrementalConfig {
+                enabled: true,
+                time_column: Some("created_at".to_string()),
+            })
+        );
+        assert_eq!(orders.partition_by, vec!["region"]);
+
+        // Annotations win over smelt.yml, field by field
+        let file = smelt_parser::File::cast(
+            smelt_parser::parse("-- @materialize: table\n-- @incremental: disabled\nSELECT 1")
+                .syntax(),
+        )
+        .unwrap();
+        let (annotations, _) = smelt_parser::parse_annotations(&file);
+        let orders = config.model_config("orders", &annotations);
+        assert_eq!(orders.materialization, Some(Materialization::Table));
+        assert_eq!(
+            orders.incremental,
+            Some(IncrementalConfig {
+                enabled: false,
+                time_column: Some("created_at".to_string()),
+            })
+        );
+        assert_eq!(orders.partition_by, vec!["region"]);
+        assert_eq!(
+            config.get_materialization("orders", &annotations),
+            Materialization::Table
+        );
+
+        // A unique key is one column or a list of them
+        let customers = config.model_config("customers", &none);
+        assert_eq!(customers.materialization, Some(Materialization::Merge));
+        assert_eq!(customers.unique_key, vec!["customer_id"]);
+        assert_eq!(
+            config.model_config("prices", &none).unique_key,
+            vec!["region", "sku"]
+        );
+
+        // Snapshots detect changes by one of their settings
+        let history = config.model_config("customer_history", &none);
+        assert_eq!(history.materialization, Some(Materialization::Snapshot));
+        assert_eq!(
+            history.snapshot_strategy(),
+            Ok(SnapshotStrategy::Check {
+                columns: vec!["status".to_string()],
+            })
+        );
+        let file = smelt_parser::File::cast(
+            smelt_parser::parse("-- @snapshot.updated_at: changed_at\nSELECT 1").syntax(),
+        )
+        .unwrap();
+        let (annotations, _) = smelt_parser::parse_annotations(&file);
+        assert_eq!(
+            config
+                .model_config("customer_history", &annotations)
+                .snapshot_strategy(),
+            Ok(SnapshotStrategy::Timestamp {
+                updated_at: "changed_at".to_string(),
+            })
+        );
+        assert!(customers.snapshot_strategy().is_err());
+        assert_eq!(
+            config
+                .snapshot_settings("customer_history", &none)
+                .unwrap()
+                .0,
+            vec!["customer_id"]
+        );
+        assert!(config.snapshot_settings("prices", &none).is_err());
+
+        // Unconfigured models fall back to the project default
+        assert_eq!(
+            config.get_materialization("other", &none),
+            Materialization::View
+        );
+
+        // A time column makes a table, or a model with no materialization, incremental
+        let file = smelt_parser::File::cast(
+            smelt_parser::parse("-- @incremental.time_column: day\nSELECT 1").syntax(),
+        )
+        .unwrap();
+        let (annotations, _) = smelt_parser::parse_annotations(&file);
+        assert_eq!(
+            config.get_materialization("other", &annotations),
+            Materialization::Incremental
+        );
+        assert_eq!(
+            config.get_materialization("orders", &annotations),
             Materialization::View
         );
     }
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Context, Result};
-use rowan::TextRange;
-use smelt_parser::File as AstFile;
+use rowan::{TextRange, TextSize};
+use smelt_parser::{AnnotationError, File as AstFile, LexOptions, LiteralKind, ModelAnnotations};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
@@ -10,7 +10,10 @@ pub struct ModelFile {
     pub path: PathBuf,
     pub content: String,
     pub refs: Vec<RefInfo>,
+    pub sources: Vec<SourceRefInfo>,
     pub parse_errors: Vec<smelt_parser::ParseError>,
+    pub annotations: ModelAnnotations,
+    pub annotation_errors: Vec<AnnotationError>,
 }
 
 #[derive(Debug, Clone)]
@@ -18,11 +21,45 @@ pub struct RefInfo {
     pub model_name: String,
     pub has_named_params: bool,
     pub range: TextRange,
+    pub params: Vec<RefParam>,
+    /// Alias of the table reference (`smelt.ref('x') AS a`)
+    pub alias: Option<String>,
+}
+
+/// A `name => value` argument of a ref call
+#[derive(Debug, Clone)]
+pub struct RefParam {
+    pub name: String,
+    /// The value expression as written
+    pub value: String,
+    /// Kind of the value if it is a plain literal
+    pub literal: Option<LiteralKind>,
+    pub range: TextRange,
+    /// Range of `value` in the model
+    pub value_range: TextRange,
+}
+
+/// A well-formed `smelt.source('schema', 'table')` call
+#[derive(Debug, Clone)]
+pub struct SourceRefInfo {
+    pub schema: String,
+    pub table: String,
+    pub range: TextRange,
+    /// Alias of the table reference (`smelt.source('raw', 'x') AS a`)
+    pub alias: Option<String>,
+}
+
+impl SourceRefInfo {
+    /// "schema.table", as declared in sources.yml
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}", self.schema, self.table)
+    }
 }
 
 pub struct ModelDiscovery {
     project_root: PathBuf,
     model_paths: Vec<String>,
+    lex_options: LexOptions,
 }
 
 impl ModelDiscovery {
@@ -30,9 +67,16 @@ impl ModelDiscovery {
         Self {
             project_root,
             model_paths,
+            lex_options: LexOptions::default(),
         }
     }
 
+    /// Lex models with a target dialect's rules (defaults to standard SQL)
+    pub fn with_lex_options(mut self, lex_options: LexOptions) -> Self {
+        self.lex_options = lex_options;
+        self
+    }
+
     pub fn discover_models(&self) -> Result<Vec<ModelFile>> {
         let mut models = Vec::new();
 
@@ -80,37 +124,115 @@ impl ModelDiscovery {
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Failed to read model file: {:?}", path))?;
 
+        Ok(ModelFile::from_sql_with(
+            name,
+            path.to_path_buf(),
+            content,
+            self.lex_options,
+        ))
+    }
+}
+
+/// Forward-slash path relative to the project root, as written to manifests
+/// and matched by `path:` selectors
+pub(crate) fn relative_path(project_dir: &Path, path: &Path) -> String {
+    let relative: PathBuf = path
+        .strip_prefix(project_dir)
+        .unwrap_or(path)
+        .components()
+        .collect();
+    relative
+        .iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl ModelFile {
+    /// Parse a model's SQL and extract its refs, sources and header annotations
+    pub fn from_sql(name: String, path: PathBuf, content: String) -> Self {
+        Self::from_sql_with(name, path, content, LexOptions::default())
+    }
+
+    /// Like [`ModelFile::from_sql`], lexing with a target dialect's rules
+    pub fn from_sql_with(
+        name: String,
+        path: PathBuf,
+        content: String,
+        lex_options: LexOptions,
+    ) -> Self {
         // Parse using smelt-parser
-        let parse = smelt_parser::parse(&content);
+        let parse = smelt_parser::parse_with(&content, lex_options);
 
-        // Extract refs using AST
-        let refs = if let Some(file) = AstFile::cast(parse.syntax()) {
-            extract_refs(&file)
-        } else {
-            Vec::new()
-        };
+        // Extract refs, sources and header annotations using AST
+        let (refs, sources, (annotations, annotation_errors)) =
+            if let Some(file) = AstFile::cast(parse.syntax()) {
+                (
+                    extract_refs(&file),
+                    extract_sources(&file),
+                    smelt_parser::parse_annotations(&file),
+                )
+            } else {
+                (Vec::new(), Vec::new(), Default::default())
+            };
 
-        Ok(ModelFile {
+        ModelFile {
             name,
-            path: path.to_path_buf(),
+            path,
             content,
             refs,
+            sources,
             parse_errors: parse.errors,
-        })
+            annotations,
+            annotation_errors,
+        }
     }
 }
 
-fn extract_refs(file: &AstFile) -> Vec<RefInfo> {
+pub(crate) fn extract_refs(file: &AstFile) -> Vec<RefInfo> {
     file.refs()
         .filter_map(|ref_call| {
             let model_name = ref_call.model_name()?;
-            let has_params = ref_call.named_params().count() > 0;
+            let params: Vec<RefParam> = ref_call
+                .named_params()
+                .filter_map(|param| {
+                    let value = param.value()?;
+                    let text = value.text();
+                    let start = value.range().start()
+                        + TextSize::of(&text[..text.len() - text.trim_start().len()]);
+                    Some(RefParam {
+                        name: param.name()?,
+                        value: text.trim().to_string(),
+                        literal: value.as_literal().map(|l| l.kind()),
+                        range: param.range(),
+                        value_range: TextRange::at(start, TextSize::of(text.trim())),
+                    })
+                })
+                .collect();
             let range = ref_call.range();
 
             Some(RefInfo {
                 model_name,
-                has_named_params: has_params,
+                has_named_params: !params.is_empty(),
                 range,
+                params,
+                alias: ref_call.table_ref().and_then(|t| t.alias()),
+            })
+        })
+        .collect()
+}
+
+/// Extract well-formed smelt.source() calls. Malformed calls are reported by
+/// the compiler.
+pub(crate) fn extract_sources(file: &AstFile) -> Vec<SourceRefInfo> {
+    file.sources()
+        .filter(|source| source.is_well_formed())
+        .filter_map(|source| {
+            Some(SourceRefInfo {
+                schema: source.schema_name()?,
+                table: source.table_name()?,
+                range: source.range(),
+                alias: source.table_ref().and_then(|t| t.alias()),
             })
         })
         .collect()
@@ -153,6 +275,35 @@ FROM smelt.ref('raw_events', filter => event_type = 'page_view')
         assert_eq!(refs.len(), 1);
         assert_eq!(refs[0].model_name, "raw_events");
         assert!(refs[0].has_named_params);
+
+        let param = &refs[0].params[0];
+        assert_eq!(param.name, "filter");
+        assert_eq!(param.value, "event_type = 'page_view'");
+        assert_eq!(param.literal, None);
+        assert_eq!(refs[0].alias, None);
+    }
+
+    #[test]
+    fn test_extract_ref_params_and_alias() {
+        let sql = "SELECT e.user_id FROM smelt.ref('raw_events', limit => 100, sample => 0.5) AS e";
+
+        let parse = smelt_parser::parse(sql);
+        let file = AstFile::cast(parse.syntax()).unwrap();
+        let refs = extract_refs(&file);
+
+        let params: Vec<_> = refs[0]
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), p.value.as_str(), p.literal))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("limit", "100", Some(LiteralKind::Number)),
+                ("sample", "0.5", Some(LiteralKind::Number)),
+            ]
+        );
+        assert_eq!(refs[0].alias.as_deref(), Some("e"));
     }
 
     #[test]
@@ -174,4 +325,39 @@ WHERE a.id = b.id
         assert_eq!(refs[0].model_name, "model_a");
         assert_eq!(refs[1].model_name, "model_b");
     }
+
+    #[test]
+    fn test_nested_refs() {
+        let sql = r#"
+SELECT user_id FROM smelt.ref('users')
+UNION ALL
+SELECT user_id
+FROM (SELECT user_id FROM smelt.ref('events')) e
+WHERE EXISTS (SELECT 1 FROM smelt.ref('sessions') s WHERE s.user_id = e.user_id)
+"#;
+
+        let parse = smelt_parser::parse(sql);
+        assert!(parse.errors.is_empty());
+        let file = AstFile::cast(parse.syntax()).unwrap();
+        let refs = extract_refs(&file);
+
+        let names: Vec<_> = refs.iter().map(|r| r.model_name.as_str()).collect();
+        assert_eq!(names, vec!["users", "events", "sessions"]);
+    }
+
+    #[test]
+    fn test_extract_sources() {
+        let sql =
+            "SELECT * FROM smelt.source('raw', 'events') e JOIN smelt.source('raw') u ON TRUE";
+
+        let parse = smelt_parser::parse(sql);
+        let file = AstFile::cast(parse.syntax()).unwrap();
+        let sources = extract_sources(&file);
+
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].qualified_name(), "raw.events");
+        assert_eq!(&sql[sources[0].range], "smelt.source('raw', 'events')");
+        assert_eq!(sources[0].alias.as_deref(), Some("e"));
+        assert!(extract_refs(&file).is_empty());
+    }
 }
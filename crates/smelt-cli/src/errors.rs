@@ -39,14 +39,37 @@ pub enum CliError {
         message: String,
     },
 
+    #[error("Invalid selector '{selector}': {message}\n\n  = help: Select models by name (stg_*), tag:<tag>, path:<dir>, or with graph operators (+model, model+, @model)")]
+    InvalidSelector { selector: String, message: String },
+
     #[error("Circular dependency detected involving models: {models}")]
     CircularDependency { models: String },
 
     #[error("Source tables not found in database:\n  {}\n\nHint: Create source tables manually or use 'smelt seed' command", missing.join("\n  "))]
     SourceTablesNotFound { missing: Vec<String> },
 
-    #[error("Model '{model}' uses named parameters which are not yet supported\n\n  --> {file}:{line}:{col}\n   |\n{snippet}\n   |\n   = note: Named parameters will be supported in a future release\n   = help: For now, use: FROM smelt.ref('model_name') without parameters")]
+    #[error("Model '{model}' uses named parameter '{param}' which is not yet supported\n\n  --> {file}:{line}:{col}\n   |\n{snippet}\n   |\n   = note: Named parameters other than filter, limit and sample will be supported in a future release")]
     NamedParametersNotSupported {
+        model: String,
+        param: String,
+        file: PathBuf,
+        line: u32,
+        col: u32,
+        snippet: String,
+    },
+
+    #[error("Model '{model}' has an invalid smelt.ref() parameter: {message}\n\n  --> {file}:{line}:{col}\n   |\n{snippet}\n   |\n   = help: Supported parameters: filter => <condition>, limit => <rows>, sample => <fraction between 0 and 1>")]
+    InvalidRefParameter {
+        model: String,
+        message: String,
+        file: PathBuf,
+        line: u32,
+        col: u32,
+        snippet: String,
+    },
+
+    #[error("Model '{model}' has an invalid smelt.source() call\n\n  --> {file}:{line}:{col}\n   |\n{snippet}\n   |\n   = help: Use smelt.source('schema', 'table') with a table declared in sources.yml")]
+    InvalidSourceCall {
         model: String,
         file: PathBuf,
         line: u32,
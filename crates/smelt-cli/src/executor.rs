@@ -2,7 +2,7 @@ use crate::compiler::CompiledModel;
 use crate::config::SourceConfig;
 use crate::errors::CliError;
 use anyhow::Result;
-use smelt_backend::{Backend, ExecutionResult, Materialization};
+use smelt_backend::{Backend, ExecutionResult, SnapshotStrategy};
 
 /// Execute a compiled model using any Backend implementation.
 pub async fn execute_model(
@@ -11,14 +11,73 @@ pub async fn execute_model(
     schema: &str,
     show_results: bool,
 ) -> Result<ExecutionResult> {
-    // Convert CLI Materialization to Backend Materialization
-    let materialization = match compiled.materialization {
-        crate::config::Materialization::Table => Materialization::Table,
-        crate::config::Materialization::View => Materialization::View,
-    };
+    backend
+        .execute_model(schema, &compiled.name, &compiled.sql, compiled.materialization.into(), show_results)
+        .await
+        .map_err(|e| {
+            CliError::ExecutionError {
+                model: compiled.name.clone(),
+                sql: compiled.sql.clone(),
+                source: e.into(),
+            }
+            .into()
+        })
+}
+
+/// Execute the new rows of an incremental model (see `SqlCompiler::compile_incremental`).
+pub async fn execute_incremental(
+    backend: &dyn Backend,
+    compiled: &CompiledModel,
+    schema: &str,
+    time_column: &str,
+    since: &str,
+    show_results: bool,
+) -> Result<ExecutionResult> {
+    backend
+        .execute_incremental(schema, &compiled.name, &compiled.sql, time_column, since, show_results)
+        .await
+        .map_err(|e| {
+            CliError::ExecutionError {
+                model: compiled.name.clone(),
+                sql: compiled.sql.clone(),
+                source: e.into(),
+            }
+            .into()
+        })
+}
 
+/// Upsert the rows of a merge model into its existing table by `unique_key`.
+pub async fn execute_merge(
+    backend: &dyn Backend,
+    compiled: &CompiledModel,
+    schema: &str,
+    unique_key: &[String],
+    show_results: bool,
+) -> Result<ExecutionResult> {
+    backend
+        .merge_into(schema, &compiled.name, &compiled.sql, unique_key, show_results)
+        .await
+        .map_err(|e| {
+            CliError::ExecutionError {
+                model: compiled.name.clone(),
+                sql: compiled.sql.clone(),
+                source: e.into(),
+            }
+            .into()
+        })
+}
+
+/// Record the rows of a snapshot model as new versions in its table.
+pub async fn execute_snapshot(
+    backend: &dyn Backend,
+    compiled: &CompiledModel,
+    schema: &str,
+    unique_key: &[String],
+    strategy: &SnapshotStrategy,
+    show_results: bool,
+) -> Result<ExecutionResult> {
     backend
-        .execute_model(schema, &compiled.name, &compiled.sql, materialization, show_results)
+        .execute_snapshot(schema, &compiled.name, &compiled.sql, unique_key, strategy, show_results)
         .await
         .map_err(|e| {
             CliError::ExecutionError {
@@ -83,6 +142,7 @@ mod tests {
             name: "test_model".to_string(),
             sql: "SELECT 1 as id, 'test' as name".to_string(),
             materialization: crate::config::Materialization::Table,
+            source_map: Default::default(),
         };
 
         let result = execute_model(&backend, &compiled, "main", false).await.unwrap();
@@ -103,6 +163,7 @@ mod tests {
             name: "test_view".to_string(),
             sql: "SELECT 1 as id, 'test' as name".to_string(),
             materialization: crate::config::Materialization::View,
+            source_map: Default::default(),
         };
 
         let result = execute_model(&backend, &compiled, "main", false).await.unwrap();
@@ -122,6 +183,7 @@ mod tests {
             name: "test_preview".to_string(),
             sql: "SELECT 1 as id UNION SELECT 2 UNION SELECT 3".to_string(),
             materialization: crate::config::Materialization::Table,
+            source_map: Default::default(),
         };
 
         let result = execute_model(&backend, &compiled, "main", true).await.unwrap();
@@ -7,6 +7,8 @@ use std::collections::{HashMap, HashSet, VecDeque};
 pub struct DependencyGraph {
     /// model_name -> dependencies (model names it references)
     dependencies: HashMap<String, Vec<String>>,
+    /// model_name -> sources it reads via smelt.source() (schema.table)
+    source_dependencies: HashMap<String, Vec<String>>,
     /// model_name -> ModelFile
     models: HashMap<String, ModelFile>,
     /// External sources (from sources.yml)
@@ -16,6 +18,7 @@ pub struct DependencyGraph {
 impl DependencyGraph {
     pub fn build(models: Vec<ModelFile>, sources: Option<&SourceConfig>) -> Result<Self> {
         let mut dependencies = HashMap::new();
+        let mut source_dependencies = HashMap::new();
         let mut models_map = HashMap::new();
 
         // Build source set (schema.table format)
@@ -31,13 +34,17 @@ impl DependencyGraph {
         // Build dependency map
         for model in models {
             let deps: Vec<String> = model.refs.iter().map(|r| r.model_name.clone()).collect();
+            let source_deps: Vec<String> =
+                model.sources.iter().map(|s| s.qualified_name()).collect();
 
             dependencies.insert(model.name.clone(), deps);
+            source_dependencies.insert(model.name.clone(), source_deps);
             models_map.insert(model.name.clone(), model);
         }
 
         Ok(Self {
             dependencies,
+            source_dependencies,
             models: models_map,
             sources: source_set,
         })
@@ -59,6 +66,17 @@ impl DependencyGraph {
             }
         }
 
+        for (model_name, sources) in &self.source_dependencies {
+            for source in sources {
+                if !self.is_source(source) {
+                    errors.push(format!(
+                        "Model '{}' references undefined source '{}' (not declared in sources.yml)",
+                        model_name, source
+                    ));
+                }
+            }
+        }
+
         if !errors.is_empty() {
             return Err(CliError::DependencyError {
                 message: errors.join("\n  "),
@@ -69,13 +87,86 @@ impl DependencyGraph {
         Ok(())
     }
 
+    /// Exact schema.table match against sources.yml
     fn is_source(&self, name: &str) -> bool {
-        // Check both plain name and schema.table format
         self.sources.contains(name)
-            || self
-                .sources
-                .iter()
-                .any(|s| s.ends_with(&format!(".{}", name)))
+    }
+
+    /// Sources (schema.table) a model reads via smelt.source()
+    pub fn source_dependencies(&self, model_name: &str) -> &[String] {
+        self.source_dependencies
+            .get(model_name)
+            .map(|s| s.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// External sources declared in sources.yml
+    pub fn sources(&self) -> &HashSet<String> {
+        &self.sources
+    }
+
+    /// Models a model reads via smelt.ref(), skipping sources
+    pub fn parents(&self, model_name: &str) -> Vec<&str> {
+        let mut parents: Vec<&str> = self
+            .dependencies
+            .get(model_name)
+            .into_iter()
+            .flatten()
+            .filter(|dep| self.models.contains_key(*dep))
+            .map(|dep| dep.as_str())
+            .collect();
+        // A model may ref the same model more than once
+        parents.sort();
+        parents.dedup();
+        parents
+    }
+
+    /// Models that read a model via smelt.ref()
+    pub fn children(&self, model_name: &str) -> Vec<&str> {
+        let mut children: Vec<&str> = self
+            .dependencies
+            .iter()
+            .filter(|(_, deps)| deps.iter().any(|d| d == model_name))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        children.sort();
+        children
+    }
+
+    /// Models a model depends on, directly or transitively, up to `depth`
+    /// levels away (all of them if None). The model itself is not included.
+    pub fn ancestors(&self, model_name: &str, depth: Option<usize>) -> HashSet<String> {
+        self.walk(model_name, depth, |name| self.parents(name))
+    }
+
+    /// Models that depend on a model, directly or transitively, up to `depth`
+    /// levels away (all of them if None). The model itself is not included.
+    pub fn descendants(&self, model_name: &str, depth: Option<usize>) -> HashSet<String> {
+        self.walk(model_name, depth, |name| self.children(name))
+    }
+
+    fn walk<'a>(
+        &'a self,
+        start: &str,
+        depth: Option<usize>,
+        next: impl Fn(&str) -> Vec<&'a str>,
+    ) -> HashSet<String> {
+        let mut found = HashSet::new();
+        let mut queue = VecDeque::from([(start.to_string(), 0)]);
+
+        while let Some((name, level)) = queue.pop_front() {
+            if depth.is_some_and(|d| level >= d) {
+                continue;
+            }
+            for neighbour in next(&name) {
+                // Cycles lead back to the start; it is never its own ancestor
+                if neighbour != start && found.insert(neighbour.to_string()) {
+                    queue.push_back((neighbour.to_string(), level + 1));
+                }
+            }
+        }
+
+        found
     }
 
     /// Topological sort to determine execution order using Kahn's algorithm
@@ -156,7 +247,8 @@ impl DependencyGraph {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::discovery::RefInfo;
+    use crate::config::{SourceColumn, SourceConfig, SourceSchema, SourceTable};
+    use crate::discovery::{RefInfo, SourceRefInfo};
     use rowan::TextRange;
 
     fn make_model(name: &str, deps: Vec<&str>) -> ModelFile {
@@ -166,6 +258,8 @@ mod tests {
                 model_name: dep.to_string(),
                 has_named_params: false,
                 range: TextRange::default(),
+                params: vec![],
+                alias: None,
             })
             .collect();
 
@@ -174,7 +268,10 @@ mod tests {
             path: format!("{}.sql", name).into(),
             content: String::new(),
             refs,
+            sources: Vec::new(),
             parse_errors: Vec::new(),
+            annotations: Default::default(),
+            annotation_errors: Vec::new(),
         }
     }
 
@@ -221,6 +318,31 @@ mod tests {
         assert_ne!(order[1], order[2]);
     }
 
+    #[test]
+    fn test_ancestors_and_descendants() {
+        // A -> B -> C, A -> D
+        let models = vec![
+            make_model("A", vec![]),
+            make_model("B", vec!["A"]),
+            make_model("C", vec!["B", "raw.events"]),
+            make_model("D", vec!["A"]),
+        ];
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let sorted = |set: HashSet<String>| {
+            let mut names: Vec<String> = set.into_iter().collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(graph.parents("C"), vec!["B"]);
+        assert_eq!(graph.children("A"), vec!["B", "D"]);
+        assert_eq!(sorted(graph.ancestors("C", None)), vec!["A", "B"]);
+        assert_eq!(sorted(graph.ancestors("C", Some(1))), vec!["B"]);
+        assert_eq!(sorted(graph.descendants("A", None)), vec!["B", "C", "D"]);
+        assert_eq!(sorted(graph.descendants("A", Some(1))), vec!["B", "D"]);
+        assert!(graph.descendants("C", None).is_empty());
+    }
+
     #[test]
     fn test_circular_dependency() {
         // A -> B -> C -> A
@@ -251,16 +373,11 @@ mod tests {
         assert!(err_msg.contains("nonexistent"));
     }
 
-    #[test]
-    fn test_source_reference() {
-        use crate::config::{SourceColumn, SourceConfig, SourceSchema, SourceTable};
-
-        let models = vec![make_model("A", vec!["source.events"])];
-
+    fn make_sources(schema: &str, table: &str) -> SourceConfig {
         let mut sources = HashMap::new();
         let mut tables = HashMap::new();
         tables.insert(
-            "events".to_string(),
+            table.to_string(),
             SourceTable {
                 description: String::new(),
                 columns: vec![SourceColumn {
@@ -270,14 +387,48 @@ mod tests {
                 }],
             },
         );
-        sources.insert("source".to_string(), SourceSchema { tables });
+        sources.insert(schema.to_string(), SourceSchema { tables });
 
-        let source_config = SourceConfig {
+        SourceConfig {
             version: 1,
             sources,
-        };
+        }
+    }
+
+    #[test]
+    fn test_source_reference() {
+        let models = vec![make_model("A", vec!["source.events"])];
+        let source_config = make_sources("source", "events");
 
         let graph = DependencyGraph::build(models, Some(&source_config)).unwrap();
         assert!(graph.validate().is_ok());
     }
+
+    #[test]
+    fn test_source_dependencies() {
+        let mut model = make_model("A", vec![]);
+        model.sources = vec![SourceRefInfo {
+            schema: "raw".to_string(),
+            table: "events".to_string(),
+            range: TextRange::default(),
+            alias: None,
+        }];
+        let source_config = make_sources("raw", "events");
+
+        let graph = DependencyGraph::build(vec![model.clone()], Some(&source_config)).unwrap();
+        graph.validate().unwrap();
+        assert_eq!(graph.source_dependencies("A"), ["raw.events"]);
+        assert_eq!(graph.execution_order().unwrap(), vec!["A"]);
+
+        // Sources must match schema.table exactly
+        model.sources[0].schema = "staging".to_string();
+        let graph = DependencyGraph::build(vec![model], Some(&source_config)).unwrap();
+        let err_msg = graph.validate().unwrap_err().to_string();
+        assert!(err_msg.contains("undefined source 'staging.events'"));
+
+        let graph =
+            DependencyGraph::build(vec![make_model("B", vec!["events"])], Some(&source_config))
+                .unwrap();
+        assert!(graph.validate().is_err());
+    }
 }
@@ -0,0 +1,338 @@
+/// Incremental runs of `incremental` models
+///
+/// An incremental model's table is extended instead of rebuilt. A run reads
+/// the largest `incremental.time_column` value already in the table (the
+/// watermark), then recomputes only the rows at or after it by filtering the
+/// ref or source the time column is read from. Column lineage finds that
+/// upstream, so the model's SQL needs no conditionals.
+///
+/// Rows at the watermark itself are recomputed too: the previous run may have
+/// seen only some of them.
+///
+/// A model whose query can't be recomputed by time range (an aggregate over
+/// all time, say) is rebuilt in full; `smelt_db::incrementality` explains why.
+use crate::config::Config;
+use crate::discovery::ModelFile;
+use anyhow::Result;
+use smelt_backend::{Backend, SqlDialect};
+use smelt_db::{ColumnSource, Database, IncrementalityAnalysis, Schema};
+use std::sync::Arc;
+
+/// The relation an incremental model's time column is read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upstream {
+    /// smelt.ref('model')
+    Model(String),
+    /// smelt.source('schema', 'table')
+    Source { schema: String, table: String },
+}
+
+/// How to select the new rows of an incremental model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalPlan {
+    /// Output column of the model holding the time of each row
+    pub time_column: String,
+    /// Where the time column is read from
+    pub upstream: Upstream,
+    /// Name of the time column in the upstream
+    pub upstream_column: String,
+}
+
+impl IncrementalPlan {
+    /// Condition on the upstream selecting the rows at or after `since`
+    pub fn upstream_filter(&self, dialect: SqlDialect, since: &str) -> String {
+        format!(
+            "{} >= {}",
+            dialect.quote_identifier(&self.upstream_column),
+            dialect.string_literal(since)
+        )
+    }
+}
+
+/// The `incremental.time_column` of a model, from its annotations or smelt.yml
+fn time_column(model: &ModelFile, config: &Config) -> Option<String> {
+    config
+        .model_config(&model.name, &model.annotations)
+        .incremental
+        .and_then(|incremental| incremental.time_column)
+}
+
+/// Whether a model's query can be run incrementally on its time column
+pub fn analyze(db: &Database, model: &ModelFile, config: &Config) -> Arc<IncrementalityAnalysis> {
+    db.incrementality(model.path.clone(), time_column(model, config))
+}
+
+/// Work out how to run an incremental model, from its time column's lineage.
+///
+/// Errors with the reason when the model can't be run incrementally; it is
+/// then rebuilt in full like a table.
+pub fn plan(db: &Database, model: &ModelFile, config: &Config) -> Result<IncrementalPlan, String> {
+    let time_column = time_column(model, config)
+        .ok_or("no time column is set (add `-- @incremental.time_column: <column>`)")?;
+
+    let schema = db.model_schema(model.path.clone());
+    let source = match schema.find_column(&time_column) {
+        Some(column) => column.source.clone(),
+        None => {
+            // SELECT * from a single model passes its columns through
+            let wildcards: Vec<&str> = schema
+                .columns
+                .iter()
+                .filter_map(|c| match &c.source {
+                    ColumnSource::Wildcard { model_name } => Some(model_name.as_str()),
+                    _ => None,
+                })
+                .collect();
+            match wildcards.as_slice() {
+                [model_name] => ColumnSource::FromModel {
+                    model_name: model_name.to_string(),
+                    column_name: time_column.clone(),
+                },
+                _ => return Err(format!("the model has no column '{}'", time_column)),
+            }
+        }
+    };
+
+    let (upstream, upstream_column) = match source {
+        ColumnSource::FromModel {
+            model_name,
+            column_name,
+        } => (Upstream::Model(model_name), column_name),
+        ColumnSource::ExternalTable {
+            table_name,
+            column_name,
+        } => {
+            let source = model
+                .sources
+                .iter()
+                .find(|s| s.qualified_name().eq_ignore_ascii_case(&table_name))
+                .ok_or_else(|| {
+                    format!(
+                        "'{}' is read from {}, which isn't a smelt.source() table",
+                        time_column, table_name
+                    )
+                })?;
+            (
+                Upstream::Source {
+                    schema: source.schema.clone(),
+                    table: source.table.clone(),
+                },
+                column_name,
+            )
+        }
+        ColumnSource::Computed | ColumnSource::ComputedFrom { .. } => {
+            return Err(format!(
+                "'{}' is computed by an expression rather than read from an upstream column",
+                time_column
+            ))
+        }
+        ColumnSource::Wildcard { .. } | ColumnSource::Unknown => {
+            return Err(format!(
+                "the upstream column of '{}' can't be determined",
+                time_column
+            ))
+        }
+    };
+
+    let analysis = db.incrementality(model.path.clone(), Some(time_column.clone()));
+    if !analysis.classification.is_incremental() {
+        let blockers: Vec<&str> = analysis.blockers().map(|r| r.message.as_str()).collect();
+        return Err(blockers.join("; "));
+    }
+
+    Ok(IncrementalPlan {
+        time_column,
+        upstream,
+        upstream_column,
+    })
+}
+
+/// The largest time column value in a model's table, or None when the table
+/// doesn't exist yet or is empty and must be built in full
+pub async fn watermark(
+    backend: &dyn Backend,
+    schema: &str,
+    model_name: &str,
+    plan: &IncrementalPlan,
+) -> Result<Option<String>> {
+    if !backend.table_exists(schema, model_name).await? {
+        return Ok(None);
+    }
+    Ok(backend
+        .max_value(schema, model_name, &plan.time_column)
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lineage::project_database;
+    use crate::test_util::write;
+    use crate::ModelDiscovery;
+    use smelt_db::Incrementality;
+
+    #[test]
+    fn test_plan_follows_time_column_lineage() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "smelt.yml",
+            "name: shop\nversion: 1\ntargets:\n  dev:\n    type: duckdb\n    database: shop.duckdb\n    schema: main\n",
+        );
+        write(
+            dir.path(),
+            "sources.yml",
+            "version: 1\nsources:\n  raw:\n    tables:\n      orders:\n        columns:\n          - name: ordered_at\n            type: TIMESTAMP\n          - name: amount\n            type: DOUBLE\n",
+        );
+        write(
+            dir.path(),
+            "models/orders.sql",
+            "-- @incremental.time_column: order_time\n\
+             SELECT o.ordered_at AS order_time, o.amount FROM smelt.source('raw', 'orders') o",
+        );
+        write(
+            dir.path(),
+            "models/big_orders.sql",
+            "-- @incremental.time_column: order_time\n\
+             SELECT * FROM smelt.ref('orders') WHERE amount > 100",
+        );
+        write(
+            dir.path(),
+            "models/daily.sql",
+            "-- @incremental.time_column: day\n\
+             SELECT CAST(order_time AS DATE) AS day, SUM(amount) AS total FROM smelt.ref('orders') GROUP BY 1",
+        );
+        write(
+            dir.path(),
+            "models/top_orders.sql",
+            "-- @incremental.time_column: order_time\n\
+             SELECT order_time, amount FROM smelt.ref('orders') ORDER BY amount DESC LIMIT 10",
+        );
+        write(
+            dir.path(),
+            "models/untimed.sql",
+            "-- @materialize: incremental\nSELECT 1 AS n",
+        );
+
+        let config = Config::load(dir.path()).unwrap();
+        let models = ModelDiscovery::new(dir.path().to_path_buf(), config.model_paths.clone())
+            .discover_models()
+            .unwrap();
+        let db = project_database(dir.path(), &models, Default::default()).unwrap();
+        let model = |name: &str| models.iter().find(|m| m.name == name).unwrap();
+
+        assert_eq!(
+            plan(&db, model("orders"), &config),
+            Ok(IncrementalPlan {
+                time_column: "order_time".to_string(),
+                upstream: Upstream::Source {
+                    schema: "raw".to_string(),
+                    table: "orders".to_string(),
+                },
+                upstream_column: "ordered_at".to_string(),
+            })
+        );
+        assert_eq!(
+            plan(&db, model("big_orders"), &config),
+            Ok(IncrementalPlan {
+                time_column: "order_time".to_string(),
+                upstream: Upstream::Model("orders".to_string()),
+                upstream_column: "order_time".to_string(),
+            })
+        );
+        assert_eq!(
+            plan(&db, model("daily"), &config).unwrap_err(),
+            "'day' is computed by an expression rather than read from an upstream column"
+        );
+        assert_eq!(
+            plan(&db, model("top_orders"), &config).unwrap_err(),
+            "LIMIT picks rows from the whole result"
+        );
+        assert_eq!(
+            analyze(&db, model("big_orders"), &config).classification,
+            Incrementality::AppendSafe
+        );
+        assert!(plan(&db, model("untimed"), &config)
+            .unwrap_err()
+            .starts_with("no time column is set"));
+
+        let filter = plan(&db, model("orders"), &config)
+            .unwrap()
+            .upstream_filter(SqlDialect::DuckDB, "2024-01-02 10:00:00");
+        assert_eq!(filter, "ordered_at >= '2024-01-02 10:00:00'");
+    }
+
+    #[tokio::test]
+    async fn test_incremental_run_adds_new_rows() {
+        use crate::{executor, SqlCompiler};
+        use smelt_backend_duckdb::DuckDbBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        let backend = DuckDbBackend::new(&dir.path().join("test.duckdb"), "main")
+            .await
+            .unwrap();
+        backend
+            .execute_sql(
+                "CREATE TABLE main.events AS SELECT * FROM (VALUES \
+                 (DATE '2024-01-01', 'click'), (DATE '2024-01-02', 'click')) t(day, kind)",
+            )
+            .await
+            .unwrap();
+
+        let model = ModelFile::from_sql(
+            "clicks".to_string(),
+            "models/clicks.sql".into(),
+            "-- @incremental.time_column: day\n\
+             SELECT day, kind FROM smelt.ref('events') WHERE kind = 'click'"
+                .to_string(),
+        );
+        let plan = IncrementalPlan {
+            time_column: "day".to_string(),
+            upstream: Upstream::Model("events".to_string()),
+            upstream_column: "day".to_string(),
+        };
+        let compiler = SqlCompiler::new(
+            serde_yaml::from_str(
+                "name: t\nversion: 1\ntargets:\n  dev:\n    type: duckdb\n    schema: main\n",
+            )
+            .unwrap(),
+        );
+
+        // The first run builds the whole table
+        assert_eq!(
+            watermark(&backend, "main", "clicks", &plan).await.unwrap(),
+            None
+        );
+        let compiled = compiler.compile(&model, "main").unwrap();
+        assert_eq!(
+            compiled.materialization,
+            crate::Materialization::Incremental
+        );
+        executor::execute_model(&backend, &compiled, "main", false)
+            .await
+            .unwrap();
+
+        backend
+            .execute_sql(
+                "INSERT INTO main.events VALUES \
+                 (DATE '2024-01-02', 'click'), (DATE '2024-01-03', 'click'), (DATE '2024-01-03', 'view')",
+            )
+            .await
+            .unwrap();
+
+        // Later runs rebuild the rows from the watermark on
+        let since = watermark(&backend, "main", "clicks", &plan)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(since, "2024-01-02");
+        let compiled = compiler
+            .compile_incremental(&model, "main", &plan, &since)
+            .unwrap();
+        let result =
+            executor::execute_incremental(&backend, &compiled, "main", "day", &since, false)
+                .await
+                .unwrap();
+        assert_eq!(result.row_count, 4);
+    }
+}
@@ -4,9 +4,21 @@ pub mod discovery;
 pub mod errors;
 pub mod executor;
 pub mod graph;
+pub mod incremental;
+pub mod lineage;
+pub mod manifest;
+pub mod scheduler;
+pub mod selector;
+#[cfg(test)]
+mod test_util;
 
-pub use compiler::{CompiledModel, SqlCompiler};
+pub use compiler::{CompiledModel, SourceMap, SqlCompiler};
 pub use config::{find_project_root, BackendType, Config, Materialization, SourceConfig};
-pub use discovery::{ModelDiscovery, ModelFile, RefInfo};
+pub use discovery::{ModelDiscovery, ModelFile, RefInfo, SourceRefInfo};
 pub use errors::CliError;
 pub use graph::DependencyGraph;
+pub use incremental::IncrementalPlan;
+pub use lineage::LineageFormat;
+pub use manifest::{Manifest, ManifestModel};
+pub use scheduler::{ModelRun, ScheduleReport, Scheduler};
+pub use selector::ModelSelection;
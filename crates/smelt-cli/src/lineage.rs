@@ -0,0 +1,257 @@
+use crate::discovery::ModelFile;
+use crate::errors::CliError;
+use crate::manifest::remove_stale_files;
+use anyhow::{anyhow, Context, Result};
+use smelt_db::{
+    Database, Direction, Inputs, LineageGraph, NodeKind, ProjectSettings, SourceCatalog,
+};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Output format of `smelt lineage`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineageFormat {
+    Text,
+    Json,
+    Yaml,
+    Dot,
+}
+
+/// Load a project's models and sources.yml into a smelt-db database
+pub fn project_database(
+    project_dir: &Path,
+    models: &[ModelFile],
+    settings: ProjectSettings,
+) -> Result<Database> {
+    let mut db = Database::default();
+    db.set_project_settings(Arc::new(settings));
+
+    for model in models {
+        db.set_file_text(model.path.clone(), Arc::new(model.content.clone()));
+    }
+    db.set_all_files(Arc::new(models.iter().map(|m| m.path.clone()).collect()));
+
+    let sources_path = project_dir.join("sources.yml");
+    if sources_path.exists() {
+        let text = std::fs::read_to_string(&sources_path)?;
+        let catalog = SourceCatalog::from_yaml(sources_path.clone(), &text).map_err(|e| {
+            CliError::ConfigLoadError {
+                path: sources_path,
+                source: e.into(),
+            }
+        })?;
+        db.set_source_catalog(Arc::new(catalog));
+    }
+
+    Ok(db)
+}
+
+/// Write `.smelt/lineage/<model>.yaml` for every selected model and remove
+/// the files of models that no longer exist
+pub fn write_lineage_files(
+    project_dir: &Path,
+    graph: &LineageGraph,
+    models: &[ModelFile],
+    selected: &HashSet<String>,
+) -> Result<Vec<PathBuf>> {
+    let lineage_dir = project_dir.join(".smelt").join("lineage");
+    std::fs::create_dir_all(&lineage_dir)
+        .with_context(|| format!("Failed to create {}", lineage_dir.display()))?;
+    remove_stale_files(&lineage_dir, "yaml", |name| {
+        models.iter().any(|m| m.name == name)
+    })?;
+
+    let mut written = Vec::new();
+    for model in models.iter().filter(|m| selected.contains(&m.name)) {
+        let path = lineage_dir.join(format!("{}.yaml", model.name));
+        let yaml = serde_yaml::to_string(&graph.model_lineage(&model.name))?;
+        std::fs::write(&path, yaml)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+/// Resolve `model` or `model.column` to the columns to start from
+pub fn lineage_targets(graph: &LineageGraph, target: &str) -> Result<Vec<String>> {
+    if graph.node(target).is_some() {
+        return Ok(vec![target.to_string()]);
+    }
+
+    let columns: Vec<String> = graph.model_columns(target).map(|n| n.id.clone()).collect();
+    if !columns.is_empty() {
+        return Ok(columns);
+    }
+
+    match target.split_once('.') {
+        Some((model, column)) if graph.model_columns(model).next().is_some() => Err(anyhow!(
+            "Model '{}' has no column '{}'. Columns: {}",
+            model,
+            column,
+            graph
+                .model_columns(model)
+                .map(|n| n.column.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        _ => Err(anyhow!("No model or column named '{}'", target)),
+    }
+}
+
+/// Render a lineage graph in the requested format
+pub fn render(graph: &LineageGraph, format: LineageFormat) -> Result<String> {
+    Ok(match format {
+        LineageFormat::Text => render_text(graph),
+        LineageFormat::Json => graph.to_json()?,
+        LineageFormat::Yaml => graph.to_yaml()?,
+        LineageFormat::Dot => graph.to_dot(),
+    })
+}
+
+/// Trees of upstream and downstream columns, one per starting column
+pub fn render_tree(graph: &LineageGraph, targets: &[String], direction: Direction) -> String {
+    let mut out = String::new();
+
+    for target in targets {
+        let node = graph.node(target);
+        let data_type = node.and_then(|n| n.data_type.as_deref());
+        match data_type {
+            Some(data_type) => writeln!(out, "{} {}", target, data_type).unwrap(),
+            None => writeln!(out, "{}", target).unwrap(),
+        }
+        if let Some(expression) = node.and_then(|n| n.expression.as_deref()) {
+            writeln!(out, "  = {}", expression).unwrap();
+        }
+        if direction != Direction::Downstream {
+            write_tree(graph, target, true, 1, &mut vec![target.clone()], &mut out);
+        }
+        if direction != Direction::Upstream {
+            write_tree(graph, target, false, 1, &mut vec![target.clone()], &mut out);
+        }
+    }
+
+    out
+}
+
+fn write_tree(
+    graph: &LineageGraph,
+    id: &str,
+    upstream: bool,
+    depth: usize,
+    path: &mut Vec<String>,
+    out: &mut String,
+) {
+    let next: Vec<&str> = if upstream {
+        graph.inputs(id).map(|e| e.from.as_str()).collect()
+    } else {
+        graph.outputs(id).map(|e| e.to.as_str()).collect()
+    };
+
+    for next_id in next {
+        let arrow = if upstream { "<-" } else { "->" };
+        writeln!(out, "{}{} {}", "  ".repeat(depth), arrow, next_id).unwrap();
+        // Lineage is acyclic between models, but don't trust broken refs
+        if !path.iter().any(|p| p == next_id) {
+            path.push(next_id.to_string());
+            write_tree(graph, next_id, upstream, depth + 1, path, out);
+            path.pop();
+        }
+    }
+}
+
+/// Every model column with the columns it is derived from
+fn render_text(graph: &LineageGraph) -> String {
+    let mut out = String::new();
+    for node in graph.nodes.iter().filter(|n| n.kind == NodeKind::Model) {
+        let inputs: Vec<&str> = graph.inputs(&node.id).map(|e| e.from.as_str()).collect();
+        if inputs.is_empty() {
+            writeln!(out, "{}", node.id).unwrap();
+        } else {
+            writeln!(out, "{} <- {}", node.id, inputs.join(", ")).unwrap();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write;
+    use crate::ModelDiscovery;
+    use smelt_db::Schema;
+
+    #[test]
+    fn test_lineage_files_and_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "sources.yml",
+            "version: 1\nsources:\n  raw:\n    tables:\n      users:\n        columns:\n          - name: id\n            type: INTEGER\n",
+        );
+        write(
+            dir.path(),
+            "models/users.sql",
+            "SELECT id AS user_id FROM smelt.source('raw', 'users')",
+        );
+        write(
+            dir.path(),
+            "models/stats.sql",
+            "SELECT COUNT(DISTINCT user_id) AS users FROM smelt.ref('users')",
+        );
+
+        let models = ModelDiscovery::new(dir.path().to_path_buf(), vec!["models".to_string()])
+            .discover_models()
+            .unwrap();
+        let db = project_database(dir.path(), &models, ProjectSettings::default()).unwrap();
+        let graph = db.lineage_graph();
+
+        // A model that was renamed away, and one left out by the selection
+        write(
+            dir.path(),
+            ".smelt/lineage/old_users.yaml",
+            "model: old_users\n",
+        );
+        write(dir.path(), ".smelt/lineage/stats.yaml", "model: stats\n");
+        write(dir.path(), ".smelt/lineage/notes.txt", "kept");
+
+        let selected = HashSet::from(["users".to_string()]);
+        let written = write_lineage_files(dir.path(), &graph, &models, &selected).unwrap();
+        assert_eq!(written, vec![dir.path().join(".smelt/lineage/users.yaml")]);
+        let lineage_dir = dir.path().join(".smelt/lineage");
+        assert!(!lineage_dir.join("old_users.yaml").exists());
+        assert_eq!(
+            std::fs::read_to_string(lineage_dir.join("stats.yaml")).unwrap(),
+            "model: stats\n"
+        );
+        assert!(lineage_dir.join("notes.txt").exists());
+
+        let all = models.iter().map(|m| m.name.clone()).collect();
+        let written = write_lineage_files(dir.path(), &graph, &models, &all).unwrap();
+        assert_eq!(written.len(), 2);
+        let users = std::fs::read_to_string(dir.path().join(".smelt/lineage/users.yaml")).unwrap();
+        assert_eq!(
+            users,
+            "model: users\ncolumns:\n- name: user_id\n  type: INTEGER\n  derived_from: raw.users.id\n"
+        );
+
+        let targets = lineage_targets(&graph, "stats.users").unwrap();
+        let tree = render_tree(&graph, &targets, Direction::Upstream);
+        assert_eq!(
+            tree,
+            "stats.users BIGINT\n  = COUNT(DISTINCT user_id)\n  <- users.user_id\n    <- raw.users.id\n"
+        );
+
+        let targets = lineage_targets(&graph, "users").unwrap();
+        let tree = render_tree(&graph, &targets, Direction::Downstream);
+        assert_eq!(tree, "users.user_id INTEGER\n  -> stats.users\n");
+
+        let err = lineage_targets(&graph, "users.email").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Model 'users' has no column 'email'. Columns: user_id"
+        );
+    }
+}
@@ -1,13 +1,17 @@
 use anyhow::{Context, Result};
 use arrow::util::pretty;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use smelt_backend::Backend;
 use smelt_backend_duckdb::DuckDbBackend;
 use smelt_cli::{
-    executor, find_project_root, BackendType, Config, DependencyGraph, ModelDiscovery,
-    SourceConfig, SqlCompiler,
+    executor, find_project_root, incremental, lineage, manifest, BackendType, Config,
+    DependencyGraph, LineageFormat, Materialization, ModelDiscovery, ModelFile, ModelSelection,
+    Scheduler, SourceConfig, SqlCompiler,
 };
+use smelt_db::{Direction, Schema};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[cfg(feature = "spark")]
 use smelt_backend_spark::SparkBackend;
@@ -24,6 +28,10 @@ struct Cli {
 enum Commands {
     /// Run models and materialize them in the target database
     Run(RunArgs),
+    /// Write compiled SQL and a manifest to target/ without connecting to a database
+    Compile(CompileArgs),
+    /// Show column-level lineage and export it for data catalogs
+    Lineage(LineageArgs),
 }
 
 #[derive(Parser)]
@@ -40,6 +48,9 @@ struct RunArgs {
     #[arg(long, default_value = "dev")]
     target: String,
 
+    #[command(flatten)]
+    selection: SelectionArgs,
+
     /// Display query results after execution
     #[arg(long)]
     show_results: bool,
@@ -51,6 +62,78 @@ struct RunArgs {
     /// Parse and validate without executing
     #[arg(long)]
     dry_run: bool,
+
+    /// Maximum number of models to run at once
+    #[arg(long, default_value_t = 4)]
+    threads: usize,
+
+    /// Rebuild incremental and merge models in full instead of adding new rows
+    /// (snapshots always keep their history)
+    #[arg(long)]
+    full_refresh: bool,
+}
+
+#[derive(Parser)]
+struct CompileArgs {
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Target environment from smelt.yml
+    #[arg(long, default_value = "dev")]
+    target: String,
+
+    #[command(flatten)]
+    selection: SelectionArgs,
+}
+
+#[derive(Parser)]
+struct LineageArgs {
+    /// Model or column to trace (`model` or `model.column`); the whole
+    /// project if omitted
+    target: Option<String>,
+
+    /// Path to smelt project root
+    #[arg(long, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Only follow lineage upstream (the columns the target is derived from)
+    #[arg(long, conflicts_with = "downstream")]
+    upstream: bool,
+
+    /// Only follow lineage downstream (the columns derived from the target)
+    #[arg(long)]
+    downstream: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    format: LineageFormat,
+
+    #[command(flatten)]
+    selection: SelectionArgs,
+
+    /// Write the output to a file instead of stdout
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// `--select` and `--exclude`, shared by the commands that work on models
+#[derive(Args)]
+struct SelectionArgs {
+    /// Models to include: names (`stg_*` globs), `tag:<tag>`, `path:<dir>`,
+    /// with `+model`, `model+` and `@model` to add dependencies or dependents
+    #[arg(long, short = 's', value_name = "SELECTOR")]
+    select: Vec<String>,
+
+    /// Models to leave out, in the same syntax as --select
+    #[arg(long, value_name = "SELECTOR")]
+    exclude: Vec<String>,
+}
+
+impl SelectionArgs {
+    fn parse(&self) -> Result<ModelSelection> {
+        ModelSelection::parse(&self.select, &self.exclude)
+    }
 }
 
 #[tokio::main]
@@ -59,9 +142,133 @@ async fn main() -> Result<()> {
 
     match cli.command {
         Commands::Run(args) => run(args).await,
+        Commands::Compile(args) => compile(args),
+        Commands::Lineage(args) => show_lineage(args),
     }
 }
 
+fn compile(args: CompileArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = Config::load(&project_dir)
+        .with_context(|| "Failed to load smelt.yml configuration")?;
+    let sources = SourceConfig::load(&project_dir).ok();
+
+    let settings = config.project_settings(Some(&args.target));
+
+    let discovery = ModelDiscovery::new(project_dir.clone(), config.model_paths.clone())
+        .with_lex_options(settings.lex_options);
+    let models = discovery
+        .discover_models()
+        .with_context(|| "Failed to discover models")?;
+
+    let graph = DependencyGraph::build(models, sources.as_ref())
+        .with_context(|| "Failed to build dependency graph")?;
+    graph
+        .validate()
+        .with_context(|| "Dependency validation failed")?;
+    let execution_order = graph
+        .execution_order()
+        .with_context(|| "Failed to determine execution order")?;
+    let execution_order = args.selection.parse()?.filter_order(
+        &execution_order,
+        &graph,
+        &config,
+        &project_dir,
+    )?;
+
+    let manifest =
+        manifest::compile_project(&project_dir, &config, &args.target, &graph, &execution_order)?;
+
+    for name in &manifest.execution_order {
+        println!("  {} → {}", name, manifest.models[name].compiled_path);
+    }
+    println!(
+        "✓ Compiled {} models for target '{}' ({})",
+        manifest.models.len(),
+        manifest.target,
+        manifest.dialect
+    );
+    println!(
+        "  Manifest: {}",
+        project_dir.join("target").join("manifest.json").display()
+    );
+
+    Ok(())
+}
+
+fn show_lineage(args: LineageArgs) -> Result<()> {
+    let project_dir = find_project_root(&args.project_dir)
+        .with_context(|| format!("Failed to find project root from {:?}", args.project_dir))?;
+    let config = Config::load(&project_dir)
+        .with_context(|| "Failed to load smelt.yml configuration")?;
+
+    let settings = config.project_settings(None);
+
+    let discovery = ModelDiscovery::new(project_dir.clone(), config.model_paths.clone())
+        .with_lex_options(settings.lex_options);
+    let models = discovery
+        .discover_models()
+        .with_context(|| "Failed to discover models")?;
+
+    // Lineage is built from every model; the selection only limits output
+    let selection = args.selection.parse()?;
+    let selected = selection.resolve(
+        &DependencyGraph::build(models.clone(), None)?,
+        &config,
+        &project_dir,
+    )?;
+    let selected_models: Vec<_> = models
+        .iter()
+        .filter(|m| selected.contains(&m.name))
+        .cloned()
+        .collect();
+
+    let db = lineage::project_database(&project_dir, &models, settings)?;
+    let graph = db.lineage_graph();
+
+    // Keep .smelt/lineage in step with the models on every invocation.
+    // Status goes to stderr so exports can be piped.
+    let written = lineage::write_lineage_files(&project_dir, &graph, &models, &selected)?;
+    eprintln!(
+        "Wrote {} lineage files to {}",
+        written.len(),
+        project_dir.join(".smelt").join("lineage").display()
+    );
+
+    let direction = match (args.upstream, args.downstream) {
+        (true, _) => Direction::Upstream,
+        (_, true) => Direction::Downstream,
+        _ => Direction::Both,
+    };
+
+    let output = match &args.target {
+        Some(target) => {
+            let targets = lineage::lineage_targets(&graph, target)?;
+            match args.format {
+                LineageFormat::Text => lineage::render_tree(&graph, &targets, direction),
+                format => {
+                    let starts: Vec<&str> = targets.iter().map(|t| t.as_str()).collect();
+                    lineage::render(&graph.traverse(&starts, direction), format)?
+                }
+            }
+        }
+        None if selection.is_all() => lineage::render(&graph, args.format)?,
+        None => {
+            let names: Vec<&str> = selected_models.iter().map(|m| m.name.as_str()).collect();
+            lineage::render(&graph.for_models(&names), args.format)?
+        }
+    };
+
+    match args.output {
+        Some(path) => std::fs::write(&path, output)
+            .with_context(|| format!("Failed to write {}", path.display()))?,
+        None => print!("{}", output),
+    }
+
+    Ok(())
+}
+
 async fn run(args: RunArgs) -> Result<()> {
     // 1. Find project root
     let project_dir = find_project_root(&args.project_dir)
@@ -97,7 +304,9 @@ async fn run(args: RunArgs) -> Result<()> {
     }
 
     // 3. Discover models
-    let discovery = ModelDiscovery::new(project_dir.clone(), config.model_paths.clone());
+    let settings = config.project_settings(Some(&args.target));
+    let discovery = ModelDiscovery::new(project_dir.clone(), config.model_paths.clone())
+        .with_lex_options(settings.lex_options);
     let models = discovery
         .discover_models()
         .with_context(|| "Failed to discover models")?;
@@ -112,6 +321,12 @@ async fn run(args: RunArgs) -> Result<()> {
                 eprintln!("  - {} at {:?}", error.message, error.range);
             }
         }
+        if !model.annotation_errors.is_empty() {
+            eprintln!("\nWarning: Invalid annotations in {}:", model.name);
+            for error in &model.annotation_errors {
+                eprintln!("  - {} at {:?}", error.message, error.range);
+            }
+        }
     }
 
     // 4. Build dependency graph
@@ -122,11 +337,19 @@ async fn run(args: RunArgs) -> Result<()> {
         .validate()
         .with_context(|| "Dependency validation failed")?;
 
-    // 5. Determine execution order
+    // 5. Determine execution order of the selected models
     let execution_order = graph
         .execution_order()
         .with_context(|| "Failed to determine execution order")?;
 
+    let selection = args.selection.parse()?;
+    let total = execution_order.len();
+    let execution_order =
+        selection.filter_order(&execution_order, &graph, &config, &project_dir)?;
+    if !selection.is_all() {
+        println!("Selected {} of {} models", execution_order.len(), total);
+    }
+
     println!(
         "\nExecution order: {}",
         execution_order
@@ -137,7 +360,74 @@ async fn run(args: RunArgs) -> Result<()> {
             .join(" → ")
     );
 
-    if args.dry_run {
+    // Incremental models filter the upstream their time column is read from
+    let mut incremental_plans = HashMap::new();
+    let incremental_models: Vec<&ModelFile> = execution_order
+        .iter()
+        .filter_map(|name| graph.get_model(name).ok())
+        .filter(|model| {
+            config.get_materialization(&model.name, &model.annotations)
+                == Materialization::Incremental
+        })
+        .collect();
+
+    // Merge models upsert into their table by unique key once it exists
+    let mut merge_keys = HashMap::new();
+    for model in execution_order.iter().filter_map(|name| graph.get_model(name).ok()) {
+        if config.get_materialization(&model.name, &model.annotations) != Materialization::Merge {
+            continue;
+        }
+        let unique_key = config.model_config(&model.name, &model.annotations).unique_key;
+        if unique_key.is_empty() {
+            anyhow::bail!(
+                "Model '{}' is materialized as merge but has no unique key (add `-- @unique_key: <column>`)",
+                model.name
+            );
+        }
+        if !args.full_refresh {
+            merge_keys.insert(model.name.clone(), unique_key);
+        }
+    }
+
+    // Snapshot models record new versions of changed rows
+    let mut snapshots = HashMap::new();
+    for model in execution_order.iter().filter_map(|name| graph.get_model(name).ok()) {
+        if config.get_materialization(&model.name, &model.annotations) == Materialization::Snapshot {
+            let settings = config.snapshot_settings(&model.name, &model.annotations)?;
+            snapshots.insert(model.name.clone(), settings);
+        }
+    }
+
+    let db = if args.dry_run || (!args.full_refresh && !incremental_models.is_empty()) {
+        let models: Vec<ModelFile> = graph.models().values().cloned().collect();
+        Some(lineage::project_database(&project_dir, &models, settings)?)
+    } else {
+        None
+    };
+    if let (false, Some(db)) = (args.full_refresh, &db) {
+        for model in incremental_models {
+            match incremental::plan(db, model, &config) {
+                Ok(plan) => {
+                    incremental_plans.insert(model.name.clone(), plan);
+                }
+                Err(reason) => eprintln!(
+                    "\nWarning: {} will be rebuilt in full: {}",
+                    model.name, reason
+                ),
+            }
+        }
+    }
+
+    if let (true, Some(db)) = (args.dry_run, &db) {
+        println!("\nIncrementality:");
+        for model in execution_order.iter().filter_map(|name| graph.get_model(name).ok()) {
+            let analysis = incremental::analyze(db, model, &config);
+            println!("  {}: {}", model.name, analysis.classification);
+            for reason in &analysis.reasons {
+                let mark = if reason.allows.is_incremental() { "✓" } else { "⚠" };
+                println!("    {} {}", mark, reason.message);
+            }
+        }
         println!("\n[DRY RUN] Skipping execution");
         return Ok(());
     }
@@ -200,63 +490,185 @@ async fn run(args: RunArgs) -> Result<()> {
             .with_context(|| "Source validation failed")?;
     }
 
-    // 8. Compile and execute each model
-    let compiler = SqlCompiler::new(config.clone());
+    // 8. Compile and execute the models, up to --threads at a time
+    let compiler = Arc::new(
+        SqlCompiler::new(config.clone()).with_dialect(backend.dialect(), backend.capabilities()),
+    );
+    let backend: Arc<dyn Backend> = Arc::from(backend);
 
     println!("\n{}", "=".repeat(60));
-    println!("Executing models...");
+    println!("Executing models ({} threads)...", args.threads.max(1));
     println!("{}", "=".repeat(60));
 
-    let mut results = Vec::new();
-
-    for model_name in &execution_order {
-        let model = graph.get_model(model_name)?;
-
-        println!("\n▶ Running model: {}", model_name);
-
-        // Compile
-        let compiled = compiler
-            .compile(model, &target_config.schema)
-            .with_context(|| format!("Failed to compile model: {}", model_name))?;
-
-        if args.verbose {
-            println!("\n  Compiled SQL:");
-            println!("  {}", "─".repeat(58));
-            for line in compiled.sql.lines() {
-                println!("  {}", line);
-            }
-            println!("  {}", "─".repeat(58));
-        }
+    let report = Scheduler::new(&graph, args.threads)
+        .run(
+            &execution_order,
+            |model_name| {
+                println!("\n▶ Running model: {}", model_name);
+
+                // Compile. Errors name the model, so they're reported as is
+                let compiled = graph
+                    .get_model(model_name)
+                    .and_then(|model| compiler.compile(model, &target_config.schema));
+
+                if let (true, Ok(compiled)) = (args.verbose, &compiled) {
+                    print_sql("Compiled SQL", &compiled.sql);
+                }
+
+                // Execute
+                let backend = Arc::clone(&backend);
+                let compiler = Arc::clone(&compiler);
+                let incremental = incremental_plans
+                    .get(model_name)
+                    .zip(graph.get_model(model_name).ok())
+                    .map(|(plan, model)| (plan.clone(), model.clone()));
+                let unique_key = merge_keys.get(model_name).cloned();
+                let snapshot = snapshots.get(model_name).cloned();
+                let schema = target_config.schema.clone();
+                let (show_results, verbose) = (args.show_results, args.verbose);
+                async move {
+                    let compiled = compiled?;
+
+                    // An incremental table that already has rows only gets the new ones
+                    if let Some((plan, model)) = incremental {
+                        let since =
+                            incremental::watermark(backend.as_ref(), &schema, &model.name, &plan)
+                                .await?;
+                        if let Some(since) = since {
+                            println!(
+                                "  ↻ {}: adding rows with {} >= {}",
+                                model.name, plan.time_column, since
+                            );
+                            let compiled =
+                                compiler.compile_incremental(&model, &schema, &plan, &since)?;
+                            if verbose {
+                                print_sql("Incremental SQL", &compiled.sql);
+                            }
+                            return executor::execute_incremental(
+                                backend.as_ref(),
+                                &compiled,
+                                &schema,
+                                &plan.time_column,
+                                &since,
+                                show_results,
+                            )
+                            .await;
+                        }
+                    }
+
+                    // A merge table that already exists gets the rows upserted
+                    if let Some(unique_key) = unique_key {
+                        if backend.table_exists(&schema, &compiled.name).await? {
+                            println!(
+                                "  ↻ {}: merging on {}",
+                                compiled.name,
+                                unique_key.join(", ")
+                            );
+                            return executor::execute_merge(
+                                backend.as_ref(),
+                                &compiled,
+                                &schema,
+                                &unique_key,
+                                show_results,
+                            )
+                            .await;
+                        }
+                    }
+
+                    if let Some((unique_key, strategy)) = snapshot {
+                        return executor::execute_snapshot(
+                            backend.as_ref(),
+                            &compiled,
+                            &schema,
+                            &unique_key,
+                            &strategy,
+                            show_results,
+                        )
+                        .await;
+                    }
+
+                    executor::execute_model(backend.as_ref(), &compiled, &schema, show_results)
+                        .await
+                }
+            },
+            |run| match &run.result {
+                Ok(result) => {
+                    println!(
+                        "  ✓ {} ({} rows, {:?})",
+                        result.model_name, result.row_count, run.duration
+                    );
+
+                    // Show preview if requested
+                    if let Some(ref batches) = result.preview {
+                        println!("\n  Preview:");
+                        if let Err(e) = pretty::print_batches(batches) {
+                            eprintln!("  Failed to print result preview: {}", e);
+                        }
+                        println!();
+                    }
+                }
+                Err(e) => eprintln!("  ✗ {} failed\n\n{}\n", run.name, e),
+            },
+        )
+        .await;
 
-        // Execute
-        let result = executor::execute_model(backend.as_ref(), &compiled, &target_config.schema, args.show_results)
-            .await
-            .with_context(|| format!("Failed to execute model: {}", model_name))?;
+    // 9. Summary
+    println!("\n{}", "=".repeat(60));
+    println!("Summary");
+    println!("{}", "=".repeat(60));
 
+    let failed: Vec<&str> = report.failed().map(|r| r.name.as_str()).collect();
+    let succeeded = report.runs.len() - failed.len();
+    if failed.is_empty() {
+        println!("✓ Executed {} models successfully", succeeded);
+    } else {
         println!(
-            "  ✓ {} ({} rows, {:?})",
-            result.model_name, result.row_count, result.duration
+            "✗ {} succeeded, {} failed, {} skipped",
+            succeeded,
+            failed.len(),
+            report.skipped.len()
         );
+    }
+    println!("  Total time: {:?}", report.elapsed);
 
-        // Show preview if requested
-        if let Some(ref batches) = result.preview {
-            println!("\n  Preview:");
-            pretty::print_batches(batches)
-                .with_context(|| "Failed to print result preview")?;
-            println!();
-        }
-
-        results.push(result);
+    let mut runs: Vec<_> = report.runs.iter().collect();
+    runs.sort_by_key(|r| r.started);
+    println!("\n  Model timings (start → duration):");
+    for run in runs {
+        let status = if run.result.is_ok() { "✓" } else { "✗" };
+        println!(
+            "    {} {:<30} +{:>10.3?} → {:?}",
+            status, run.name, run.started, run.duration
+        );
     }
 
-    // 9. Summary
-    println!("\n{}", "=".repeat(60));
-    println!("Summary");
-    println!("{}", "=".repeat(60));
-    println!("✓ Executed {} models successfully", results.len());
+    if !report.critical_path.is_empty() {
+        println!(
+            "\n  Critical path ({:?}): {}",
+            report.critical_path_duration(),
+            report.critical_path.join(" → ")
+        );
+    }
+    for name in &report.skipped {
+        println!("  Skipped {} (a model it depends on failed)", name);
+    }
 
-    let total_duration: std::time::Duration = results.iter().map(|r| r.duration).sum();
-    println!("  Total time: {:?}", total_duration);
+    if !failed.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} model(s) failed: {}",
+            failed.len(),
+            failed.join(", ")
+        ));
+    }
 
     Ok(())
 }
+
+fn print_sql(heading: &str, sql: &str) {
+    println!("\n  {}:", heading);
+    println!("  {}", "─".repeat(58));
+    for line in sql.lines() {
+        println!("  {}", line);
+    }
+    println!("  {}", "─".repeat(58));
+}
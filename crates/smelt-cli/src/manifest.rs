@@ -0,0 +1,413 @@
+use crate::compiler::{CompiledModel, SqlCompiler};
+use crate::config::{Config, Materialization};
+use crate::discovery::relative_path;
+use crate::graph::DependencyGraph;
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use smelt_backend::{BackendCapabilities, SqlDialect};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// `target/manifest.json`: every compiled model of a project
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub project: String,
+    pub target: String,
+    pub dialect: String,
+    pub schema: String,
+    pub execution_order: Vec<String>,
+    pub models: BTreeMap<String, ManifestModel>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestModel {
+    /// Model file, relative to the project root
+    pub path: String,
+    /// Compiled SQL file, relative to the project root
+    pub compiled_path: String,
+    pub materialization: Materialization,
+    /// Models read via smelt.ref()
+    pub refs: Vec<String>,
+    /// Sources read via smelt.source() (schema.table)
+    pub sources: Vec<String>,
+    /// SHA-256 of the model file
+    pub checksum: String,
+    /// SHA-256 of the compiled SQL file
+    pub compiled_checksum: String,
+}
+
+/// Compile the models in `execution_order` for a target and write
+/// `target/compiled/<model>.sql` and `target/manifest.json` under the project
+/// root.
+///
+/// Nothing connects to the target: the dialect comes from its type, and the
+/// compiled files hold the statements `Backend::execute_model` would issue.
+pub fn compile_project(
+    project_dir: &Path,
+    config: &Config,
+    target_name: &str,
+    graph: &DependencyGraph,
+    execution_order: &[String],
+) -> Result<Manifest> {
+    let target = config.targets.get(target_name).ok_or_else(|| {
+        anyhow!(
+            "Target '{}' not found in smelt.yml. Available targets: {}",
+            target_name,
+            config
+                .targets
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+    let backend_type = target.backend_type();
+    let dialect = backend_type.dialect();
+    let capabilities = backend_type.capabilities();
+    let compiler = SqlCompiler::new(config.clone()).with_dialect(dialect, capabilities.clone());
+
+    let compiled_dir = project_dir.join("target").join("compiled");
+    std::fs::create_dir_all(&compiled_dir)
+        .with_context(|| format!("Failed to create {}", compiled_dir.display()))?;
+    remove_stale_files(&compiled_dir, "sql", |name| {
+        graph.models().contains_key(name)
+    })?;
+
+    let mut models = BTreeMap::new();
+    for model_name in execution_order {
+        let model = graph.get_model(model_name)?;
+        let mut compiled = compiler
+            .compile(model, &target.schema)
+            .with_context(|| format!("Failed to compile model: {}", model_name))?;
+        // The first run of a snapshot builds its table with every row current
+        if compiled.materialization == Materialization::Snapshot {
+            let (_, strategy) = config.snapshot_settings(model_name, &model.annotations)?;
+            compiled.sql = dialect.snapshot_select_sql(compiled.sql.trim_end(), &strategy);
+        }
+
+        let path = relative_path(project_dir, &model.path);
+        let compiled_sql = compiled_file(&compiled, &path, dialect, &capabilities, &target.schema);
+        let compiled_path = compiled_dir.join(format!("{}.sql", model_name));
+        std::fs::write(&compiled_path, &compiled_sql)
+            .with_context(|| format!("Failed to write {}", compiled_path.display()))?;
+
+        models.insert(
+            model_name.clone(),
+            ManifestModel {
+                path,
+                compiled_path: relative_path(project_dir, &compiled_path),
+                materialization: compiled.materialization,
+                refs: model.refs.iter().map(|r| r.model_name.clone()).collect(),
+                sources: model.sources.iter().map(|s| s.qualified_name()).collect(),
+                checksum: sha256(&model.content),
+                compiled_checksum: sha256(&compiled_sql),
+            },
+        );
+    }
+
+    let manifest = Manifest {
+        project: config.name.clone(),
+        target: target_name.to_string(),
+        dialect: dialect.name().to_string(),
+        schema: target.schema.clone(),
+        execution_order: execution_order.to_vec(),
+        models,
+    };
+
+    let manifest_path = project_dir.join("target").join("manifest.json");
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    Ok(manifest)
+}
+
+/// Remove the `<model>.<extension>` files in `dir` of models that no longer
+/// exist. Files of models left out by a selection are kept from earlier runs.
+pub(crate) fn remove_stale_files(
+    dir: &Path,
+    extension: &str,
+    is_model: impl Fn(&str) -> bool,
+) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_stale = path.extension().is_some_and(|ext| ext == extension)
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| !is_model(stem));
+        if is_stale {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// A header naming the model, then the statements that materialize it
+fn compiled_file(
+    compiled: &CompiledModel,
+    path: &str,
+    dialect: SqlDialect,
+    capabilities: &BackendCapabilities,
+    schema: &str,
+) -> String {
+    let mut out = String::new();
+    writeln!(out, "-- model: {}", compiled.name).unwrap();
+    writeln!(out, "-- source: {}", path).unwrap();
+    writeln!(
+        out,
+        "-- materialization: {}",
+        match compiled.materialization {
+            Materialization::Table => "table",
+            Materialization::View => "view",
+            Materialization::Incremental => "incremental",
+            Materialization::Merge => "merge",
+            Materialization::Snapshot => "snapshot",
+        }
+    )
+    .unwrap();
+
+    let statements = dialect.materialize_statements(
+        capabilities,
+        schema,
+        &compiled.name,
+        compiled.sql.trim_end(),
+        compiled.materialization.into(),
+    );
+    for statement in statements {
+        write!(out, "\n{};\n", statement).unwrap();
+    }
+    out
+}
+
+fn sha256(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write;
+    use crate::ModelDiscovery;
+
+    #[test]
+    fn test_compile_project() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "smelt.yml",
+            "name: shop\nversion: 1\ntargets:\n  dev:\n    type: duckdb\n    database: shop.duckdb\n    schema: main\ndefault_materialization: table\n",
+        );
+        write(
+            dir.path(),
+            "models/users.sql",
+            "SELECT id FROM smelt.source('raw', 'users')\n",
+        );
+        write(
+            dir.path(),
+            "models/stats.sql",
+            "-- @materialize: view\nSELECT COUNT(*) AS users FROM smelt.ref('users')\n",
+        );
+        // Left over from a model that no longer exists
+        write(dir.path(), "target/compiled/old.sql", "SELECT 1");
+
+        let config = Config::load(dir.path()).unwrap();
+        let models = ModelDiscovery::new(dir.path().to_path_buf(), config.model_paths.clone())
+            .discover_models()
+            .unwrap();
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let order = graph.execution_order().unwrap();
+
+        let manifest = compile_project(dir.path(), &config, "dev", &graph, &order).unwrap();
+
+        let stats = std::fs::read_to_string(dir.path().join("target/compiled/stats.sql")).unwrap();
+        assert_eq!(
+            stats,
+            "-- model: stats\n-- source: models/stats.sql\n-- materialization: view\n\
+             \nCREATE OR REPLACE VIEW main.stats AS -- @materialize: view\nSELECT COUNT(*) AS users FROM main.users;\n"
+        );
+
+        // Tables are built under a staging name and swapped in
+        let users = std::fs::read_to_string(dir.path().join("target/compiled/users.sql")).unwrap();
+        assert!(users.ends_with(
+            "\nDROP TABLE IF EXISTS main.users__smelt_staging;\n\
+             \nCREATE TABLE main.users__smelt_staging AS SELECT id FROM raw.users;\n\
+             \nBEGIN TRANSACTION;\n\
+             \nDROP TABLE IF EXISTS main.users;\n\
+             \nALTER TABLE main.users__smelt_staging RENAME TO users;\n\
+             \nCOMMIT;\n"
+        ));
+        assert!(!dir.path().join("target/compiled/old.sql").exists());
+
+        assert_eq!(manifest.dialect, "DuckDB");
+        assert_eq!(manifest.execution_order, vec!["users", "stats"]);
+        let users = &manifest.models["users"];
+        assert_eq!(users.path, "models/users.sql");
+        assert_eq!(users.compiled_path, "target/compiled/users.sql");
+        assert_eq!(users.materialization, Materialization::Table);
+        assert_eq!(users.sources, vec!["raw.users"]);
+        assert_eq!(
+            users.checksum,
+            sha256("SELECT id FROM smelt.source('raw', 'users')\n")
+        );
+        assert_eq!(manifest.models["stats"].refs, vec!["users"]);
+        assert_eq!(
+            manifest.models["stats"].materialization,
+            Materialization::View
+        );
+
+        let json: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("target/manifest.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["models"]["stats"]["materialization"], "view");
+        assert_eq!(
+            json["models"]["stats"]["compiled_checksum"],
+            sha256(&stats).as_str()
+        );
+    }
+
+    #[test]
+    fn test_manifest_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "smelt.yml",
+            "name: shop\nversion: 1\ntargets:\n  prod:\n    type: spark\n    schema: analytics\n\
+             default_materialization: table\nmodels:\n  daily:\n    materialization: view\n",
+        );
+        write(
+            dir.path(),
+            "models/staging/orders.sql",
+            "SELECT id, day FROM smelt.source('raw', 'orders')\n",
+        );
+        write(
+            dir.path(),
+            "models/marts/daily.sql",
+            "SELECT o.day, COUNT(*) AS n FROM smelt.ref('orders') o \
+             JOIN smelt.source('raw', 'days') d ON d.day = o.day GROUP BY 1\n",
+        );
+
+        let config = Config::load(dir.path()).unwrap();
+        let models = ModelDiscovery::new(dir.path().to_path_buf(), config.model_paths.clone())
+            .discover_models()
+            .unwrap();
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let order = graph.execution_order().unwrap();
+
+        let err = compile_project(dir.path(), &config, "dev", &graph, &order).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Target 'dev' not found in smelt.yml. Available targets: prod"
+        );
+
+        compile_project(dir.path(), &config, "prod", &graph, &order).unwrap();
+        let json: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join("target/manifest.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "project": "shop",
+                "target": "prod",
+                "dialect": "Spark SQL",
+                "schema": "analytics",
+                "execution_order": ["orders", "daily"],
+                "models": {
+                    "daily": {
+                        "path": "models/marts/daily.sql",
+                        "compiled_path": "target/compiled/daily.sql",
+                        "materialization": "view",
+                        "refs": ["orders"],
+                        "sources": ["raw.days"],
+                        "checksum": json["models"]["daily"]["checksum"],
+                        "compiled_checksum": json["models"]["daily"]["compiled_checksum"],
+                    },
+                    "orders": {
+                        "path": "models/staging/orders.sql",
+                        "compiled_path": "target/compiled/orders.sql",
+                        "materialization": "table",
+                        "refs": [],
+                        "sources": ["raw.orders"],
+                        "checksum": sha256("SELECT id, day FROM smelt.source('raw', 'orders')\n"),
+                        "compiled_checksum": json["models"]["orders"]["compiled_checksum"],
+                    },
+                },
+            })
+        );
+
+        // Spark has neither CREATE OR REPLACE TABLE nor transactional DDL,
+        // and renames to a qualified name
+        let orders =
+            std::fs::read_to_string(dir.path().join("target/compiled/orders.sql")).unwrap();
+        assert_eq!(
+            orders,
+            "-- model: orders\n-- source: models/staging/orders.sql\n-- materialization: table\n\
+             \nDROP TABLE IF EXISTS analytics.orders__smelt_staging;\n\
+             \nCREATE TABLE analytics.orders__smelt_staging AS SELECT id, day FROM raw.orders;\n\
+             \nDROP TABLE IF EXISTS analytics.orders;\n\
+             \nALTER TABLE analytics.orders__smelt_staging RENAME TO analytics.orders;\n"
+        );
+        assert_eq!(
+            json["models"]["orders"]["compiled_checksum"],
+            sha256(&orders).as_str()
+        );
+    }
+
+    #[test]
+    fn test_output_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "smelt.yml",
+            "name: shop
+version: 1
+targets:
+  dev:
+    type: duckdb
+    schema: main
+",
+        );
+        write(dir.path(), "models/a.sql", "SELECT 1 AS x\n");
+        write(
+            dir.path(),
+            "models/nested/b.sql",
+            "SELECT x FROM smelt.ref('a')\n",
+        );
+        // Compiled earlier: b is still a model, gone no longer is
+        write(dir.path(), "target/compiled/b.sql", "-- earlier run");
+        write(dir.path(), "target/compiled/gone.sql", "-- earlier run");
+        write(dir.path(), "target/compiled/notes.txt", "kept");
+
+        let config = Config::load(dir.path()).unwrap();
+        let models = ModelDiscovery::new(dir.path().to_path_buf(), config.model_paths.clone())
+            .discover_models()
+            .unwrap();
+        let graph = DependencyGraph::build(models, None).unwrap();
+
+        // Only `a` is selected
+        let manifest =
+            compile_project(dir.path(), &config, "dev", &graph, &["a".to_string()]).unwrap();
+        assert_eq!(manifest.execution_order, vec!["a"]);
+        assert_eq!(manifest.models.keys().collect::<Vec<_>>(), vec!["a"]);
+
+        // One flat file per model, whatever directory the model is in
+        let mut files: Vec<_> = std::fs::read_dir(dir.path().join("target/compiled"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["a.sql", "b.sql", "notes.txt"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("target/compiled/b.sql")).unwrap(),
+            "-- earlier run"
+        );
+        assert!(dir.path().join("target/manifest.json").exists());
+    }
+}
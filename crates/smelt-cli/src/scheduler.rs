@@ -0,0 +1,342 @@
+/// Concurrent execution of models in dependency order
+///
+/// A model is dispatched as soon as every model it depends on has finished,
+/// with at most `threads` models running at once. Dependencies outside the
+/// models being run (left out by `--select`) are taken to be built already.
+/// When a model fails, the models that depend on it are skipped and the rest
+/// of the graph keeps running.
+use crate::graph::DependencyGraph;
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+pub struct Scheduler<'a> {
+    graph: &'a DependencyGraph,
+    threads: usize,
+}
+
+/// A model that was dispatched, with its timing
+#[derive(Debug)]
+pub struct ModelRun<T> {
+    pub name: String,
+    /// When the model started, relative to the start of the run
+    pub started: Duration,
+    pub duration: Duration,
+    pub result: Result<T>,
+}
+
+#[derive(Debug)]
+pub struct ScheduleReport<T> {
+    /// Dispatched models, in the order they finished
+    pub runs: Vec<ModelRun<T>>,
+    /// Models not run because a model they depend on failed
+    pub skipped: Vec<String>,
+    /// Wall-clock time of the whole run
+    pub elapsed: Duration,
+    /// The chain of dependent models with the longest total duration
+    pub critical_path: Vec<String>,
+}
+
+impl<'a> Scheduler<'a> {
+    pub fn new(graph: &'a DependencyGraph, threads: usize) -> Self {
+        Self {
+            graph,
+            threads: threads.max(1),
+        }
+    }
+
+    /// Run `models` (in execution order). `start` creates the future that
+    /// runs one model; `finished` is called as each model completes.
+    pub async fn run<T, F, Fut>(
+        &self,
+        models: &[String],
+        mut start: F,
+        mut finished: impl FnMut(&ModelRun<T>),
+    ) -> ScheduleReport<T>
+    where
+        F: FnMut(&str) -> Fut,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let position: HashMap<&str, usize> = models
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+        let mut waiting_on: Vec<usize> = models
+            .iter()
+            .map(|name| self.selected_parents(name, &position).count())
+            .collect();
+
+        // Ready models by position, so independent models start in execution order
+        let mut ready: BTreeSet<usize> =
+            (0..models.len()).filter(|&i| waiting_on[i] == 0).collect();
+        let mut tasks = JoinSet::new();
+        let mut runs = Vec::new();
+        let run_start = Instant::now();
+
+        loop {
+            while tasks.len() < self.threads {
+                let Some(index) = ready.pop_first() else {
+                    break;
+                };
+                let future = start(&models[index]);
+                let started = run_start.elapsed();
+                tasks.spawn(async move {
+                    let begin = Instant::now();
+                    let result = future.await;
+                    (index, started, begin.elapsed(), result)
+                });
+            }
+
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let (index, started, duration, result) =
+                joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+
+            if result.is_ok() {
+                for child in self.graph.children(&models[index]) {
+                    if let Some(&child) = position.get(child) {
+                        waiting_on[child] -= 1;
+                        if waiting_on[child] == 0 {
+                            ready.insert(child);
+                        }
+                    }
+                }
+            }
+
+            let run = ModelRun {
+                name: models[index].clone(),
+                started,
+                duration,
+                result,
+            };
+            finished(&run);
+            runs.push(run);
+        }
+
+        let skipped = models
+            .iter()
+            .filter(|name| !runs.iter().any(|r| &r.name == *name))
+            .cloned()
+            .collect();
+        let critical_path = self.critical_path(models, &runs, &position);
+
+        ScheduleReport {
+            runs,
+            skipped,
+            elapsed: run_start.elapsed(),
+            critical_path,
+        }
+    }
+
+    fn selected_parents<'b>(
+        &'b self,
+        name: &str,
+        position: &'b HashMap<&str, usize>,
+    ) -> impl Iterator<Item = usize> + 'b {
+        self.graph
+            .parents(name)
+            .into_iter()
+            .filter_map(|parent| position.get(parent).copied())
+    }
+
+    /// Longest chain of successful runs by total duration
+    fn critical_path<T>(
+        &self,
+        models: &[String],
+        runs: &[ModelRun<T>],
+        position: &HashMap<&str, usize>,
+    ) -> Vec<String> {
+        let durations: HashMap<&str, Duration> = runs
+            .iter()
+            .filter(|r| r.result.is_ok())
+            .map(|r| (r.name.as_str(), r.duration))
+            .collect();
+
+        // Models are in execution order, so parents are always seen first
+        let mut longest: Vec<Option<(Duration, Option<usize>)>> = vec![None; models.len()];
+        for (index, name) in models.iter().enumerate() {
+            let Some(&duration) = durations.get(name.as_str()) else {
+                continue;
+            };
+            let parent = self
+                .selected_parents(name, position)
+                .filter_map(|p| longest[p].map(|(total, _)| (total, p)))
+                .max_by_key(|(total, _)| *total);
+            longest[index] = Some(match parent {
+                Some((total, parent)) => (total + duration, Some(parent)),
+                None => (duration, None),
+            });
+        }
+
+        let mut path = Vec::new();
+        let mut next = (0..models.len())
+            .filter_map(|i| longest[i].map(|(total, _)| (total, i)))
+            .max_by_key(|(total, _)| *total)
+            .map(|(_, i)| i);
+        while let Some(index) = next {
+            path.push(models[index].clone());
+            next = longest[index].and_then(|(_, parent)| parent);
+        }
+        path.reverse();
+        path
+    }
+}
+
+impl<T> ScheduleReport<T> {
+    pub fn failed(&self) -> impl Iterator<Item = &ModelRun<T>> {
+        self.runs.iter().filter(|r| r.result.is_err())
+    }
+
+    /// Total duration of the models on the critical path
+    pub fn critical_path_duration(&self) -> Duration {
+        self.critical_path
+            .iter()
+            .filter_map(|name| self.runs.iter().find(|r| &r.name == name))
+            .map(|r| r.duration)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{ModelFile, RefInfo};
+    use anyhow::anyhow;
+    use rowan::TextRange;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn make_model(name: &str, deps: Vec<&str>) -> ModelFile {
+        let mut model = ModelFile::from_sql(
+            name.to_string(),
+            format!("{}.sql", name).into(),
+            String::new(),
+        );
+        model.refs = deps
+            .into_iter()
+            .map(|dep| RefInfo {
+                model_name: dep.to_string(),
+                has_named_params: false,
+                range: TextRange::default(),
+                params: vec![],
+                alias: None,
+            })
+            .collect();
+        model
+    }
+
+    /// A -> B -> D and A -> C -> D, with E independent
+    fn diamond() -> DependencyGraph {
+        DependencyGraph::build(
+            vec![
+                make_model("A", vec![]),
+                make_model("B", vec!["A"]),
+                make_model("C", vec!["A"]),
+                make_model("D", vec!["B", "C"]),
+                make_model("E", vec![]),
+            ],
+            None,
+        )
+        .unwrap()
+    }
+
+    fn sleep_ms(name: &str) -> u64 {
+        match name {
+            "B" => 60,
+            _ => 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_run_respects_dependencies() {
+        let graph = diamond();
+        let order = graph.execution_order().unwrap();
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let finished_order = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let report = Scheduler::new(&graph, 2)
+            .run(
+                &order,
+                |name| {
+                    let (name, running, max_running, finished_order) = (
+                        name.to_string(),
+                        Arc::clone(&running),
+                        Arc::clone(&max_running),
+                        Arc::clone(&finished_order),
+                    );
+                    async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(sleep_ms(&name))).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        finished_order.lock().unwrap().push(name.clone());
+                        Ok(name)
+                    }
+                },
+                |_| {},
+            )
+            .await;
+
+        assert_eq!(report.runs.len(), 5);
+        assert!(report.skipped.is_empty());
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+
+        let finished = finished_order.lock().unwrap().clone();
+        let at = |name: &str| finished.iter().position(|n| n == name).unwrap();
+        assert!(at("A") < at("B") && at("A") < at("C"));
+        assert!(at("B") < at("D") && at("C") < at("D"));
+
+        // B is the slow branch of the diamond
+        assert_eq!(report.critical_path, vec!["A", "B", "D"]);
+        assert!(report.critical_path_duration() >= Duration::from_millis(80));
+    }
+
+    #[tokio::test]
+    async fn test_failure_skips_dependents() {
+        let graph = diamond();
+        let order = graph.execution_order().unwrap();
+
+        let mut finished = Vec::new();
+        let report = Scheduler::new(&graph, 4)
+            .run(
+                &order,
+                |name| {
+                    let name = name.to_string();
+                    async move {
+                        match name.as_str() {
+                            "C" => Err(anyhow!("boom")),
+                            _ => Ok(()),
+                        }
+                    }
+                },
+                |run| finished.push(run.name.clone()),
+            )
+            .await;
+
+        let failed: Vec<_> = report.failed().map(|r| r.name.as_str()).collect();
+        assert_eq!(failed, vec!["C"]);
+        assert_eq!(report.skipped, vec!["D"]);
+        finished.sort();
+        assert_eq!(finished, vec!["A", "B", "C", "E"]);
+    }
+
+    #[tokio::test]
+    async fn test_unselected_dependencies_are_built() {
+        let graph = diamond();
+
+        // Only D: its parents B and C aren't part of the run
+        let report = Scheduler::new(&graph, 1)
+            .run(&["D".to_string()], |_| async { Ok(()) }, |_| {})
+            .await;
+
+        assert_eq!(report.runs.len(), 1);
+        assert_eq!(report.critical_path, vec!["D"]);
+    }
+}
@@ -0,0 +1,453 @@
+/// Model selection for `--select` and `--exclude`
+///
+/// Each selector picks models by one or more comma-separated criteria, all of
+/// which must match:
+///
+/// ```text
+/// orders              a model by name; `stg_*` globs over names
+/// tag:nightly         models tagged in smelt.yml or with `-- @tags:`
+/// path:models/staging models under a directory, relative to the project root
+///                     (a bare `models/staging` or `models/orders.sql` works too)
+/// +orders             orders and every model it depends on (`2+orders`: two levels)
+/// orders+             orders and every model that depends on it (`orders+1`)
+/// @orders             orders, its descendants, and everything they depend on
+/// ```
+///
+/// Selectors given together (space-separated or repeated flags) are unioned,
+/// then the excluded models are removed.
+use crate::config::Config;
+use crate::discovery::{relative_path, ModelFile};
+use crate::errors::CliError;
+use crate::graph::DependencyGraph;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Parsed `--select` and `--exclude` arguments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelSelection {
+    select: Vec<Selector>,
+    exclude: Vec<Selector>,
+}
+
+/// One selector: criteria that must all match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    text: String,
+    criteria: Vec<Criterion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Criterion {
+    method: Method,
+    /// `+orders`, `2+orders`
+    parents: Option<Depth>,
+    /// `orders+`, `orders+2`
+    children: Option<Depth>,
+    /// `@orders`
+    with_ancestors_of_children: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Method {
+    Name(String),
+    Tag(String),
+    Path(String),
+}
+
+/// How far a graph operator reaches; None is unlimited
+type Depth = Option<usize>;
+
+impl ModelSelection {
+    /// Parse the values of `--select` and `--exclude`. Each value may hold
+    /// several space-separated selectors.
+    pub fn parse(select: &[String], exclude: &[String]) -> Result<Self> {
+        let parse_all = |values: &[String]| -> Result<Vec<Selector>> {
+            values
+                .iter()
+                .flat_map(|v| v.split_whitespace())
+                .map(str::parse)
+                .collect()
+        };
+        Ok(Self {
+            select: parse_all(select)?,
+            exclude: parse_all(exclude)?,
+        })
+    }
+
+    /// True if neither --select nor --exclude was given
+    pub fn is_all(&self) -> bool {
+        self.select.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Names of the selected models; every model if nothing was selected.
+    ///
+    /// A `--select` selector that matches no model is an error, so typos
+    /// don't silently run nothing.
+    pub fn resolve(
+        &self,
+        graph: &DependencyGraph,
+        config: &Config,
+        project_dir: &Path,
+    ) -> Result<HashSet<String>> {
+        let mut selected: HashSet<String> = if self.select.is_empty() {
+            graph.models().keys().cloned().collect()
+        } else {
+            let mut selected = HashSet::new();
+            for selector in &self.select {
+                let matched = selector.resolve(graph, config, project_dir);
+                if matched.is_empty() {
+                    return Err(CliError::InvalidSelector {
+                        selector: selector.text.clone(),
+                        message: "matches no models".to_string(),
+                    }
+                    .into());
+                }
+                selected.extend(matched);
+            }
+            selected
+        };
+
+        for selector in &self.exclude {
+            for name in selector.resolve(graph, config, project_dir) {
+                selected.remove(&name);
+            }
+        }
+
+        Ok(selected)
+    }
+
+    /// Keep the selected models of an execution order, in order
+    pub fn filter_order(
+        &self,
+        execution_order: &[String],
+        graph: &DependencyGraph,
+        config: &Config,
+        project_dir: &Path,
+    ) -> Result<Vec<String>> {
+        let selected = self.resolve(graph, config, project_dir)?;
+        Ok(execution_order
+            .iter()
+            .filter(|name| selected.contains(*name))
+            .cloned()
+            .collect())
+    }
+}
+
+impl Selector {
+    fn resolve(
+        &self,
+        graph: &DependencyGraph,
+        config: &Config,
+        project_dir: &Path,
+    ) -> HashSet<String> {
+        let mut sets = self
+            .criteria
+            .iter()
+            .map(|c| c.resolve(graph, config, project_dir));
+        let first = sets.next().unwrap_or_default();
+        sets.fold(first, |acc, set| acc.intersection(&set).cloned().collect())
+    }
+}
+
+impl FromStr for Selector {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let criteria = text
+            .split(',')
+            .map(|part| Criterion::parse(part.trim()))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|message| CliError::InvalidSelector {
+                selector: text.to_string(),
+                message,
+            })?;
+        Ok(Self {
+            text: text.to_string(),
+            criteria,
+        })
+    }
+}
+
+impl Criterion {
+    fn parse(text: &str) -> Result<Self, String> {
+        let (with_ancestors_of_children, rest) = match text.strip_prefix('@') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+
+        // `+orders` and `2+orders`
+        let (parents, rest) = match rest.split_once('+') {
+            Some((digits, rest)) if digits.chars().all(|c| c.is_ascii_digit()) => {
+                (Some(parse_depth(digits)?), rest)
+            }
+            _ => (None, rest),
+        };
+        // `orders+` and `orders+2`
+        let (children, rest) = match rest.rsplit_once('+') {
+            Some((rest, digits)) if digits.chars().all(|c| c.is_ascii_digit()) => {
+                (Some(parse_depth(digits)?), rest)
+            }
+            _ => (None, rest),
+        };
+
+        if with_ancestors_of_children && (parents.is_some() || children.is_some()) {
+            return Err("'@' can't be combined with '+'".to_string());
+        }
+
+        let method = match rest.split_once(':') {
+            Some(("tag", tag)) => Method::Tag(tag.to_string()),
+            Some(("path", path)) => Method::Path(path.to_string()),
+            Some((method, _)) => {
+                return Err(format!(
+                    "unknown method '{}'. Expected tag:, path: or a model name",
+                    method
+                ))
+            }
+            None if rest.contains('/') || rest.ends_with(".sql") => Method::Path(rest.to_string()),
+            None => Method::Name(rest.to_string()),
+        };
+        let value = match &method {
+            Method::Name(v) | Method::Tag(v) | Method::Path(v) => v,
+        };
+        if value.is_empty() || value.contains('+') || value.contains('@') {
+            return Err(format!("expected a model, tag or path in '{}'", text));
+        }
+
+        Ok(Self {
+            method,
+            parents,
+            children,
+            with_ancestors_of_children,
+        })
+    }
+
+    fn resolve(
+        &self,
+        graph: &DependencyGraph,
+        config: &Config,
+        project_dir: &Path,
+    ) -> HashSet<String> {
+        let matched: Vec<&String> = graph
+            .models()
+            .iter()
+            .filter(|(_, model)| self.method.matches(model, config, project_dir))
+            .map(|(name, _)| name)
+            .collect();
+
+        let mut selected: HashSet<String> = matched.iter().map(|n| n.to_string()).collect();
+        for name in matched {
+            if let Some(depth) = self.parents {
+                selected.extend(graph.ancestors(name, depth));
+            }
+            if let Some(depth) = self.children {
+                selected.extend(graph.descendants(name, depth));
+            }
+            if self.with_ancestors_of_children {
+                let descendants = graph.descendants(name, None);
+                for descendant in &descendants {
+                    selected.extend(graph.ancestors(descendant, None));
+                }
+                selected.extend(descendants);
+            }
+        }
+        selected
+    }
+}
+
+impl Method {
+    fn matches(&self, model: &ModelFile, config: &Config, project_dir: &Path) -> bool {
+        match self {
+            Method::Name(pattern) => glob_match(pattern, &model.name),
+            Method::Tag(tag) => config
+                .model_config(&model.name, &model.annotations)
+                .tags
+                .iter()
+                .any(|t| t == tag),
+            Method::Path(pattern) => {
+                let path = relative_path(project_dir, &model.path);
+                let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+                if pattern.contains(['*', '?']) {
+                    glob_match(pattern, &path)
+                } else {
+                    path == pattern || path.starts_with(&format!("{}/", pattern))
+                }
+            }
+        }
+    }
+}
+
+fn parse_depth(digits: &str) -> Result<Depth, String> {
+    if digits.is_empty() {
+        return Ok(None);
+    }
+    digits
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("invalid depth '{}'", digits))
+}
+
+/// Match `*` (any run of characters) and `?` (one character)
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // Positions to resume from after the last `*`
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write;
+    use crate::ModelDiscovery;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("stg_*", "stg_orders"));
+        assert!(glob_match("*_daily", "revenue_daily"));
+        assert!(glob_match("s?g_*s", "stg_orders"));
+        assert!(glob_match(
+            "models/*/orders.sql",
+            "models/staging/orders.sql"
+        ));
+        assert!(!glob_match("stg_*", "orders"));
+        assert!(!glob_match("orders", "orders_v2"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |text: &str| text.parse::<Selector>().unwrap_err().to_string();
+
+        assert!(err("owner:me").contains("unknown method 'owner'"));
+        assert!(err("@+orders").contains("'@' can't be combined with '+'"));
+        assert!(err("tag:").contains("expected a model, tag or path"));
+        assert!(err("+").contains("expected a model, tag or path"));
+    }
+
+    #[test]
+    fn test_select_and_exclude() {
+        // raw -> stg_orders -> orders -> revenue
+        //                            \-> (revenue also reads stg_customers)
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "smelt.yml",
+            "name: shop\nversion: 1\ntargets: {}\nmodels:\n  revenue:\n    tags: [finance]\n",
+        );
+        write(
+            dir.path(),
+            "models/staging/stg_orders.sql",
+            "-- @tags: nightly\nSELECT 1 AS id",
+        );
+        write(
+            dir.path(),
+            "models/staging/stg_customers.sql",
+            "SELECT 1 AS id",
+        );
+        write(
+            dir.path(),
+            "models/orders.sql",
+            "SELECT * FROM smelt.ref('stg_orders')",
+        );
+        write(
+            dir.path(),
+            "models/marts/revenue.sql",
+            "SELECT * FROM smelt.ref('orders') JOIN smelt.ref('stg_customers') USING (id)",
+        );
+
+        let config = Config::load(dir.path()).unwrap();
+        let models = ModelDiscovery::new(dir.path().to_path_buf(), config.model_paths.clone())
+            .discover_models()
+            .unwrap();
+        let graph = DependencyGraph::build(models, None).unwrap();
+        let order = graph.execution_order().unwrap();
+
+        let select = |select: &[&str], exclude: &[&str]| {
+            let to_vec = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            let mut names: Vec<String> = ModelSelection::parse(&to_vec(select), &to_vec(exclude))
+                .unwrap()
+                .resolve(&graph, &config, dir.path())
+                .unwrap()
+                .into_iter()
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(select(&[], &[]).len(), 4);
+        assert_eq!(select(&["orders"], &[]), vec!["orders"]);
+        assert_eq!(select(&["+orders"], &[]), vec!["orders", "stg_orders"]);
+        assert_eq!(select(&["orders+"], &[]), vec!["orders", "revenue"]);
+        assert_eq!(
+            select(&["@stg_orders"], &[]),
+            vec!["orders", "revenue", "stg_customers", "stg_orders"]
+        );
+        assert_eq!(
+            select(&["1+revenue"], &[]),
+            vec!["orders", "revenue", "stg_customers"]
+        );
+        assert_eq!(select(&["stg_*"], &[]), vec!["stg_customers", "stg_orders"]);
+        assert_eq!(
+            select(&["tag:nightly tag:finance"], &[]),
+            vec!["revenue", "stg_orders"]
+        );
+        assert_eq!(
+            select(&["path:models/staging"], &[]),
+            vec!["stg_customers", "stg_orders"]
+        );
+        assert_eq!(select(&["models/marts/revenue.sql"], &[]), vec!["revenue"]);
+        assert_eq!(
+            select(&["+revenue,stg_*"], &[]),
+            vec!["stg_customers", "stg_orders"]
+        );
+        assert_eq!(
+            select(&["+revenue"], &["tag:nightly", "stg_customers"]),
+            vec!["orders", "revenue"]
+        );
+        assert_eq!(
+            select(&[], &["path:models/staging/"]),
+            vec!["orders", "revenue"]
+        );
+
+        let selection = ModelSelection::parse(&["orders+".to_string()], &[]).unwrap();
+        assert_eq!(
+            selection
+                .filter_order(&order, &graph, &config, dir.path())
+                .unwrap(),
+            vec!["orders", "revenue"]
+        );
+
+        let err = ModelSelection::parse(&["ordres".to_string()], &[])
+            .unwrap()
+            .resolve(&graph, &config, dir.path())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid selector 'ordres': matches no models\n\n  = help: Select models by name (stg_*), tag:<tag>, path:<dir>, or with graph operators (+model, model+, @model)"
+        );
+    }
+}
@@ -0,0 +1,10 @@
+//! Fixtures shared by the tests of this crate.
+
+use std::path::Path;
+
+/// Write `text` to `path` under `dir`, creating its directories
+pub fn write(dir: &Path, path: &str, text: &str) {
+    let path = dir.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, text).unwrap();
+}
@@ -0,0 +1,792 @@
+/// Incrementality analysis
+///
+/// An incremental run recomputes only the rows at or after the watermark: it
+/// filters the upstream the time column is read from, deletes that range
+/// from the table and inserts the result. Whether that builds the same table
+/// as a full rebuild depends on the shape of the query:
+///
+/// - append-safe: every output row comes from one upstream row, so new
+///   upstream rows only add output rows
+/// - merge-safe: output rows combine several upstream rows, but only rows of
+///   the same time value, so recomputing a range replaces them whole
+/// - full-refresh only: some output rows depend on upstream rows outside
+///   their time range (an aggregate over all time, a window across days, a
+///   LIMIT), and only a full rebuild gets them right
+///
+/// Every SELECT in the model is checked, including CTE bodies and subqueries,
+/// and each finding is kept as a reason so the classification can be
+/// explained.
+use std::fmt;
+use std::path::PathBuf;
+
+use smelt_parser::{
+    CompoundSelect, Expr, File as AstFile, FunctionCall, JoinType, LiteralKind, SelectStmt,
+    TextRange, WindowSpec,
+};
+
+use crate::names::is_niladic_function;
+use crate::{ColumnSource, Schema};
+
+/// How a model can be kept up to date
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Incrementality {
+    /// New rows can be appended
+    AppendSafe,
+    /// A recomputed time range can replace the rows it covers
+    MergeSafe,
+    /// Only a full rebuild is correct
+    FullRefreshOnly,
+}
+
+impl Incrementality {
+    pub fn is_incremental(self) -> bool {
+        self != Incrementality::FullRefreshOnly
+    }
+}
+
+impl fmt::Display for Incrementality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Incrementality::AppendSafe => "append-safe",
+            Incrementality::MergeSafe => "merge-safe",
+            Incrementality::FullRefreshOnly => "full-refresh only",
+        })
+    }
+}
+
+/// One finding of the analysis
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IncrementalityReason {
+    /// The best classification this part of the query allows
+    pub allows: Incrementality,
+    pub message: String,
+    /// The part of the query the finding is about
+    pub range: TextRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalityAnalysis {
+    /// The time column the model was analyzed for, if one is set
+    pub time_column: Option<String>,
+    /// The most restrictive classification of any reason
+    pub classification: Incrementality,
+    /// Findings, in source order after the time column's
+    pub reasons: Vec<IncrementalityReason>,
+}
+
+impl IncrementalityAnalysis {
+    /// Reasons the model can only be rebuilt in full
+    pub fn blockers(&self) -> impl Iterator<Item = &IncrementalityReason> + '_ {
+        self.reasons
+            .iter()
+            .filter(|r| r.allows == Incrementality::FullRefreshOnly)
+    }
+}
+
+/// Aggregate functions, which combine the rows of a group
+const AGGREGATES: &[&str] = &[
+    "COUNT",
+    "COUNT_IF",
+    "SUM",
+    "MIN",
+    "MAX",
+    "PRODUCT",
+    "BOOL_AND",
+    "BOOL_OR",
+    "BIT_AND",
+    "BIT_OR",
+    "ANY_VALUE",
+    "AVG",
+    "MEDIAN",
+    "MODE",
+    "QUANTILE",
+    "PERCENTILE_CONT",
+    "PERCENTILE_DISC",
+    "STDDEV",
+    "STDDEV_SAMP",
+    "STDDEV_POP",
+    "VARIANCE",
+    "VAR_SAMP",
+    "VAR_POP",
+    "STRING_AGG",
+    "LISTAGG",
+    "GROUP_CONCAT",
+    "ARRAY_AGG",
+    "LIST",
+    "FIRST",
+    "LAST",
+    "ARG_MIN",
+    "ARG_MAX",
+    "APPROX_COUNT_DISTINCT",
+];
+
+/// Functions returning the time of the run
+const CLOCK_FUNCTIONS: &[&str] = &[
+    "NOW",
+    "TODAY",
+    "CURRENT_DATE",
+    "CURRENT_TIMESTAMP",
+    "GET_CURRENT_TIMESTAMP",
+];
+
+pub(crate) fn analyze(
+    db: &dyn Schema,
+    path: PathBuf,
+    time_column: Option<String>,
+) -> IncrementalityAnalysis {
+    let mut analyzer = Analyzer {
+        time_column: time_column.as_deref(),
+        reasons: Vec::new(),
+    };
+
+    let parse = db.parse_file(path.clone());
+    match AstFile::cast(parse.syntax()).and_then(|file| Some((file.select_stmt()?, file))) {
+        Some((main, file)) => {
+            analyzer.check_time_column(db, path, &main);
+            for select in file.select_stmts() {
+                analyzer.check_select(&select);
+            }
+            for compound in file.syntax().descendants().filter_map(CompoundSelect::cast) {
+                analyzer.check_compound(&compound);
+            }
+
+            if analyzer
+                .reasons
+                .iter()
+                .all(|r| r.allows == Incrementality::AppendSafe)
+            {
+                analyzer.push(
+                    Incrementality::AppendSafe,
+                    main.range(),
+                    "each row is read from a single upstream row".to_string(),
+                );
+            }
+        }
+        None => analyzer.push(
+            Incrementality::FullRefreshOnly,
+            TextRange::default(),
+            "the model has no query".to_string(),
+        ),
+    }
+
+    let reasons = analyzer.reasons;
+    IncrementalityAnalysis {
+        classification: reasons
+            .iter()
+            .map(|r| r.allows)
+            .max()
+            .unwrap_or(Incrementality::AppendSafe),
+        time_column,
+        reasons,
+    }
+}
+
+/// Where a SELECT outputs the time column
+enum TimeKey {
+    /// A select item, with its 1-based position
+    Item {
+        ordinal: usize,
+        expr: Expr,
+    },
+    /// Passed through by `*`
+    Wildcard,
+    Missing,
+}
+
+struct Analyzer<'a> {
+    time_column: Option<&'a str>,
+    reasons: Vec<IncrementalityReason>,
+}
+
+impl Analyzer<'_> {
+    fn push(&mut self, allows: Incrementality, range: TextRange, message: String) {
+        self.reasons.push(IncrementalityReason {
+            allows,
+            message,
+            range,
+        });
+    }
+
+    /// How the time column is named in messages
+    fn label(&self) -> String {
+        match self.time_column {
+            Some(column) => format!("'{}'", column),
+            None => "a time column".to_string(),
+        }
+    }
+
+    /// The time column must be read from an upstream column for the
+    /// upstream to be filtered
+    fn check_time_column(&mut self, db: &dyn Schema, path: PathBuf, main: &SelectStmt) {
+        let Some(time_column) = self.time_column else {
+            return;
+        };
+        let schema = db.model_schema(path);
+        let Some(column) = schema
+            .columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(time_column))
+        else {
+            if let Some(wildcard) = schema
+                .columns
+                .iter()
+                .find(|c| matches!(c.source, ColumnSource::Wildcard { .. }))
+            {
+                self.push(
+                    Incrementality::AppendSafe,
+                    wildcard.range,
+                    format!("'{}' is passed through by *", time_column),
+                );
+            } else {
+                self.push(
+                    Incrementality::FullRefreshOnly,
+                    main.range(),
+                    format!("the model has no column '{}'", time_column),
+                );
+            }
+            return;
+        };
+
+        let (allows, message) = match &column.source {
+            ColumnSource::FromModel {
+                model_name,
+                column_name,
+            } => (
+                Incrementality::AppendSafe,
+                format!(
+                    "'{}' is read from {}.{}",
+                    time_column, model_name, column_name
+                ),
+            ),
+            ColumnSource::ExternalTable {
+                table_name,
+                column_name,
+            } => (
+                Incrementality::AppendSafe,
+                format!(
+                    "'{}' is read from {}.{}",
+                    time_column, table_name, column_name
+                ),
+            ),
+            ColumnSource::Wildcard { model_name } => (
+                Incrementality::AppendSafe,
+                format!("'{}' is passed through from {}", time_column, model_name),
+            ),
+            ColumnSource::Computed | ColumnSource::ComputedFrom { .. } => (
+                Incrementality::FullRefreshOnly,
+                format!(
+                    "'{}' is computed by an expression, so its upstream rows can't be selected",
+                    time_column
+                ),
+            ),
+            ColumnSource::Unknown => (
+                Incrementality::FullRefreshOnly,
+                format!(
+                    "the upstream column of '{}' can't be determined",
+                    time_column
+                ),
+            ),
+        };
+        self.push(allows, column.range, message);
+    }
+
+    fn check_select(&mut self, select: &SelectStmt) {
+        let key = self.time_key(select);
+        let calls = select.function_calls();
+        let label = self.label();
+
+        // Aggregation
+        let aggregates: Vec<&FunctionCall> = calls
+            .iter()
+            .filter(|f| !f.is_window_function() && is_aggregate(f))
+            .collect();
+        let group_by = select.group_by_clause();
+        let grouped_by_time = group_by
+            .as_ref()
+            .is_some_and(|g| g.expressions().any(|e| self.matches_key(&e, &key)));
+        match &group_by {
+            Some(group_by) if grouped_by_time => self.push(
+                Incrementality::MergeSafe,
+                group_by.range(),
+                format!(
+                    "GROUP BY includes {}, so a recomputed range rebuilds whole groups",
+                    label
+                ),
+            ),
+            Some(group_by) => self.push(
+                Incrementality::FullRefreshOnly,
+                group_by.range(),
+                format!(
+                    "GROUP BY doesn't include {}, so groups combine rows from different time ranges",
+                    label
+                ),
+            ),
+            None => {
+                if let Some(first) = aggregates.first() {
+                    self.push(
+                        Incrementality::FullRefreshOnly,
+                        first.range(),
+                        format!("{} aggregates every row into one", first.text().trim()),
+                    );
+                }
+            }
+        }
+        // Window functions
+        for window in calls.iter().filter(|f| f.is_window_function()) {
+            let name = window.name().unwrap_or_default().to_uppercase();
+            let partitioned_by_time = self
+                .window_partitions(select, window)
+                .iter()
+                .any(|e| self.matches_key(e, &key));
+            if partitioned_by_time {
+                self.push(
+                    Incrementality::MergeSafe,
+                    window.range(),
+                    format!("window function {} is partitioned by {}", name, label),
+                );
+            } else {
+                self.push(
+                    Incrementality::FullRefreshOnly,
+                    window.range(),
+                    format!(
+                        "window function {} isn't partitioned by {}, so it reads rows across time ranges",
+                        name, label
+                    ),
+                );
+            }
+        }
+
+        // SELECT DISTINCT
+        if let Some(range) = select.distinct_range() {
+            if matches!(key, TimeKey::Missing) {
+                self.push(
+                    Incrementality::FullRefreshOnly,
+                    range,
+                    format!(
+                        "DISTINCT without {} removes duplicates across time ranges",
+                        label
+                    ),
+                );
+            } else {
+                self.push(
+                    Incrementality::MergeSafe,
+                    range,
+                    format!(
+                        "DISTINCT includes {}, so duplicates are removed within a time range",
+                        label
+                    ),
+                );
+            }
+        }
+
+        // Joins
+        for join in select.from_clause().iter().flat_map(|f| f.joins()) {
+            let table = join
+                .table_ref()
+                .and_then(|t| t.alias().or_else(|| t.table_name()))
+                .unwrap_or_else(|| "a subquery".to_string());
+            match join.join_type() {
+                JoinType::Right | JoinType::Full => self.push(
+                    Incrementality::FullRefreshOnly,
+                    join.range(),
+                    format!(
+                        "{} JOIN keeps unmatched rows of {}, which a time range can't select",
+                        if join.join_type() == JoinType::Right {
+                            "RIGHT"
+                        } else {
+                            "FULL"
+                        },
+                        table
+                    ),
+                ),
+                _ => self.push(
+                    Incrementality::MergeSafe,
+                    join.range(),
+                    format!(
+                        "rows joined from {} are read when their range is recomputed; \
+                         older rows keep earlier matches",
+                        table
+                    ),
+                ),
+            }
+        }
+
+        // Filters relative to the time of the run
+        if let Some(condition) = select.where_clause().and_then(|w| w.expression()) {
+            let clock = condition
+                .column_refs()
+                .into_iter()
+                .find(is_niladic_function)
+                .map(|c| c.name().to_uppercase())
+                .or_else(|| {
+                    calls
+                        .iter()
+                        .filter(|f| condition.range().contains_range(f.range()))
+                        .filter_map(|f| f.name())
+                        .map(|name| name.to_uppercase())
+                        .find(|name| CLOCK_FUNCTIONS.contains(&name.as_str()))
+                });
+            if let Some(clock) = clock {
+                self.push(
+                    Incrementality::FullRefreshOnly,
+                    condition.range(),
+                    format!(
+                        "WHERE depends on {}, so rows built earlier are never filtered out",
+                        clock
+                    ),
+                );
+            }
+        }
+
+        if let Some(limit) = select.limit_clause().filter(|l| l.expression().is_some()) {
+            self.push(
+                Incrementality::FullRefreshOnly,
+                limit.range(),
+                "LIMIT picks rows from the whole result".to_string(),
+            );
+        }
+    }
+
+    fn check_compound(&mut self, compound: &CompoundSelect) {
+        let label = self.label();
+        let keyed = compound
+            .select_stmts()
+            .all(|s| !matches!(self.time_key(&s), TimeKey::Missing));
+        for (op, all) in compound.operators() {
+            if all {
+                continue;
+            }
+            let op = format!("{:?}", op).to_uppercase();
+            if keyed {
+                self.push(
+                    Incrementality::MergeSafe,
+                    compound.range(),
+                    format!(
+                        "{} includes {}, so duplicates are removed within a time range",
+                        op, label
+                    ),
+                );
+            } else {
+                self.push(
+                    Incrementality::FullRefreshOnly,
+                    compound.range(),
+                    format!("{} without {} compares rows across time ranges", op, label),
+                );
+            }
+        }
+
+        if let Some(limit) = compound.limit_clause().filter(|l| l.expression().is_some()) {
+            self.push(
+                Incrementality::FullRefreshOnly,
+                limit.range(),
+                "LIMIT picks rows from the whole result".to_string(),
+            );
+        }
+    }
+
+    fn time_key(&self, select: &SelectStmt) -> TimeKey {
+        let Some(time_column) = self.time_column else {
+            return TimeKey::Missing;
+        };
+        let items: Vec<_> = select
+            .select_list()
+            .iter()
+            .flat_map(|l| l.items())
+            .collect();
+        for (index, item) in items.iter().enumerate() {
+            let named = item
+                .column_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case(time_column));
+            if let (true, Some(expr)) = (named, item.expression()) {
+                return TimeKey::Item {
+                    ordinal: index + 1,
+                    expr,
+                };
+            }
+        }
+        if items.iter().any(|item| item.wildcard().is_some()) {
+            TimeKey::Wildcard
+        } else {
+            TimeKey::Missing
+        }
+    }
+
+    /// Whether a GROUP BY or PARTITION BY expression is the time column
+    fn matches_key(&self, expr: &Expr, key: &TimeKey) -> bool {
+        let Some(time_column) = self.time_column else {
+            return false;
+        };
+        let names_time_column = expr
+            .as_column_ref()
+            .is_some_and(|c| c.name().eq_ignore_ascii_case(time_column));
+        match key {
+            TimeKey::Item {
+                ordinal,
+                expr: item,
+            } => {
+                let is_ordinal = expr.as_literal().is_some_and(|l| {
+                    l.kind() == LiteralKind::Number && l.text().trim() == ordinal.to_string()
+                });
+                names_time_column
+                    || is_ordinal
+                    || normalize(&expr.text()) == normalize(&item.text())
+            }
+            TimeKey::Wildcard => names_time_column,
+            TimeKey::Missing => false,
+        }
+    }
+
+    /// PARTITION BY expressions of a window, following named windows
+    fn window_partitions(&self, select: &SelectStmt, window: &FunctionCall) -> Vec<Expr> {
+        let Some(over) = window.over_clause() else {
+            return Vec::new();
+        };
+        let named = |name: Option<String>| -> Option<WindowSpec> {
+            select.window_clause()?.find(&name?)?.window_spec()
+        };
+        let mut spec = over.window_spec().or_else(|| named(over.window_name()));
+        // A window may extend a named one, which then supplies the partitioning
+        for _ in 0..8 {
+            match spec {
+                Some(ref s) if s.partition_by().is_empty() && s.base_window_name().is_some() => {
+                    spec = named(s.base_window_name());
+                }
+                _ => break,
+            }
+        }
+        spec.map(|s| s.partition_by()).unwrap_or_default()
+    }
+}
+
+fn is_aggregate(func: &FunctionCall) -> bool {
+    let name = func.name().unwrap_or_default().to_uppercase();
+    AGGREGATES.contains(&name.as_str())
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<String>().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, Inputs};
+    use std::sync::Arc;
+
+    fn analyze_sql(sql: &str, time_column: Option<&str>) -> Arc<IncrementalityAnalysis> {
+        let mut db = Database::default();
+        let upstream = PathBuf::from("models/orders.sql");
+        db.set_file_text(
+            upstream.clone(),
+            Arc::new(
+                "SELECT order_id, customer_id, order_date, amount FROM raw.orders".to_string(),
+            ),
+        );
+        let path = PathBuf::from("models/model.sql");
+        db.set_file_text(path.clone(), Arc::new(sql.to_string()));
+        db.set_all_files(Arc::new(vec![upstream, path.clone()]));
+        db.incrementality(path, time_column.map(str::to_string))
+    }
+
+    fn messages(analysis: &IncrementalityAnalysis) -> Vec<&str> {
+        analysis
+            .reasons
+            .iter()
+            .map(|r| r.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_row_by_row_models_are_append_safe() {
+        let analysis = analyze_sql(
+            "SELECT order_id, order_date, amount * 2 AS doubled \
+             FROM smelt.ref('orders') WHERE amount > 0",
+            Some("order_date"),
+        );
+        assert_eq!(analysis.classification, Incrementality::AppendSafe);
+        assert_eq!(
+            messages(&analysis),
+            vec![
+                "'order_date' is read from orders.order_date",
+                "each row is read from a single upstream row",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grouping_by_time_is_merge_safe() {
+        for group_by in ["order_date", "1", "o.order_date"] {
+            let analysis = analyze_sql(
+                &format!(
+                    "SELECT o.order_date, AVG(o.amount) AS avg_amount, COUNT(DISTINCT o.customer_id) AS customers \
+                     FROM smelt.ref('orders') o GROUP BY {}",
+                    group_by
+                ),
+                Some("order_date"),
+            );
+            assert_eq!(
+                analysis.classification,
+                Incrementality::MergeSafe,
+                "{}",
+                group_by
+            );
+        }
+
+        let analysis = analyze_sql(
+            "SELECT customer_id, MIN(order_date) AS order_date, AVG(amount) AS avg_amount \
+             FROM smelt.ref('orders') GROUP BY customer_id",
+            Some("order_date"),
+        );
+        assert_eq!(analysis.classification, Incrementality::FullRefreshOnly);
+        let blockers: Vec<_> = analysis.blockers().map(|r| r.message.as_str()).collect();
+        assert_eq!(
+            blockers,
+            vec![
+                "'order_date' is computed by an expression, so its upstream rows can't be selected",
+                "GROUP BY doesn't include 'order_date', so groups combine rows from different time ranges",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_windows_must_be_partitioned_by_time() {
+        let analysis = analyze_sql(
+            "SELECT order_id, order_date, \
+             ROW_NUMBER() OVER (PARTITION BY order_date ORDER BY amount DESC) AS daily_rank, \
+             SUM(amount) OVER w AS running \
+             FROM smelt.ref('orders') WINDOW w AS (PARTITION BY customer_id ORDER BY order_date)",
+            Some("order_date"),
+        );
+        assert_eq!(analysis.classification, Incrementality::FullRefreshOnly);
+        let allows: Vec<_> = analysis.reasons.iter().map(|r| r.allows).collect();
+        assert_eq!(
+            allows,
+            vec![
+                Incrementality::AppendSafe,
+                Incrementality::MergeSafe,
+                Incrementality::FullRefreshOnly,
+            ]
+        );
+        assert_eq!(
+            analysis.reasons[2].message,
+            "window function SUM isn't partitioned by 'order_date', so it reads rows across time ranges"
+        );
+    }
+
+    #[test]
+    fn test_distinct_joins_and_limits() {
+        let analysis = analyze_sql(
+            "SELECT DISTINCT o.order_date, c.region \
+             FROM smelt.ref('orders') o JOIN raw.customers c ON o.customer_id = c.id",
+            Some("order_date"),
+        );
+        assert_eq!(analysis.classification, Incrementality::MergeSafe);
+
+        let analysis = analyze_sql(
+            "SELECT o.order_date, c.region \
+             FROM smelt.ref('orders') o FULL JOIN raw.customers c ON o.customer_id = c.id",
+            Some("order_date"),
+        );
+        assert_eq!(
+            analysis.blockers().next().unwrap().message,
+            "FULL JOIN keeps unmatched rows of c, which a time range can't select"
+        );
+
+        let analysis = analyze_sql(
+            "SELECT order_date, amount FROM smelt.ref('orders') \
+             WHERE order_date > CURRENT_DATE - 7 ORDER BY amount DESC LIMIT 10",
+            Some("order_date"),
+        );
+        let blockers: Vec<_> = analysis.blockers().map(|r| r.message.as_str()).collect();
+        assert_eq!(
+            blockers,
+            vec![
+                "WHERE depends on CURRENT_DATE, so rows built earlier are never filtered out",
+                "LIMIT picks rows from the whole result",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subqueries_and_set_operations() {
+        // The scalar subquery aggregates all of orders
+        let analysis = analyze_sql(
+            "SELECT order_date, amount / (SELECT SUM(amount) FROM smelt.ref('orders')) AS share \
+             FROM smelt.ref('orders')",
+            Some("order_date"),
+        );
+        assert_eq!(analysis.classification, Incrementality::FullRefreshOnly);
+
+        let analysis = analyze_sql(
+            "SELECT order_date, order_id FROM smelt.ref('orders') \
+             UNION SELECT order_date, order_id FROM raw.returns",
+            Some("order_date"),
+        );
+        assert_eq!(analysis.classification, Incrementality::MergeSafe);
+
+        // Without a time column, only row-by-row queries are incremental
+        let analysis = analyze_sql(
+            "SELECT customer_id, COUNT(*) AS orders FROM smelt.ref('orders') GROUP BY 1",
+            None,
+        );
+        assert_eq!(analysis.classification, Incrementality::FullRefreshOnly);
+        assert_eq!(
+            messages(&analysis),
+            vec!["GROUP BY doesn't include a time column, so groups combine rows from different time ranges"]
+        );
+    }
+
+    #[test]
+    fn test_incremental_models_warn_when_rebuilt_in_full() {
+        let mut db = Database::default();
+        let path = PathBuf::from("models/totals.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "-- @incremental.time_column: order_date\n\
+                 SELECT order_date, SUM(amount) AS total FROM raw.orders"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![path.clone()]));
+
+        let diagnostics = db.diagnostics(path);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, crate::DiagnosticSeverity::Warning);
+        assert_eq!(
+            diagnostics[0].message,
+            "Incremental model will be rebuilt in full: SUM(amount) aggregates every row into one"
+        );
+        assert_eq!(diagnostics[0].range.start.line, 1);
+    }
+
+    #[test]
+    fn test_incremental_settings_from_smelt_yml() {
+        let mut db = Database::default();
+        let path = PathBuf::from("models/totals.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new("SELECT order_date, SUM(amount) AS total FROM raw.orders".to_string()),
+        );
+        db.set_all_files(Arc::new(vec![path.clone()]));
+        assert!(db.diagnostics(path.clone()).is_empty());
+
+        let settings = crate::ProjectSettings::from_yaml(
+            "models:
+  totals:
+    materialization: incremental
+    incremental:
+      time_column: order_date
+",
+        )
+        .unwrap();
+        db.set_project_settings(Arc::new(settings));
+
+        let diagnostics = db.diagnostics(path);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Incremental model will be rebuilt in full: SUM(amount) aggregates every row into one"
+        );
+    }
+}
@@ -6,11 +6,34 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use smelt_parser::{self, File as AstFile, RefCall};
+use smelt_parser::{
+    self, AnnotationErrorKind, ColumnRef, Cte, Expr, File as AstFile, RefCall, SelectStmt,
+    SourceCall, TableRef, Wildcard,
+};
+pub use smelt_parser::ModelAnnotations;
 
 pub mod schema;
 pub use schema::{Column, ColumnSource, ModelSchema};
 
+pub mod sources;
+pub use sources::{SourceCatalog, SourceColumn, SourceTable};
+
+pub mod project;
+pub use project::ProjectSettings;
+
+pub mod types;
+pub use types::SqlType;
+
+pub mod names;
+pub use names::{BindingTarget, ColumnBinding};
+
+pub mod lineage;
+pub use lineage::{Direction, EdgeKind, LineageGraph, LineageNode, ModelLineage, NodeKind};
+
+pub mod incrementality;
+pub use incrementality::{Incrementality, IncrementalityAnalysis, IncrementalityReason};
+use types::{TypeError, TypeScope};
+
 /// Input queries - these are set by the LSP when files change
 #[salsa::query_group(InputsStorage)]
 pub trait Inputs {
@@ -22,6 +45,14 @@ pub trait Inputs {
     /// Get all file paths in the project
     #[salsa::input]
     fn all_files(&self) -> Arc<Vec<PathBuf>>;
+
+    /// Tables declared in sources.yml
+    #[salsa::input]
+    fn source_catalog(&self) -> Arc<SourceCatalog>;
+
+    /// Settings from smelt.yml
+    #[salsa::input]
+    fn project_settings(&self) -> Arc<ProjectSettings>;
 }
 
 /// Syntax queries - parsing and CST construction
@@ -37,6 +68,15 @@ pub trait Syntax: Inputs {
     /// Extract all ref() calls from a model with their positions
     fn model_refs(&self, path: PathBuf) -> Arc<Vec<RefLocation>>;
 
+    /// Extract all smelt.source() calls from a model with their positions
+    fn model_sources(&self, path: PathBuf) -> Arc<Vec<SourceLocation>>;
+
+    /// Leading `-- @key: value` annotations of a model
+    fn model_annotations(&self, path: PathBuf) -> Arc<ModelAnnotations>;
+
+    /// A model's annotations merged over its smelt.yml `models:` entry
+    fn model_settings(&self, path: PathBuf) -> Arc<ModelAnnotations>;
+
     /// Get all models in the project
     fn all_models(&self) -> Arc<HashMap<PathBuf, Model>>;
 }
@@ -56,27 +96,65 @@ pub trait Semantic: Syntax {
 #[salsa::query_group(SchemaStorage)]
 pub trait Schema: Semantic {
     /// Extract the output schema from a model
+    #[salsa::cycle(model_schema_cycle)]
     fn model_schema(&self, path: PathBuf) -> Arc<ModelSchema>;
 
     /// Get available columns at a specific position in a file
     /// (for autocomplete context)
     fn available_columns(&self, path: PathBuf) -> Arc<Vec<Column>>;
+
+    /// Column lineage of every model in the project
+    fn lineage_graph(&self) -> Arc<LineageGraph>;
+
+    /// Bind every column reference in a file to the relation providing it
+    fn column_bindings(&self, path: PathBuf) -> Arc<Vec<ColumnBinding>>;
+
+    /// Unknown and ambiguous column references
+    fn name_diagnostics(&self, path: PathBuf) -> Arc<Vec<Diagnostic>>;
+
+    /// Type mismatches in a model's expressions
+    fn type_diagnostics(&self, path: PathBuf) -> Arc<Vec<Diagnostic>>;
+
+    /// Whether a model can be run incrementally on `time_column`, and why
+    fn incrementality(
+        &self,
+        path: PathBuf,
+        time_column: Option<String>,
+    ) -> Arc<IncrementalityAnalysis>;
+
+    /// Incremental models whose query can only be rebuilt in full
+    fn incrementality_diagnostics(&self, path: PathBuf) -> Arc<Vec<Diagnostic>>;
+
+    /// All diagnostics for a file: syntax, references, column names, types and incrementality
+    fn diagnostics(&self, path: PathBuf) -> Arc<Vec<Diagnostic>>;
 }
 
 /// The main database that combines all query groups
 #[salsa::database(InputsStorage, SyntaxStorage, SemanticStorage, SchemaStorage)]
-#[derive(Default)]
 pub struct Database {
     storage: salsa::Storage<Self>,
 }
 
+impl Default for Database {
+    fn default() -> Self {
+        let mut db = Self {
+            storage: Default::default(),
+        };
+        // A project without sources.yml declares no sources
+        db.set_source_catalog(Arc::new(SourceCatalog::default()));
+        db.set_project_settings(Arc::new(ProjectSettings::default()));
+        db
+    }
+}
+
 impl salsa::Database for Database {}
 
 // Query implementations
 
 fn parse_file(db: &dyn Syntax, path: PathBuf) -> Arc<smelt_parser::Parse> {
     let text = db.file_text(path);
-    Arc::new(smelt_parser::parse(&text))
+    let options = db.project_settings().lex_options;
+    Arc::new(smelt_parser::parse_with(&text, options))
 }
 
 fn parse_model(db: &dyn Syntax, path: PathBuf) -> Option<Arc<Model>> {
@@ -124,6 +202,44 @@ fn model_refs(db: &dyn Syntax, path: PathBuf) -> Arc<Vec<RefLocation>> {
     }
 }
 
+fn model_sources(db: &dyn Syntax, path: PathBuf) -> Arc<Vec<SourceLocation>> {
+    let parse = db.parse_file(path.clone());
+    let text = db.file_text(path);
+
+    let sources = match AstFile::cast(parse.syntax()) {
+        Some(file) => file
+            .sources()
+            .map(|source| SourceLocation {
+                schema: source.schema_name(),
+                table: source.table_name(),
+                is_well_formed: source.is_well_formed(),
+                range: smelt_parser::ast::text_range_to_range(&text, source.range()),
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Arc::new(sources)
+}
+
+fn model_annotations(db: &dyn Syntax, path: PathBuf) -> Arc<ModelAnnotations> {
+    let parse = db.parse_file(path);
+    match AstFile::cast(parse.syntax()) {
+        Some(file) => Arc::new(smelt_parser::parse_annotations(&file).0),
+        None => Arc::new(ModelAnnotations::default()),
+    }
+}
+
+fn model_settings(db: &dyn Syntax, path: PathBuf) -> Arc<ModelAnnotations> {
+    let model_name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let annotations = db.model_annotations(path);
+    Arc::new(db.project_settings().model_settings(&model_name, &annotations))
+}
+
 fn all_models(db: &dyn Syntax) -> Arc<HashMap<PathBuf, Model>> {
     let files = db.all_files();
     let mut models = HashMap::new();
@@ -178,6 +294,26 @@ fn file_diagnostics(db: &dyn Semantic, path: PathBuf) -> Arc<Vec<Diagnostic>> {
         return Arc::new(diagnostics);
     }
 
+    // Check header annotations
+    if let Some(file) = AstFile::cast(parse.syntax()) {
+        let text = db.file_text(path.clone());
+        for error in smelt_parser::parse_annotations(&file).1 {
+            let severity = match error.kind {
+                AnnotationErrorKind::UnknownKey | AnnotationErrorKind::Misplaced => {
+                    DiagnosticSeverity::Warning
+                }
+                AnnotationErrorKind::InvalidValue | AnnotationErrorKind::Duplicate => {
+                    DiagnosticSeverity::Error
+                }
+            };
+            diagnostics.push(Diagnostic {
+                severity,
+                message: error.message,
+                range: smelt_parser::ast::text_range_to_range(&text, error.range),
+            });
+        }
+    }
+
     // Check for undefined refs with accurate positions
     let refs = db.model_refs(path.clone());
     for ref_loc in refs.iter() {
@@ -190,6 +326,26 @@ fn file_diagnostics(db: &dyn Semantic, path: PathBuf) -> Arc<Vec<Diagnostic>> {
         }
     }
 
+    // Check smelt.source() calls against sources.yml
+    let catalog = db.source_catalog();
+    for source in db.model_sources(path.clone()).iter() {
+        let message = match (&source.schema, &source.table) {
+            _ if !source.is_well_formed => {
+                "smelt.source() expects two string arguments: smelt.source('schema', 'table')"
+                    .to_string()
+            }
+            (Some(schema), Some(table)) if catalog.find(schema, table).is_none() => {
+                format!("Undefined source: '{}.{}' is not declared in sources.yml", schema, table)
+            }
+            _ => continue,
+        };
+        diagnostics.push(Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            message,
+            range: source.range,
+        });
+    }
+
     Arc::new(diagnostics)
 }
 
@@ -207,6 +363,15 @@ pub struct RefLocation {
     pub range: Range,
 }
 
+/// smelt.source() call with position information
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub is_well_formed: bool,
+    pub range: Range,
+}
+
 /// Position in a file (line, column)
 pub type Position = smelt_parser::ast::Position;
 
@@ -234,7 +399,6 @@ fn model_schema(db: &dyn Schema, path: PathBuf) -> Arc<ModelSchema> {
     // Parse the model
     let parse = db.parse_file(path.clone());
     let syntax = parse.syntax();
-    let _text = db.file_text(path.clone());
 
     let file = match AstFile::cast(syntax) {
         Some(f) => f,
@@ -246,47 +410,369 @@ fn model_schema(db: &dyn Schema, path: PathBuf) -> Arc<ModelSchema> {
         None => return Arc::new(ModelSchema::empty()),
     };
 
-    let select_list = match select_stmt.select_list() {
-        Some(l) => l,
-        None => return Arc::new(ModelSchema::empty()),
+    let columns = select_schema(db, &select_stmt, &mut Vec::new());
+
+    Arc::new(ModelSchema { columns })
+}
+
+/// Models that ref each other in a cycle have no schema
+fn model_schema_cycle(_db: &dyn Schema, _cycle: &[String], _path: &PathBuf) -> Arc<ModelSchema> {
+    Arc::new(ModelSchema::empty())
+}
+
+/// A FROM clause relation whose columns can be traced
+enum FromRelation {
+    /// smelt.ref('model_name')
+    Model(String),
+    /// A CTE defined in the same file, a subquery in the FROM clause or a
+    /// smelt.source() table, with its resolved columns
+    Derived(Vec<Column>),
+}
+
+/// A FROM clause entry and the names it can be qualified by
+struct ScopedRelation {
+    /// The alias, or else the model, CTE or table name (a qualified table
+    /// like raw.events matches both `raw.events` and `events`)
+    names: Vec<String>,
+    /// None for plain tables and table functions whose columns aren't known
+    relation: Option<FromRelation>,
+    /// Full name of an external table, for lineage of its columns
+    table_name: Option<String>,
+}
+
+impl ScopedRelation {
+    fn new(table_ref: &TableRef, default_names: Vec<String>, relation: Option<FromRelation>) -> Self {
+        let names = match table_ref.alias() {
+            Some(alias) => vec![alias],
+            None => default_names,
+        };
+        Self {
+            names,
+            relation,
+            table_name: None,
+        }
+    }
+
+    fn with_table_name(mut self, table_name: Option<String>) -> Self {
+        self.table_name = table_name;
+        self
+    }
+
+    /// Name shown in messages
+    fn display_name(&self) -> &str {
+        self.names.first().map(|n| n.as_str()).unwrap_or("subquery")
+    }
+
+    fn matches(&self, qualifier: &str) -> bool {
+        self.names.iter().any(|n| n.eq_ignore_ascii_case(qualifier))
+    }
+}
+
+/// Resolve the FROM clause of a SELECT into model refs, CTEs and subqueries.
+/// smelt.source() tables resolve to the columns declared in sources.yml.
+/// Plain tables (e.g. raw.events) are external and not included.
+fn from_relations(
+    db: &dyn Schema,
+    select_stmt: &SelectStmt,
+    visiting: &mut Vec<String>,
+) -> Vec<FromRelation> {
+    scoped_relations(db, select_stmt, visiting)
+        .into_iter()
+        .filter_map(|r| r.relation)
+        .collect()
+}
+
+/// Every entry of the FROM clause of a SELECT with the names it is known by
+fn scoped_relations(
+    db: &dyn Schema,
+    select_stmt: &SelectStmt,
+    visiting: &mut Vec<String>,
+) -> Vec<ScopedRelation> {
+    let from_clause = match select_stmt.from_clause() {
+        Some(f) => f,
+        None => return Vec::new(),
     };
 
-    // Get refs from FROM clause to determine sources
-    let from_refs: Vec<String> = if let Some(from_clause) = select_stmt.from_clause() {
-        from_clause
-            .table_refs()
-            .filter_map(|table_ref| {
-                table_ref
-                    .function_call()
-                    .and_then(RefCall::from_function_call)
-                    .and_then(|r| r.model_name())
+    let mut relations = Vec::new();
+
+    for table_ref in from_clause.table_refs() {
+        let scoped = if let Some(func) = table_ref.function_call() {
+            if let Some(model_name) =
+                RefCall::from_function_call(func.clone()).and_then(|r| r.model_name())
+            {
+                ScopedRelation::new(
+                    &table_ref,
+                    vec![model_name.clone()],
+                    Some(FromRelation::Model(model_name)),
+                )
+            } else if let Some(source) = SourceCall::from_function_call(func) {
+                let names = source
+                    .table_name()
+                    .into_iter()
+                    .chain(source.qualified_name())
+                    .collect();
+                let columns = source_columns(db, &source, &table_ref);
+                ScopedRelation::new(&table_ref, names, columns.map(FromRelation::Derived))
+                    .with_table_name(source.qualified_name())
+            } else {
+                ScopedRelation::new(&table_ref, Vec::new(), None)
+            }
+        } else if let Some(subquery) = table_ref.subquery() {
+            let columns = subquery
+                .select_stmt()
+                .map(|s| select_schema(db, &s, visiting))
+                .unwrap_or_default();
+            ScopedRelation::new(&table_ref, Vec::new(), Some(FromRelation::Derived(columns)))
+        } else if let Some(table_name) = table_ref.table_name() {
+            // Only unqualified names can refer to a CTE
+            let cte = if table_name.contains('.') {
+                None
+            } else {
+                select_stmt.find_cte(&table_name)
+            };
+            let relation = cte.map(|cte| FromRelation::Derived(cte_schema(db, &cte, visiting)));
+            let mut names = vec![table_name.clone()];
+            if let Some((_, table)) = table_name.rsplit_once('.') {
+                names.insert(0, table.to_string());
+            }
+            let external = relation.is_none().then_some(table_name);
+            ScopedRelation::new(&table_ref, names, relation).with_table_name(external)
+        } else {
+            continue;
+        };
+        relations.push(scoped);
+    }
+
+    relations
+}
+
+/// Columns named in the JOIN ... USING clauses of a SELECT
+fn using_columns(select_stmt: &SelectStmt) -> Vec<String> {
+    select_stmt
+        .from_clause()
+        .map(|f| {
+            f.joins()
+                .filter_map(|j| j.condition())
+                .flat_map(|c| c.using_columns())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Columns of a smelt.source() table, as declared in sources.yml
+fn source_columns(
+    db: &dyn Schema,
+    source: &SourceCall,
+    table_ref: &TableRef,
+) -> Option<Vec<Column>> {
+    let catalog = db.source_catalog();
+    let table = catalog.find(&source.schema_name()?, &source.table_name()?)?;
+    let table_name = table.qualified_name();
+
+    Some(
+        table
+            .columns
+            .iter()
+            .map(|column| Column {
+                name: column.name.clone(),
+                alias: None,
+                source: ColumnSource::ExternalTable {
+                    table_name: table_name.clone(),
+                    column_name: column.name.clone(),
+                },
+                data_type: SqlType::parse(&column.column_type),
+                expression: column.name.clone(),
+                range: table_ref.range(),
             })
-            .collect()
+            .collect(),
+    )
+}
+
+/// Extract the output columns of a CTE
+fn cte_schema(db: &dyn Schema, cte: &Cte, visiting: &mut Vec<String>) -> Vec<Column> {
+    let name = cte.name().unwrap_or_default().to_lowercase();
+
+    // A CTE referencing itself (recursive member or invalid SQL) adds no columns
+    if visiting.contains(&name) {
+        return Vec::new();
+    }
+
+    let select_stmt = match cte.select_stmt() {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+
+    visiting.push(name);
+    let mut columns = select_schema(db, &select_stmt, visiting);
+    visiting.pop();
+
+    // An explicit column list renames the outputs positionally: WITH t(a, b) AS (...)
+    for (column, name) in columns.iter_mut().zip(cte.column_names()) {
+        column.name = name;
+    }
+
+    columns
+}
+
+/// Trace a column read from a CTE or subquery back to its source
+fn derived_column_source(columns: &[Column], column_name: &str) -> ColumnSource {
+    if let Some(column) = columns.iter().find(|c| c.name == column_name) {
+        return column.source.clone();
+    }
+
+    // The CTE or subquery selected * from a single model
+    match columns {
+        [Column {
+            source: ColumnSource::Wildcard { model_name },
+            ..
+        }] => ColumnSource::FromModel {
+            model_name: model_name.clone(),
+            column_name: column_name.to_string(),
+        },
+        _ => ColumnSource::Unknown,
+    }
+}
+
+/// Columns of a relation, when they are all known. A wildcard over a model
+/// with unknown columns hides which columns exist, and a model in a ref
+/// cycle has an empty schema.
+fn relation_columns(db: &dyn Schema, relation: &FromRelation) -> Option<Vec<Column>> {
+    let columns = match relation {
+        FromRelation::Model(model_name) => db
+            .resolve_ref(model_name.clone())
+            .map(|path| db.model_schema(path).columns.clone())?,
+        FromRelation::Derived(columns) => columns.clone(),
+    };
+    if columns.is_empty() || columns.iter().any(|c| c.name == "*") {
+        return None;
+    }
+    Some(columns)
+}
+
+/// Trace a column reference to the relation in scope that provides it
+fn column_ref_source(
+    db: &dyn Schema,
+    scope: &[ScopedRelation],
+    using_columns: &[String],
+    col_ref: &ColumnRef,
+) -> ColumnSource {
+    let name = col_ref.name();
+
+    let scoped = match col_ref.qualifier() {
+        Some(qualifier) => scope.iter().find(|r| r.matches(qualifier)),
+        // A lone relation provides every unqualified name
+        None if scope.len() == 1 => scope.first(),
+        None => {
+            let providers: Vec<&ScopedRelation> = scope
+                .iter()
+                .filter(|r| {
+                    r.relation
+                        .as_ref()
+                        .and_then(|relation| relation_columns(db, relation))
+                        .is_some_and(|cols| cols.iter().any(|c| c.name.eq_ignore_ascii_case(name)))
+                })
+                .collect();
+            let is_using = using_columns.iter().any(|c| c.eq_ignore_ascii_case(name));
+            match providers.as_slice() {
+                [only] => Some(*only),
+                [first, ..] if is_using => Some(*first),
+                _ => None,
+            }
+        }
+    };
+
+    match scoped {
+        Some(ScopedRelation {
+            relation: Some(FromRelation::Model(model_name)),
+            ..
+        }) => ColumnSource::FromModel {
+            model_name: model_name.clone(),
+            column_name: name.to_string(),
+        },
+        Some(ScopedRelation {
+            relation: Some(FromRelation::Derived(columns)),
+            ..
+        }) => derived_column_source(columns, name),
+        Some(ScopedRelation {
+            table_name: Some(table_name),
+            ..
+        }) => ColumnSource::ExternalTable {
+            table_name: table_name.clone(),
+            column_name: name.to_string(),
+        },
+        _ => ColumnSource::Unknown,
+    }
+}
+
+/// Trace a select item's expression to the upstream columns it reads
+fn expression_source(
+    db: &dyn Schema,
+    scope: &[ScopedRelation],
+    using_columns: &[String],
+    expr: &Expr,
+) -> ColumnSource {
+    if let Some(col_ref) = expr.as_column_ref() {
+        return column_ref_source(db, scope, using_columns, &col_ref);
+    }
+
+    let mut inputs = Vec::new();
+    for col_ref in expr.column_refs() {
+        if names::is_niladic_function(&col_ref) {
+            continue;
+        }
+        let upstream = match column_ref_source(db, scope, using_columns, &col_ref) {
+            source @ (ColumnSource::FromModel { .. } | ColumnSource::ExternalTable { .. }) => {
+                vec![source]
+            }
+            // An expression over a computed column depends on that column's inputs
+            ColumnSource::ComputedFrom { inputs } => inputs,
+            _ => Vec::new(),
+        };
+        for source in upstream {
+            if !inputs.contains(&source) {
+                inputs.push(source);
+            }
+        }
+    }
+
+    if inputs.is_empty() {
+        ColumnSource::Computed
     } else {
-        Vec::new()
+        ColumnSource::ComputedFrom { inputs }
+    }
+}
+
+/// Extract the output columns of a SELECT statement
+fn select_schema(
+    db: &dyn Schema,
+    select_stmt: &SelectStmt,
+    visiting: &mut Vec<String>,
+) -> Vec<Column> {
+    let select_list = match select_stmt.select_list() {
+        Some(l) => l,
+        None => return Vec::new(),
     };
 
+    // Get refs, CTEs and subqueries from FROM clause to determine sources
+    let scoped = scoped_relations(db, select_stmt, visiting);
+    let using_columns = using_columns(select_stmt);
+    let relations: Vec<&FromRelation> = scoped.iter().filter_map(|r| r.relation.as_ref()).collect();
+    let scope = type_scope(db, relations.iter().copied());
+
     // Extract columns from select list
     let mut columns = Vec::new();
 
     for item in select_list.items() {
-        // Handle SELECT *
-        if let Some(expr) = item.expression() {
-            if expr.text().trim() == "*" {
-                // Wildcard - need to expand from source(s)
-                for ref_name in &from_refs {
-                    columns.push(Column {
-                        name: "*".to_string(),
-                        alias: None,
-                        source: ColumnSource::Wildcard {
-                            model_name: ref_name.clone(),
-                        },
-                        expression: "*".to_string(),
-                        range: item.range(),
-                    });
-                }
-                continue;
-            }
+        // SELECT *, alias.*
+        if let Some(wildcard) = item.wildcard() {
+            columns.extend(expand_wildcard(
+                db,
+                &wildcard,
+                &scoped,
+                &using_columns,
+                &scope,
+            ));
+            continue;
         }
 
         // Regular column
@@ -299,54 +785,286 @@ fn model_schema(db: &dyn Schema, path: PathBuf) -> Arc<ModelSchema> {
         let expression = item.expression().map(|e| e.text()).unwrap_or_default();
 
         // Determine source
-        let source = if let Some(expr) = item.expression() {
-            // Check for function calls first (before column refs)
-            if expr.as_function_call().is_some() {
-                // Functions like COUNT, SUM, etc. are computed
-                ColumnSource::Computed
-            } else if let Some(col_ref) = expr.as_column_ref() {
-                // Simple column reference - try to trace to upstream model
-                let column_name = col_ref.name().to_string();
-
-                // If there's exactly one ref, assume it's from that model
-                if from_refs.len() == 1 {
-                    ColumnSource::FromModel {
-                        model_name: from_refs[0].clone(),
-                        column_name,
-                    }
-                } else if from_refs.is_empty() {
-                    // No refs - external table
-                    ColumnSource::ExternalTable {
-                        table_name: col_ref.qualifier().unwrap_or("unknown").to_string(),
-                    }
-                } else {
-                    // Multiple refs - need qualifier to determine source
-                    if let Some(_qualifier) = col_ref.qualifier() {
-                        // Check if qualifier matches a ref
-                        // For now, mark as Unknown - would need alias resolution
-                        ColumnSource::Unknown
-                    } else {
-                        ColumnSource::Unknown
-                    }
-                }
-            } else {
-                // Complex expression (binary op, etc.)
-                ColumnSource::Computed
-            }
-        } else {
-            ColumnSource::Unknown
+        let source = match item.expression() {
+            Some(expr) => expression_source(db, &scoped, &using_columns, &expr),
+            None => ColumnSource::Unknown,
         };
 
+        let data_type = item
+            .expression()
+            .map(|e| types::infer_type(&e, &scope, &mut Vec::new()))
+            .unwrap_or(SqlType::Unknown);
+
         columns.push(Column {
             name,
             alias,
             source,
+            data_type,
             expression,
             range: item.range(),
         });
     }
 
-    Arc::new(ModelSchema { columns })
+    columns
+}
+
+/// Expand `*` or `alias.*` into the columns of the relations it selects from,
+/// applying EXCLUDE and REPLACE. A model whose columns can't be known (an
+/// unresolved ref, a ref cycle) stays a single `*` placeholder column.
+fn expand_wildcard(
+    db: &dyn Schema,
+    wildcard: &Wildcard,
+    scoped: &[ScopedRelation],
+    using_columns: &[String],
+    scope: &TypeScope,
+) -> Vec<Column> {
+    let qualifier = wildcard.qualifier();
+    let excluded = wildcard.excluded_columns();
+    let placeholder = |model_name: &str| Column {
+        name: "*".to_string(),
+        alias: None,
+        source: ColumnSource::Wildcard {
+            model_name: model_name.to_string(),
+        },
+        data_type: SqlType::Unknown,
+        expression: "*".to_string(),
+        range: wildcard.range(),
+    };
+
+    let mut columns: Vec<Column> = Vec::new();
+    let selected = scoped
+        .iter()
+        .filter(|r| qualifier.as_deref().is_none_or(|q| r.matches(q)));
+
+    for relation in selected {
+        let relation_columns = match &relation.relation {
+            Some(FromRelation::Model(model_name)) => {
+                let upstream = db
+                    .resolve_ref(model_name.clone())
+                    .map(|path| db.model_schema(path))
+                    .filter(|schema| !schema.columns.is_empty());
+                match upstream {
+                    Some(upstream) => upstream
+                        .columns
+                        .iter()
+                        .map(|c| match c.name.as_str() {
+                            "*" => placeholder(model_name),
+                            _ => Column {
+                                name: c.name.clone(),
+                                alias: None,
+                                source: ColumnSource::FromModel {
+                                    model_name: model_name.clone(),
+                                    column_name: c.name.clone(),
+                                },
+                                data_type: c.data_type,
+                                expression: c.name.clone(),
+                                range: wildcard.range(),
+                            },
+                        })
+                        .collect(),
+                    None => vec![placeholder(model_name)],
+                }
+            }
+            // CTE, subquery and source columns are already known
+            Some(FromRelation::Derived(derived_columns)) => derived_columns.clone(),
+            // Plain tables' columns aren't known
+            None => Vec::new(),
+        };
+
+        for column in relation_columns {
+            let is_named = |name: &String| name.eq_ignore_ascii_case(&column.name);
+            if excluded.iter().any(is_named) {
+                continue;
+            }
+            // A JOIN ... USING column appears once in SELECT *
+            if qualifier.is_none()
+                && using_columns.iter().any(is_named)
+                && columns.iter().any(|c| is_named(&c.name))
+            {
+                continue;
+            }
+            columns.push(column);
+        }
+    }
+
+    // REPLACE (expr AS col) swaps in a new expression, keeping the position
+    for replacement in wildcard.replacements() {
+        let (Some(name), Some(expr)) = (replacement.column_name(), replacement.expression())
+        else {
+            continue;
+        };
+        if let Some(column) = columns
+            .iter_mut()
+            .find(|c| c.name.eq_ignore_ascii_case(&name))
+        {
+            *column = Column {
+                name: column.name.clone(),
+                alias: replacement.alias(),
+                source: expression_source(db, scoped, using_columns, &expr),
+                data_type: types::infer_type(&expr, scope, &mut Vec::new()),
+                expression: expr.text(),
+                range: replacement.range(),
+            };
+        }
+    }
+
+    columns
+}
+
+/// Column types visible to the expressions of a SELECT
+fn type_scope<'a>(
+    db: &dyn Schema,
+    relations: impl IntoIterator<Item = &'a FromRelation>,
+) -> TypeScope {
+    let mut columns = Vec::new();
+    for relation in relations {
+        match relation {
+            FromRelation::Model(model_name) => {
+                if let Some(upstream_path) = db.resolve_ref(model_name.clone()) {
+                    let upstream = db.model_schema(upstream_path);
+                    columns.extend(upstream.columns.iter().map(|c| (c.name.clone(), c.data_type)));
+                }
+            }
+            FromRelation::Derived(derived_columns) => {
+                columns.extend(derived_columns.iter().map(|c| (c.name.clone(), c.data_type)));
+            }
+        }
+    }
+    TypeScope::new(columns)
+}
+
+fn lineage_graph(db: &dyn Schema) -> Arc<LineageGraph> {
+    Arc::new(lineage::build(db))
+}
+
+fn column_bindings(db: &dyn Schema, path: PathBuf) -> Arc<Vec<ColumnBinding>> {
+    let parse = db.parse_file(path);
+    match AstFile::cast(parse.syntax()) {
+        Some(file) => Arc::new(names::bind_columns(db, &file)),
+        None => Arc::new(Vec::new()),
+    }
+}
+
+fn name_diagnostics(db: &dyn Schema, path: PathBuf) -> Arc<Vec<Diagnostic>> {
+    let text = db.file_text(path.clone());
+    let diagnostics = db
+        .column_bindings(path)
+        .iter()
+        .filter_map(|binding| {
+            Some(Diagnostic {
+                severity: DiagnosticSeverity::Error,
+                message: binding.error_message()?,
+                range: smelt_parser::ast::text_range_to_range(&text, binding.range),
+            })
+        })
+        .collect();
+
+    Arc::new(diagnostics)
+}
+
+fn type_diagnostics(db: &dyn Schema, path: PathBuf) -> Arc<Vec<Diagnostic>> {
+    let parse = db.parse_file(path.clone());
+    let file = match AstFile::cast(parse.syntax()) {
+        Some(f) => f,
+        None => return Arc::new(Vec::new()),
+    };
+
+    // Every SELECT is checked in its own scope, including CTE bodies and subqueries
+    let mut errors: Vec<TypeError> = Vec::new();
+    for select_stmt in file.select_stmts() {
+        let relations = from_relations(db, &select_stmt, &mut Vec::new());
+        let scope = type_scope(db, &relations);
+
+        let select_exprs = select_stmt
+            .select_list()
+            .into_iter()
+            .flat_map(|l| l.items().filter_map(|i| i.expression()).collect::<Vec<_>>());
+        let join_conditions = select_stmt
+            .from_clause()
+            .into_iter()
+            .flat_map(|f| f.joins().collect::<Vec<_>>())
+            .filter_map(|j| j.condition().and_then(|c| c.on_expr()));
+        let conditions = select_stmt
+            .where_clause()
+            .and_then(|w| w.expression())
+            .into_iter()
+            .chain(join_conditions)
+            .chain(select_stmt.having_clause().and_then(|h| h.expression()))
+            .chain(select_stmt.qualify_clause().and_then(|q| q.expression()));
+
+        for expr in select_exprs {
+            types::infer_type(&expr, &scope, &mut errors);
+        }
+        for condition in conditions {
+            let ty = types::infer_type(&condition, &scope, &mut errors);
+            if ty.is_known() && ty != SqlType::Boolean {
+                errors.push(TypeError {
+                    message: format!("Condition must be BOOLEAN, found {}", ty),
+                    range: condition.range(),
+                });
+            }
+        }
+    }
+
+    let text = db.file_text(path);
+    let diagnostics = errors
+        .into_iter()
+        .map(|error| Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            message: error.message,
+            range: smelt_parser::ast::text_range_to_range(&text, error.range),
+        })
+        .collect();
+
+    Arc::new(diagnostics)
+}
+
+fn incrementality(
+    db: &dyn Schema,
+    path: PathBuf,
+    time_column: Option<String>,
+) -> Arc<IncrementalityAnalysis> {
+    Arc::new(incrementality::analyze(db, path, time_column))
+}
+
+fn incrementality_diagnostics(db: &dyn Schema, path: PathBuf) -> Arc<Vec<Diagnostic>> {
+    let settings = db.model_settings(path.clone());
+    if !db.project_settings().is_incremental(&settings) {
+        return Arc::new(Vec::new());
+    }
+
+    let time_column = settings
+        .incremental
+        .as_ref()
+        .and_then(|i| i.time_column.clone());
+    let analysis = db.incrementality(path.clone(), time_column);
+    let text = db.file_text(path);
+    let diagnostics = analysis
+        .blockers()
+        .map(|reason| Diagnostic {
+            severity: DiagnosticSeverity::Warning,
+            message: format!(
+                "Incremental model will be rebuilt in full: {}",
+                reason.message
+            ),
+            range: smelt_parser::ast::text_range_to_range(&text, reason.range),
+        })
+        .collect();
+
+    Arc::new(diagnostics)
+}
+
+fn diagnostics(db: &dyn Schema, path: PathBuf) -> Arc<Vec<Diagnostic>> {
+    let mut diagnostics = (*db.file_diagnostics(path.clone())).clone();
+
+    // Names and types are only meaningful for a model that parses
+    if db.parse_file(path.clone()).errors.is_empty() && db.parse_model(path.clone()).is_some() {
+        diagnostics.extend(db.name_diagnostics(path.clone()).iter().cloned());
+        diagnostics.extend(db.type_diagnostics(path.clone()).iter().cloned());
+        diagnostics.extend(db.incrementality_diagnostics(path).iter().cloned());
+    }
+
+    Arc::new(diagnostics)
 }
 
 fn available_columns(db: &dyn Schema, path: PathBuf) -> Arc<Vec<Column>> {
@@ -354,33 +1072,33 @@ fn available_columns(db: &dyn Schema, path: PathBuf) -> Arc<Vec<Column>> {
     let schema = db.model_schema(path.clone());
     let mut available = schema.columns.clone();
 
-    // Get refs in FROM clause and add their columns
+    // Get refs, CTEs and subqueries in every FROM clause (including CTE bodies
+    // and nested subqueries) and add their columns
     let parse = db.parse_file(path.clone());
     let syntax = parse.syntax();
 
     if let Some(file) = AstFile::cast(syntax) {
-        if let Some(select_stmt) = file.select_stmt() {
-            if let Some(from_clause) = select_stmt.from_clause() {
-                for table_ref in from_clause.table_refs() {
-                    if let Some(func) = table_ref.function_call() {
-                        if let Some(ref_call) = RefCall::from_function_call(func) {
-                            if let Some(model_name) = ref_call.model_name() {
-                                // Resolve upstream model schema
-                                if let Some(upstream_path) = db.resolve_ref(model_name.clone()) {
-                                    let upstream_schema = db.model_schema(upstream_path);
-
-                                    // Add upstream columns to available list
-                                    for col in upstream_schema.columns.iter() {
-                                        // Skip wildcards
-                                        if col.name == "*" {
-                                            continue;
-                                        }
-                                        available.push(col.clone());
-                                    }
-                                }
-                            }
+        for select_stmt in file.select_stmts() {
+            for relation in from_relations(db, &select_stmt, &mut Vec::new()) {
+                match relation {
+                    FromRelation::Model(model_name) => {
+                        // Resolve upstream model schema
+                        if let Some(upstream_path) = db.resolve_ref(model_name) {
+                            let upstream_schema = db.model_schema(upstream_path);
+
+                            // Add upstream columns to available list (skipping wildcards)
+                            available.extend(
+                                upstream_schema
+                                    .columns
+                                    .iter()
+                                    .filter(|col| col.name != "*")
+                                    .cloned(),
+                            );
                         }
                     }
+                    FromRelation::Derived(derived_columns) => {
+                        available.extend(derived_columns.into_iter().filter(|col| col.name != "*"));
+                    }
                 }
             }
         }
@@ -517,38 +1235,590 @@ mod tests {
     }
 
     #[test]
-    fn test_undefined_ref_diagnostic_position() {
+    fn test_join_refs_have_no_diagnostics() {
         let mut db = Database::default();
 
-        // Create a model with an undefined ref
-        let path = PathBuf::from("test_model.sql");
+        let users_path = PathBuf::from("models/users.sql");
         db.set_file_text(
-            path.clone(),
-            Arc::new("SELECT * FROM smelt.ref('nonexistent_model')".to_string()),
+            users_path.clone(),
+            Arc::new("SELECT\n  user_id,\n  user_name\nFROM raw.users".to_string()),
         );
 
-        // Register the file (no other files, so ref won't resolve)
-        db.set_all_files(Arc::new(vec![path.clone()]));
+        let events_path = PathBuf::from("models/events.sql");
+        db.set_file_text(
+            events_path.clone(),
+            Arc::new("SELECT\n  event_id,\n  user_id\nFROM raw.events".to_string()),
+        );
 
-        // Get diagnostics
-        let diagnostics = db.file_diagnostics(path);
+        let activity_path = PathBuf::from("models/user_activity.sql");
+        db.set_file_text(
+            activity_path.clone(),
+            Arc::new(
+                "SELECT\n  u.user_id,\n  COUNT(e.event_id) AS total_events\nFROM smelt.ref('users') u\nLEFT JOIN smelt.ref('events') e ON u.user_id = e.user_id\nGROUP BY u.user_id"
+                    .to_string(),
+            ),
+        );
 
-        // Should have exactly one diagnostic for undefined ref
-        assert_eq!(diagnostics.len(), 1);
-        let diag = &diagnostics[0];
+        db.set_all_files(Arc::new(vec![
+            users_path,
+            events_path,
+            activity_path.clone(),
+        ]));
 
-        // Check severity and message
-        assert_eq!(diag.severity, DiagnosticSeverity::Error);
-        assert!(diag.message.contains("Undefined model reference: 'nonexistent_model'"));
+        let refs = db.model_refs(activity_path.clone());
+        let names: Vec<&str> = refs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["users", "events"]);
 
-        // Check position - should point to the string parameter 'nonexistent_model'
-        // In "SELECT * FROM smelt.ref('nonexistent_model')", the STRING token (including quotes)
-        // starts at position 24 and ends at position 43 (exclusive)
-        assert_eq!(diag.range.start.line, 0);
-        assert_eq!(diag.range.start.column, 24);  // Opening quote ' (0-indexed)
-        assert_eq!(diag.range.end.line, 0);
-        assert_eq!(diag.range.end.column, 43);    // One past closing quote ' (exclusive)
-    }
+        assert!(db.file_diagnostics(activity_path.clone()).is_empty());
+
+        // Columns from both sides of the join are available for completion
+        let available = db.available_columns(activity_path);
+        let column_names: Vec<&str> = available.iter().map(|c| c.name.as_str()).collect();
+        assert!(column_names.contains(&"user_name"));
+        assert!(column_names.contains(&"event_id"));
+    }
+
+    #[test]
+    fn test_model_annotations_and_diagnostics() {
+        let mut db = Database::default();
+
+        let path = PathBuf::from("models/daily_revenue.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "-- @incremental.time_column: order_date\n-- @materialize: tabel\n-- @owner: finance\nSELECT order_date FROM raw.orders"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![path.clone()]));
+
+        let annotations = db.model_annotations(path.clone());
+        assert!(annotations.is_incremental());
+        assert_eq!(
+            annotations.incremental.as_ref().unwrap().time_column.as_deref(),
+            Some("order_date")
+        );
+        assert_eq!(annotations.materialize, None);
+
+        let diagnostics = db.file_diagnostics(path);
+        let severities: Vec<_> = diagnostics.iter().map(|d| d.severity).collect();
+        assert_eq!(
+            severities,
+            vec![DiagnosticSeverity::Error, DiagnosticSeverity::Warning]
+        );
+        assert!(diagnostics[0].message.contains("'tabel'"));
+        assert_eq!(diagnostics[0].range.start, Position { line: 1, column: 17 });
+        assert!(diagnostics[1].message.contains("'@owner'"));
+    }
+
+    #[test]
+    fn test_schema_through_ctes() {
+        let mut db = Database::default();
+
+        let raw_events_path = PathBuf::from("models/raw_events.sql");
+        db.set_file_text(
+            raw_events_path.clone(),
+            Arc::new("SELECT\n  user_id,\n  event_id,\n  event_time\nFROM source.events".to_string()),
+        );
+
+        let sessions_path = PathBuf::from("models/user_sessions.sql");
+        db.set_file_text(
+            sessions_path.clone(),
+            Arc::new(
+                "WITH events AS (\n  SELECT * FROM smelt.ref('raw_events')\n),\nper_user(uid, n) AS (\n  SELECT user_id, COUNT(*) FROM events GROUP BY user_id\n)\nSELECT\n  event_id,\n  uid,\n  n AS event_count\nFROM per_user, events"
+                    .to_string(),
+            ),
+        );
+
+        db.set_all_files(Arc::new(vec![raw_events_path.clone(), sessions_path.clone()]));
+
+        let cte_path = PathBuf::from("models/first_events.sql");
+        db.set_file_text(
+            cte_path.clone(),
+            Arc::new(
+                "WITH events AS (\n  SELECT user_id, event_time FROM smelt.ref('raw_events')\n)\nSELECT user_id, event_time AS first_seen FROM events"
+                    .to_string(),
+            ),
+        );
+
+        let schema = db.model_schema(cte_path);
+        assert_eq!(schema.column_names(), vec!["user_id", "first_seen"]);
+
+        // Columns read from a CTE are traced through it to the upstream model
+        match &schema.columns[1].source {
+            ColumnSource::FromModel { model_name, column_name } => {
+                assert_eq!(model_name, "raw_events");
+                assert_eq!(column_name, "event_time");
+            }
+            other => panic!("Expected FromModel source, got {:?}", other),
+        }
+
+        // With several relations in FROM, unqualified names are traced to the
+        // relation that provides them
+        let schema = db.model_schema(sessions_path.clone());
+        assert_eq!(schema.column_names(), vec!["event_id", "uid", "event_count"]);
+        assert_eq!(
+            schema.columns[1].source,
+            ColumnSource::FromModel {
+                model_name: "raw_events".to_string(),
+                column_name: "user_id".to_string(),
+            }
+        );
+        // `events` selects * from the model, expanded to its columns
+        assert_eq!(
+            schema.columns[0].source,
+            ColumnSource::FromModel {
+                model_name: "raw_events".to_string(),
+                column_name: "event_id".to_string(),
+            }
+        );
+
+        // Completion sees the upstream model and both CTEs' columns
+        let available = db.available_columns(sessions_path);
+        let column_names: Vec<&str> = available.iter().map(|c| c.name.as_str()).collect();
+        assert!(column_names.contains(&"event_time"));
+        assert!(column_names.contains(&"uid"));
+        assert!(column_names.contains(&"n"));
+    }
+
+    #[test]
+    fn test_cte_column_list_and_wildcard() {
+        let mut db = Database::default();
+
+        let raw_events_path = PathBuf::from("models/raw_events.sql");
+        db.set_file_text(
+            raw_events_path.clone(),
+            Arc::new("SELECT\n  user_id,\n  event_id\nFROM source.events".to_string()),
+        );
+
+        let path = PathBuf::from("models/renamed.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "WITH renamed(uid, eid) AS (SELECT user_id, event_id FROM smelt.ref('raw_events'))\nSELECT * FROM renamed"
+                    .to_string(),
+            ),
+        );
+
+        db.set_all_files(Arc::new(vec![raw_events_path, path.clone()]));
+
+        let schema = db.model_schema(path);
+        assert_eq!(schema.column_names(), vec!["uid", "eid"]);
+        assert_eq!(
+            schema.columns[1].source,
+            ColumnSource::FromModel {
+                model_name: "raw_events".to_string(),
+                column_name: "event_id".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_lineage_through_table_aliases() {
+        let mut db = Database::default();
+
+        let users_path = PathBuf::from("models/users.sql");
+        db.set_file_text(
+            users_path.clone(),
+            Arc::new("SELECT user_id, discount FROM raw.users".to_string()),
+        );
+        let orders_path = PathBuf::from("models/orders.sql");
+        db.set_file_text(
+            orders_path.clone(),
+            Arc::new("SELECT order_id, user_id, amount FROM raw.orders".to_string()),
+        );
+
+        let path = PathBuf::from("models/order_totals.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "SELECT u.user_id, order_id, o.amount * (1 - discount) AS net, net_total, 'web' AS channel\n\
+                 FROM smelt.ref('users') u\n\
+                 JOIN smelt.ref('orders') o ON o.user_id = u.user_id\n\
+                 JOIN (SELECT user_id, SUM(amount) AS net_total FROM smelt.ref('orders') GROUP BY user_id) t\n  ON t.user_id = u.user_id"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![users_path, orders_path, path.clone()]));
+
+        let from_model = |model: &str, column: &str| ColumnSource::FromModel {
+            model_name: model.to_string(),
+            column_name: column.to_string(),
+        };
+        let schema = db.model_schema(path);
+        let sources: Vec<_> = schema.columns.iter().map(|c| c.source.clone()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                // Qualified by alias
+                from_model("users", "user_id"),
+                // Only one relation in scope provides it
+                from_model("orders", "order_id"),
+                // Every upstream column an expression reads
+                ColumnSource::ComputedFrom {
+                    inputs: vec![from_model("orders", "amount"), from_model("users", "discount")],
+                },
+                // Through the derived table's aggregate
+                ColumnSource::ComputedFrom {
+                    inputs: vec![from_model("orders", "amount")],
+                },
+                ColumnSource::Computed,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alias_resolution() {
+        let mut db = Database::default();
+        let users = PathBuf::from("models/users.sql");
+        db.set_file_text(
+            users.clone(),
+            Arc::new("SELECT user_id, name FROM raw.users".to_string()),
+        );
+        let orders = PathBuf::from("models/orders.sql");
+        db.set_file_text(
+            orders.clone(),
+            Arc::new("SELECT order_id, user_id, name FROM raw.orders".to_string()),
+        );
+        let path = PathBuf::from("models/report.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "SELECT users.user_id, o.order_id, e.kind, s.name, name, x.user_id AS missing\n\
+                 FROM smelt.ref('users')\n\
+                 JOIN smelt.ref('orders') AS o ON o.user_id = users.user_id\n\
+                 JOIN raw.events e ON e.user_id = users.user_id\n\
+                 JOIN smelt.source('raw', 'shops') s ON s.id = o.order_id"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![users, orders, path.clone()]));
+
+        let schema = db.model_schema(path);
+        let sources: Vec<_> = schema.columns.iter().map(|c| c.source.clone()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                // An unaliased ref is qualified by its model name
+                ColumnSource::FromModel {
+                    model_name: "users".to_string(),
+                    column_name: "user_id".to_string(),
+                },
+                // AS alias
+                ColumnSource::FromModel {
+                    model_name: "orders".to_string(),
+                    column_name: "order_id".to_string(),
+                },
+                // Plain tables and sources keep their qualified names
+                ColumnSource::ExternalTable {
+                    table_name: "raw.events".to_string(),
+                    column_name: "kind".to_string(),
+                },
+                ColumnSource::ExternalTable {
+                    table_name: "raw.shops".to_string(),
+                    column_name: "name".to_string(),
+                },
+                // Both models have `name`
+                ColumnSource::Unknown,
+                // No relation is called x
+                ColumnSource::Unknown,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multi_input_lineage() {
+        let mut db = Database::default();
+        let users = PathBuf::from("models/users.sql");
+        db.set_file_text(
+            users.clone(),
+            Arc::new("SELECT user_id, discount, country FROM raw.users".to_string()),
+        );
+        let orders = PathBuf::from("models/orders.sql");
+        db.set_file_text(
+            orders.clone(),
+            Arc::new("SELECT order_id, user_id, amount, tax FROM raw.orders".to_string()),
+        );
+        let path = PathBuf::from("models/net.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "WITH priced AS (\n\
+                   SELECT o.user_id, o.amount + o.tax AS gross FROM smelt.ref('orders') o\n\
+                 )\n\
+                 SELECT p.gross * (1 - u.discount) - p.gross * u.discount AS net,\n\
+                   CASE WHEN u.country = 'NL' THEN p.gross ELSE 0 END AS nl_gross,\n\
+                   COALESCE(u.discount, r.rate) AS rate\n\
+                 FROM priced p\n\
+                 JOIN smelt.ref('users') u ON u.user_id = p.user_id\n\
+                 LEFT JOIN raw.rates r ON r.country = u.country"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![users, orders, path.clone()]));
+
+        let from_model = |model: &str, column: &str| ColumnSource::FromModel {
+            model_name: model.to_string(),
+            column_name: column.to_string(),
+        };
+        let schema = db.model_schema(path);
+        let sources: Vec<_> = schema.columns.iter().map(|c| c.source.clone()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                // A CTE column computed from two inputs contributes both,
+                // and columns read twice are listed once
+                ColumnSource::ComputedFrom {
+                    inputs: vec![
+                        from_model("orders", "amount"),
+                        from_model("orders", "tax"),
+                        from_model("users", "discount"),
+                    ],
+                },
+                ColumnSource::ComputedFrom {
+                    inputs: vec![
+                        from_model("users", "country"),
+                        from_model("orders", "amount"),
+                        from_model("orders", "tax"),
+                    ],
+                },
+                // Models and external tables mix
+                ColumnSource::ComputedFrom {
+                    inputs: vec![
+                        from_model("users", "discount"),
+                        ColumnSource::ExternalTable {
+                            table_name: "raw.rates".to_string(),
+                            column_name: "rate".to_string(),
+                        },
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_expansion() {
+        let mut db = Database::default();
+        db.set_source_catalog(Arc::new(SourceCatalog::new(
+            None,
+            vec![SourceTable {
+                schema: "raw".to_string(),
+                name: "users".to_string(),
+                description: String::new(),
+                columns: ["user_id", "email", "country"]
+                    .iter()
+                    .map(|name| SourceColumn {
+                        name: name.to_string(),
+                        column_type: "VARCHAR".to_string(),
+                        description: String::new(),
+                    })
+                    .collect(),
+                range: None,
+            }],
+        )));
+
+        let users = PathBuf::from("models/users.sql");
+        db.set_file_text(
+            users.clone(),
+            Arc::new("SELECT * EXCLUDE (email) FROM smelt.source('raw', 'users')".to_string()),
+        );
+        let orders = PathBuf::from("models/orders.sql");
+        db.set_file_text(
+            orders.clone(),
+            Arc::new("SELECT 1 AS order_id, 'u1' AS user_id, 10 AS amount".to_string()),
+        );
+        let report = PathBuf::from("models/report.sql");
+        db.set_file_text(
+            report.clone(),
+            Arc::new(
+                "SELECT u.*, o.* REPLACE (amount * 100 AS amount) FROM smelt.ref('users') u JOIN smelt.ref('orders') o USING (user_id)"
+                    .to_string(),
+            ),
+        );
+        let joined = PathBuf::from("models/joined.sql");
+        db.set_file_text(
+            joined.clone(),
+            Arc::new("SELECT * FROM smelt.ref('report') JOIN smelt.ref('missing') ON TRUE".to_string()),
+        );
+        db.set_all_files(Arc::new(vec![users.clone(), orders, report.clone(), joined.clone()]));
+
+        // Source columns come from sources.yml, minus EXCLUDE
+        let schema = db.model_schema(users);
+        assert_eq!(schema.column_names(), vec!["user_id", "country"]);
+        assert_eq!(schema.columns[1].data_type, SqlType::Varchar);
+
+        // alias.* selects one relation; REPLACE keeps the column's position
+        let schema = db.model_schema(report);
+        assert_eq!(
+            schema.column_names(),
+            vec!["user_id", "country", "order_id", "user_id", "amount"]
+        );
+        assert_eq!(
+            schema.columns[1].source,
+            ColumnSource::FromModel {
+                model_name: "users".to_string(),
+                column_name: "country".to_string(),
+            }
+        );
+        assert_eq!(schema.columns[4].expression, "amount * 100");
+        assert_eq!(
+            schema.columns[4].source,
+            ColumnSource::ComputedFrom {
+                inputs: vec![ColumnSource::FromModel {
+                    model_name: "orders".to_string(),
+                    column_name: "amount".to_string(),
+                }],
+            }
+        );
+
+        // Expansion is recursive; a broken ref stays a placeholder
+        let schema = db.model_schema(joined);
+        assert_eq!(
+            schema.column_names(),
+            vec!["user_id", "country", "order_id", "user_id", "amount", "*"]
+        );
+        assert_eq!(
+            schema.columns[5].source,
+            ColumnSource::Wildcard {
+                model_name: "missing".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_wildcard_using_and_cycles() {
+        let mut db = Database::default();
+        let a = PathBuf::from("models/a.sql");
+        let b = PathBuf::from("models/b.sql");
+        let c = PathBuf::from("models/c.sql");
+        db.set_file_text(a.clone(), Arc::new("SELECT 1 AS id, 2 AS x".to_string()));
+        db.set_file_text(
+            b.clone(),
+            Arc::new("SELECT * FROM smelt.ref('a') JOIN smelt.ref('c') USING (id)".to_string()),
+        );
+        db.set_file_text(c.clone(), Arc::new("SELECT * FROM smelt.ref('b')".to_string()));
+        db.set_all_files(Arc::new(vec![a, b.clone(), c.clone()]));
+
+        // b and c select * from each other: salsa's cycle recovery gives
+        // both an empty schema instead of recursing forever
+        assert!(db.model_schema(c.clone()).columns.is_empty());
+        assert!(db.model_schema(b.clone()).columns.is_empty());
+
+        // Once the cycle is broken, USING columns appear once
+        db.set_file_text(c, Arc::new("SELECT 1 AS id, 3 AS y".to_string()));
+        assert_eq!(db.model_schema(b).column_names(), vec!["id", "x", "y"]);
+    }
+
+    #[test]
+    fn test_recursive_cte_schema() {
+        let mut db = Database::default();
+
+        let path = PathBuf::from("models/numbers.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "WITH RECURSIVE numbers AS (\n  SELECT n FROM numbers\n  UNION ALL\n  SELECT n FROM numbers\n)\nSELECT n FROM numbers"
+                    .to_string(),
+            ),
+        );
+
+        // A self-referencing anchor must not recurse forever
+        let schema = db.model_schema(path);
+        assert_eq!(schema.column_names(), vec!["n"]);
+        assert_eq!(schema.columns[0].source, ColumnSource::Unknown);
+    }
+
+    #[test]
+    fn test_schema_through_subqueries() {
+        let mut db = Database::default();
+
+        let orders_path = PathBuf::from("models/orders.sql");
+        db.set_file_text(
+            orders_path.clone(),
+            Arc::new("SELECT\n  order_id,\n  customer_id,\n  amount\nFROM raw.orders".to_string()),
+        );
+
+        let customers_path = PathBuf::from("models/customers.sql");
+        db.set_file_text(
+            customers_path.clone(),
+            Arc::new("SELECT customer_id FROM raw.customers".to_string()),
+        );
+
+        let big_path = PathBuf::from("models/big_orders.sql");
+        db.set_file_text(
+            big_path.clone(),
+            Arc::new(
+                "SELECT order_id, total\n\
+                 FROM (\n  SELECT order_id, customer_id, amount AS total FROM smelt.ref('orders')\n) o\n\
+                 WHERE o.customer_id IN (SELECT customer_id FROM smelt.ref('customers'))"
+                    .to_string(),
+            ),
+        );
+
+        db.set_all_files(Arc::new(vec![
+            orders_path,
+            customers_path,
+            big_path.clone(),
+        ]));
+
+        // Refs inside subqueries are dependencies of the model
+        let refs = db.model_refs(big_path.clone());
+        let ref_names: Vec<_> = refs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(ref_names, vec!["orders", "customers"]);
+        assert!(db.file_diagnostics(big_path.clone()).is_empty());
+
+        // Columns read from a derived table are traced through it
+        let schema = db.model_schema(big_path);
+        assert_eq!(schema.column_names(), vec!["order_id", "total"]);
+        assert_eq!(
+            schema.columns[0].source,
+            ColumnSource::FromModel {
+                model_name: "orders".to_string(),
+                column_name: "order_id".to_string(),
+            }
+        );
+        assert_eq!(
+            schema.columns[1].source,
+            ColumnSource::FromModel {
+                model_name: "orders".to_string(),
+                column_name: "amount".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_undefined_ref_diagnostic_position() {
+        let mut db = Database::default();
+
+        // Create a model with an undefined ref
+        let path = PathBuf::from("test_model.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new("SELECT * FROM smelt.ref('nonexistent_model')".to_string()),
+        );
+
+        // Register the file (no other files, so ref won't resolve)
+        db.set_all_files(Arc::new(vec![path.clone()]));
+
+        // Get diagnostics
+        let diagnostics = db.file_diagnostics(path);
+
+        // Should have exactly one diagnostic for undefined ref
+        assert_eq!(diagnostics.len(), 1);
+        let diag = &diagnostics[0];
+
+        // Check severity and message
+        assert_eq!(diag.severity, DiagnosticSeverity::Error);
+        assert!(diag.message.contains("Undefined model reference: 'nonexistent_model'"));
+
+        // Check position - should point to the string parameter 'nonexistent_model'
+        // In "SELECT * FROM smelt.ref('nonexistent_model')", the STRING token (including quotes)
+        // starts at position 24 and ends at position 43 (exclusive)
+        assert_eq!(diag.range.start.line, 0);
+        assert_eq!(diag.range.start.column, 24);  // Opening quote ' (0-indexed)
+        assert_eq!(diag.range.end.line, 0);
+        assert_eq!(diag.range.end.column, 43);    // One past closing quote ' (exclusive)
+    }
 
     #[test]
     fn test_undefined_ref_diagnostic_position_multiline() {
@@ -619,6 +1889,27 @@ mod tests {
         println!("Expected to highlight 'nonexistent_model' on line 2");
     }
 
+    #[test]
+    fn test_undefined_ref_diagnostic_position_non_ascii() {
+        let mut db = Database::default();
+
+        // Multi-byte characters before the ref must not shift the reported columns
+        let path = PathBuf::from("cafe.sql");
+        let content = "-- Café réservations\nSELECT 'crème brûlée' AS \"Dessert Ü\" FROM smelt.ref('nonexistent_model')";
+        db.set_file_text(path.clone(), Arc::new(content.to_string()));
+        db.set_all_files(Arc::new(vec![path.clone()]));
+
+        let diagnostics = db.file_diagnostics(path);
+        assert_eq!(diagnostics.len(), 1);
+        let diag = &diagnostics[0];
+
+        let line = content.lines().nth(1).unwrap();
+        let start_column = line[..line.find("'nonexistent_model'").unwrap()].chars().count() as u32;
+        assert_eq!(diag.range.start.line, 1);
+        assert_eq!(diag.range.start.column, start_column);
+        assert_eq!(diag.range.end.column, start_column + "'nonexistent_model'".len() as u32);
+    }
+
     #[test]
     fn test_lexer_positions() {
         use smelt_parser::lexer::tokenize;
@@ -636,4 +1927,227 @@ mod tests {
         }
         println!("Final offset: {}", offset);
     }
+
+    #[test]
+    fn test_source_columns_and_diagnostics() {
+        let mut db = Database::default();
+        db.set_source_catalog(Arc::new(SourceCatalog::new(
+            None,
+            vec![SourceTable {
+                schema: "raw".to_string(),
+                name: "events".to_string(),
+                description: String::new(),
+                columns: vec![SourceColumn {
+                    name: "event_id".to_string(),
+                    column_type: "INTEGER".to_string(),
+                    description: String::new(),
+                }],
+                range: None,
+            }],
+        )));
+
+        let path = PathBuf::from("models/events.sql");
+        db.set_file_text(
+            path.clone(),
+            Arc::new(
+                "SELECT event_id\nFROM smelt.source('raw', 'events') e\nJOIN smelt.source('raw', 'evnts') x ON TRUE\nJOIN smelt.source('raw') y ON TRUE"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![path.clone()]));
+
+        // Columns are traced to the declared source table
+        let schema = db.model_schema(path.clone());
+        assert_eq!(
+            schema.columns[0].source,
+            ColumnSource::ExternalTable {
+                table_name: "raw.events".to_string(),
+                column_name: "event_id".to_string(),
+            }
+        );
+        assert!(db
+            .available_columns(path.clone())
+            .iter()
+            .any(|c| c.name == "event_id" && c.expression == "event_id"));
+
+        let diagnostics = db.file_diagnostics(path);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Undefined source: 'raw.evnts' is not declared in sources.yml",
+                "smelt.source() expects two string arguments: smelt.source('schema', 'table')",
+            ]
+        );
+        assert_eq!(diagnostics[0].range.start, Position { line: 2, column: 5 });
+    }
+
+    #[test]
+    fn test_types_flow_from_sources_through_models() {
+        let mut db = Database::default();
+        let column = |name: &str, column_type: &str| SourceColumn {
+            name: name.to_string(),
+            column_type: column_type.to_string(),
+            description: String::new(),
+        };
+        db.set_source_catalog(Arc::new(SourceCatalog::new(
+            None,
+            vec![SourceTable {
+                schema: "raw".to_string(),
+                name: "orders".to_string(),
+                description: String::new(),
+                columns: vec![
+                    column("order_id", "INTEGER"),
+                    column("status", "VARCHAR"),
+                    column("amount", "DECIMAL(10, 2)"),
+                ],
+                range: None,
+            }],
+        )));
+
+        let orders = PathBuf::from("models/orders.sql");
+        db.set_file_text(
+            orders.clone(),
+            Arc::new(
+                "SELECT order_id, status, amount * 2 AS doubled FROM smelt.source('raw', 'orders')"
+                    .to_string(),
+            ),
+        );
+        let totals = PathBuf::from("models/totals.sql");
+        db.set_file_text(
+            totals.clone(),
+            Arc::new(
+                "SELECT status, SUM(doubled) AS total, SUM(status) AS bad\n\
+                 FROM smelt.ref('orders')\n\
+                 WHERE order_id = 'x'\n\
+                 GROUP BY status"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![orders.clone(), totals.clone()]));
+
+        let types: Vec<_> = db
+            .model_schema(totals.clone())
+            .columns
+            .iter()
+            .map(|c| c.data_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![SqlType::Varchar, SqlType::Decimal, SqlType::Unknown]
+        );
+
+        let diagnostics = db.diagnostics(totals);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "SUM expects a numeric argument, found VARCHAR",
+                "Cannot compare INTEGER with VARCHAR",
+            ]
+        );
+        assert_eq!(diagnostics[1].range.start, Position { line: 2, column: 6 });
+        assert!(db.diagnostics(orders).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_and_ambiguous_columns() {
+        let mut db = Database::default();
+        let users = PathBuf::from("models/users.sql");
+        let orders = PathBuf::from("models/orders.sql");
+        let report = PathBuf::from("models/report.sql");
+        db.set_file_text(
+            users.clone(),
+            Arc::new("SELECT 1 AS user_id, 'a' AS user_name".to_string()),
+        );
+        db.set_file_text(
+            orders.clone(),
+            Arc::new("SELECT 1 AS order_id, 1 AS user_id, 10 AS amount".to_string()),
+        );
+        db.set_file_text(
+            report.clone(),
+            Arc::new(
+                "SELECT u.user_nme, user_id, o.amount, amout\nFROM smelt.ref('users') u\nJOIN smelt.ref('orders') o ON o.user_id = u.user_id"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![users, orders, report.clone()]));
+
+        let diagnostics = db.diagnostics(report.clone());
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Unknown column 'user_nme' in 'u'. Did you mean 'user_name'?",
+                "Ambiguous column 'user_id' could refer to u.user_id, o.user_id. Qualify it with a table alias",
+                "Unknown column 'amout'. Did you mean 'amount'?",
+            ]
+        );
+        assert_eq!(diagnostics[0].range.start, Position { line: 0, column: 7 });
+        assert_eq!(diagnostics[0].range.end, Position { line: 0, column: 17 });
+
+        // Qualified references bind to the aliased model's column
+        let bindings = db.column_bindings(report);
+        let amount = bindings.iter().find(|b| b.name == "amount").unwrap();
+        assert!(matches!(
+            &amount.target,
+            BindingTarget::Column { relation, column } if relation == "o" && column.name == "amount"
+        ));
+    }
+
+    #[test]
+    fn test_column_binding_scopes() {
+        let mut db = Database::default();
+        let a = PathBuf::from("models/a.sql");
+        let b = PathBuf::from("models/b.sql");
+        let c = PathBuf::from("models/c.sql");
+        db.set_file_text(a.clone(), Arc::new("SELECT 1 AS id, 2 AS x".to_string()));
+        db.set_file_text(b.clone(), Arc::new("SELECT 1 AS id, 3 AS y".to_string()));
+        db.set_file_text(
+            c.clone(),
+            Arc::new(
+                "WITH big AS (SELECT id, x FROM smelt.ref('a') WHERE x > 1)
+SELECT id, y, x * 2 AS doubled, warehouse.col, current_date
+FROM big
+JOIN smelt.ref('b') USING (id)
+JOIN warehouse.raw_table warehouse ON TRUE
+WHERE EXISTS (SELECT 1 FROM smelt.ref('a') inner_a WHERE inner_a.id = big.id)
+ORDER BY doubled"
+                    .to_string(),
+            ),
+        );
+        db.set_all_files(Arc::new(vec![a, b, c.clone()]));
+
+        // USING columns, correlated references, output aliases and opaque
+        // tables are all accepted
+        assert!(db.diagnostics(c.clone()).is_empty());
+
+        let bindings = db.column_bindings(c);
+        let target = |qualifier: Option<&str>, name: &str| {
+            bindings
+                .iter()
+                .find(|b| b.qualifier.as_deref() == qualifier && b.name == name)
+                .map(|b| b.target.clone())
+                .unwrap()
+        };
+        assert!(matches!(
+            target(Some("big"), "id"),
+            BindingTarget::Column { relation, .. } if relation == "big"
+        ));
+        assert_eq!(target(None, "doubled"), BindingTarget::OutputAlias);
+        assert_eq!(target(Some("warehouse"), "col"), BindingTarget::Unresolved);
+    }
+
+    #[test]
+    fn test_circular_refs_have_empty_schema() {
+        let mut db = Database::default();
+        let a = PathBuf::from("models/a.sql");
+        let b = PathBuf::from("models/b.sql");
+        db.set_file_text(a.clone(), Arc::new("SELECT x FROM smelt.ref('b')".to_string()));
+        db.set_file_text(b.clone(), Arc::new("SELECT x FROM smelt.ref('a')".to_string()));
+        db.set_all_files(Arc::new(vec![a.clone(), b]));
+
+        assert!(db.model_schema(a.clone()).columns.is_empty());
+        assert!(db.diagnostics(a).is_empty());
+    }
 }
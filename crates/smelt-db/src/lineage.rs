@@ -0,0 +1,529 @@
+/// Project-wide column lineage
+///
+/// Every output column of every model is a node, and every `ColumnSource`
+/// that points upstream becomes an edge from the column it reads. Upstream
+/// columns of sources.yml tables and plain external tables are nodes too, so
+/// a column can be traced from the raw table through every model using it.
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::Serialize;
+
+use crate::{ColumnSource, Schema};
+
+/// Column lineage of a project (or a part of it)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LineageGraph {
+    /// Columns, sorted by id
+    pub nodes: Vec<LineageNode>,
+    /// Dependencies, from the upstream column to the column reading it
+    pub edges: Vec<LineageEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineageNode {
+    /// "model.column", or "schema.table.column" for a source column
+    pub id: String,
+    /// Model name, or the source's qualified table name
+    pub relation: String,
+    pub column: String,
+    pub kind: NodeKind,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    /// Expression of a model column that isn't a plain column reference
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Model,
+    /// A table declared in sources.yml
+    Source,
+    /// A table read by name that isn't a model or a declared source
+    External,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct LineageEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    /// The column is passed through (possibly renamed)
+    Direct,
+    /// The column is one input of an expression
+    Computed,
+}
+
+/// Which way to follow edges from a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Upstream,
+    Downstream,
+    Both,
+}
+
+/// Lineage of one model's columns, as persisted in `.smelt/lineage/<model>.yaml`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelLineage {
+    pub model: String,
+    pub columns: Vec<ColumnLineage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnLineage {
+    pub name: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    /// The upstream column passed through unchanged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub derived_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// Upstream columns an expression reads
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
+impl LineageGraph {
+    pub fn node(&self, id: &str) -> Option<&LineageNode> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    /// Output columns of a model, in id order
+    pub fn model_columns<'a>(&'a self, model: &'a str) -> impl Iterator<Item = &'a LineageNode> {
+        self.nodes
+            .iter()
+            .filter(move |n| n.kind == NodeKind::Model && n.relation == model)
+    }
+
+    /// Edges into a column
+    pub fn inputs<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a LineageEdge> {
+        self.edges.iter().filter(move |e| e.to == id)
+    }
+
+    /// Edges out of a column
+    pub fn outputs<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a LineageEdge> {
+        self.edges.iter().filter(move |e| e.from == id)
+    }
+
+    /// The columns reachable from `start` in a direction, with the edges
+    /// followed to reach them
+    pub fn traverse(&self, start: &[&str], direction: Direction) -> LineageGraph {
+        let mut ids: BTreeSet<String> = start.iter().map(|id| id.to_string()).collect();
+        let mut edges = BTreeSet::new();
+
+        let mut walk = |upstream: bool| {
+            let mut queue: VecDeque<String> = start.iter().map(|id| id.to_string()).collect();
+            let mut seen: BTreeSet<String> = queue.iter().cloned().collect();
+            while let Some(id) = queue.pop_front() {
+                for edge in &self.edges {
+                    let next = match upstream {
+                        true if edge.to == id => &edge.from,
+                        false if edge.from == id => &edge.to,
+                        _ => continue,
+                    };
+                    edges.insert(edge.clone());
+                    ids.insert(next.clone());
+                    if seen.insert(next.clone()) {
+                        queue.push_back(next.clone());
+                    }
+                }
+            }
+        };
+        if direction != Direction::Downstream {
+            walk(true);
+        }
+        if direction != Direction::Upstream {
+            walk(false);
+        }
+
+        LineageGraph {
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|n| ids.contains(&n.id))
+                .cloned()
+                .collect(),
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// The columns of some models with the columns they are derived from
+    pub fn for_models(&self, models: &[&str]) -> LineageGraph {
+        let is_selected = |id: &str| {
+            self.node(id)
+                .is_some_and(|n| n.kind == NodeKind::Model && models.contains(&n.relation.as_str()))
+        };
+        let edges: Vec<LineageEdge> = self
+            .edges
+            .iter()
+            .filter(|e| is_selected(&e.to))
+            .cloned()
+            .collect();
+
+        LineageGraph {
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|n| is_selected(&n.id) || edges.iter().any(|e| e.from == n.id))
+                .cloned()
+                .collect(),
+            edges,
+        }
+    }
+
+    /// Lineage of a model's columns in the `.smelt/lineage` file layout
+    pub fn model_lineage(&self, model: &str) -> ModelLineage {
+        let columns = self
+            .model_columns(model)
+            .map(|node| {
+                let inputs: Vec<&LineageEdge> = self.inputs(&node.id).collect();
+                let derived_from = match inputs.as_slice() {
+                    [edge] if edge.kind == EdgeKind::Direct => Some(edge.from.clone()),
+                    _ => None,
+                };
+                let depends_on = match derived_from {
+                    Some(_) => Vec::new(),
+                    None => inputs.iter().map(|e| e.from.clone()).collect(),
+                };
+                ColumnLineage {
+                    name: node.column.clone(),
+                    data_type: node.data_type.clone(),
+                    derived_from,
+                    expression: node.expression.clone(),
+                    depends_on,
+                }
+            })
+            .collect();
+
+        ModelLineage {
+            model: model.to_string(),
+            columns,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+
+    /// Graphviz DOT, with one cluster per model or table
+    pub fn to_dot(&self) -> String {
+        let mut relations: BTreeMap<&str, Vec<&LineageNode>> = BTreeMap::new();
+        for node in &self.nodes {
+            relations.entry(&node.relation).or_default().push(node);
+        }
+
+        let mut dot = String::from("digraph lineage {\n  rankdir=LR;\n  node [shape=box];\n");
+        for (i, (relation, nodes)) in relations.iter().enumerate() {
+            let style = match nodes[0].kind {
+                NodeKind::Model => "",
+                NodeKind::Source | NodeKind::External => " style=dashed;",
+            };
+            dot.push_str(&format!(
+                "  subgraph cluster_{} {{\n    label={};{}\n",
+                i,
+                dot_string(relation),
+                style
+            ));
+            for node in nodes {
+                dot.push_str(&format!(
+                    "    {} [label={}];\n",
+                    dot_string(&node.id),
+                    dot_string(&node.column)
+                ));
+            }
+            dot.push_str("  }\n");
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Direct => "",
+                EdgeKind::Computed => " [style=dashed]",
+            };
+            dot.push_str(&format!(
+                "  {} -> {}{};\n",
+                dot_string(&edge.from),
+                dot_string(&edge.to),
+                style
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Build the lineage graph of every model in the project
+pub(crate) fn build(db: &dyn Schema) -> LineageGraph {
+    let models = db.all_models();
+    let mut models: Vec<_> = models.values().collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let catalog = db.source_catalog();
+    let mut nodes: BTreeMap<String, LineageNode> = BTreeMap::new();
+    let mut edges = BTreeSet::new();
+
+    for model in &models {
+        let schema = db.model_schema(model.path.clone());
+        for column in schema.columns.iter().filter(|c| c.name != "*") {
+            let id = format!("{}.{}", model.name, column.name);
+            let is_computed = matches!(
+                column.source,
+                ColumnSource::Computed | ColumnSource::ComputedFrom { .. }
+            );
+
+            nodes.insert(
+                id.clone(),
+                LineageNode {
+                    id: id.clone(),
+                    relation: model.name.clone(),
+                    column: column.name.clone(),
+                    kind: NodeKind::Model,
+                    data_type: column
+                        .data_type
+                        .is_known()
+                        .then(|| column.data_type.to_string()),
+                    expression: is_computed.then(|| column.expression.clone()),
+                },
+            );
+
+            let inputs: Vec<(&ColumnSource, EdgeKind)> = match &column.source {
+                ColumnSource::ComputedFrom { inputs } => {
+                    inputs.iter().map(|i| (i, EdgeKind::Computed)).collect()
+                }
+                source => vec![(source, EdgeKind::Direct)],
+            };
+            for (input, kind) in inputs {
+                let (relation, column_name, node_kind) = match input {
+                    ColumnSource::FromModel {
+                        model_name,
+                        column_name,
+                    } => (model_name, column_name, NodeKind::Model),
+                    ColumnSource::ExternalTable {
+                        table_name,
+                        column_name,
+                    } => {
+                        let is_source = table_name
+                            .split_once('.')
+                            .is_some_and(|(schema, table)| catalog.find(schema, table).is_some());
+                        let kind = if is_source {
+                            NodeKind::Source
+                        } else {
+                            NodeKind::External
+                        };
+                        (table_name, column_name, kind)
+                    }
+                    _ => continue,
+                };
+                let from = format!("{}.{}", relation, column_name);
+                // Upstream model columns get their own node from their model
+                if node_kind != NodeKind::Model {
+                    nodes.entry(from.clone()).or_insert_with(|| LineageNode {
+                        id: from.clone(),
+                        relation: relation.clone(),
+                        column: column_name.clone(),
+                        kind: node_kind,
+                        data_type: None,
+                        expression: None,
+                    });
+                }
+                edges.insert(LineageEdge {
+                    from,
+                    to: id.clone(),
+                    kind,
+                });
+            }
+        }
+    }
+
+    // Declared source columns carry their sources.yml type
+    for node in nodes.values_mut().filter(|n| n.kind == NodeKind::Source) {
+        let (schema, table) = node.relation.split_once('.').unwrap_or_default();
+        node.data_type = catalog
+            .find(schema, table)
+            .and_then(|t| t.columns.iter().find(|c| c.name == node.column))
+            .map(|c| c.column_type.clone())
+            .filter(|t| !t.is_empty());
+    }
+
+    // Edges from columns that don't exist upstream still name a node
+    let missing: Vec<LineageNode> = edges
+        .iter()
+        .filter(|e| !nodes.contains_key(&e.from))
+        .filter_map(|e| {
+            let (relation, column) = e.from.split_once('.')?;
+            Some(LineageNode {
+                id: e.from.clone(),
+                relation: relation.to_string(),
+                column: column.to_string(),
+                kind: NodeKind::Model,
+                data_type: None,
+                expression: None,
+            })
+        })
+        .collect();
+    for node in missing {
+        nodes.insert(node.id.clone(), node);
+    }
+
+    LineageGraph {
+        nodes: nodes.into_values().collect(),
+        edges: edges.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, Inputs, SourceCatalog, SourceColumn, SourceTable};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn project() -> Database {
+        let mut db = Database::default();
+        db.set_source_catalog(Arc::new(SourceCatalog::new(
+            None,
+            vec![SourceTable {
+                schema: "raw".to_string(),
+                name: "orders".to_string(),
+                description: String::new(),
+                columns: [("order_id", "INTEGER"), ("amount", "DECIMAL(10,2)")]
+                    .iter()
+                    .map(|(name, column_type)| SourceColumn {
+                        name: name.to_string(),
+                        column_type: column_type.to_string(),
+                        description: String::new(),
+                    })
+                    .collect(),
+                range: None,
+            }],
+        )));
+
+        let files = [
+            (
+                "models/orders.sql",
+                "SELECT order_id, amount FROM smelt.source('raw', 'orders')",
+            ),
+            (
+                "models/revenue.sql",
+                "SELECT order_id AS id, SUM(amount) AS total, COUNT(*) AS n FROM smelt.ref('orders') GROUP BY order_id",
+            ),
+        ];
+        let mut paths = Vec::new();
+        for (path, text) in files {
+            db.set_file_text(PathBuf::from(path), Arc::new(text.to_string()));
+            paths.push(PathBuf::from(path));
+        }
+        db.set_all_files(Arc::new(paths));
+        db
+    }
+
+    #[test]
+    fn test_build_and_traverse() {
+        let db = project();
+        let graph = db.lineage_graph();
+
+        let ids: Vec<_> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "orders.amount",
+                "orders.order_id",
+                "raw.orders.amount",
+                "raw.orders.order_id",
+                "revenue.id",
+                "revenue.n",
+                "revenue.total",
+            ]
+        );
+        // Source columns carry their sources.yml type
+        assert_eq!(
+            graph
+                .node("raw.orders.amount")
+                .unwrap()
+                .data_type
+                .as_deref(),
+            Some("DECIMAL(10,2)")
+        );
+        assert_eq!(
+            graph.node("raw.orders.order_id").unwrap().kind,
+            NodeKind::Source
+        );
+
+        let upstream = graph.traverse(&["revenue.total"], Direction::Upstream);
+        let ids: Vec<_> = upstream.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["orders.amount", "raw.orders.amount", "revenue.total"]
+        );
+        assert_eq!(upstream.edges.len(), 2);
+
+        let downstream = graph.traverse(&["raw.orders.order_id"], Direction::Downstream);
+        let ids: Vec<_> = downstream.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["orders.order_id", "raw.orders.order_id", "revenue.id"]
+        );
+
+        // A model's columns with their direct inputs
+        let orders = graph.for_models(&["orders"]);
+        let ids: Vec<_> = orders.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "orders.amount",
+                "orders.order_id",
+                "raw.orders.amount",
+                "raw.orders.order_id",
+            ]
+        );
+        assert_eq!(orders.edges.len(), 2);
+    }
+
+    #[test]
+    fn test_model_lineage_and_exports() {
+        let db = project();
+        let graph = db.lineage_graph();
+
+        let yaml = serde_yaml::to_string(&graph.model_lineage("revenue")).unwrap();
+        assert_eq!(
+            yaml,
+            "model: revenue
+columns:
+- name: id
+  type: INTEGER
+  derived_from: orders.order_id
+- name: n
+  type: BIGINT
+  expression: COUNT(*)
+- name: total
+  type: DECIMAL
+  expression: SUM(amount)
+  depends_on:
+  - orders.amount
+"
+        );
+
+        let json = graph.to_json().unwrap();
+        assert!(json.contains("\"kind\": \"computed\""));
+
+        let dot = graph
+            .traverse(&["revenue.total"], Direction::Upstream)
+            .to_dot();
+        assert!(dot.contains("label=\"raw.orders\"; style=dashed;"));
+        assert!(dot.contains("\"orders.amount\" -> \"revenue.total\" [style=dashed];"));
+        assert!(dot.contains("\"raw.orders.amount\" -> \"orders.amount\";"));
+    }
+}
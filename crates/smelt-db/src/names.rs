@@ -0,0 +1,255 @@
+/// Column name resolution
+///
+/// Binds every column reference in a model to the relation in scope that
+/// provides it: a model ref, a smelt.source() table, a CTE or a subquery.
+/// Relations whose columns aren't known (plain tables, `SELECT *` from a
+/// model) make unqualified names unresolvable rather than unknown, so only
+/// references that can't exist are reported.
+use rowan::TextRange;
+use smelt_parser::{ColumnRef, File as AstFile, SelectStmt};
+
+use crate::{relation_columns, scoped_relations, using_columns, Column, Schema, ScopedRelation};
+
+/// A column reference and what it refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnBinding {
+    pub name: String,
+    pub qualifier: Option<String>,
+    /// Range of the reference, including any qualifier
+    pub range: TextRange,
+    pub target: BindingTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindingTarget {
+    /// A column of a model, source, CTE or subquery in scope
+    Column { relation: String, column: Column },
+    /// An output column alias of the same SELECT (`ORDER BY total`)
+    OutputAlias,
+    /// Provided by more than one relation in scope
+    Ambiguous { relations: Vec<String> },
+    /// Not provided by any relation in scope
+    Unknown {
+        relation: Option<String>,
+        suggestion: Option<String>,
+    },
+    /// May come from a relation whose columns aren't known
+    Unresolved,
+}
+
+impl ColumnBinding {
+    /// Error message for unknown and ambiguous references
+    pub fn error_message(&self) -> Option<String> {
+        match &self.target {
+            BindingTarget::Unknown {
+                relation,
+                suggestion,
+            } => {
+                let mut message = match relation {
+                    Some(relation) => format!("Unknown column '{}' in '{}'", self.name, relation),
+                    None => format!("Unknown column '{}'", self.name),
+                };
+                if let Some(suggestion) = suggestion {
+                    message.push_str(&format!(". Did you mean '{}'?", suggestion));
+                }
+                Some(message)
+            }
+            BindingTarget::Ambiguous { relations } => Some(format!(
+                "Ambiguous column '{}' could refer to {}. Qualify it with a table alias",
+                self.name,
+                relations
+                    .iter()
+                    .map(|r| format!("{}.{}", r, self.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// SQL keywords that parse as column references but are niladic functions
+const NILADIC_FUNCTIONS: &[&str] = &[
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "localtime",
+    "localtimestamp",
+    "current_user",
+    "current_role",
+    "current_schema",
+    "current_catalog",
+    "session_user",
+];
+
+/// Whether an unqualified reference is a keyword like CURRENT_DATE
+pub(crate) fn is_niladic_function(col_ref: &ColumnRef) -> bool {
+    col_ref.qualifier().is_none()
+        && NILADIC_FUNCTIONS.contains(&col_ref.name().to_lowercase().as_str())
+}
+
+/// A relation in scope with its columns, if known
+struct ScopeEntry {
+    scoped: ScopedRelation,
+    columns: Option<Vec<Column>>,
+}
+
+impl ScopeEntry {
+    fn find(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .as_ref()?
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Bind the column references of every SELECT in a file
+pub(crate) fn bind_columns(db: &dyn Schema, file: &AstFile) -> Vec<ColumnBinding> {
+    let mut bindings = Vec::new();
+
+    for select_stmt in file.select_stmts() {
+        // Innermost scope first, then the queries around a correlated subquery
+        let scopes: Vec<Vec<ScopeEntry>> = std::iter::once(select_stmt.clone())
+            .chain(select_stmt.enclosing_selects())
+            .map(|s| scope_entries(db, &s))
+            .collect();
+        let aliases: Vec<String> = select_stmt
+            .select_list()
+            .map(|l| l.items().filter_map(|i| i.alias()).collect())
+            .unwrap_or_default();
+        let using_columns = using_columns(&select_stmt);
+
+        for col_ref in select_stmt.column_refs() {
+            if is_niladic_function(&col_ref) {
+                continue;
+            }
+            let target = match col_ref.qualifier() {
+                Some(qualifier) => bind_qualified(&scopes, qualifier, col_ref.name()),
+                None => bind_unqualified(&scopes, &col_ref, &aliases, &using_columns),
+            };
+            bindings.push(ColumnBinding {
+                name: col_ref.name().to_string(),
+                qualifier: col_ref.qualifier().map(String::from),
+                range: col_ref.range(),
+                target,
+            });
+        }
+    }
+
+    bindings
+}
+
+fn scope_entries(db: &dyn Schema, select_stmt: &SelectStmt) -> Vec<ScopeEntry> {
+    scoped_relations(db, select_stmt, &mut Vec::new())
+        .into_iter()
+        .map(|scoped| {
+            let columns = scoped
+                .relation
+                .as_ref()
+                .and_then(|relation| relation_columns(db, relation));
+            ScopeEntry { scoped, columns }
+        })
+        .collect()
+}
+
+fn bind_qualified(scopes: &[Vec<ScopeEntry>], qualifier: &str, name: &str) -> BindingTarget {
+    for scope in scopes {
+        let Some(entry) = scope.iter().find(|e| e.scoped.matches(qualifier)) else {
+            continue;
+        };
+        return match (&entry.columns, entry.find(name)) {
+            (None, _) => BindingTarget::Unresolved,
+            (Some(_), Some(column)) => BindingTarget::Column {
+                relation: entry.scoped.display_name().to_string(),
+                column: column.clone(),
+            },
+            (Some(columns), None) => BindingTarget::Unknown {
+                relation: Some(entry.scoped.display_name().to_string()),
+                suggestion: suggest(name, columns.iter().map(|c| c.name.as_str())),
+            },
+        };
+    }
+
+    // Not a relation in scope: a struct field access or something we can't see
+    BindingTarget::Unresolved
+}
+
+fn bind_unqualified(
+    scopes: &[Vec<ScopeEntry>],
+    col_ref: &ColumnRef,
+    aliases: &[String],
+    using_columns: &[String],
+) -> BindingTarget {
+    let name = col_ref.name();
+
+    for (depth, scope) in scopes.iter().enumerate() {
+        let providers: Vec<&ScopeEntry> = scope.iter().filter(|e| e.find(name).is_some()).collect();
+        let is_using = depth == 0 && using_columns.iter().any(|c| c.eq_ignore_ascii_case(name));
+
+        match providers.as_slice() {
+            [first, ..] if providers.len() == 1 || is_using => {
+                return BindingTarget::Column {
+                    relation: first.scoped.display_name().to_string(),
+                    column: first.find(name).cloned().expect("provider has the column"),
+                }
+            }
+            [_, _, ..] => {
+                return BindingTarget::Ambiguous {
+                    relations: providers
+                        .iter()
+                        .map(|e| e.scoped.display_name().to_string())
+                        .collect(),
+                }
+            }
+            _ => {}
+        }
+
+        if depth == 0 && aliases.iter().any(|a| a.eq_ignore_ascii_case(name)) {
+            return BindingTarget::OutputAlias;
+        }
+        if scope.iter().any(|e| e.columns.is_none()) {
+            return BindingTarget::Unresolved;
+        }
+    }
+
+    // Without a FROM clause there is nothing to check against
+    let Some(scope) = scopes.first().filter(|s| !s.is_empty()) else {
+        return BindingTarget::Unresolved;
+    };
+    let candidates = scope
+        .iter()
+        .flat_map(|e| e.columns.iter().flatten().map(|c| c.name.as_str()))
+        .chain(aliases.iter().map(|a| a.as_str()));
+    BindingTarget::Unknown {
+        relation: None,
+        suggestion: suggest(name, candidates),
+    }
+}
+
+/// The closest candidate by edit distance, if it is close enough to be a typo
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let name = name.to_lowercase();
+    let max_distance = name.chars().count().max(3) / 3;
+
+    candidates
+        .map(|c| (strsim::levenshtein(&name, &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest() {
+        let columns = ["user_id", "user_name", "signup_date"];
+        let suggest = |name| suggest(name, columns.iter().copied());
+
+        assert_eq!(suggest("user_nme").as_deref(), Some("user_name"));
+        assert_eq!(suggest("USER_ID").as_deref(), Some("user_id"));
+        assert_eq!(suggest("signupdate").as_deref(), Some("signup_date"));
+        assert_eq!(suggest("email"), None);
+    }
+}
@@ -0,0 +1,225 @@
+/// Project settings from smelt.yml
+///
+/// The LSP reads them straight from smelt.yml; the CLI builds them from its
+/// loaded configuration for the selected target.
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use smelt_parser::annotations::{IncrementalAnnotation, Materialization, ModelAnnotations};
+use smelt_parser::LexOptions;
+
+/// Settings that change how a project's models are analyzed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectSettings {
+    /// Lexing rules of the target's SQL dialect
+    pub lex_options: LexOptions,
+    /// `default_materialization` (views when unset)
+    pub default_materialization: Option<Materialization>,
+    /// `models:` entries, as the header annotations they stand for. Only
+    /// the materialization and incremental settings are kept.
+    pub models: BTreeMap<String, ModelAnnotations>,
+}
+
+/// Target the CLI uses when none is given
+const DEFAULT_TARGET: &str = "dev";
+
+impl ProjectSettings {
+    /// Read a smelt.yml file, for its `dev` target (or its only target)
+    pub fn from_yaml(text: &str) -> Result<Self, serde_yaml::Error> {
+        let file: ProjectFile = serde_yaml::from_str(text)?;
+
+        let target = file.targets.get(DEFAULT_TARGET).or_else(|| {
+            let mut targets = file.targets.values();
+            targets.next().filter(|_| targets.next().is_none())
+        });
+        Ok(Self {
+            lex_options: target
+                .map(|t| lex_options(&t.target_type))
+                .unwrap_or_default(),
+            default_materialization: file
+                .default_materialization
+                .as_deref()
+                .and_then(materialization),
+            models: file
+                .models
+                .into_iter()
+                .map(|(name, entry)| {
+                    let settings = ModelAnnotations {
+                        materialize: entry.materialization.as_deref().and_then(materialization),
+                        incremental: entry.incremental.map(|i| IncrementalAnnotation {
+                            enabled: i.enabled,
+                            time_column: i.time_column,
+                        }),
+                        ..Default::default()
+                    };
+                    (name, settings)
+                })
+                .collect(),
+        })
+    }
+
+    /// A model's materialization and incremental settings: its `models:`
+    /// entry, overridden field by field by its header annotations
+    pub fn model_settings(
+        &self,
+        model_name: &str,
+        annotations: &ModelAnnotations,
+    ) -> ModelAnnotations {
+        let mut settings = self.models.get(model_name).cloned().unwrap_or_default();
+        if annotations.materialize.is_some() {
+            settings.materialize = annotations.materialize;
+        }
+        if let Some(ref incremental) = annotations.incremental {
+            let time_column = incremental.time_column.clone().or_else(|| {
+                settings
+                    .incremental
+                    .as_ref()
+                    .and_then(|i| i.time_column.clone())
+            });
+            settings.incremental = Some(IncrementalAnnotation {
+                enabled: incremental.enabled,
+                time_column,
+            });
+        }
+        settings
+    }
+
+    /// Whether a model with these settings is materialized incrementally
+    pub fn is_incremental(&self, settings: &ModelAnnotations) -> bool {
+        let enabled = settings.incremental.as_ref().map(|i| i.enabled);
+        match settings.materialize {
+            // Enabling incremental processing makes a table incremental
+            Some(Materialization::Table) | None if enabled == Some(true) => true,
+            Some(materialization) => {
+                materialization == Materialization::Incremental && enabled != Some(false)
+            }
+            None => self.default_materialization == Some(Materialization::Incremental),
+        }
+    }
+}
+
+/// Lexing rules for a target type ("duckdb", "spark", ...)
+pub fn lex_options(target_type: &str) -> LexOptions {
+    LexOptions {
+        backslash_escapes: target_type.eq_ignore_ascii_case("spark"),
+    }
+}
+
+fn materialization(name: &str) -> Option<Materialization> {
+    match name.to_ascii_lowercase().as_str() {
+        "table" => Some(Materialization::Table),
+        "view" => Some(Materialization::View),
+        "incremental" => Some(Materialization::Incremental),
+        "merge" => Some(Materialization::Merge),
+        "snapshot" => Some(Materialization::Snapshot),
+        _ => None,
+    }
+}
+
+// smelt.yml file structure (only the parts analysis needs)
+
+#[derive(Deserialize)]
+struct ProjectFile {
+    #[serde(default)]
+    targets: BTreeMap<String, TargetDef>,
+    #[serde(default)]
+    default_materialization: Option<String>,
+    #[serde(default)]
+    models: BTreeMap<String, ModelDef>,
+}
+
+#[derive(Deserialize)]
+struct TargetDef {
+    #[serde(rename = "type")]
+    target_type: String,
+}
+
+#[derive(Deserialize)]
+struct ModelDef {
+    #[serde(default)]
+    materialization: Option<String>,
+    #[serde(default)]
+    incremental: Option<IncrementalDef>,
+}
+
+#[derive(Deserialize)]
+struct IncrementalDef {
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    #[serde(default)]
+    time_column: Option<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lex_options_follow_target() {
+        let spark = "name: p\nversion: 1\ntargets:\n  dev:\n    type: spark\n    schema: s\n  prod:\n    type: duckdb\n    schema: s\n";
+        assert!(
+            ProjectSettings::from_yaml(spark)
+                .unwrap()
+                .lex_options
+                .backslash_escapes
+        );
+
+        let only = "targets:\n  local:\n    type: Spark\n    schema: s\n";
+        assert!(
+            ProjectSettings::from_yaml(only)
+                .unwrap()
+                .lex_options
+                .backslash_escapes
+        );
+
+        let ambiguous = "targets:\n  a:\n    type: spark\n  b:\n    type: duckdb\n";
+        assert_eq!(
+            ProjectSettings::from_yaml(ambiguous).unwrap(),
+            ProjectSettings::default()
+        );
+    }
+
+    #[test]
+    fn test_model_settings_merge_annotations_over_entries() {
+        let yml = "models:\n  orders:\n    materialization: table\n    incremental:\n      time_column: order_date\n  totals:\n    materialization: incremental\n";
+        let settings = ProjectSettings::from_yaml(yml).unwrap();
+
+        let orders = settings.model_settings("orders", &ModelAnnotations::default());
+        assert_eq!(
+            orders.incremental.as_ref().unwrap().time_column.as_deref(),
+            Some("order_date")
+        );
+        assert!(settings.is_incremental(&orders));
+
+        // Disabling it in the file keeps the time column from smelt.yml
+        let annotations = ModelAnnotations {
+            incremental: Some(IncrementalAnnotation {
+                enabled: false,
+                time_column: None,
+            }),
+            ..Default::default()
+        };
+        let orders = settings.model_settings("orders", &annotations);
+        assert!(!settings.is_incremental(&orders));
+        assert_eq!(
+            orders.incremental.unwrap().time_column.as_deref(),
+            Some("order_date")
+        );
+
+        let totals = settings.model_settings("totals", &ModelAnnotations::default());
+        assert!(settings.is_incremental(&totals));
+        let other = settings.model_settings("other", &ModelAnnotations::default());
+        assert!(!settings.is_incremental(&other));
+
+        let settings = ProjectSettings::from_yaml(
+            "default_materialization: incremental
+",
+        )
+        .unwrap();
+        assert!(settings.is_incremental(&ModelAnnotations::default()));
+    }
+}
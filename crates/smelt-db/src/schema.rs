@@ -6,6 +6,8 @@
 /// - Future refactoring API
 use rowan::TextRange;
 
+use crate::SqlType;
+
 /// Represents a column in a model's output schema
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Column {
@@ -18,6 +20,9 @@ pub struct Column {
     /// Source/lineage of this column
     pub source: ColumnSource,
 
+    /// Inferred SQL type (Unknown if it can't be determined)
+    pub data_type: SqlType,
+
     /// The SQL expression text that produces this column
     pub expression: String,
 
@@ -28,10 +33,15 @@ pub struct Column {
 /// Tracks where a column comes from (lineage)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ColumnSource {
-    /// Computed from an expression (can't be traced to upstream)
-    /// Example: `SUM(revenue)`, `user_id * 2`
+    /// Computed from an expression that reads no upstream columns
+    /// Example: `COUNT(*)`, `'web' AS channel`
     Computed,
 
+    /// Computed from an expression over upstream columns, listing every
+    /// model or external column it reads
+    /// Example: `o.amount * u.discount`, `SUM(revenue)`
+    ComputedFrom { inputs: Vec<ColumnSource> },
+
     /// Direct reference to a column from an upstream model
     /// Example: `user_id` from `{{ ref('raw_events') }}`
     FromModel {
@@ -49,12 +59,30 @@ pub enum ColumnSource {
     /// External tables that aren't sqt models
     ExternalTable {
         table_name: String,
+        column_name: String,
     },
 
     /// Unable to determine source (error recovery)
     Unknown,
 }
 
+impl ColumnSource {
+    /// "model.column" or "table.column" for a column traced to its origin
+    pub fn upstream_name(&self) -> Option<String> {
+        match self {
+            ColumnSource::FromModel {
+                model_name,
+                column_name,
+            } => Some(format!("{}.{}", model_name, column_name)),
+            ColumnSource::ExternalTable {
+                table_name,
+                column_name,
+            } => Some(format!("{}.{}", table_name, column_name)),
+            _ => None,
+        }
+    }
+}
+
 /// Schema for a model (list of output columns)
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ModelSchema {
@@ -99,6 +127,7 @@ mod tests {
                     name: "user_id".to_string(),
                     alias: None,
                     source: ColumnSource::Computed,
+                    data_type: SqlType::Unknown,
                     expression: "user_id".to_string(),
                     range: TextRange::new(0.into(), 7.into()),
                 },
@@ -106,6 +135,7 @@ mod tests {
                     name: "total".to_string(),
                     alias: Some("total".to_string()),
                     source: ColumnSource::Computed,
+                    data_type: SqlType::Unknown,
                     expression: "COUNT(*)".to_string(),
                     range: TextRange::new(9.into(), 24.into()),
                 },
@@ -125,6 +155,7 @@ mod tests {
                     name: "a".to_string(),
                     alias: None,
                     source: ColumnSource::Computed,
+                    data_type: SqlType::Unknown,
                     expression: "a".to_string(),
                     range: TextRange::new(0.into(), 1.into()),
                 },
@@ -132,6 +163,7 @@ mod tests {
                     name: "b".to_string(),
                     alias: None,
                     source: ColumnSource::Computed,
+                    data_type: SqlType::Unknown,
                     expression: "b".to_string(),
                     range: TextRange::new(3.into(), 4.into()),
                 },
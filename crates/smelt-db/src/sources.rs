@@ -0,0 +1,222 @@
+/// External sources declared in sources.yml
+///
+/// Models read raw tables through `smelt.source('schema', 'table')`. The
+/// catalog is a salsa input so the LSP can validate those calls, offer
+/// completion and jump to the declaring entry.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::{Position, Range};
+
+/// All tables declared in a project's sources.yml
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceCatalog {
+    /// Path of the sources.yml the tables were read from
+    pub path: Option<PathBuf>,
+    pub tables: Vec<SourceTable>,
+}
+
+/// A table under `sources.<schema>.tables`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceTable {
+    pub schema: String,
+    pub name: String,
+    pub description: String,
+    pub columns: Vec<SourceColumn>,
+    /// Position of the `<table>:` key in sources.yml
+    pub range: Option<Range>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceColumn {
+    pub name: String,
+    pub column_type: String,
+    pub description: String,
+}
+
+impl SourceTable {
+    /// "schema.table"
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}", self.schema, self.name)
+    }
+}
+
+impl SourceCatalog {
+    pub fn new(path: Option<PathBuf>, tables: Vec<SourceTable>) -> Self {
+        Self { path, tables }
+    }
+
+    /// Read a sources.yml file
+    pub fn from_yaml(path: PathBuf, text: &str) -> Result<Self, serde_yaml::Error> {
+        let file: SourcesFile = serde_yaml::from_str(text)?;
+
+        let mut tables = Vec::new();
+        for (schema, schema_def) in file.sources {
+            for (name, table) in schema_def.tables {
+                tables.push(SourceTable {
+                    range: find_table_key(text, &schema, &name),
+                    schema: schema.clone(),
+                    name,
+                    description: table.description,
+                    columns: table
+                        .columns
+                        .into_iter()
+                        .map(|c| SourceColumn {
+                            name: c.name,
+                            column_type: c.column_type,
+                            description: c.description,
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        Ok(Self::new(Some(path), tables))
+    }
+
+    /// Find a table by exact schema and table name
+    pub fn find(&self, schema: &str, table: &str) -> Option<&SourceTable> {
+        self.tables
+            .iter()
+            .find(|t| t.schema == schema && t.name == table)
+    }
+
+    /// Declared schema names, in order
+    pub fn schemas(&self) -> Vec<&str> {
+        let mut schemas: Vec<&str> = self.tables.iter().map(|t| t.schema.as_str()).collect();
+        schemas.dedup();
+        schemas
+    }
+
+    /// Tables declared under a schema
+    pub fn tables_in(&self, schema: &str) -> impl Iterator<Item = &SourceTable> + '_ {
+        let schema = schema.to_string();
+        self.tables.iter().filter(move |t| t.schema == schema)
+    }
+}
+
+#[derive(Deserialize)]
+struct SourcesFile {
+    #[serde(default)]
+    sources: BTreeMap<String, SchemaDef>,
+}
+
+#[derive(Deserialize)]
+struct SchemaDef {
+    #[serde(default)]
+    tables: BTreeMap<String, TableDef>,
+}
+
+#[derive(Deserialize)]
+struct TableDef {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    columns: Vec<ColumnDef>,
+}
+
+#[derive(Deserialize)]
+struct ColumnDef {
+    name: String,
+    #[serde(rename = "type", default)]
+    column_type: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// Locate the `<table>:` key nested under `<schema>:` by indentation.
+/// serde_yaml doesn't keep positions, and sources.yml is simple enough to scan.
+fn find_table_key(text: &str, schema: &str, table: &str) -> Option<Range> {
+    let mut schema_indent = None;
+
+    for (line_no, line) in text.lines().enumerate() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        let key = content.split(':').next().unwrap_or("").trim();
+
+        match schema_indent {
+            // Left the schema's block without finding the table
+            Some(schema_indent) if indent <= schema_indent => return None,
+            Some(_) if key == table && content.contains(':') => {
+                return Some(Range {
+                    start: Position {
+                        line: line_no as u32,
+                        column: indent as u32,
+                    },
+                    end: Position {
+                        line: line_no as u32,
+                        column: (indent + table.len()) as u32,
+                    },
+                });
+            }
+            Some(_) => {}
+            None if key == schema && content.ends_with(':') => schema_indent = Some(indent),
+            None => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &str = "version: 1
+
+sources:
+  raw:
+    tables:
+      users:
+        description: Raw user data
+        columns:
+          - name: user_id
+            type: INTEGER
+      events:
+        columns:
+          - name: event_id
+            type: INTEGER
+  crm:
+    tables:
+      users:
+        description: CRM contacts
+        columns: []
+";
+
+    #[test]
+    fn test_from_yaml() {
+        let catalog = SourceCatalog::from_yaml(PathBuf::from("sources.yml"), SOURCES).unwrap();
+
+        assert_eq!(catalog.schemas(), vec!["crm", "raw"]);
+        let names: Vec<_> = catalog
+            .tables_in("raw")
+            .map(|t| t.qualified_name())
+            .collect();
+        assert_eq!(names, vec!["raw.events", "raw.users"]);
+
+        let users = catalog.find("raw", "users").unwrap();
+        assert_eq!(users.description, "Raw user data");
+        assert_eq!(users.columns[0].column_type, "INTEGER");
+        assert!(catalog.find("raw", "user").is_none());
+        assert!(catalog.find("events", "raw").is_none());
+    }
+
+    #[test]
+    fn test_table_key_positions() {
+        let catalog = SourceCatalog::from_yaml(PathBuf::from("sources.yml"), SOURCES).unwrap();
+
+        // Tables with the same name in different schemas resolve to their own entries
+        let start = |schema, table| {
+            let start = catalog.find(schema, table).unwrap().range.unwrap().start;
+            (start.line, start.column)
+        };
+        assert_eq!(start("raw", "users"), (5, 6));
+        assert_eq!(start("raw", "events"), (10, 6));
+        assert_eq!(start("crm", "users"), (16, 6));
+    }
+}
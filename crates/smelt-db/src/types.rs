@@ -0,0 +1,610 @@
+/// SQL type inference
+///
+/// Types start at the columns declared in sources.yml and flow through each
+/// model's select list to downstream models. Inference is best-effort: any
+/// expression it can't type is `Unknown`, and `Unknown` never causes an error,
+/// so only definite mismatches are reported.
+use std::fmt;
+
+use rowan::TextRange;
+use smelt_parser::{Expr, FunctionCall, LiteralKind, SyntaxKind};
+
+/// Inferred type of a column or expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SqlType {
+    Boolean,
+    Integer,
+    BigInt,
+    Decimal,
+    Double,
+    Varchar,
+    Date,
+    Timestamp,
+    Interval,
+    /// The NULL literal, compatible with every type
+    Null,
+    /// Could not be inferred
+    Unknown,
+}
+
+/// Families of types that can be compared with each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    Boolean,
+    Numeric,
+    String,
+    Temporal,
+    Interval,
+}
+
+impl SqlType {
+    /// Read a type name as written in sources.yml or a CAST
+    /// (`INTEGER`, `varchar(255)`, `DECIMAL(10, 2)`, `TIMESTAMP WITH TIME ZONE`)
+    pub fn parse(name: &str) -> Self {
+        let name = name.trim().to_uppercase();
+        let base = name
+            .split(|c: char| c == '(' || c.is_whitespace())
+            .next()
+            .unwrap_or("");
+        match base {
+            "BOOLEAN" | "BOOL" => SqlType::Boolean,
+            "TINYINT" | "SMALLINT" | "INTEGER" | "INT" | "INT2" | "INT4" | "SHORT" => {
+                SqlType::Integer
+            }
+            "BIGINT" | "INT8" | "LONG" | "HUGEINT" => SqlType::BigInt,
+            "DECIMAL" | "NUMERIC" | "DEC" => SqlType::Decimal,
+            "DOUBLE" | "FLOAT" | "FLOAT4" | "FLOAT8" | "REAL" => SqlType::Double,
+            "VARCHAR" | "CHAR" | "TEXT" | "STRING" | "BPCHAR" => SqlType::Varchar,
+            "DATE" => SqlType::Date,
+            "TIMESTAMP" | "DATETIME" | "TIMESTAMPTZ" | "TIMESTAMP_NTZ" | "TIMESTAMP_LTZ" => {
+                SqlType::Timestamp
+            }
+            "INTERVAL" => SqlType::Interval,
+            _ => SqlType::Unknown,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SqlType::Boolean => "BOOLEAN",
+            SqlType::Integer => "INTEGER",
+            SqlType::BigInt => "BIGINT",
+            SqlType::Decimal => "DECIMAL",
+            SqlType::Double => "DOUBLE",
+            SqlType::Varchar => "VARCHAR",
+            SqlType::Date => "DATE",
+            SqlType::Timestamp => "TIMESTAMP",
+            SqlType::Interval => "INTERVAL",
+            SqlType::Null => "NULL",
+            SqlType::Unknown => "UNKNOWN",
+        }
+    }
+
+    /// True if the type is known (not Unknown or NULL)
+    pub fn is_known(&self) -> bool {
+        self.category().is_some()
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.category() == Some(Category::Numeric)
+    }
+
+    pub fn is_temporal(&self) -> bool {
+        self.category() == Some(Category::Temporal)
+    }
+
+    fn category(&self) -> Option<Category> {
+        match self {
+            SqlType::Boolean => Some(Category::Boolean),
+            SqlType::Integer | SqlType::BigInt | SqlType::Decimal | SqlType::Double => {
+                Some(Category::Numeric)
+            }
+            SqlType::Varchar => Some(Category::String),
+            SqlType::Date | SqlType::Timestamp => Some(Category::Temporal),
+            SqlType::Interval => Some(Category::Interval),
+            SqlType::Null | SqlType::Unknown => None,
+        }
+    }
+
+    /// Whether values of the two types can be compared. Strings compare with
+    /// dates and timestamps, which covers `order_date >= '2024-01-01'`.
+    pub fn is_comparable_with(&self, other: SqlType) -> bool {
+        match (self.category(), other.category()) {
+            (Some(a), Some(b)) => {
+                a == b
+                    || matches!(
+                        (a, b),
+                        (Category::String, Category::Temporal)
+                            | (Category::Temporal, Category::String)
+                    )
+            }
+            _ => true,
+        }
+    }
+
+    /// The type both values convert to (CASE branches, COALESCE arguments),
+    /// or None if they are incompatible
+    pub fn common_type(self, other: SqlType) -> Option<SqlType> {
+        use SqlType::*;
+        let rank = |t: SqlType| match t {
+            Integer => 0,
+            BigInt => 1,
+            Decimal => 2,
+            _ => 3,
+        };
+        match (self, other) {
+            (a, b) if a == b => Some(a),
+            (Null, t) | (t, Null) => Some(t),
+            (Unknown, _) | (_, Unknown) => Some(Unknown),
+            (a, b) if a.is_numeric() && b.is_numeric() => {
+                Some(if rank(a) >= rank(b) { a } else { b })
+            }
+            (a, b) if a.is_temporal() && b.is_temporal() => Some(Timestamp),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SqlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A definite type mismatch in an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub message: String,
+    pub range: TextRange,
+}
+
+/// Column types visible to the expressions of a SELECT
+#[derive(Debug, Clone, Default)]
+pub struct TypeScope {
+    columns: Vec<(String, SqlType)>,
+}
+
+impl TypeScope {
+    pub fn new(columns: impl IntoIterator<Item = (String, SqlType)>) -> Self {
+        Self {
+            columns: columns.into_iter().collect(),
+        }
+    }
+
+    /// Type of a column. Unknown if it isn't in scope or relations disagree.
+    pub fn lookup(&self, name: &str) -> SqlType {
+        let mut found = self
+            .columns
+            .iter()
+            .filter(|(column, _)| column.eq_ignore_ascii_case(name))
+            .map(|(_, ty)| *ty);
+        match found.next() {
+            Some(ty) if found.all(|other| other == ty) => ty,
+            _ => SqlType::Unknown,
+        }
+    }
+}
+
+/// Infer the type of an expression, collecting mismatches into `errors`
+pub fn infer_type(expr: &Expr, scope: &TypeScope, errors: &mut Vec<TypeError>) -> SqlType {
+    if let Some(col_ref) = expr.as_column_ref() {
+        return scope.lookup(col_ref.name());
+    }
+    if let Some(literal) = expr.as_literal() {
+        return match literal.kind() {
+            LiteralKind::String => SqlType::Varchar,
+            LiteralKind::Boolean => SqlType::Boolean,
+            LiteralKind::Null => SqlType::Null,
+            LiteralKind::Number if literal.text().contains(['.', 'e', 'E']) => SqlType::Decimal,
+            LiteralKind::Number => SqlType::Integer,
+        };
+    }
+    if let Some(typed) = expr.as_typed_literal() {
+        return typed
+            .type_name()
+            .map(|name| SqlType::parse(&name))
+            .unwrap_or(SqlType::Unknown);
+    }
+    if let Some(binary) = expr.as_binary_expr() {
+        let lhs = binary.lhs();
+        let rhs = binary.rhs();
+        let left = lhs.as_ref().map(|e| infer_type(e, scope, errors));
+        let right = rhs.as_ref().map(|e| infer_type(e, scope, errors));
+        let (Some(op), Some(left), Some(right)) = (binary.op(), left, right) else {
+            return SqlType::Unknown;
+        };
+        return binary_type(op, left, right, expr.range(), errors);
+    }
+    if let Some(unary) = expr.as_unary_expr() {
+        let operand = unary
+            .operand()
+            .map(|e| infer_type(&e, scope, errors))
+            .unwrap_or(SqlType::Unknown);
+        return match unary.op() {
+            Some(SyntaxKind::NOT_KW) => {
+                expect_boolean("NOT", operand, expr.range(), errors);
+                SqlType::Boolean
+            }
+            _ if operand.is_known() && !operand.is_numeric() && operand != SqlType::Interval => {
+                errors.push(TypeError {
+                    message: format!("Cannot negate a value of type {}", operand),
+                    range: expr.range(),
+                });
+                SqlType::Unknown
+            }
+            _ => operand,
+        };
+    }
+    if let Some(case) = expr.as_case_expr() {
+        let operand = case.operand().map(|e| infer_type(&e, scope, errors));
+        let mut result: Option<SqlType> = None;
+        let branches = case
+            .whens()
+            .map(|when| (when.condition(), when.result()))
+            .collect::<Vec<_>>();
+
+        for (condition, value) in branches {
+            if let Some(condition) = condition {
+                let ty = infer_type(&condition, scope, errors);
+                match operand {
+                    Some(operand) if !operand.is_comparable_with(ty) => errors.push(TypeError {
+                        message: format!("Cannot compare {} with {} in CASE", operand, ty),
+                        range: condition.range(),
+                    }),
+                    Some(_) => {}
+                    None => expect_boolean("CASE WHEN", ty, condition.range(), errors),
+                }
+            }
+            if let Some(value) = value {
+                let ty = infer_type(&value, scope, errors);
+                result = merge_branch(result, ty, value.range(), errors);
+            }
+        }
+        if let Some(value) = case.else_expr() {
+            let ty = infer_type(&value, scope, errors);
+            result = merge_branch(result, ty, value.range(), errors);
+        }
+        return result.unwrap_or(SqlType::Unknown);
+    }
+    if let Some(cast) = expr.as_cast_expr() {
+        if let Some(inner) = cast.expression() {
+            infer_type(&inner, scope, errors);
+        }
+        return cast
+            .type_name()
+            .map(|t| SqlType::parse(&t.text()))
+            .unwrap_or(SqlType::Unknown);
+    }
+    if let Some(func) = expr.as_function_call() {
+        return function_type(&func, scope, errors);
+    }
+    if let Some(is_expr) = expr.as_is_expr() {
+        if let Some(inner) = is_expr.expression() {
+            infer_type(&inner, scope, errors);
+        }
+        return SqlType::Boolean;
+    }
+    if let Some(in_expr) = expr.as_in_expr() {
+        let Some(value) = in_expr.expression() else {
+            return SqlType::Boolean;
+        };
+        let ty = infer_type(&value, scope, errors);
+        for item in in_expr.values() {
+            let item_ty = infer_type(&item, scope, errors);
+            check_comparable(ty, item_ty, item.range(), errors);
+        }
+        return SqlType::Boolean;
+    }
+    if let Some(between) = expr.as_between_expr() {
+        let ty = between
+            .expression()
+            .map(|e| infer_type(&e, scope, errors))
+            .unwrap_or(SqlType::Unknown);
+        for bound in [between.low(), between.high()].into_iter().flatten() {
+            let bound_ty = infer_type(&bound, scope, errors);
+            check_comparable(ty, bound_ty, bound.range(), errors);
+        }
+        return SqlType::Boolean;
+    }
+    if let Some(like) = expr.as_like_expr() {
+        for operand in [like.expression(), like.pattern()].into_iter().flatten() {
+            let ty = infer_type(&operand, scope, errors);
+            if ty.is_known() && ty != SqlType::Varchar {
+                errors.push(TypeError {
+                    message: format!("LIKE expects VARCHAR operands, found {}", ty),
+                    range: operand.range(),
+                });
+            }
+        }
+        return SqlType::Boolean;
+    }
+    if expr.as_exists_expr().is_some() {
+        return SqlType::Boolean;
+    }
+
+    // Scalar subqueries are checked in their own scope
+    SqlType::Unknown
+}
+
+fn binary_type(
+    op: SyntaxKind,
+    left: SqlType,
+    right: SqlType,
+    range: TextRange,
+    errors: &mut Vec<TypeError>,
+) -> SqlType {
+    use SyntaxKind::*;
+    match op {
+        AND_KW | OR_KW => {
+            let name = if op == AND_KW { "AND" } else { "OR" };
+            expect_boolean(name, left, range, errors);
+            expect_boolean(name, right, range, errors);
+            SqlType::Boolean
+        }
+        EQ | NE | LT | GT | LE | GE => {
+            check_comparable(left, right, range, errors);
+            SqlType::Boolean
+        }
+        CONCAT => SqlType::Varchar,
+        PLUS | MINUS | STAR | DIVIDE | PERCENT => arithmetic_type(op, left, right, range, errors),
+        _ => SqlType::Unknown,
+    }
+}
+
+fn arithmetic_type(
+    op: SyntaxKind,
+    left: SqlType,
+    right: SqlType,
+    range: TextRange,
+    errors: &mut Vec<TypeError>,
+) -> SqlType {
+    use SyntaxKind::*;
+    let symbol = match op {
+        PLUS => "+",
+        MINUS => "-",
+        STAR => "*",
+        DIVIDE => "/",
+        _ => "%",
+    };
+
+    // Date arithmetic: date +/- interval, date +/- days, date - date
+    if matches!(op, PLUS | MINUS) {
+        match (left, right) {
+            (t, SqlType::Interval) | (SqlType::Interval, t) if t.is_temporal() => {
+                return SqlType::Timestamp
+            }
+            (SqlType::Date, t) | (t, SqlType::Date) if t.is_numeric() => return SqlType::Date,
+            (a, b) if op == MINUS && a.is_temporal() && b.is_temporal() => return SqlType::Unknown,
+            (SqlType::Interval, SqlType::Interval) => return SqlType::Interval,
+            _ => {}
+        }
+    }
+
+    if !left.is_known() || !right.is_known() {
+        return if left.is_numeric() || right.is_numeric() {
+            if left.is_numeric() {
+                left
+            } else {
+                right
+            }
+        } else {
+            SqlType::Unknown
+        };
+    }
+    if !left.is_numeric() || !right.is_numeric() {
+        errors.push(TypeError {
+            message: format!(
+                "Operator '{}' cannot be applied to {} and {}",
+                symbol, left, right
+            ),
+            range,
+        });
+        return SqlType::Unknown;
+    }
+
+    if op == DIVIDE {
+        return SqlType::Double;
+    }
+    left.common_type(right).unwrap_or(SqlType::Unknown)
+}
+
+fn function_type(func: &FunctionCall, scope: &TypeScope, errors: &mut Vec<TypeError>) -> SqlType {
+    let name = func.name().unwrap_or_default().to_uppercase();
+    let args: Vec<(SqlType, TextRange)> = func
+        .args()
+        .map(|arg| (infer_type(&arg, scope, errors), arg.range()))
+        .collect();
+    let first = args.first().map(|(ty, _)| *ty).unwrap_or(SqlType::Unknown);
+
+    let numeric_arg = |errors: &mut Vec<TypeError>| {
+        if let Some(&(ty, range)) = args.first() {
+            if ty.is_known() && !ty.is_numeric() {
+                errors.push(TypeError {
+                    message: format!("{} expects a numeric argument, found {}", name, ty),
+                    range,
+                });
+            }
+        }
+    };
+
+    match name.as_str() {
+        "COUNT" | "ROW_NUMBER" | "RANK" | "DENSE_RANK" | "NTILE" | "LENGTH" | "STRLEN" => {
+            SqlType::BigInt
+        }
+        "SUM" => {
+            numeric_arg(errors);
+            match first {
+                SqlType::Integer | SqlType::BigInt => SqlType::BigInt,
+                ty if ty.is_numeric() => ty,
+                _ => SqlType::Unknown,
+            }
+        }
+        "AVG" | "STDDEV" | "STDDEV_SAMP" | "STDDEV_POP" | "VARIANCE" | "MEDIAN" => {
+            numeric_arg(errors);
+            SqlType::Double
+        }
+        "ABS" | "ROUND" | "FLOOR" | "CEIL" | "CEILING" => {
+            numeric_arg(errors);
+            first
+        }
+        "MIN" | "MAX" | "ANY_VALUE" | "FIRST" | "LAST" | "FIRST_VALUE" | "LAST_VALUE" | "LAG"
+        | "LEAD" | "NTH_VALUE" => first,
+        "COALESCE" | "IFNULL" | "NULLIF" | "GREATEST" | "LEAST" => {
+            let mut result = None;
+            for &(ty, range) in &args {
+                result = merge_branch(result, ty, range, errors);
+            }
+            result.unwrap_or(SqlType::Unknown)
+        }
+        "LOWER" | "UPPER" | "TRIM" | "LTRIM" | "RTRIM" | "CONCAT" | "SUBSTRING" | "SUBSTR"
+        | "REPLACE" | "LEFT" | "RIGHT" | "STRING_AGG" | "MD5" => SqlType::Varchar,
+        "CURRENT_DATE" | "TODAY" => SqlType::Date,
+        "NOW" | "CURRENT_TIMESTAMP" | "DATE_TRUNC" => SqlType::Timestamp,
+        "DATE_PART" | "EXTRACT" | "YEAR" | "MONTH" | "DAY" | "HOUR" | "DATE_DIFF" | "DATEDIFF" => {
+            SqlType::BigInt
+        }
+        "BOOL_AND" | "BOOL_OR" => SqlType::Boolean,
+        _ => SqlType::Unknown,
+    }
+}
+
+/// Combine the type of another CASE / COALESCE branch into the result
+fn merge_branch(
+    result: Option<SqlType>,
+    ty: SqlType,
+    range: TextRange,
+    errors: &mut Vec<TypeError>,
+) -> Option<SqlType> {
+    let Some(current) = result else {
+        return Some(ty);
+    };
+    match current.common_type(ty) {
+        Some(common) => Some(common),
+        None => {
+            errors.push(TypeError {
+                message: format!("Incompatible branch types {} and {}", current, ty),
+                range,
+            });
+            Some(SqlType::Unknown)
+        }
+    }
+}
+
+fn check_comparable(left: SqlType, right: SqlType, range: TextRange, errors: &mut Vec<TypeError>) {
+    if !left.is_comparable_with(right) {
+        errors.push(TypeError {
+            message: format!("Cannot compare {} with {}", left, right),
+            range,
+        });
+    }
+}
+
+fn expect_boolean(context: &str, ty: SqlType, range: TextRange, errors: &mut Vec<TypeError>) {
+    if ty.is_known() && ty != SqlType::Boolean {
+        errors.push(TypeError {
+            message: format!("{} expects a BOOLEAN condition, found {}", context, ty),
+            range,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smelt_parser::File;
+
+    /// Infer the type of the first select item against `columns`
+    fn infer(sql: &str, columns: &[(&str, SqlType)]) -> (SqlType, Vec<String>) {
+        let file = File::cast(smelt_parser::parse(sql).syntax()).unwrap();
+        let item = file
+            .select_stmt()
+            .and_then(|s| s.select_list())
+            .and_then(|l| l.items().next())
+            .unwrap();
+        let scope = TypeScope::new(columns.iter().map(|(n, t)| (n.to_string(), *t)));
+        let mut errors = Vec::new();
+        let ty = infer_type(&item.expression().unwrap(), &scope, &mut errors);
+        (ty, errors.into_iter().map(|e| e.message).collect())
+    }
+
+    #[test]
+    fn test_parse_type_names() {
+        assert_eq!(SqlType::parse("integer"), SqlType::Integer);
+        assert_eq!(SqlType::parse("VARCHAR(255)"), SqlType::Varchar);
+        assert_eq!(SqlType::parse("DECIMAL(10, 2)"), SqlType::Decimal);
+        assert_eq!(
+            SqlType::parse("TIMESTAMP WITH TIME ZONE"),
+            SqlType::Timestamp
+        );
+        assert_eq!(SqlType::parse("STRUCT(a INT)"), SqlType::Unknown);
+    }
+
+    #[test]
+    fn test_infer_expression_types() {
+        let columns = [
+            ("id", SqlType::Integer),
+            ("amount", SqlType::Decimal),
+            ("name", SqlType::Varchar),
+            ("created", SqlType::Date),
+        ];
+        let ty = |sql: &str| {
+            let (ty, errors) = infer(sql, &columns);
+            assert!(errors.is_empty(), "{}: {:?}", sql, errors);
+            ty
+        };
+
+        assert_eq!(ty("SELECT 1"), SqlType::Integer);
+        assert_eq!(ty("SELECT 1.5"), SqlType::Decimal);
+        assert_eq!(ty("SELECT id + amount"), SqlType::Decimal);
+        assert_eq!(ty("SELECT id / 2"), SqlType::Double);
+        assert_eq!(ty("SELECT SUM(id)"), SqlType::BigInt);
+        assert_eq!(ty("SELECT COUNT(*)"), SqlType::BigInt);
+        assert_eq!(ty("SELECT MAX(created)"), SqlType::Date);
+        assert_eq!(ty("SELECT CAST(id AS VARCHAR)"), SqlType::Varchar);
+        assert_eq!(ty("SELECT id::DOUBLE"), SqlType::Double);
+        assert_eq!(ty("SELECT name || '!'"), SqlType::Varchar);
+        assert_eq!(ty("SELECT created >= '2024-01-01'"), SqlType::Boolean);
+        assert_eq!(ty("SELECT created + INTERVAL 1 DAY"), SqlType::Timestamp);
+        assert_eq!(
+            ty("SELECT CASE WHEN id > 1 THEN id ELSE NULL END"),
+            SqlType::Integer
+        );
+        assert_eq!(ty("SELECT COALESCE(amount, 0)"), SqlType::Decimal);
+        assert_eq!(ty("SELECT unknown_column + 1"), SqlType::Integer);
+        assert_eq!(ty("SELECT some_udf(id)"), SqlType::Unknown);
+    }
+
+    #[test]
+    fn test_type_mismatches() {
+        let columns = [("id", SqlType::Integer), ("name", SqlType::Varchar)];
+        let errors = |sql: &str| infer(sql, &columns).1;
+
+        assert_eq!(
+            errors("SELECT name = id"),
+            vec!["Cannot compare VARCHAR with INTEGER"]
+        );
+        assert_eq!(
+            errors("SELECT SUM(name)"),
+            vec!["SUM expects a numeric argument, found VARCHAR"]
+        );
+        assert_eq!(
+            errors("SELECT name + 1"),
+            vec!["Operator '+' cannot be applied to VARCHAR and INTEGER"]
+        );
+        assert_eq!(
+            errors("SELECT CASE WHEN id > 0 THEN id ELSE name END"),
+            vec!["Incompatible branch types INTEGER and VARCHAR"]
+        );
+        assert_eq!(
+            errors("SELECT id IN (1, 'two')"),
+            vec!["Cannot compare INTEGER with VARCHAR"]
+        );
+        assert_eq!(
+            errors("SELECT id AND TRUE"),
+            vec!["AND expects a BOOLEAN condition, found INTEGER"]
+        );
+
+        // Unknown types never produce errors
+        assert!(errors("SELECT other = 1 AND SUM(other) > 0").is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use tower_lsp::jsonrpc::Result;
@@ -5,8 +6,9 @@ use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 use tokio::sync::Mutex;
 
-use smelt_db::{Database, Diagnostic as DbDiagnostic, DiagnosticSeverity as DbSeverity, Inputs, Schema, Semantic, Syntax};
+use smelt_db::{Database, Diagnostic as DbDiagnostic, DiagnosticSeverity as DbSeverity, Inputs, ProjectSettings, Schema, Semantic, SourceCatalog, Syntax};
 use smelt_parser::ast::File as AstFile;
+use smelt_parser::SyntaxKind;
 
 struct Backend {
     client: Client,
@@ -53,7 +55,7 @@ impl Backend {
         };
 
         let db = self.db.lock().await;
-        let diagnostics = db.file_diagnostics(path);
+        let diagnostics = db.diagnostics(path);
 
         let lsp_diagnostics: Vec<lsp_types::Diagnostic> = diagnostics
             .iter()
@@ -64,6 +66,66 @@ impl Backend {
             .publish_diagnostics(uri, lsp_diagnostics, None)
             .await;
     }
+
+    /// Reload sources.yml and refresh diagnostics of every model, since
+    /// smelt.source() calls may have become valid or invalid
+    async fn reload_sources(&self, path: PathBuf, text: &str) {
+        let catalog = SourceCatalog::from_yaml(path.clone(), text);
+        self.reload_input(path, catalog, |db, catalog| {
+            db.set_source_catalog(Arc::new(catalog))
+        })
+        .await;
+    }
+
+    /// Reload smelt.yml and refresh diagnostics of every model, since the
+    /// target's dialect decides how models are lexed
+    async fn reload_project(&self, path: PathBuf, text: &str) {
+        let settings = ProjectSettings::from_yaml(text);
+        self.reload_input(path, settings, |db, settings| {
+            db.set_project_settings(Arc::new(settings))
+        })
+        .await;
+    }
+
+    /// Set an input read from `path` and republish every model's
+    /// diagnostics, or log why the file couldn't be read
+    async fn reload_input<T, E: std::fmt::Display>(
+        &self,
+        path: PathBuf,
+        parsed: std::result::Result<T, E>,
+        set_input: impl FnOnce(&mut Database, T),
+    ) {
+        match parsed {
+            Ok(input) => {
+                let files = {
+                    let mut db = self.db.lock().await;
+                    set_input(&mut db, input);
+                    db.all_files()
+                };
+                for file in files.iter() {
+                    if let Ok(uri) = Url::from_file_path(file) {
+                        self.publish_diagnostics(uri).await;
+                    }
+                }
+            }
+            Err(e) => {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Failed to read {}: {}", path.display(), e),
+                    )
+                    .await;
+            }
+        }
+    }
+}
+
+fn is_sources_file(path: &Path) -> bool {
+    path.file_name().and_then(|s| s.to_str()) == Some("sources.yml")
+}
+
+fn is_project_file(path: &Path) -> bool {
+    path.file_name().and_then(|s| s.to_str()) == Some("smelt.yml")
 }
 
 #[tower_lsp::async_trait]
@@ -92,6 +154,21 @@ impl LanguageServer for Backend {
 
                         db.set_all_files(Arc::new(files));
                     }
+
+                    // Load declared sources for smelt.source() calls
+                    let sources_path = path.join("sources.yml");
+                    if let Ok(content) = std::fs::read_to_string(&sources_path) {
+                        if let Ok(catalog) = SourceCatalog::from_yaml(sources_path, &content) {
+                            db.set_source_catalog(Arc::new(catalog));
+                        }
+                    }
+
+                    // Load the target's dialect from smelt.yml
+                    if let Ok(content) = std::fs::read_to_string(path.join("smelt.yml")) {
+                        if let Ok(settings) = ProjectSettings::from_yaml(&content) {
+                            db.set_project_settings(Arc::new(settings));
+                        }
+                    }
                 }
             }
         }
@@ -130,6 +207,15 @@ impl LanguageServer for Backend {
             Err(_) => return,
         };
 
+        if is_sources_file(&path) {
+            self.reload_sources(path, &params.text_document.text).await;
+            return;
+        }
+        if is_project_file(&path) {
+            self.reload_project(path, &params.text_document.text).await;
+            return;
+        }
+
         // Update file content in database
         let mut db = self.db.lock().await;
         db.set_file_text(path, Arc::new(params.text_document.text));
@@ -148,6 +234,15 @@ impl LanguageServer for Backend {
 
         // Get new text (we use FULL sync, so there's only one change)
         if let Some(change) = params.content_changes.into_iter().next() {
+            if is_sources_file(&path) {
+                self.reload_sources(path, &change.text).await;
+                return;
+            }
+            if is_project_file(&path) {
+                self.reload_project(path, &change.text).await;
+                return;
+            }
+
             // Update in database - Salsa will handle incremental recomputation
             let mut db = self.db.lock().await;
             db.set_file_text(path, Arc::new(change.text));
@@ -223,6 +318,38 @@ impl LanguageServer for Backend {
                     }
                 }
             }
+
+            // Jump from smelt.source() to its entry in sources.yml
+            for source in file.sources() {
+                let range = source.range();
+                let start: usize = range.start().into();
+                let end: usize = range.end().into();
+
+                if cursor_offset >= start && cursor_offset <= end {
+                    let catalog = db.source_catalog();
+                    let (Some(schema), Some(table)) = (source.schema_name(), source.table_name())
+                    else {
+                        continue;
+                    };
+                    if let (Some(sources_path), Some(table)) =
+                        (&catalog.path, catalog.find(&schema, &table))
+                    {
+                        if let Ok(target_uri) = Url::from_file_path(sources_path) {
+                            let range = table
+                                .range
+                                .map(|r| Range {
+                                    start: Position::new(r.start.line, r.start.column),
+                                    end: Position::new(r.end.line, r.end.column),
+                                })
+                                .unwrap_or_default();
+                            return Ok(Some(GotoDefinitionResponse::Scalar(Location {
+                                uri: target_uri,
+                                range,
+                            })));
+                        }
+                    }
+                }
+            }
         }
 
         Ok(None)
@@ -265,6 +392,34 @@ impl LanguageServer for Backend {
             offset
         };
 
+        // Explain how an incremental model can be run from its annotations
+        let annotation = syntax
+            .token_at_offset((cursor_offset as u32).into())
+            .find(|t| t.kind() == SyntaxKind::COMMENT)
+            .filter(|t| t.text().contains("@incremental") || t.text().contains("@materialize"));
+        if annotation.is_some() {
+            let time_column = db
+                .model_settings(path.clone())
+                .incremental
+                .as_ref()
+                .and_then(|i| i.time_column.clone());
+            let analysis = db.incrementality(path.clone(), time_column);
+
+            let mut content = format!("**Incrementality: {}**\n\n", analysis.classification);
+            for reason in &analysis.reasons {
+                let mark = if reason.allows.is_incremental() { "✓" } else { "⚠" };
+                content.push_str(&format!("- {} {}\n", mark, reason.message));
+            }
+
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: content,
+                }),
+                range: None,
+            }));
+        }
+
         // Check if hovering over a ref() call
         if let Some(file) = AstFile::cast(syntax) {
             for ref_call in file.refs() {
@@ -290,6 +445,9 @@ impl LanguageServer for Backend {
                                 }
 
                                 content.push_str(&format!("- `{}`", col.name));
+                                if col.data_type.is_known() {
+                                    content.push_str(&format!(" {}", col.data_type));
+                                }
 
                                 // Show source if available
                                 match &col.source {
@@ -302,10 +460,12 @@ impl LanguageServer for Backend {
                                             model_name, column_name
                                         ));
                                     }
-                                    smelt_db::ColumnSource::Computed => {
-                                        if !col.expression.is_empty() && col.expression != col.name {
-                                            content.push_str(&format!(" = `{}`", col.expression));
-                                        }
+                                    smelt_db::ColumnSource::Computed
+                                    | smelt_db::ColumnSource::ComputedFrom { .. }
+                                        if !col.expression.is_empty()
+                                            && col.expression != col.name =>
+                                    {
+                                        content.push_str(&format!(" = `{}`", col.expression));
                                     }
                                     _ => {}
                                 }
@@ -324,6 +484,44 @@ impl LanguageServer for Backend {
                     }
                 }
             }
+
+            // Show the declared columns of a smelt.source() table
+            for source in file.sources() {
+                let range = source.range();
+                let start: usize = range.start().into();
+                let end: usize = range.end().into();
+
+                if cursor_offset >= start && cursor_offset <= end {
+                    let catalog = db.source_catalog();
+                    let (Some(schema), Some(table)) = (source.schema_name(), source.table_name())
+                    else {
+                        continue;
+                    };
+                    if let Some(table) = catalog.find(&schema, &table) {
+                        let mut content = format!("**Source: {}**\n\n", table.qualified_name());
+                        if !table.description.is_empty() {
+                            content.push_str(&format!("{}\n\n", table.description));
+                        }
+                        content.push_str("Columns:\n");
+
+                        for col in &table.columns {
+                            content.push_str(&format!("- `{}` {}", col.name, col.column_type));
+                            if !col.description.is_empty() {
+                                content.push_str(&format!(" - {}", col.description));
+                            }
+                            content.push('\n');
+                        }
+
+                        return Ok(Some(Hover {
+                            contents: HoverContents::Markup(MarkupContent {
+                                kind: MarkupKind::Markdown,
+                                value: content,
+                            }),
+                            range: None,
+                        }));
+                    }
+                }
+            }
         }
 
         Ok(None)
@@ -379,6 +577,35 @@ impl LanguageServer for Backend {
                     })
                     .collect()
             }
+            CompletionContext::InsideSourceSchema => {
+                // Complete schema names declared in sources.yml
+                let catalog = db.source_catalog();
+                catalog
+                    .schemas()
+                    .into_iter()
+                    .map(|schema| CompletionItem {
+                        label: schema.to_string(),
+                        kind: Some(CompletionItemKind::MODULE),
+                        detail: Some(format!("Source schema: {}", schema)),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+            CompletionContext::InsideSourceTable(schema) => {
+                // Complete table names declared under the schema
+                let catalog = db.source_catalog();
+                catalog
+                    .tables_in(&schema)
+                    .map(|table| CompletionItem {
+                        label: table.name.clone(),
+                        kind: Some(CompletionItemKind::STRUCT),
+                        detail: Some(format!("Source: {}", table.qualified_name())),
+                        documentation: (!table.description.is_empty())
+                            .then(|| Documentation::String(table.description.clone())),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
             CompletionContext::ColumnName => {
                 // Complete column names from available columns
                 let available = db.available_columns(path);
@@ -390,6 +617,9 @@ impl LanguageServer for Backend {
                         if let Some(alias) = &col.alias {
                             detail = format!("{} AS {}", detail, alias);
                         }
+                        if col.data_type.is_known() {
+                            detail = format!("{}: {}", detail, col.data_type);
+                        }
 
                         CompletionItem {
                             label: col.name.clone(),
@@ -406,6 +636,22 @@ impl LanguageServer for Backend {
                                 smelt_db::ColumnSource::Computed => {
                                     Some(Documentation::String("Computed column".to_string()))
                                 }
+                                smelt_db::ColumnSource::ComputedFrom { inputs } => {
+                                    Some(Documentation::String(format!(
+                                        "Computed from {}",
+                                        inputs
+                                            .iter()
+                                            .filter_map(|i| i.upstream_name())
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    )))
+                                }
+                                smelt_db::ColumnSource::ExternalTable {
+                                    table_name, ..
+                                } => Some(Documentation::String(format!(
+                                    "From source '{}'",
+                                    table_name
+                                ))),
                                 _ => None,
                             },
                             ..Default::default()
@@ -427,8 +673,10 @@ impl LanguageServer for Backend {
 /// Completion context types
 #[derive(Debug)]
 enum CompletionContext {
-    InsideRef,   // Cursor inside ref('|')
-    ColumnName,  // Cursor in a position where column name is expected
+    InsideRef,                 // Cursor inside ref('|')
+    InsideSourceSchema,        // Cursor inside source('|')
+    InsideSourceTable(String), // Cursor inside source('schema', '|')
+    ColumnName,                // Cursor in a position where column name is expected
     None,
 }
 
@@ -437,6 +685,20 @@ fn determine_completion_context(text: &str, offset: usize) -> CompletionContext
     // Look backward from cursor to determine context
     let before_cursor = &text[..offset.min(text.len())];
 
+    // Check if we're inside source('') - the first string is the schema,
+    // the second the table
+    if let Some(source_start) = before_cursor.rfind("source(") {
+        let after_source = &before_cursor[source_start + "source(".len()..];
+        if !after_source.contains(')') {
+            let parts: Vec<&str> = after_source.split(['\'', '"']).collect();
+            match parts.len() {
+                2 => return CompletionContext::InsideSourceSchema,
+                4 => return CompletionContext::InsideSourceTable(parts[1].to_string()),
+                _ => {}
+            }
+        }
+    }
+
     // Check if we're inside ref('')
     // Simple heuristic: look for ref(' before cursor and no closing )
     if let Some(ref_start) = before_cursor.rfind("ref(") {
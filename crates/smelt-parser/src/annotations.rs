@@ -0,0 +1,444 @@
+/// Model header annotations
+///
+/// Models can configure themselves with `-- @key: value` line comments placed
+/// before the query:
+///
+/// ```sql
+/// -- @materialize: table
+/// -- @incremental: enabled
+/// -- @incremental.time_column: order_date
+/// -- @partition_by: order_date, region
+/// -- @tags: finance, nightly
+/// SELECT ...
+/// ```
+///
+/// Snapshot models name the columns that detect a changed row:
+///
+/// ```sql
+/// -- @materialize: snapshot
+/// -- @unique_key: customer_id
+/// -- @snapshot.updated_at: updated_at
+/// SELECT ...
+/// ```
+///
+/// The lexer keeps these as COMMENT trivia; this module reads them back out of
+/// the CST into a typed `ModelAnnotations`.
+use crate::ast::File;
+use crate::SyntaxKind::*;
+use rowan::{TextRange, TextSize};
+
+/// Materialization requested by `@materialize`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Materialization {
+    Table,
+    View,
+    Incremental,
+    Merge,
+    Snapshot,
+}
+
+/// Settings from `@incremental` and `@incremental.*`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IncrementalAnnotation {
+    pub enabled: bool,
+    pub time_column: Option<String>,
+}
+
+/// Settings from `@snapshot.*`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SnapshotAnnotation {
+    pub updated_at: Option<String>,
+    pub check_columns: Vec<String>,
+}
+
+/// Typed view of a model's header annotations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ModelAnnotations {
+    /// `@materialize: table|view|incremental|merge|snapshot`
+    pub materialize: Option<Materialization>,
+
+    /// `@incremental: enabled|disabled` and `@incremental.time_column: col`.
+    /// Setting a time column alone enables incremental processing.
+    pub incremental: Option<IncrementalAnnotation>,
+
+    /// `@partition_by: col1, col2`
+    pub partition_by: Vec<String>,
+
+    /// `@unique_key: col1, col2`, matching the rows a merge model upserts
+    /// or a snapshot model versions
+    pub unique_key: Vec<String>,
+
+    /// `@snapshot.updated_at: col` or `@snapshot.check_columns: col1, col2`
+    pub snapshot: Option<SnapshotAnnotation>,
+
+    /// `@tags: tag1, tag2`, for `--select tag:tag1`
+    pub tags: Vec<String>,
+}
+
+impl ModelAnnotations {
+    /// True if no annotation was given
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn is_incremental(&self) -> bool {
+        self.incremental.as_ref().is_some_and(|i| i.enabled)
+    }
+}
+
+/// Kind of problem found while reading annotations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnnotationErrorKind {
+    /// `@key` is not a known annotation
+    UnknownKey,
+    /// The value could not be interpreted for its key
+    InvalidValue,
+    /// The same key was given twice
+    Duplicate,
+    /// A known annotation after the start of the query (ignored)
+    Misplaced,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnotationError {
+    pub kind: AnnotationErrorKind,
+    pub message: String,
+    pub range: TextRange,
+}
+
+/// Known annotation keys
+const KEYS: &[&str] = &[
+    "materialize",
+    "incremental",
+    "incremental.time_column",
+    "partition_by",
+    "unique_key",
+    "snapshot.updated_at",
+    "snapshot.check_columns",
+    "tags",
+];
+
+/// A `-- @key: value` comment split into its parts
+struct RawAnnotation<'a> {
+    key: &'a str,
+    value: &'a str,
+    range: TextRange,
+    value_range: TextRange,
+}
+
+/// Read the leading annotations of a model file.
+///
+/// Only comments before the first token of the query count; known
+/// annotations further down are reported as misplaced.
+pub fn parse_annotations(file: &File) -> (ModelAnnotations, Vec<AnnotationError>) {
+    let mut annotations = ModelAnnotations::default();
+    let mut errors = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+    let mut in_header = true;
+
+    for token in file
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|e| e.into_token())
+    {
+        if !token.kind().is_trivia() {
+            in_header = false;
+            continue;
+        }
+        if token.kind() != COMMENT {
+            continue;
+        }
+        let Some(raw) = split_annotation(token.text(), token.text_range().start()) else {
+            continue;
+        };
+
+        if !in_header {
+            if KEYS.contains(&raw.key) {
+                errors.push(AnnotationError {
+                    kind: AnnotationErrorKind::Misplaced,
+                    message: format!(
+                        "Annotation '@{}' must appear before the query and is ignored",
+                        raw.key
+                    ),
+                    range: raw.range,
+                });
+            }
+            continue;
+        }
+
+        if !KEYS.contains(&raw.key) {
+            errors.push(AnnotationError {
+                kind: AnnotationErrorKind::UnknownKey,
+                message: format!(
+                    "Unknown annotation '@{}'. Expected one of: {}",
+                    raw.key,
+                    KEYS.iter()
+                        .map(|k| format!("@{}", k))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                range: raw.range,
+            });
+            continue;
+        }
+        if seen.iter().any(|k| k == raw.key) {
+            errors.push(AnnotationError {
+                kind: AnnotationErrorKind::Duplicate,
+                message: format!("Duplicate annotation '@{}'", raw.key),
+                range: raw.range,
+            });
+            continue;
+        }
+        seen.push(raw.key.to_string());
+
+        if let Err(message) = apply(&mut annotations, &raw) {
+            errors.push(AnnotationError {
+                kind: AnnotationErrorKind::InvalidValue,
+                message,
+                range: raw.value_range,
+            });
+        }
+    }
+
+    (annotations, errors)
+}
+
+/// Split `-- @key: value` into key and value, or None for ordinary comments
+fn split_annotation(text: &str, start: TextSize) -> Option<RawAnnotation<'_>> {
+    let body = text.strip_prefix("--")?;
+    let body_start = text.len() - body.trim_start().len();
+    let body = body.trim_start().strip_prefix('@')?;
+    let body = body.trim_end();
+
+    let (key, value, value_start) = match body.find(':') {
+        Some(colon) => {
+            let after = &body[colon + 1..];
+            let value = after.trim_start();
+            (
+                body[..colon].trim_end(),
+                value,
+                body_start + 1 + colon + 1 + (after.len() - value.len()),
+            )
+        }
+        None => (body, "", body_start + 1 + body.len()),
+    };
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let offset = |n: usize| start + TextSize::from(n as u32);
+    Some(RawAnnotation {
+        key,
+        value,
+        range: TextRange::at(start, TextSize::of(text.trim_end())),
+        value_range: TextRange::new(offset(value_start), offset(value_start + value.len())),
+    })
+}
+
+fn apply(annotations: &mut ModelAnnotations, raw: &RawAnnotation) -> Result<(), String> {
+    match raw.key {
+        "materialize" => {
+            annotations.materialize = Some(match raw.value.to_ascii_lowercase().as_str() {
+                "table" => Materialization::Table,
+                "view" => Materialization::View,
+                "incremental" => Materialization::Incremental,
+                "merge" => Materialization::Merge,
+                "snapshot" => Materialization::Snapshot,
+                _ => {
+                    return Err(format!(
+                        "Invalid value '{}' for '@materialize'. Expected 'table', 'view', 'incremental', 'merge' or 'snapshot'",
+                        raw.value
+                    ))
+                }
+            });
+        }
+        "incremental" => {
+            // A bare `-- @incremental` enables it
+            let enabled = match raw.value.to_ascii_lowercase().as_str() {
+                "" | "enabled" | "true" => true,
+                "disabled" | "false" => false,
+                _ => {
+                    return Err(format!(
+                        "Invalid value '{}' for '@incremental'. Expected 'enabled' or 'disabled'",
+                        raw.value
+                    ))
+                }
+            };
+            annotations
+                .incremental
+                .get_or_insert(IncrementalAnnotation {
+                    enabled,
+                    time_column: None,
+                })
+                .enabled = enabled;
+        }
+        "incremental.time_column" => {
+            if !is_identifier(raw.value) {
+                return Err(format!(
+                    "Invalid value '{}' for '@incremental.time_column'. Expected a column name",
+                    raw.value
+                ));
+            }
+            annotations
+                .incremental
+                .get_or_insert(IncrementalAnnotation {
+                    enabled: true,
+                    time_column: None,
+                })
+                .time_column = Some(raw.value.to_string());
+        }
+        "partition_by" => annotations.partition_by = column_list(raw)?,
+        "unique_key" => annotations.unique_key = column_list(raw)?,
+        "snapshot.updated_at" => {
+            if !is_identifier(raw.value) {
+                return Err(format!(
+                    "Invalid value '{}' for '@snapshot.updated_at'. Expected a column name",
+                    raw.value
+                ));
+            }
+            annotations.snapshot.get_or_insert_with(Default::default).updated_at =
+                Some(raw.value.to_string());
+        }
+        "snapshot.check_columns" => {
+            annotations.snapshot.get_or_insert_with(Default::default).check_columns =
+                column_list(raw)?;
+        }
+        "tags" => {
+            let tags: Vec<_> = raw.value.split(',').map(str::trim).collect();
+            if !tags.iter().all(|t| is_tag(t)) {
+                return Err(format!(
+                    "Invalid value '{}' for '@tags'. Expected a comma-separated list of tags",
+                    raw.value
+                ));
+            }
+            annotations.tags = tags.into_iter().map(String::from).collect();
+        }
+        _ => unreachable!("unknown keys are reported before applying"),
+    }
+    Ok(())
+}
+
+/// A comma-separated list of column names
+fn column_list(raw: &RawAnnotation) -> Result<Vec<String>, String> {
+    let columns: Vec<_> = raw.value.split(',').map(str::trim).collect();
+    if !columns.iter().all(|c| is_identifier(c)) {
+        return Err(format!(
+            "Invalid value '{}' for '@{}'. Expected a comma-separated list of column names",
+            raw.value, raw.key
+        ));
+    }
+    Ok(columns.into_iter().map(String::from).collect())
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Tags are identifiers that may also contain `-`, like `data-quality`
+fn is_tag(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn read(input: &str) -> (ModelAnnotations, Vec<AnnotationError>) {
+        let file = File::cast(parse(input).syntax()).unwrap();
+        parse_annotations(&file)
+    }
+
+    #[test]
+    fn test_readme_annotations() {
+        let (annotations, errors) = read(
+            "-- models/daily_revenue.sql\n\
+             -- @incremental: enabled\n\
+             -- @incremental.time_column: order_date\n\
+             \n\
+             SELECT order_date FROM smelt.ref('orders')",
+        );
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert_eq!(
+            annotations.incremental,
+            Some(IncrementalAnnotation {
+                enabled: true,
+                time_column: Some("order_date".to_string()),
+            })
+        );
+        assert!(annotations.is_incremental());
+        assert_eq!(annotations.materialize, None);
+    }
+
+    #[test]
+    fn test_all_annotations() {
+        let (annotations, errors) = read(
+            "/* header */\n\
+             --@materialize:TABLE\n\
+             -- @partition_by: order_date, region\n\
+             -- @incremental\n\
+             -- @unique_key: order_id\n\
+             SELECT 1",
+        );
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert_eq!(annotations.materialize, Some(Materialization::Table));
+        assert_eq!(annotations.partition_by, vec!["order_date", "region"]);
+        assert_eq!(annotations.unique_key, vec!["order_id"]);
+        assert!(annotations.is_incremental());
+
+        let (annotations, _) = read("SELECT 1");
+        assert!(annotations.is_empty());
+
+        let (annotations, errors) = read(
+            "-- @materialize: snapshot\n\
+             -- @unique_key: customer_id\n\
+             -- @snapshot.check_columns: status, tier\n\
+             SELECT 1",
+        );
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert_eq!(annotations.materialize, Some(Materialization::Snapshot));
+        assert_eq!(
+            annotations.snapshot,
+            Some(SnapshotAnnotation {
+                updated_at: None,
+                check_columns: vec!["status".to_string(), "tier".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_annotation_errors() {
+        let input = "-- @materialize: tabel\n\
+                     -- @materialise: table\n\
+                     -- @incremental: disabled\n\
+                     -- @incremental: enabled\n\
+                     -- @owner: data-team\n\
+                     -- @todo fix this later\n\
+                     SELECT 1\n\
+                     -- @partition_by: id\n";
+        let (annotations, errors) = read(input);
+
+        let kinds: Vec<_> = errors.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                AnnotationErrorKind::InvalidValue,
+                AnnotationErrorKind::UnknownKey,
+                AnnotationErrorKind::Duplicate,
+                AnnotationErrorKind::UnknownKey,
+                AnnotationErrorKind::Misplaced,
+            ]
+        );
+
+        // Invalid values point at the value, other errors at the whole comment
+        assert_eq!(&input[errors[0].range], "tabel");
+        assert_eq!(&input[errors[1].range], "-- @materialise: table");
+
+        // The first valid value wins and misplaced annotations are ignored
+        assert_eq!(annotations.materialize, None);
+        assert!(!annotations.is_incremental());
+        assert!(annotations.partition_by.is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 /// Typed AST wrappers over Rowan CST
-use crate::syntax_kind::SyntaxNode;
-use crate::SyntaxKind::*;
+use crate::syntax_kind::{SyntaxNode, SyntaxToken};
+use crate::SyntaxKind::{self, *};
 use rowan::TextRange;
 
 /// Root file node
@@ -16,8 +16,32 @@ impl File {
         }
     }
 
+    pub fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+
+    /// The model's main SELECT (for a UNION, its first operand, which
+    /// determines the output columns)
     pub fn select_stmt(&self) -> Option<SelectStmt> {
-        self.0.children().find_map(SelectStmt::cast)
+        self.0.children().find_map(first_select)
+    }
+
+    /// All SELECT statements in the file, including CTE bodies and UNION operands
+    pub fn select_stmts(&self) -> impl Iterator<Item = SelectStmt> + '_ {
+        self.0.descendants().filter_map(SelectStmt::cast)
+    }
+
+    /// The top-level WITH clause, if any
+    pub fn with_clause(&self) -> Option<WithClause> {
+        self.0
+            .children()
+            .filter(|n| matches!(n.kind(), SELECT_STMT | COMPOUND_SELECT))
+            .find_map(|n| n.children().find_map(WithClause::cast))
+    }
+
+    /// Statements of a multi-statement file (empty for model files)
+    pub fn statements(&self) -> impl Iterator<Item = Statement> + '_ {
+        self.0.children().filter_map(Statement::cast)
     }
 
     /// Find all ref('model') function calls in the file
@@ -27,6 +51,67 @@ impl File {
             .filter_map(FunctionCall::cast)
             .filter_map(RefCall::from_function_call)
     }
+
+    /// Find all smelt.source('schema', 'table') calls in the file
+    pub fn sources(&self) -> impl Iterator<Item = SourceCall> + '_ {
+        self.0
+            .descendants()
+            .filter_map(FunctionCall::cast)
+            .filter_map(SourceCall::from_function_call)
+    }
+
+    /// Find all window function calls (func(...) OVER ...) in the file
+    pub fn window_functions(&self) -> impl Iterator<Item = FunctionCall> + '_ {
+        self.0
+            .descendants()
+            .filter_map(FunctionCall::cast)
+            .filter(|f| f.is_window_function())
+    }
+}
+
+/// One statement of a multi-statement file (see `parse_statements`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement(SyntaxNode);
+
+impl Statement {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == STATEMENT {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Leading keyword of the statement, uppercased (e.g. "CREATE", "SELECT")
+    pub fn keyword(&self) -> Option<String> {
+        self.0
+            .descendants_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| !t.kind().is_trivia())
+            .map(|t| t.text().to_ascii_uppercase())
+    }
+
+    /// Whether the statement is a query (SELECT / WITH) that was fully parsed
+    pub fn is_query(&self) -> bool {
+        self.0
+            .children()
+            .any(|n| matches!(n.kind(), SELECT_STMT | COMPOUND_SELECT))
+    }
+
+    /// The statement's main SELECT, for query statements
+    pub fn select_stmt(&self) -> Option<SelectStmt> {
+        self.0.children().find_map(first_select)
+    }
+
+    /// Statement text without surrounding trivia or the terminating `;`
+    pub fn text(&self) -> String {
+        let text = self.0.text().to_string();
+        text.trim().trim_end_matches(';').trim_end().to_string()
+    }
+
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
 }
 
 /// SELECT statement
@@ -53,6 +138,320 @@ impl SelectStmt {
     pub fn where_clause(&self) -> Option<WhereClause> {
         self.0.children().find_map(WhereClause::cast)
     }
+
+    pub fn group_by_clause(&self) -> Option<GroupByClause> {
+        self.0.children().find_map(GroupByClause::cast)
+    }
+
+    pub fn having_clause(&self) -> Option<HavingClause> {
+        self.0.children().find_map(HavingClause::cast)
+    }
+
+    pub fn qualify_clause(&self) -> Option<QualifyClause> {
+        self.0.children().find_map(QualifyClause::cast)
+    }
+
+    pub fn order_by_clause(&self) -> Option<OrderByClause> {
+        self.0.children().find_map(OrderByClause::cast)
+    }
+
+    pub fn limit_clause(&self) -> Option<LimitClause> {
+        self.0.children().find_map(LimitClause::cast)
+    }
+
+    pub fn offset_clause(&self) -> Option<OffsetClause> {
+        self.0.children().find_map(OffsetClause::cast)
+    }
+
+    /// Check for SELECT DISTINCT (including DISTINCT ON)
+    pub fn is_distinct(&self) -> bool {
+        self.distinct_range().is_some()
+    }
+
+    /// Get the range of the DISTINCT keyword of SELECT DISTINCT
+    pub fn distinct_range(&self) -> Option<TextRange> {
+        self.0
+            .children_with_tokens()
+            .find(|e| e.kind() == DISTINCT_KW)
+            .map(|e| e.text_range())
+    }
+
+    pub fn window_clause(&self) -> Option<WindowClause> {
+        self.0.children().find_map(WindowClause::cast)
+    }
+
+    pub fn with_clause(&self) -> Option<WithClause> {
+        self.0.children().find_map(WithClause::cast)
+    }
+
+    /// Find a CTE visible from this SELECT by name.
+    ///
+    /// Searches the WITH clauses of enclosing queries from the innermost
+    /// outwards, so nested definitions shadow outer ones.
+    pub fn find_cte(&self, name: &str) -> Option<Cte> {
+        self.0
+            .ancestors()
+            .filter_map(|node| node.children().find_map(WithClause::cast))
+            .find_map(|with_clause| {
+                with_clause
+                    .ctes()
+                    .find(|cte| cte.name().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+            })
+    }
+
+    /// Column references that belong to this SELECT's scope, in source order.
+    ///
+    /// References in subqueries and CTE bodies belong to their own SELECT, and
+    /// the FROM clause contributes only JOIN conditions (arguments of table
+    /// functions such as smelt.ref() filters refer to the called relation).
+    pub fn column_refs(&self) -> Vec<ColumnRef> {
+        let mut refs = Vec::new();
+        collect_scope_column_refs(&self.0, &mut refs);
+        refs
+    }
+
+    /// Function calls that belong to this SELECT's scope, in source order,
+    /// including calls nested in other calls' arguments.
+    ///
+    /// Calls in subqueries and CTE bodies belong to their own SELECT, and
+    /// table functions in the FROM clause (such as smelt.ref()) are skipped.
+    pub fn function_calls(&self) -> Vec<FunctionCall> {
+        let mut calls = Vec::new();
+        collect_function_calls(&self.0, &mut calls);
+        calls
+    }
+
+    /// SELECTs whose FROM clauses are visible to this one, innermost first.
+    ///
+    /// A subquery in an expression may refer to the columns of the queries
+    /// around it; CTE bodies and derived tables in FROM may not.
+    pub fn enclosing_selects(&self) -> Vec<SelectStmt> {
+        let mut selects = Vec::new();
+        for node in self.0.ancestors().skip(1) {
+            match node.kind() {
+                CTE | TABLE_REF => break,
+                SELECT_STMT => selects.push(SelectStmt(node)),
+                _ => {}
+            }
+        }
+        selects
+    }
+
+    /// Get the text range of this SELECT statement
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+fn collect_scope_column_refs(node: &SyntaxNode, refs: &mut Vec<ColumnRef>) {
+    for child in node.children() {
+        collect_column_refs(&child, refs);
+    }
+}
+
+fn collect_function_calls(node: &SyntaxNode, calls: &mut Vec<FunctionCall>) {
+    for child in node.children() {
+        match child.kind() {
+            SUBQUERY | SELECT_STMT | COMPOUND_SELECT | WITH_CLAUSE | TABLE_REF => {}
+            FUNCTION_CALL => {
+                calls.push(FunctionCall(child.clone()));
+                collect_function_calls(&child, calls);
+            }
+            _ => collect_function_calls(&child, calls),
+        }
+    }
+}
+
+/// Set operator combining the operands of a compound query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+/// Compound query (SELECT ... {UNION|INTERSECT|EXCEPT} [ALL] SELECT ...)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompoundSelect(SyntaxNode);
+
+impl CompoundSelect {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == COMPOUND_SELECT {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn with_clause(&self) -> Option<WithClause> {
+        self.0.children().find_map(WithClause::cast)
+    }
+
+    /// The SELECT operands, in order. An operand in parentheses contributes
+    /// its first SELECT.
+    pub fn select_stmts(&self) -> impl Iterator<Item = SelectStmt> + '_ {
+        self.0.children().filter_map(first_select)
+    }
+
+    /// The operators between operands, in order, each with whether ALL was given
+    pub fn operators(&self) -> Vec<(SetOperator, bool)> {
+        let tokens: Vec<_> = self
+            .0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| !t.kind().is_trivia())
+            .collect();
+
+        tokens
+            .iter()
+            .enumerate()
+            .filter_map(|(i, t)| {
+                let op = match t.kind() {
+                    UNION_KW => SetOperator::Union,
+                    INTERSECT_KW => SetOperator::Intersect,
+                    EXCEPT_KW => SetOperator::Except,
+                    _ => return None,
+                };
+                let all = tokens.get(i + 1).is_some_and(|next| next.kind() == ALL_KW);
+                Some((op, all))
+            })
+            .collect()
+    }
+
+    /// ORDER BY applying to the combined result
+    pub fn order_by_clause(&self) -> Option<OrderByClause> {
+        self.0.children().find_map(OrderByClause::cast)
+    }
+
+    /// LIMIT applying to the combined result
+    pub fn limit_clause(&self) -> Option<LimitClause> {
+        self.0.children().find_map(LimitClause::cast)
+    }
+
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+/// Parenthesized query used as a derived table or inside an expression
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Subquery(SyntaxNode);
+
+impl Subquery {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == SUBQUERY {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// The SELECT defining the subquery's columns (for a compound, its first operand)
+    pub fn select_stmt(&self) -> Option<SelectStmt> {
+        self.0.children().find_map(first_select)
+    }
+
+    /// The compound query, if the subquery is a UNION / INTERSECT / EXCEPT
+    pub fn compound_select(&self) -> Option<CompoundSelect> {
+        self.0.children().find_map(CompoundSelect::cast)
+    }
+
+    /// Get the text range of this subquery, including parentheses
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+/// First SELECT of a query node (the node itself, or a compound's first
+/// operand, looking inside parentheses)
+fn first_select(node: SyntaxNode) -> Option<SelectStmt> {
+    match node.kind() {
+        SELECT_STMT => SelectStmt::cast(node),
+        COMPOUND_SELECT | SUBQUERY => node.children().find_map(first_select),
+        _ => None,
+    }
+}
+
+/// WITH clause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WithClause(SyntaxNode);
+
+impl WithClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == WITH_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Check for WITH RECURSIVE
+    pub fn is_recursive(&self) -> bool {
+        self.0
+            .children_with_tokens()
+            .any(|e| e.kind() == RECURSIVE_KW)
+    }
+
+    pub fn ctes(&self) -> impl Iterator<Item = Cte> + '_ {
+        self.0.children().filter_map(Cte::cast)
+    }
+}
+
+/// Common table expression (name [(columns)] AS (query))
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cte(SyntaxNode);
+
+impl Cte {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == CTE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the CTE name
+    pub fn name(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_ident())
+            .map(|t| ident_text(&t))
+    }
+
+    /// Get the text range of the CTE name
+    pub fn name_range(&self) -> Option<TextRange> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_ident())
+            .map(|t| t.text_range())
+    }
+
+    /// Get the explicit column names (`WITH t(a, b) AS ...`), empty if none
+    pub fn column_names(&self) -> Vec<String> {
+        self.0
+            .children()
+            .find(|n| n.kind() == COLUMN_LIST)
+            .map(|list| {
+                list.children_with_tokens()
+                    .filter_map(|e| e.into_token())
+                    .filter(|t| t.kind().is_ident())
+                    .map(|t| ident_text(&t))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The SELECT defining the CTE's columns (for a recursive CTE, the anchor)
+    pub fn select_stmt(&self) -> Option<SelectStmt> {
+        self.0.children().find_map(first_select)
+    }
+
+    /// Get the text range of this CTE
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
 }
 
 /// SELECT list (columns)
@@ -91,20 +490,14 @@ impl SelectItem {
         self.0.children().find_map(Expr::cast)
     }
 
-    /// Get the explicit alias if present (the identifier after AS keyword)
+    /// Get the alias if present (explicit `AS total` or implicit `total`).
+    /// The expression is a child node, so the only direct identifier is the alias.
     pub fn alias(&self) -> Option<String> {
-        let mut found_as = false;
-
-        for child in self.0.children_with_tokens() {
-            if let Some(token) = child.as_token() {
-                if token.kind() == AS_KW {
-                    found_as = true;
-                } else if found_as && token.kind() == IDENT {
-                    return Some(token.text().to_string());
-                }
-            }
-        }
-        None
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_ident())
+            .map(|t| ident_text(&t))
     }
 
     /// Get the effective column name (alias if present, otherwise inferred from expression)
@@ -122,12 +515,81 @@ impl SelectItem {
         }
     }
 
+    /// The wildcard if this item is `*` or `alias.*`, with any modifiers
+    pub fn wildcard(&self) -> Option<Wildcard> {
+        let node = self.expression()?.inner();
+        let is_wildcard = matches!(node.kind(), EXPRESSION | COLUMN_REF)
+            && node
+                .children_with_tokens()
+                .filter_map(|e| e.into_token())
+                .any(|t| t.kind() == STAR);
+        is_wildcard.then(|| Wildcard {
+            item: self.0.clone(),
+            node,
+        })
+    }
+
     /// Get the text range of this select item
     pub fn range(&self) -> TextRange {
         self.0.text_range()
     }
 }
 
+/// `*` or `alias.*` in a select list, with DuckDB's EXCLUDE and REPLACE modifiers
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Wildcard {
+    item: SyntaxNode,
+    node: SyntaxNode,
+}
+
+impl Wildcard {
+    /// The relation the wildcard selects from (`u` in `u.*`)
+    pub fn qualifier(&self) -> Option<String> {
+        let idents: Vec<_> = self
+            .node
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| t.kind().is_ident())
+            .map(|t| ident_text(&t))
+            .collect();
+        if idents.is_empty() {
+            None
+        } else {
+            Some(idents.join("."))
+        }
+    }
+
+    /// Columns left out by `EXCLUDE (a, b)`
+    pub fn excluded_columns(&self) -> Vec<String> {
+        self.item
+            .children()
+            .filter(|n| n.kind() == WILDCARD_EXCLUDE)
+            .flat_map(|n| {
+                n.descendants_with_tokens()
+                    .filter_map(|e| e.into_token())
+                    .filter(|t| t.kind().is_ident())
+                    .skip(1) // EXCLUDE
+                    .map(|t| ident_text(&t))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Replacement expressions from `REPLACE (expr AS col, ...)`, named by
+    /// the column they replace
+    pub fn replacements(&self) -> Vec<SelectItem> {
+        self.item
+            .children()
+            .filter(|n| n.kind() == WILDCARD_REPLACE)
+            .flat_map(|n| n.children().filter_map(SelectItem::cast).collect::<Vec<_>>())
+            .collect()
+    }
+
+    pub fn range(&self) -> TextRange {
+        self.item.text_range()
+    }
+}
+
 /// FROM clause
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FromClause(SyntaxNode);
@@ -141,126 +603,982 @@ impl FromClause {
         }
     }
 
+    /// All table references in the FROM clause, including the right-hand side of JOINs
     pub fn table_refs(&self) -> impl Iterator<Item = TableRef> + '_ {
-        self.0.children().filter_map(TableRef::cast)
+        self.0.children().flat_map(|child| {
+            if child.kind() == JOIN_CLAUSE {
+                child.children().find_map(TableRef::cast)
+            } else {
+                TableRef::cast(child)
+            }
+        })
+    }
+
+    /// JOIN clauses in the FROM clause
+    pub fn joins(&self) -> impl Iterator<Item = JoinClause> + '_ {
+        self.0.children().filter_map(JoinClause::cast)
     }
 }
 
-/// Table reference (identifier or template expression)
+/// Kind of join
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+    Cross,
+}
+
+/// JOIN clause (join type, right-hand table and ON/USING condition)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct TableRef(SyntaxNode);
+pub struct JoinClause(SyntaxNode);
 
-impl TableRef {
+impl JoinClause {
     pub fn cast(node: SyntaxNode) -> Option<Self> {
-        if node.kind() == TABLE_REF {
+        if node.kind() == JOIN_CLAUSE {
             Some(Self(node))
         } else {
             None
         }
     }
 
-    /// Check if this is a function call reference (like ref('model'))
-    pub fn is_function_call(&self) -> bool {
-        self.0.children().any(|n| n.kind() == FUNCTION_CALL)
+    /// Get the join type (a bare JOIN is an inner join)
+    pub fn join_type(&self) -> JoinType {
+        let first_keyword = self
+            .0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_keyword())
+            .map(|t| t.kind());
+
+        match first_keyword {
+            Some(LEFT_KW) => JoinType::Left,
+            Some(RIGHT_KW) => JoinType::Right,
+            Some(FULL_KW) => JoinType::Full,
+            Some(CROSS_KW) => JoinType::Cross,
+            _ => JoinType::Inner,
+        }
     }
 
-    /// Get the function call if this table ref is a function (like ref('model'))
-    pub fn function_call(&self) -> Option<FunctionCall> {
-        self.0.children().find_map(FunctionCall::cast)
+    /// Get the right-hand table reference
+    pub fn table_ref(&self) -> Option<TableRef> {
+        self.0.children().find_map(TableRef::cast)
     }
 
-    pub fn identifier(&self) -> Option<String> {
+    /// Get the ON/USING condition if present
+    pub fn condition(&self) -> Option<JoinCondition> {
+        self.0.children().find_map(JoinCondition::cast)
+    }
+
+    /// Get the text range of this join clause
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+/// Join condition (ON expression or USING column list)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JoinCondition(SyntaxNode);
+
+impl JoinCondition {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == JOIN_CONDITION {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the ON expression (None for USING)
+    pub fn on_expr(&self) -> Option<Expr> {
+        self.0.children().find(|n| n.kind() == EXPRESSION).map(Expr)
+    }
+
+    /// Get the USING column names (empty for ON)
+    pub fn using_columns(&self) -> Vec<String> {
+        self.0
+            .children()
+            .find(|n| n.kind() == COLUMN_LIST)
+            .map(|list| {
+                list.children_with_tokens()
+                    .filter_map(|e| e.into_token())
+                    .filter(|t| t.kind().is_ident())
+                    .map(|t| ident_text(&t))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Table reference (identifier or template expression)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableRef(SyntaxNode);
+
+impl TableRef {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == TABLE_REF {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Check if this is a function call reference (like ref('model'))
+    pub fn is_function_call(&self) -> bool {
+        self.0.children().any(|n| n.kind() == FUNCTION_CALL)
+    }
+
+    /// Get the function call if this table ref is a function (like ref('model'))
+    pub fn function_call(&self) -> Option<FunctionCall> {
+        self.0.children().find_map(FunctionCall::cast)
+    }
+
+    /// Get the subquery if this is a derived table: (SELECT ...) alias
+    pub fn subquery(&self) -> Option<Subquery> {
+        self.0.children().find_map(Subquery::cast)
+    }
+
+    pub fn identifier(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_ident())
+            .map(|t| ident_text(&t))
+    }
+
+    /// Get the table name including any schema qualifier (e.g. "source.events").
+    /// None for function call references like smelt.ref() and for subqueries.
+    pub fn table_name(&self) -> Option<String> {
+        if self.is_function_call() || self.subquery().is_some() {
+            return None;
+        }
+
+        let mut name = String::new();
+        for token in self
+            .0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| !t.kind().is_trivia())
+        {
+            match token.kind() {
+                kind if kind.is_ident() && (name.is_empty() || name.ends_with('.')) => {
+                    name.push_str(&ident_text(&token))
+                }
+                DOT => name.push('.'),
+                _ => break,
+            }
+        }
+
+        if name.is_empty() {
+            None
+        } else {
+            Some(name)
+        }
+    }
+
+    /// Get the alias if present (explicit `AS u` or implicit `u`)
+    pub fn alias(&self) -> Option<String> {
+        let tokens: Vec<_> = self
+            .0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| !t.kind().is_trivia())
+            .collect();
+
+        if let Some(as_pos) = tokens.iter().position(|t| t.kind() == AS_KW) {
+            return tokens
+                .get(as_pos + 1)
+                .filter(|t| t.kind().is_ident())
+                .map(ident_text);
+        }
+
+        // smelt.ref('x') u / (SELECT ...) u - the only direct identifier is the alias
+        if self.is_function_call() || self.subquery().is_some() {
+            return tokens
+                .iter()
+                .find(|t| t.kind().is_ident())
+                .map(ident_text);
+        }
+
+        // schema.table u - an identifier directly following another identifier
+        match tokens.as_slice() {
+            [.., prev, last] if prev.kind().is_ident() && last.kind().is_ident() => {
+                Some(ident_text(last))
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the text range of this table reference
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+/// WHERE clause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WhereClause(SyntaxNode);
+
+impl WhereClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == WHERE_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the filter expression
+    pub fn expression(&self) -> Option<Expr> {
+        self.0.children().find(|n| n.kind() == EXPRESSION).map(Expr)
+    }
+}
+
+/// GROUP BY clause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GroupByClause(SyntaxNode);
+
+impl GroupByClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == GROUP_BY_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the grouping expressions
+    pub fn expressions(&self) -> impl Iterator<Item = Expr> + '_ {
+        self.0
+            .children()
+            .filter(|n| n.kind() == EXPRESSION)
+            .map(Expr)
+    }
+
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+/// HAVING clause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HavingClause(SyntaxNode);
+
+impl HavingClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == HAVING_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the filter expression
+    pub fn expression(&self) -> Option<Expr> {
+        self.0.children().find(|n| n.kind() == EXPRESSION).map(Expr)
+    }
+}
+
+/// QUALIFY clause (filter on window function results)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QualifyClause(SyntaxNode);
+
+impl QualifyClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == QUALIFY_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the filter expression
+    pub fn expression(&self) -> Option<Expr> {
+        self.0.children().find(|n| n.kind() == EXPRESSION).map(Expr)
+    }
+}
+
+/// ORDER BY clause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OrderByClause(SyntaxNode);
+
+impl OrderByClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == ORDER_BY_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = OrderByItem> + '_ {
+        self.0.children().filter_map(OrderByItem::cast)
+    }
+}
+
+/// ORDER BY item (expression [ASC|DESC] [NULLS FIRST|LAST])
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OrderByItem(SyntaxNode);
+
+impl OrderByItem {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == ORDER_BY_ITEM {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the sort expression
+    pub fn expression(&self) -> Option<Expr> {
+        self.0.children().find(|n| n.kind() == EXPRESSION).map(Expr)
+    }
+
+    /// Check for DESC ordering (ASC is the default)
+    pub fn is_descending(&self) -> bool {
+        self.0
+            .children_with_tokens()
+            .any(|e| e.kind() == DESC_KW)
+    }
+
+    /// Get explicit NULLS placement: Some(true) for NULLS FIRST, Some(false)
+    /// for NULLS LAST, None if unspecified
+    pub fn nulls_first(&self) -> Option<bool> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .skip_while(|t| t.kind() != NULLS_KW)
+            .find(|t| t.kind() == IDENT)
+            .map(|t| t.text().eq_ignore_ascii_case("FIRST"))
+    }
+}
+
+/// LIMIT clause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LimitClause(SyntaxNode);
+
+impl LimitClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == LIMIT_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the limit expression (None for LIMIT ALL)
+    pub fn expression(&self) -> Option<Expr> {
+        self.0.children().find(|n| n.kind() == EXPRESSION).map(Expr)
+    }
+
+    /// Get the row count if the limit is a literal number
+    pub fn count(&self) -> Option<u64> {
+        self.expression()?.text().trim().parse().ok()
+    }
+
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+/// OFFSET clause
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OffsetClause(SyntaxNode);
+
+impl OffsetClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == OFFSET_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the offset expression
+    pub fn expression(&self) -> Option<Expr> {
+        self.0.children().find(|n| n.kind() == EXPRESSION).map(Expr)
+    }
+
+    /// Get the row count if the offset is a literal number
+    pub fn count(&self) -> Option<u64> {
+        self.expression()?.text().trim().parse().ok()
+    }
+}
+
+/// Expression node (represents any SQL expression)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Expr(SyntaxNode);
+
+impl Expr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind().is_expression() {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Try to infer a column name from this expression
+    /// Used when there's no explicit alias
+    pub fn infer_name(&self) -> Option<String> {
+        // Column references are named by the column (without the qualifier)
+        if let Some(col_ref) = self.as_column_ref() {
+            return Some(col_ref.name().to_string());
+        }
+
+        // Anything else (*, function calls, arithmetic, CASE, ...) is named
+        // by its full text
+        let text = self.text();
+        let text = text.trim();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        }
+    }
+
+    /// Get the full text of this expression
+    pub fn text(&self) -> String {
+        self.0.text().to_string()
+    }
+
+    /// Get the text range of this expression
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+
+    /// The expression node itself, looking through the EXPRESSION wrapper
+    /// and redundant parentheses
+    fn inner(&self) -> SyntaxNode {
+        let mut node = self.0.clone();
+        while matches!(node.kind(), EXPRESSION | PAREN_EXPR) {
+            let has_own_tokens = node
+                .children_with_tokens()
+                .filter_map(|e| e.into_token())
+                .any(|t| !t.kind().is_trivia() && !matches!(t.kind(), LPAREN | RPAREN));
+            match node.children().next() {
+                Some(child) if !has_own_tokens => node = child,
+                _ => break,
+            }
+        }
+        node
+    }
+
+    /// Check if this is a simple column reference (identifier possibly qualified)
+    pub fn as_column_ref(&self) -> Option<ColumnRef> {
+        ColumnRef::from_expr(self)
+    }
+
+    /// Check if this is a function call
+    pub fn as_function_call(&self) -> Option<FunctionCall> {
+        FunctionCall::cast(self.inner())
+    }
+
+    pub fn as_literal(&self) -> Option<Literal> {
+        Literal::cast(self.inner())
+    }
+
+    pub fn as_typed_literal(&self) -> Option<TypedLiteral> {
+        TypedLiteral::cast(self.inner())
+    }
+
+    pub fn as_binary_expr(&self) -> Option<BinaryExpr> {
+        BinaryExpr::cast(self.inner())
+    }
+
+    pub fn as_unary_expr(&self) -> Option<UnaryExpr> {
+        UnaryExpr::cast(self.inner())
+    }
+
+    pub fn as_case_expr(&self) -> Option<CaseExpr> {
+        CaseExpr::cast(self.inner())
+    }
+
+    pub fn as_cast_expr(&self) -> Option<CastExpr> {
+        CastExpr::cast(self.inner())
+    }
+
+    pub fn as_is_expr(&self) -> Option<IsExpr> {
+        IsExpr::cast(self.inner())
+    }
+
+    pub fn as_in_expr(&self) -> Option<InExpr> {
+        InExpr::cast(self.inner())
+    }
+
+    pub fn as_between_expr(&self) -> Option<BetweenExpr> {
+        BetweenExpr::cast(self.inner())
+    }
+
+    pub fn as_like_expr(&self) -> Option<LikeExpr> {
+        LikeExpr::cast(self.inner())
+    }
+
+    pub fn as_exists_expr(&self) -> Option<ExistsExpr> {
+        ExistsExpr::cast(self.inner())
+    }
+
+    pub fn as_subquery(&self) -> Option<Subquery> {
+        Subquery::cast(self.inner())
+    }
+
+    /// All column references in this expression, in source order.
+    /// References inside subqueries belong to the subquery's scope and are skipped.
+    pub fn column_refs(&self) -> Vec<ColumnRef> {
+        let mut refs = Vec::new();
+        collect_column_refs(&self.0, &mut refs);
+        refs
+    }
+}
+
+fn collect_column_refs(node: &SyntaxNode, refs: &mut Vec<ColumnRef>) {
+    match node.kind() {
+        COLUMN_REF => refs.extend(ColumnRef::from_node(node)),
+        SUBQUERY | SELECT_STMT | COMPOUND_SELECT | WITH_CLAUSE | TABLE_REF => {}
+        // EXTRACT(HOUR FROM ts) - the field name is not a column
+        FUNCTION_CALL
+            if FunctionCall(node.clone())
+                .name()
+                .is_some_and(|n| n.eq_ignore_ascii_case("extract")) =>
+        {
+            for arg in FunctionCall(node.clone()).args().skip(1) {
+                collect_column_refs(&arg.0, refs);
+            }
+        }
+        _ => collect_scope_column_refs(node, refs),
+    }
+}
+
+/// Text of an identifier token, without the quotes of a quoted identifier
+fn ident_text(token: &SyntaxToken) -> String {
+    let text = token.text();
+    if token.kind() != QUOTED_IDENT {
+        return text.to_string();
+    }
+
+    // "name" or `name`, with a doubled quote escaping the quote character
+    let quote = &text[..1];
+    match text[1..].strip_suffix(quote) {
+        Some(inner) => inner.replace(&quote.repeat(2), quote),
+        None => text[1..].to_string(),
+    }
+}
+
+/// Expression children of a node, in order
+fn expr_children(node: &SyntaxNode) -> impl Iterator<Item = Expr> {
+    node.children().filter_map(Expr::cast)
+}
+
+/// Kind of literal value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LiteralKind {
+    String,
+    Number,
+    Boolean,
+    Null,
+}
+
+/// Literal: 'text', 42, 3.14, TRUE, FALSE, NULL
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Literal(SyntaxNode);
+
+impl Literal {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == LITERAL {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn kind(&self) -> LiteralKind {
+        match self.token_kind() {
+            Some(STRING) => LiteralKind::String,
+            Some(TRUE_KW | FALSE_KW) => LiteralKind::Boolean,
+            Some(NULL_KW) => LiteralKind::Null,
+            _ => LiteralKind::Number,
+        }
+    }
+
+    /// Get the literal as written (strings keep their quotes)
+    pub fn text(&self) -> String {
+        self.0.text().to_string()
+    }
+
+    fn token_kind(&self) -> Option<SyntaxKind> {
+        self.0.first_token().map(|t| t.kind())
+    }
+}
+
+/// Typed literal: DATE '2024-01-01', TIMESTAMP '...', INTERVAL 30 MINUTE
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypedLiteral(SyntaxNode);
+
+impl TypedLiteral {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == TYPED_LITERAL {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the type keyword, uppercased (e.g. "DATE", "INTERVAL")
+    pub fn type_name(&self) -> Option<String> {
+        self.0
+            .first_token()
+            .map(|t| t.text().to_uppercase())
+    }
+
+    /// Get the value as written (the string or number after the type)
+    pub fn value_text(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| matches!(t.kind(), STRING | NUMBER))
+            .map(|t| t.text().to_string())
+    }
+
+    /// Get the unit of an interval written as INTERVAL 30 MINUTE, uppercased
+    pub fn unit(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| t.kind() == IDENT)
+            .nth(1)
+            .map(|t| t.text().to_uppercase())
+    }
+}
+
+/// Binary operation: left op right
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinaryExpr(SyntaxNode);
+
+impl BinaryExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == BINARY_EXPR {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the operator token kind (e.g. PLUS, AND_KW, CONCAT)
+    pub fn op(&self) -> Option<SyntaxKind> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .map(|t| t.kind())
+            .find(|k| !k.is_trivia())
+    }
+
+    pub fn lhs(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+
+    pub fn rhs(&self) -> Option<Expr> {
+        expr_children(&self.0).nth(1)
+    }
+}
+
+/// Unary operation: NOT expr, -expr, +expr
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnaryExpr(SyntaxNode);
+
+impl UnaryExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == UNARY_EXPR {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the operator token kind (NOT_KW, MINUS or PLUS)
+    pub fn op(&self) -> Option<SyntaxKind> {
+        self.0.first_token().map(|t| t.kind())
+    }
+
+    pub fn operand(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+}
+
+/// CASE expression (searched or simple)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CaseExpr(SyntaxNode);
+
+impl CaseExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == CASE_EXPR {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the operand of a simple CASE (CASE status WHEN 'a' THEN ...)
+    pub fn operand(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+
+    pub fn whens(&self) -> impl Iterator<Item = CaseWhen> + '_ {
+        self.0.children().filter_map(CaseWhen::cast)
+    }
+
+    /// Get the ELSE result
+    pub fn else_expr(&self) -> Option<Expr> {
+        self.0
+            .children()
+            .find(|n| n.kind() == CASE_ELSE)
+            .and_then(|n| expr_children(&n).next())
+    }
+}
+
+/// WHEN condition THEN result
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CaseWhen(SyntaxNode);
+
+impl CaseWhen {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == CASE_WHEN {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn condition(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+
+    pub fn result(&self) -> Option<Expr> {
+        expr_children(&self.0).nth(1)
+    }
+}
+
+/// CAST(expr AS type), TRY_CAST(expr AS type) or expr::type
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CastExpr(SyntaxNode);
+
+impl CastExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == CAST_EXPR {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the expression being cast
+    pub fn expression(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+
+    pub fn type_name(&self) -> Option<TypeName> {
+        self.0.children().find_map(TypeName::cast)
+    }
+}
+
+/// Type name in a cast (INTEGER, VARCHAR(255), DECIMAL(10, 2))
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeName(SyntaxNode);
+
+impl TypeName {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == TYPE_NAME {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the type name without parameters, uppercased (e.g. "DECIMAL", "DOUBLE PRECISION")
+    pub fn name(&self) -> String {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .take_while(|t| t.kind() != LPAREN)
+            .filter(|t| t.kind() == IDENT)
+            .map(|t| t.text().to_uppercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Get the full type as written, including parameters
+    pub fn text(&self) -> String {
+        self.0.text().to_string()
+    }
+}
+
+/// What an IS expression tests for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IsTest {
+    Null,
+    True,
+    False,
+    DistinctFrom(Expr),
+}
+
+/// expr IS [NOT] {NULL | TRUE | FALSE | DISTINCT FROM expr}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IsExpr(SyntaxNode);
+
+impl IsExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == IS_EXPR {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the expression being tested
+    pub fn expression(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.0.children_with_tokens().any(|e| e.kind() == NOT_KW)
+    }
+
+    pub fn test(&self) -> Option<IsTest> {
+        let kind = self
+            .0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .map(|t| t.kind())
+            .find(|k| matches!(k, NULL_KW | TRUE_KW | FALSE_KW | DISTINCT_KW))?;
+
+        match kind {
+            NULL_KW => Some(IsTest::Null),
+            TRUE_KW => Some(IsTest::True),
+            FALSE_KW => Some(IsTest::False),
+            _ => expr_children(&self.0).nth(1).map(IsTest::DistinctFrom),
+        }
+    }
+}
+
+/// IN predicate: expr [NOT] IN (values) or expr [NOT] IN (query)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InExpr(SyntaxNode);
+
+impl InExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == IN_EXPR {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the expression being tested
+    pub fn expression(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.0.children_with_tokens().any(|e| e.kind() == NOT_KW)
+    }
+
+    /// Get the subquery for IN (SELECT ...)
+    pub fn subquery(&self) -> Option<Subquery> {
+        self.0.children().skip(1).find_map(Subquery::cast)
+    }
+
+    /// Get the listed values for IN (a, b, c)
+    pub fn values(&self) -> impl Iterator<Item = Expr> + '_ {
         self.0
-            .children_with_tokens()
-            .filter_map(|e| e.into_token())
-            .find(|t| t.kind() == IDENT)
-            .map(|t| t.text().to_string())
+            .children()
+            .skip(1)
+            .filter(|n| n.kind() == EXPRESSION)
+            .map(Expr)
     }
 }
 
-/// WHERE clause
+/// expr [NOT] BETWEEN low AND high
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct WhereClause(SyntaxNode);
+pub struct BetweenExpr(SyntaxNode);
 
-impl WhereClause {
+impl BetweenExpr {
     pub fn cast(node: SyntaxNode) -> Option<Self> {
-        if node.kind() == WHERE_CLAUSE {
+        if node.kind() == BETWEEN_EXPR {
             Some(Self(node))
         } else {
             None
         }
     }
+
+    /// Get the expression being tested
+    pub fn expression(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.0.children_with_tokens().any(|e| e.kind() == NOT_KW)
+    }
+
+    pub fn low(&self) -> Option<Expr> {
+        expr_children(&self.0).nth(1)
+    }
+
+    pub fn high(&self) -> Option<Expr> {
+        expr_children(&self.0).nth(2)
+    }
 }
 
-/// Expression node (represents any SQL expression)
+/// expr [NOT] LIKE|ILIKE pattern [ESCAPE char]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Expr(SyntaxNode);
+pub struct LikeExpr(SyntaxNode);
 
-impl Expr {
+impl LikeExpr {
     pub fn cast(node: SyntaxNode) -> Option<Self> {
-        // Accept any node that looks like an expression
-        match node.kind() {
-            EXPRESSION | BINARY_EXPR | FUNCTION_CALL => Some(Self(node)),
-            _ => {
-                // Also try to wrap the node if it contains expression-like children
-                if node.children().any(|n| matches!(n.kind(), EXPRESSION | BINARY_EXPR | FUNCTION_CALL)) {
-                    Some(Self(node))
-                } else {
-                    None
-                }
-            }
+        if node.kind() == LIKE_EXPR {
+            Some(Self(node))
+        } else {
+            None
         }
     }
 
-    /// Try to infer a column name from this expression
-    /// Used when there's no explicit alias
-    pub fn infer_name(&self) -> Option<String> {
-        // Check for wildcard (*)
-        if self.text().trim() == "*" {
-            return Some("*".to_string());
-        }
-
-        // Check if this is a function call
-        if let Some(_func) = self.as_function_call() {
-            // For function calls without alias, use the full function text
-            return Some(self.text());
-        }
+    /// Get the expression being matched
+    pub fn expression(&self) -> Option<Expr> {
+        expr_children(&self.0).next()
+    }
 
-        // Check if this is a simple column reference
-        if let Some(col_ref) = self.as_column_ref() {
-            // For qualified names (table.column), use just the column part
-            return Some(col_ref.name().to_string());
-        }
+    pub fn pattern(&self) -> Option<Expr> {
+        expr_children(&self.0).nth(1)
+    }
 
-        // For other complex expressions, try to find the first identifier
-        for child in self.0.children_with_tokens() {
-            if let Some(token) = child.as_token() {
-                if token.kind() == IDENT {
-                    return Some(token.text().to_string());
-                }
-            }
-        }
+    pub fn is_negated(&self) -> bool {
+        self.0.children_with_tokens().any(|e| e.kind() == NOT_KW)
+    }
 
-        None
+    /// Check for ILIKE
+    pub fn is_case_insensitive(&self) -> bool {
+        self.0.children_with_tokens().any(|e| e.kind() == ILIKE_KW)
     }
+}
 
-    /// Get the full text of this expression
-    pub fn text(&self) -> String {
-        self.0.text().to_string()
+/// EXISTS predicate: [NOT] EXISTS (query)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExistsExpr(SyntaxNode);
+
+impl ExistsExpr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == EXISTS_EXPR {
+            Some(Self(node))
+        } else {
+            None
+        }
     }
 
-    /// Check if this is a simple column reference (identifier possibly qualified)
-    pub fn as_column_ref(&self) -> Option<ColumnRef> {
-        ColumnRef::from_expr(self)
+    pub fn is_negated(&self) -> bool {
+        self.0.children_with_tokens().any(|e| e.kind() == NOT_KW)
     }
 
-    /// Check if this is a function call
-    pub fn as_function_call(&self) -> Option<FunctionCall> {
-        self.0.children().find_map(FunctionCall::cast)
-            .or_else(|| {
-                // Check if this node itself is a function call
-                FunctionCall::cast(self.0.clone())
-            })
+    pub fn subquery(&self) -> Option<Subquery> {
+        self.0.children().find_map(Subquery::cast)
     }
 }
 
@@ -269,42 +1587,51 @@ impl Expr {
 pub struct ColumnRef {
     qualifier: Option<String>,
     name: String,
+    range: TextRange,
 }
 
 impl ColumnRef {
     /// Try to parse a column reference from an expression
     pub fn from_expr(expr: &Expr) -> Option<Self> {
-        let tokens: Vec<_> = expr.0
+        let node = expr.inner();
+        if node.kind() == COLUMN_REF {
+            Self::from_node(&node)
+        } else {
+            None
+        }
+    }
+
+    /// Read a COLUMN_REF node. Qualified wildcards (t.*) are not column references.
+    fn from_node(node: &SyntaxNode) -> Option<Self> {
+        let tokens: Vec<_> = node
             .children_with_tokens()
             .filter_map(|e| e.into_token())
-            .filter(|t| t.kind() == IDENT || t.kind() == DOT)
+            .filter(|t| !t.kind().is_trivia())
             .collect();
 
-        if tokens.is_empty() {
+        if tokens.iter().any(|t| t.kind() == STAR) {
             return None;
         }
 
-        // Simple identifier
-        if tokens.len() == 1 && tokens[0].kind() == IDENT {
-            return Some(ColumnRef {
-                qualifier: None,
-                name: tokens[0].text().to_string(),
-            });
-        }
-
-        // Qualified identifier: table.column
-        if tokens.len() >= 3
-            && tokens[0].kind() == IDENT
-            && tokens[1].kind() == DOT
-            && tokens[2].kind() == IDENT
-        {
-            return Some(ColumnRef {
-                qualifier: Some(tokens[0].text().to_string()),
-                name: tokens[2].text().to_string(),
-            });
-        }
+        let mut idents: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.kind().is_ident())
+            .map(ident_text)
+            .collect();
 
-        None
+        // The last identifier is the column: table.column or schema.table.column
+        let name = idents.pop()?;
+        let qualifier = if idents.is_empty() {
+            None
+        } else {
+            Some(idents.join("."))
+        };
+
+        Some(ColumnRef {
+            qualifier,
+            name,
+            range: node.text_range(),
+        })
     }
 
     pub fn name(&self) -> &str {
@@ -314,6 +1641,11 @@ impl ColumnRef {
     pub fn qualifier(&self) -> Option<&str> {
         self.qualifier.as_deref()
     }
+
+    /// Get the text range of the reference, including any qualifier
+    pub fn range(&self) -> TextRange {
+        self.range
+    }
 }
 
 /// Function call expression
@@ -346,10 +1678,10 @@ impl FunctionCall {
             return Some(tokens[2].text().to_string());
         }
 
-        // Simple call: just IDENT
+        // Simple call: just IDENT (or a keyword-named function like LEFT())
         tokens
             .iter()
-            .find(|t| t.kind() == IDENT)
+            .find(|t| t.kind() == IDENT || t.kind().is_keyword())
             .map(|t| t.text().to_string())
     }
 
@@ -377,14 +1709,244 @@ impl FunctionCall {
         self.0.text().to_string()
     }
 
-    /// Get all named parameters from this function call
+    /// Get the OVER clause if this is a window function call
+    pub fn over_clause(&self) -> Option<OverClause> {
+        self.0.children().find_map(OverClause::cast)
+    }
+
+    pub fn is_window_function(&self) -> bool {
+        self.over_clause().is_some()
+    }
+
+    /// Check for an aggregate over distinct values: COUNT(DISTINCT x)
+    pub fn is_distinct(&self) -> bool {
+        self.0
+            .children()
+            .filter(|n| n.kind() == ARG_LIST)
+            .flat_map(|list| list.children_with_tokens())
+            .any(|e| e.kind() == DISTINCT_KW)
+    }
+
+    /// Get the text range of this function call
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
+    }
+
+    /// Get the positional arguments (named parameters are excluded)
+    pub fn args(&self) -> impl Iterator<Item = Expr> + '_ {
+        self.0
+            .children()
+            .filter(|n| n.kind() == ARG_LIST)
+            .flat_map(|list| list.children())
+            .filter(|n| n.kind() == EXPRESSION)
+            .map(Expr)
+    }
+
+    /// Get the named parameters of this call (not those of calls nested in its arguments)
     pub fn named_params(&self) -> impl Iterator<Item = NamedParam> + '_ {
         self.0
-            .descendants()
+            .children()
+            .filter(|n| n.kind() == ARG_LIST)
+            .flat_map(|list| list.children())
             .filter_map(NamedParam::cast)
     }
 }
 
+/// OVER clause of a window function call
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OverClause(SyntaxNode);
+
+impl OverClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == OVER_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the window name for OVER w (a reference to a WINDOW clause definition)
+    pub fn window_name(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_ident())
+            .map(|t| ident_text(&t))
+    }
+
+    /// Get the inline window specification for OVER (...)
+    pub fn window_spec(&self) -> Option<WindowSpec> {
+        self.0.children().find_map(WindowSpec::cast)
+    }
+}
+
+/// Window specification: ([base] [PARTITION BY ...] [ORDER BY ...] [frame])
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowSpec(SyntaxNode);
+
+impl WindowSpec {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == WINDOW_SPEC {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    /// Get the named window this spec extends: OVER (w ORDER BY ts)
+    pub fn base_window_name(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_ident())
+            .map(|t| ident_text(&t))
+    }
+
+    /// Get the PARTITION BY expressions
+    pub fn partition_by(&self) -> Vec<Expr> {
+        self.0
+            .children()
+            .find(|n| n.kind() == PARTITION_BY_CLAUSE)
+            .map(|n| n.children().filter(|c| c.kind() == EXPRESSION).map(Expr).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn order_by_clause(&self) -> Option<OrderByClause> {
+        self.0.children().find_map(OrderByClause::cast)
+    }
+
+    pub fn frame(&self) -> Option<WindowFrame> {
+        self.0.children().find_map(WindowFrame::cast)
+    }
+}
+
+/// Window frame unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameUnit {
+    Rows,
+    Range,
+    Groups,
+}
+
+/// One end of a window frame
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Expr),
+    CurrentRow,
+    Following(Expr),
+    UnboundedFollowing,
+}
+
+/// Window frame: {ROWS|RANGE|GROUPS} [BETWEEN] start [AND end]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowFrame(SyntaxNode);
+
+impl WindowFrame {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == WINDOW_FRAME {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn unit(&self) -> Option<FrameUnit> {
+        let text = self.0.first_token()?.text().to_uppercase();
+        match text.as_str() {
+            "ROWS" => Some(FrameUnit::Rows),
+            "RANGE" => Some(FrameUnit::Range),
+            "GROUPS" => Some(FrameUnit::Groups),
+            _ => None,
+        }
+    }
+
+    pub fn start(&self) -> Option<FrameBound> {
+        self.bounds().next()
+    }
+
+    /// Get the end bound. A frame without BETWEEN ends at the current row.
+    pub fn end(&self) -> Option<FrameBound> {
+        let mut bounds = self.bounds();
+        bounds.next()?;
+        Some(bounds.next().unwrap_or(FrameBound::CurrentRow))
+    }
+
+    fn bounds(&self) -> impl Iterator<Item = FrameBound> + '_ {
+        self.0
+            .children()
+            .filter(|n| n.kind() == FRAME_BOUND)
+            .filter_map(|bound| {
+                let words: Vec<String> = bound
+                    .children_with_tokens()
+                    .filter_map(|e| e.into_token())
+                    .filter(|t| t.kind() == IDENT)
+                    .map(|t| t.text().to_uppercase())
+                    .collect();
+                let offset = expr_children(&bound).next();
+
+                match (words.first().map(String::as_str), words.last().map(String::as_str)) {
+                    (Some("CURRENT"), _) => Some(FrameBound::CurrentRow),
+                    (Some("UNBOUNDED"), Some("PRECEDING")) => Some(FrameBound::UnboundedPreceding),
+                    (Some("UNBOUNDED"), Some("FOLLOWING")) => Some(FrameBound::UnboundedFollowing),
+                    (_, Some("PRECEDING")) => offset.map(FrameBound::Preceding),
+                    (_, Some("FOLLOWING")) => offset.map(FrameBound::Following),
+                    _ => None,
+                }
+            })
+    }
+}
+
+/// WINDOW clause: WINDOW w AS (...), ...
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowClause(SyntaxNode);
+
+impl WindowClause {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == WINDOW_CLAUSE {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = WindowDef> + '_ {
+        self.0.children().filter_map(WindowDef::cast)
+    }
+
+    /// Find a named window definition (case-insensitive)
+    pub fn find(&self, name: &str) -> Option<WindowDef> {
+        self.definitions()
+            .find(|def| def.name().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+    }
+}
+
+/// Named window definition: w AS (window spec)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WindowDef(SyntaxNode);
+
+impl WindowDef {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == WINDOW_DEF {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .find(|t| t.kind().is_ident())
+            .map(|t| ident_text(&t))
+    }
+
+    pub fn window_spec(&self) -> Option<WindowSpec> {
+        self.0.children().find_map(WindowSpec::cast)
+    }
+}
+
 /// Named parameter in a function call (e.g., filter => expr)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct NamedParam(SyntaxNode);
@@ -398,13 +1960,23 @@ impl NamedParam {
         }
     }
 
-    /// Get the parameter name (the identifier before =>)
+    /// Get the parameter name (the identifier or keyword before =>)
     pub fn name(&self) -> Option<String> {
         self.0
             .children_with_tokens()
             .filter_map(|e| e.into_token())
-            .find(|t| t.kind() == IDENT)
-            .map(|t| t.text().to_string())
+            .find(|t| !t.kind().is_trivia())
+            .filter(|t| t.kind() != ARROW)
+            .map(|t| ident_text(&t))
+    }
+
+    /// Get the parameter value expression
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn range(&self) -> TextRange {
+        self.0.text_range()
     }
 
     /// Get the parameter value as text (everything after =>)
@@ -446,21 +2018,15 @@ impl RefCall {
 
     /// Get the model name from the ref call (first argument)
     pub fn model_name(&self) -> Option<String> {
-        // Look for the first STRING token in the function call arguments
-        self.0
-             .0
-            .descendants_with_tokens()
-            .filter_map(|e| e.into_token())
-            .find(|t| t.kind() == STRING)
-            .map(|t| {
-                let text = t.text();
-                // Strip quotes
-                text.trim_start_matches('\'')
-                    .trim_start_matches('"')
-                    .trim_end_matches('\'')
-                    .trim_end_matches('"')
-                    .to_string()
-            })
+        self.name_token().map(|t| {
+            let text = t.text();
+            // Strip quotes
+            text.trim_start_matches('\'')
+                .trim_start_matches('"')
+                .trim_end_matches('\'')
+                .trim_end_matches('"')
+                .to_string()
+        })
     }
 
     /// Get the text range of the entire ref call
@@ -470,26 +2036,127 @@ impl RefCall {
 
     /// Get the text range of just the model name string (inside quotes)
     pub fn name_range(&self) -> Option<TextRange> {
+        self.name_token().map(|t| t.text_range())
+    }
+
+    /// The quoted model name: 'model' (or "model", which lexes as a quoted identifier)
+    fn name_token(&self) -> Option<SyntaxToken> {
         self.0
+            .args()
+            .next()?
             .0
-            .descendants_with_tokens()
-            .filter_map(|e| e.into_token())
-            .find(|t| t.kind() == STRING)
-            .map(|t| t.text_range())
+            .first_token()
+            .filter(|t| matches!(t.kind(), STRING | QUOTED_IDENT))
     }
 
     /// Get all named parameters from this ref call
     pub fn named_params(&self) -> impl Iterator<Item = NamedParam> + '_ {
         self.0.named_params()
     }
+
+    /// The table reference this call appears in (`FROM smelt.ref('x') AS a`)
+    pub fn table_ref(&self) -> Option<TableRef> {
+        self.0.0.parent().and_then(TableRef::cast)
+    }
+}
+
+/// smelt.source('schema', 'table') function call wrapper
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceCall(FunctionCall);
+
+impl SourceCall {
+    /// Create a SourceCall from a FunctionCall if it's a smelt.source() call
+    pub fn from_function_call(func: FunctionCall) -> Option<Self> {
+        let name = func.name()?.to_lowercase();
+        let namespace = func.namespace()?;
+
+        if name == "source" && namespace.to_lowercase() == "smelt" {
+            Some(Self(func))
+        } else {
+            None
+        }
+    }
+
+    /// Get the underlying FunctionCall
+    pub fn function_call(&self) -> &FunctionCall {
+        &self.0
+    }
+
+    /// Get the schema name (first argument)
+    pub fn schema_name(&self) -> Option<String> {
+        self.arg_token(0).map(|t| quoted_name_text(&t))
+    }
+
+    /// Get the table name (second argument)
+    pub fn table_name(&self) -> Option<String> {
+        self.arg_token(1).map(|t| quoted_name_text(&t))
+    }
+
+    /// "schema.table", if both arguments are given
+    pub fn qualified_name(&self) -> Option<String> {
+        Some(format!("{}.{}", self.schema_name()?, self.table_name()?))
+    }
+
+    /// True if the call has exactly two string arguments
+    pub fn is_well_formed(&self) -> bool {
+        self.0.args().count() == 2
+            && self.arg_token(0).is_some()
+            && self.arg_token(1).is_some()
+            && self.0.named_params().next().is_none()
+    }
+
+    /// Get the text range of the entire source call
+    pub fn range(&self) -> TextRange {
+        self.0.0.text_range()
+    }
+
+    /// Get the text range of the schema name string
+    pub fn schema_range(&self) -> Option<TextRange> {
+        self.arg_token(0).map(|t| t.text_range())
+    }
+
+    /// Get the text range of the table name string
+    pub fn table_range(&self) -> Option<TextRange> {
+        self.arg_token(1).map(|t| t.text_range())
+    }
+
+    /// The table reference this call appears in (`FROM smelt.source('raw', 'users') u`)
+    pub fn table_ref(&self) -> Option<TableRef> {
+        self.0.0.parent().and_then(TableRef::cast)
+    }
+
+    /// The n-th positional argument, if it is a single quoted name
+    fn arg_token(&self, n: usize) -> Option<SyntaxToken> {
+        let arg = self.0.args().nth(n)?;
+        let mut tokens = arg
+            .0
+            .descendants_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| !t.kind().is_trivia());
+        let token = tokens.next()?;
+        (matches!(token.kind(), STRING | QUOTED_IDENT) && tokens.next().is_none()).then_some(token)
+    }
+}
+
+/// Text of a 'string' or "quoted identifier" used as a name
+fn quoted_name_text(token: &SyntaxToken) -> String {
+    if token.kind() != STRING {
+        return ident_text(token);
+    }
+    let text = token.text();
+    text.strip_prefix('\'')
+        .and_then(|t| t.strip_suffix('\''))
+        .unwrap_or(text)
+        .replace("''", "'")
 }
 
-/// Helper to convert TextRange offset to line/column position
+/// Helper to convert a TextRange byte offset to a line/column position
+/// (columns count characters, not bytes)
 pub fn offset_to_position(text: &str, offset: usize) -> Position {
     let mut line = 0u32;
     let mut column = 0u32;
 
-    for (i, ch) in text.chars().enumerate() {
+    for (i, ch) in text.char_indices() {
         if i >= offset {
             break;
         }
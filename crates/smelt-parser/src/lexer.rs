@@ -8,9 +8,22 @@ pub struct Token {
     pub len: usize,
 }
 
+/// Lexing rules that differ between SQL dialects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LexOptions {
+    /// Plain '...' strings take backslash escapes ('it\'s'), as in Spark SQL.
+    /// Otherwise only E'...' strings do.
+    pub backslash_escapes: bool,
+}
+
 /// Tokenize input text into a stream of tokens
 pub fn tokenize(input: &str) -> Vec<Token> {
-    let mut lexer = Lexer::new(input);
+    tokenize_with(input, LexOptions::default())
+}
+
+/// Tokenize input text with a dialect's lexing rules
+pub fn tokenize_with(input: &str, options: LexOptions) -> Vec<Token> {
+    let mut lexer = Lexer::new(input, options);
     let mut tokens = Vec::new();
 
     loop {
@@ -27,11 +40,16 @@ pub fn tokenize(input: &str) -> Vec<Token> {
 struct Lexer<'a> {
     input: &'a str,
     pos: usize,
+    options: LexOptions,
 }
 
 impl<'a> Lexer<'a> {
-    fn new(input: &'a str) -> Self {
-        Self { input, pos: 0 }
+    fn new(input: &'a str, options: LexOptions) -> Self {
+        Self {
+            input,
+            pos: 0,
+            options,
+        }
     }
 
     fn next_token(&mut self) -> Token {
@@ -48,6 +66,7 @@ impl<'a> Lexer<'a> {
 
             // Comments
             '-' if self.peek_char() == Some('-') => self.consume_comment(),
+            '/' if self.peek_char() == Some('*') => self.consume_block_comment(),
 
             // Operators & punctuation
             '(' => {
@@ -74,10 +93,32 @@ impl<'a> Lexer<'a> {
                 self.advance();
                 PLUS
             }
+            '-' => {
+                self.advance();
+                MINUS
+            }
             '/' => {
                 self.advance();
                 DIVIDE
             }
+            '%' => {
+                self.advance();
+                PERCENT
+            }
+            '|' if self.peek_char() == Some('|') => {
+                self.advance();
+                self.advance();
+                CONCAT
+            }
+            ':' if self.peek_char() == Some(':') => {
+                self.advance();
+                self.advance();
+                COLON_COLON
+            }
+            ';' => {
+                self.advance();
+                SEMICOLON
+            }
             '=' if self.peek_char() == Some('>') => {
                 self.advance();
                 self.advance();
@@ -92,6 +133,11 @@ impl<'a> Lexer<'a> {
                 self.advance();
                 NE
             }
+            '<' if self.peek_char() == Some('>') => {
+                self.advance();
+                self.advance();
+                NE
+            }
             '<' if self.peek_char() == Some('=') => {
                 self.advance();
                 self.advance();
@@ -111,8 +157,11 @@ impl<'a> Lexer<'a> {
                 GT
             }
 
-            // Strings
-            '\'' | '"' => self.consume_string(c),
+            // Strings and quoted identifiers
+            '\'' => self.consume_string(),
+            'e' | 'E' if self.peek_char() == Some('\'') => self.consume_escape_string(),
+            '$' if self.at_dollar_quote().is_some() => self.consume_dollar_string(),
+            '"' | '`' => self.consume_quoted_ident(c),
 
             // Numbers
             c if c.is_ascii_digit() => self.consume_number(),
@@ -167,31 +216,133 @@ impl<'a> Lexer<'a> {
         COMMENT
     }
 
-    fn consume_string(&mut self, quote: char) -> SyntaxKind {
+    fn consume_block_comment(&mut self) -> SyntaxKind {
+        // Consume /*
+        self.advance();
+        self.advance();
+
+        // Block comments nest: /* outer /* inner */ still outer */
+        let mut depth = 1;
+        while depth > 0 && !self.at_end() {
+            if self.current_char() == '/' && self.peek_char() == Some('*') {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else if self.current_char() == '*' && self.peek_char() == Some('/') {
+                self.advance();
+                self.advance();
+                depth -= 1;
+            } else {
+                self.advance();
+            }
+        }
+
+        COMMENT
+    }
+
+    /// Consume characters up to and including the closing `quote`.
+    /// A doubled quote ('' or "") stands for the quote character itself.
+    fn consume_quoted(&mut self, quote: char) {
         // Consume opening quote
         self.advance();
 
-        // Consume until closing quote or EOF
-        while self.current_char() != quote && self.current_char() != '\0' {
-            if self.current_char() == '\\' {
-                // Skip escaped character
+        while !self.at_end() {
+            if self.current_char() == quote {
                 self.advance();
-                if self.current_char() != '\0' {
+                if self.current_char() == quote {
+                    // Escaped quote
                     self.advance();
+                } else {
+                    return;
                 }
             } else {
                 self.advance();
             }
         }
+    }
 
-        // Consume closing quote if present
-        if self.current_char() == quote {
+    /// 'text', with '' as an escaped quote, and backslash escapes where the
+    /// dialect has them. Strings may span lines.
+    fn consume_string(&mut self) -> SyntaxKind {
+        if self.options.backslash_escapes {
+            // Consume opening quote
             self.advance();
+            self.consume_escaped_string_body();
+        } else {
+            self.consume_quoted('\'');
+        }
+        STRING
+    }
+
+    /// E'text' with backslash escapes (E'it\'s', E'line\n')
+    fn consume_escape_string(&mut self) -> SyntaxKind {
+        // Consume E and opening quote
+        self.advance();
+        self.advance();
+        self.consume_escaped_string_body();
+        STRING
+    }
+
+    /// Consume a string up to and including its closing quote, skipping
+    /// backslash-escaped characters
+    fn consume_escaped_string_body(&mut self) {
+        while !self.at_end() {
+            match self.current_char() {
+                '\\' => {
+                    // Skip escaped character
+                    self.advance();
+                    if !self.at_end() {
+                        self.advance();
+                    }
+                }
+                '\'' => {
+                    self.advance();
+                    if self.current_char() == '\'' {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+                _ => self.advance(),
+            }
+        }
+    }
+
+    /// Length of the dollar-quote delimiter ($$ or $tag$) at the current position
+    fn at_dollar_quote(&self) -> Option<usize> {
+        let rest = &self.input[self.pos..];
+        let tag_end = rest[1..].find(|c: char| !(c.is_alphanumeric() || c == '_'))? + 1;
+        if rest[tag_end..].starts_with('$') && !rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            Some(tag_end + 1)
+        } else {
+            None
+        }
+    }
+
+    /// $$text$$ or $tag$text$tag$, with no escapes
+    fn consume_dollar_string(&mut self) -> SyntaxKind {
+        let len = self.at_dollar_quote().unwrap_or(2);
+        let delimiter = self.input[self.pos..self.pos + len].to_string();
+        self.pos += len;
+
+        match self.input[self.pos..].find(&delimiter) {
+            Some(end) => self.pos += end + delimiter.len(),
+            None => self.pos = self.input.len(),
         }
 
         STRING
     }
 
+    /// "identifier" (standard SQL) or `identifier` (Spark/MySQL)
+    fn consume_quoted_ident(&mut self, quote: char) -> SyntaxKind {
+        self.consume_quoted(quote);
+        QUOTED_IDENT
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.input.len()
+    }
+
     fn consume_number(&mut self) -> SyntaxKind {
         while self.current_char().is_ascii_digit() {
             self.advance();
@@ -231,6 +382,48 @@ fn keyword_or_ident(text: &str) -> SyntaxKind {
         "AND" => AND_KW,
         "OR" => OR_KW,
         "NOT" => NOT_KW,
+        "JOIN" => JOIN_KW,
+        "INNER" => INNER_KW,
+        "LEFT" => LEFT_KW,
+        "RIGHT" => RIGHT_KW,
+        "FULL" => FULL_KW,
+        "OUTER" => OUTER_KW,
+        "CROSS" => CROSS_KW,
+        "ON" => ON_KW,
+        "USING" => USING_KW,
+        "WITH" => WITH_KW,
+        "RECURSIVE" => RECURSIVE_KW,
+        "UNION" => UNION_KW,
+        "ALL" => ALL_KW,
+        "HAVING" => HAVING_KW,
+        "QUALIFY" => QUALIFY_KW,
+        "ORDER" => ORDER_KW,
+        "ASC" => ASC_KW,
+        "DESC" => DESC_KW,
+        "NULLS" => NULLS_KW,
+        "LIMIT" => LIMIT_KW,
+        "OFFSET" => OFFSET_KW,
+        "DISTINCT" => DISTINCT_KW,
+        "INTERSECT" => INTERSECT_KW,
+        "EXCEPT" => EXCEPT_KW,
+        "IN" => IN_KW,
+        "EXISTS" => EXISTS_KW,
+        "CASE" => CASE_KW,
+        "WHEN" => WHEN_KW,
+        "THEN" => THEN_KW,
+        "ELSE" => ELSE_KW,
+        "END" => END_KW,
+        "CAST" => CAST_KW,
+        "IS" => IS_KW,
+        "NULL" => NULL_KW,
+        "TRUE" => TRUE_KW,
+        "FALSE" => FALSE_KW,
+        "BETWEEN" => BETWEEN_KW,
+        "LIKE" => LIKE_KW,
+        "ILIKE" => ILIKE_KW,
+        "OVER" => OVER_KW,
+        "PARTITION" => PARTITION_KW,
+        "WINDOW" => WINDOW_KW,
         _ => IDENT,
     }
 }
@@ -273,4 +466,164 @@ mod tests {
         assert_eq!(tokens[1].kind, WHITESPACE); // newline
         assert_eq!(tokens[2].kind, SELECT_KW);
     }
+
+    #[test]
+    fn test_join_keywords() {
+        let input = "left outer join users u on";
+        let kinds: Vec<_> = tokenize(input)
+            .into_iter()
+            .map(|t| t.kind)
+            .filter(|k| !k.is_trivia())
+            .collect();
+
+        assert_eq!(kinds, vec![LEFT_KW, OUTER_KW, JOIN_KW, IDENT, IDENT, ON_KW]);
+    }
+
+    #[test]
+    fn test_operators() {
+        let input = "a || b % 2 - c::int <> d;";
+        let kinds: Vec<_> = tokenize(input)
+            .into_iter()
+            .map(|t| t.kind)
+            .filter(|k| !k.is_trivia())
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                IDENT, CONCAT, IDENT, PERCENT, NUMBER, MINUS, IDENT, COLON_COLON, IDENT, NE, IDENT,
+                SEMICOLON
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minus_vs_comment() {
+        let input = "x-1 -- done";
+        let kinds: Vec<_> = tokenize(input).into_iter().map(|t| t.kind).collect();
+
+        assert_eq!(kinds, vec![IDENT, MINUS, NUMBER, WHITESPACE, COMMENT]);
+    }
+
+    /// Non-trivia tokens with their text
+    fn lex(input: &str) -> Vec<(SyntaxKind, &str)> {
+        let mut offset = 0;
+        tokenize(input)
+            .into_iter()
+            .map(|t| {
+                let text = &input[offset..offset + t.len];
+                offset += t.len;
+                (t.kind, text)
+            })
+            .filter(|(k, _)| !k.is_trivia())
+            .collect()
+    }
+
+    #[test]
+    fn test_quoted_identifiers() {
+        assert_eq!(
+            lex(r#"SELECT "Order Id", `spark col`, "say ""hi""" FROM t"#),
+            vec![
+                (SELECT_KW, "SELECT"),
+                (QUOTED_IDENT, r#""Order Id""#),
+                (COMMA, ","),
+                (QUOTED_IDENT, "`spark col`"),
+                (COMMA, ","),
+                (QUOTED_IDENT, r#""say ""hi""""#),
+                (FROM_KW, "FROM"),
+                (IDENT, "t"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        assert_eq!(
+            lex(r"'it''s' E'line\n\'quoted\'' 'C:\'"),
+            vec![
+                (STRING, "'it''s'"),
+                (STRING, r"E'line\n\'quoted\''"),
+                (STRING, r"'C:\'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dialect_backslash_escapes() {
+        let input = r"SELECT 'it\'s', 'C:\\' AS path FROM t";
+        let spark = LexOptions {
+            backslash_escapes: true,
+        };
+        let mut offset = 0;
+        let tokens: Vec<_> = tokenize_with(input, spark)
+            .into_iter()
+            .map(|t| {
+                let text = &input[offset..offset + t.len];
+                offset += t.len;
+                (t.kind, text)
+            })
+            .filter(|(k, _)| !k.is_trivia())
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (SELECT_KW, "SELECT"),
+                (STRING, r"'it\'s'"),
+                (COMMA, ","),
+                (STRING, r"'C:\\'"),
+                (AS_KW, "AS"),
+                (IDENT, "path"),
+                (FROM_KW, "FROM"),
+                (IDENT, "t"),
+            ]
+        );
+
+        // Without them the backslash is an ordinary character
+        assert_eq!(lex(r"'C:\' AS path")[0], (STRING, r"'C:\'"));
+    }
+
+    #[test]
+    fn test_dollar_and_multiline_strings() {
+        let input = "$$it's\n$x$$ $fn$ body $$ $fn$ 'line one\nline two'";
+        assert_eq!(
+            lex(input),
+            vec![
+                (STRING, "$$it's\n$x$$"),
+                (STRING, "$fn$ body $$ $fn$"),
+                (STRING, "'line one\nline two'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let input = "SELECT /* outer /* nested */ still comment */ a / b";
+        let tokens = tokenize(input);
+        assert_eq!(tokens[2].kind, COMMENT);
+        assert_eq!(tokens[2].len, "/* outer /* nested */ still comment */".len());
+        assert_eq!(
+            lex(input),
+            vec![(SELECT_KW, "SELECT"), (IDENT, "a"), (DIVIDE, "/"), (IDENT, "b")]
+        );
+
+        // Unterminated comments run to the end of input
+        let kinds: Vec<_> = tokenize("a /* open").into_iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![IDENT, WHITESPACE, COMMENT]);
+    }
+
+    #[test]
+    fn test_non_ascii_byte_offsets() {
+        // Token lengths are in bytes, so offsets stay on char boundaries
+        let input = "SELECT 'héllo wörld' AS \"naïve\", café FROM t -- ünïcode";
+        let tokens = tokenize(input);
+        assert_eq!(tokens.iter().map(|t| t.len).sum::<usize>(), input.len());
+
+        let lexed = lex(input);
+        assert_eq!(lexed[1], (STRING, "'héllo wörld'"));
+        assert_eq!(lexed[3], (QUOTED_IDENT, "\"naïve\""));
+        assert_eq!(lexed[5], (IDENT, "café"));
+        assert_eq!(tokens[2].len, "'héllo wörld'".len());
+        assert_eq!(tokens.last().unwrap().kind, COMMENT);
+    }
 }
+
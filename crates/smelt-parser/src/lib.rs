@@ -13,10 +13,13 @@ pub mod syntax_kind;
 pub mod lexer;
 pub mod parser;
 pub mod ast;
+pub mod annotations;
 
 pub use syntax_kind::SyntaxKind;
-pub use parser::{parse, Parse, ParseError};
+pub use lexer::LexOptions;
+pub use parser::{parse, parse_statements, parse_statements_with, parse_with, Parse, ParseError};
 pub use ast::*;
+pub use annotations::{parse_annotations, AnnotationError, AnnotationErrorKind, ModelAnnotations};
 
 /// Re-export Rowan types for convenience
 pub use rowan::TextRange;
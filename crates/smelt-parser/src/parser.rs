@@ -1,5 +1,5 @@
 /// Parser implementation with error recovery
-use crate::lexer::{tokenize, Token};
+use crate::lexer::{tokenize_with, LexOptions, Token};
 use crate::syntax_kind::{SmeltLanguage, SyntaxKind};
 use crate::SyntaxKind::*;
 use rowan::{GreenNode, GreenNodeBuilder, TextRange};
@@ -23,14 +23,40 @@ pub struct ParseError {
     pub range: TextRange,
 }
 
-/// Parse input text into a CST
+/// Parse a model file into a CST.
+///
+/// A model is a single query, optionally terminated by `;`. Anything after
+/// the statement is reported as an error.
 pub fn parse(input: &str) -> Parse {
-    let tokens = tokenize(input);
+    parse_with(input, LexOptions::default())
+}
+
+/// Parse a model file with a dialect's lexing rules
+pub fn parse_with(input: &str, options: LexOptions) -> Parse {
+    let tokens = tokenize_with(input, options);
     let mut parser = Parser::new(input, &tokens);
     parser.parse_file();
     parser.finish()
 }
 
+/// Parse a non-model SQL file (setup scripts, seeds, hooks) into a CST.
+///
+/// The file is a list of `;`-separated statements, each wrapped in a
+/// STATEMENT node. Queries are parsed in full; other statements (DDL, DML)
+/// are kept as opaque token sequences so that tooling can still split and
+/// locate them.
+pub fn parse_statements(input: &str) -> Parse {
+    parse_statements_with(input, LexOptions::default())
+}
+
+/// Parse a non-model SQL file with a dialect's lexing rules
+pub fn parse_statements_with(input: &str, options: LexOptions) -> Parse {
+    let tokens = tokenize_with(input, options);
+    let mut parser = Parser::new(input, &tokens);
+    parser.parse_statement_list();
+    parser.finish()
+}
+
 struct Parser<'a> {
     input: &'a str,
     tokens: &'a [Token],
@@ -76,10 +102,17 @@ impl<'a> Parser<'a> {
 
     /// Advance to next token, consuming trivia
     fn advance(&mut self) {
+        if let Some(token) = self.tokens.get(self.pos) {
+            self.advance_as(token.kind);
+        }
+    }
+
+    /// Advance to next token, recording it with a different kind
+    fn advance_as(&mut self, kind: SyntaxKind) {
         if self.pos < self.tokens.len() {
             let token = self.tokens[self.pos];
             let text = &self.input[self.offset..self.offset + token.len];
-            self.builder.token(token.kind.into(), text);
+            self.builder.token(kind.into(), text);
             self.offset += token.len;
             self.pos += 1;
         }
@@ -92,6 +125,38 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Check if at an identifier (plain or quoted)
+    fn at_ident(&self) -> bool {
+        self.current().is_ident()
+    }
+
+    /// Check if at a name: an identifier or a non-reserved keyword
+    fn at_name(&self) -> bool {
+        self.at_ident() || self.current().is_non_reserved_keyword()
+    }
+
+    /// Consume a name. A non-reserved keyword is recorded as IDENT, so the
+    /// tree reads it like any other name.
+    fn advance_name(&mut self) {
+        if self.current().is_non_reserved_keyword() {
+            self.advance_as(IDENT);
+        } else {
+            self.advance();
+        }
+    }
+
+    /// Expect a name (identifier or non-reserved keyword), report error if not present
+    fn expect_ident(&mut self) -> bool {
+        self.skip_trivia();
+        if self.at_name() {
+            self.advance_name();
+            true
+        } else {
+            self.error(format!("Expected IDENT, found {:?}", self.current()));
+            false
+        }
+    }
+
     /// Expect a specific token kind, report error if not present
     fn expect(&mut self, kind: SyntaxKind) -> bool {
         self.skip_trivia();
@@ -138,10 +203,117 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Check if at an identifier matching one of the given words (case-insensitive).
+    /// Used for non-reserved words like FIRST/LAST that are lexed as IDENT.
+    fn at_ident_text(&self, words: &[&str]) -> bool {
+        self.at(IDENT)
+            && self.tokens.get(self.pos).is_some_and(|token| {
+                let text = &self.input[self.offset..self.offset + token.len];
+                words.iter().any(|w| w.eq_ignore_ascii_case(text))
+            })
+    }
+
+    /// Kind of the next non-trivia token after the current one (single-token lookahead)
+    fn peek_non_trivia(&self) -> SyntaxKind {
+        self.tokens[(self.pos + 1).min(self.tokens.len())..]
+            .iter()
+            .map(|t| t.kind)
+            .find(|k| !k.is_trivia())
+            .unwrap_or(EOF)
+    }
+
+    /// Kind of the last consumed non-trivia token
+    fn prev_non_trivia(&self) -> SyntaxKind {
+        self.tokens[..self.pos.min(self.tokens.len())]
+            .iter()
+            .rev()
+            .map(|t| t.kind)
+            .find(|k| !k.is_trivia())
+            .unwrap_or(EOF)
+    }
+
     /// Check if current token is a keyword that would end a table reference
     fn at_keyword_that_ends_table_ref(&self) -> bool {
         // Keywords that can follow a table reference in the FROM clause
-        self.at_any(&[WHERE_KW, GROUP_KW])
+        self.at_any(&[WHERE_KW, GROUP_KW, ON_KW, USING_KW]) || self.at_join_keyword()
+    }
+
+    /// Kind of the n-th non-trivia token at or after the current position
+    fn nth_non_trivia(&self, n: usize) -> SyntaxKind {
+        self.tokens[self.pos.min(self.tokens.len())..]
+            .iter()
+            .map(|t| t.kind)
+            .filter(|k| !k.is_trivia())
+            .nth(n)
+            .unwrap_or(EOF)
+    }
+
+    /// Like `at_ident_text`, for the n-th non-trivia token at or after the
+    /// current position
+    fn nth_ident_text(&self, n: usize, words: &[&str]) -> bool {
+        let mut offset = self.offset;
+        let mut remaining = n;
+        for token in &self.tokens[self.pos.min(self.tokens.len())..] {
+            if token.kind.is_trivia() {
+                offset += token.len;
+                continue;
+            }
+            if remaining == 0 {
+                let text = &self.input[offset..offset + token.len];
+                return token.kind == IDENT && words.iter().any(|w| w.eq_ignore_ascii_case(text));
+            }
+            remaining -= 1;
+            offset += token.len;
+        }
+        false
+    }
+
+    /// Check if at DISTINCT as a modifier (SELECT DISTINCT a, COUNT(DISTINCT a))
+    /// rather than a column named distinct
+    fn at_distinct_modifier(&self) -> bool {
+        self.at(DISTINCT_KW)
+            && !matches!(
+                self.peek_non_trivia(),
+                COMMA | DOT | FROM_KW | AS_KW | RPAREN | SEMICOLON | EOF
+            )
+    }
+
+    /// Check if current token combines two queries
+    fn at_set_operator(&self) -> bool {
+        self.at_any(&[UNION_KW, INTERSECT_KW, EXCEPT_KW])
+    }
+
+    /// Check if at a query: SELECT, WITH or a parenthesized query
+    fn at_query(&self) -> bool {
+        self.at_any(&[SELECT_KW, WITH_KW]) || self.at_parenthesized_query()
+    }
+
+    /// Check if at `(` opening a query, possibly behind further parentheses:
+    /// `((SELECT ...) UNION ...)`
+    fn at_parenthesized_query(&self) -> bool {
+        let mut depth = 0;
+        for kind in self.tokens[self.pos.min(self.tokens.len())..]
+            .iter()
+            .map(|t| t.kind)
+            .filter(|k| !k.is_trivia())
+        {
+            match kind {
+                LPAREN => depth += 1,
+                SELECT_KW | WITH_KW => return depth > 0,
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Check if the next non-trivia token starts a query (for `(SELECT ...)`)
+    fn peek_is_query(&self) -> bool {
+        matches!(self.peek_non_trivia(), SELECT_KW | WITH_KW)
+    }
+
+    /// Check if current token starts a JOIN clause
+    fn at_join_keyword(&self) -> bool {
+        self.at_any(&[JOIN_KW, INNER_KW, LEFT_KW, RIGHT_KW, FULL_KW, CROSS_KW])
     }
 
     // ===== Parsing rules =====
@@ -151,9 +323,18 @@ impl<'a> Parser<'a> {
 
         self.skip_trivia();
 
-        // Parse SELECT statement
-        if self.at(SELECT_KW) {
-            self.parse_select_stmt();
+        // Parse SELECT statement (optionally preceded by WITH)
+        if self.at_query() {
+            self.parse_query();
+            self.skip_trivia();
+            if self.at(SEMICOLON) {
+                self.advance();
+                self.skip_trivia();
+            }
+            if !self.at(EOF) {
+                self.error("Expected end of model after statement".to_string());
+                self.sync_to(&[EOF]);
+            }
         } else if !self.at(EOF) {
             self.error("Expected SELECT statement".to_string());
             self.sync_to(&[EOF]);
@@ -167,12 +348,224 @@ impl<'a> Parser<'a> {
         self.finish_node();
     }
 
+    fn parse_statement_list(&mut self) {
+        self.start_node(FILE);
+
+        loop {
+            self.skip_trivia();
+            if self.at(EOF) {
+                break;
+            }
+            if self.at(SEMICOLON) {
+                // Empty statement
+                self.advance();
+                continue;
+            }
+            self.parse_statement();
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse one statement of a multi-statement file, including its `;`
+    fn parse_statement(&mut self) {
+        self.start_node(STATEMENT);
+
+        if self.at_query() {
+            self.parse_query();
+            self.skip_trivia();
+            if !self.at_any(&[SEMICOLON, EOF]) {
+                self.error("Expected ; after statement".to_string());
+                self.sync_to(&[SEMICOLON]);
+            }
+        } else {
+            // Other statements are not parsed, only delimited
+            while !self.at_any(&[SEMICOLON, EOF]) {
+                self.advance();
+            }
+        }
+
+        if self.at(SEMICOLON) {
+            self.advance();
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse a query: an optional WITH clause followed by a SELECT, or by
+    /// several SELECTs combined with UNION / INTERSECT / EXCEPT, then
+    /// ORDER BY / LIMIT / OFFSET.
+    ///
+    /// The node kind is only known once the body has been parsed, so the
+    /// SELECT_STMT / COMPOUND_SELECT wrappers are started at checkpoints.
+    /// A WITH clause and the trailing ORDER BY / LIMIT belong to the outermost node.
+    /// Operands in parentheses are SUBQUERY nodes; a parenthesized query on
+    /// its own is a COMPOUND_SELECT with a single operand.
+    fn parse_query(&mut self) {
+        let checkpoint = self.builder.checkpoint();
+
+        if self.at(WITH_KW) {
+            self.parse_with_clause();
+            self.skip_trivia();
+        }
+
+        let select_checkpoint = self.builder.checkpoint();
+        let parenthesized = self.at(LPAREN);
+        if parenthesized {
+            self.parse_subquery();
+        } else {
+            self.parse_select_body();
+        }
+
+        self.skip_trivia();
+        if self.at_set_operator() || parenthesized {
+            // The first SELECT becomes the first operand of the compound query
+            if !parenthesized {
+                self.start_node_at(select_checkpoint, SELECT_STMT);
+                self.finish_node();
+            }
+
+            while self.at_set_operator() {
+                self.advance();
+                self.skip_trivia();
+                if self.at_any(&[ALL_KW, DISTINCT_KW]) {
+                    self.advance();
+                }
+                self.skip_trivia();
+                if self.at(LPAREN) {
+                    self.parse_subquery();
+                } else {
+                    self.parse_select_stmt();
+                }
+                self.skip_trivia();
+            }
+
+            self.parse_query_modifiers();
+            self.start_node_at(checkpoint, COMPOUND_SELECT);
+            self.finish_node();
+        } else {
+            self.parse_query_modifiers();
+            self.start_node_at(checkpoint, SELECT_STMT);
+            self.finish_node();
+        }
+    }
+
+    /// Parse ORDER BY, LIMIT and OFFSET, which apply to the whole query
+    fn parse_query_modifiers(&mut self) {
+        self.skip_trivia();
+        if self.at(ORDER_KW) {
+            self.parse_order_by_clause();
+        }
+
+        // LIMIT and OFFSET may appear in either order
+        self.skip_trivia();
+        if self.at(LIMIT_KW) {
+            self.parse_limit_clause();
+            self.skip_trivia();
+            if self.at(OFFSET_KW) {
+                self.parse_offset_clause();
+            }
+        } else if self.at(OFFSET_KW) {
+            self.parse_offset_clause();
+            self.skip_trivia();
+            if self.at(LIMIT_KW) {
+                self.parse_limit_clause();
+            }
+        }
+    }
+
+    fn parse_with_clause(&mut self) {
+        self.start_node(WITH_CLAUSE);
+        self.expect(WITH_KW);
+
+        // A CTE may itself be named recursive: WITH recursive AS (...)
+        self.skip_trivia();
+        if self.at(RECURSIVE_KW) && !matches!(self.peek_non_trivia(), AS_KW | LPAREN) {
+            self.advance();
+        }
+
+        // Comma-separated CTEs
+        loop {
+            self.parse_cte();
+
+            self.skip_trivia();
+            if self.at(COMMA) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        self.finish_node();
+    }
+
+    fn parse_cte(&mut self) {
+        self.start_node(CTE);
+
+        // name [(col1, col2)] AS (query)
+        self.expect_ident();
+        self.skip_trivia();
+        if self.at(LPAREN) {
+            self.parse_column_list();
+        }
+
+        self.expect(AS_KW);
+        self.expect(LPAREN);
+        self.skip_trivia();
+        if self.at_query() {
+            self.parse_query();
+        } else {
+            self.error("Expected query in common table expression".to_string());
+        }
+        self.expect(RPAREN);
+
+        self.finish_node();
+    }
+
+    /// Parse a parenthesized query, as a derived table or inside an expression
+    fn parse_subquery(&mut self) {
+        self.start_node(SUBQUERY);
+        self.expect(LPAREN);
+
+        self.skip_trivia();
+        if self.at_query() {
+            self.parse_query();
+        } else {
+            self.error("Expected query in subquery".to_string());
+        }
+        self.expect(RPAREN);
+
+        self.finish_node();
+    }
+
+    /// Parse a single SELECT (an operand of a compound query)
     fn parse_select_stmt(&mut self) {
         self.start_node(SELECT_STMT);
+        self.parse_select_body();
+        self.finish_node();
+    }
 
+    /// Parse SELECT ... FROM ... WHERE ... GROUP BY ... HAVING ... WINDOW ... QUALIFY ...
+    /// into the current node
+    fn parse_select_body(&mut self) {
         // SELECT
         self.expect(SELECT_KW);
 
+        // DISTINCT [ON (...)] / ALL
+        self.skip_trivia();
+        if self.at_distinct_modifier() {
+            self.advance();
+            self.skip_trivia();
+            if self.at(ON_KW) {
+                self.advance();
+                self.expect(LPAREN);
+                self.parse_expression_list();
+                self.expect(RPAREN);
+            }
+        } else if self.at(ALL_KW) {
+            self.advance();
+        }
+
         // Select list
         self.parse_select_list();
 
@@ -194,29 +587,38 @@ impl<'a> Parser<'a> {
             self.parse_group_by_clause();
         }
 
-        self.finish_node();
+        // HAVING clause
+        self.skip_trivia();
+        if self.at(HAVING_KW) {
+            self.parse_having_clause();
+        }
+
+        // WINDOW clause
+        self.skip_trivia();
+        if self.at(WINDOW_KW) {
+            self.parse_window_clause();
+        }
+
+        // QUALIFY clause
+        self.skip_trivia();
+        if self.at(QUALIFY_KW) {
+            self.parse_qualify_clause();
+        }
     }
 
     fn parse_select_list(&mut self) {
         self.start_node(SELECT_LIST);
         self.skip_trivia();
 
-        // Handle SELECT *
-        if self.at(STAR) {
-            self.start_node(SELECT_ITEM);
-            self.advance();
-            self.finish_node();
-        } else {
-            // Parse comma-separated select items
-            loop {
-                self.parse_select_item();
+        // Parse comma-separated select items (`*` is parsed as an expression)
+        loop {
+            self.parse_select_item();
 
-                self.skip_trivia();
-                if self.at(COMMA) {
-                    self.advance();
-                } else {
-                    break;
-                }
+            self.skip_trivia();
+            if self.at(COMMA) {
+                self.advance();
+            } else {
+                break;
             }
         }
 
@@ -230,32 +632,86 @@ impl<'a> Parser<'a> {
         // Parse expression
         self.parse_expression();
 
+        // DuckDB wildcard modifiers: * EXCLUDE (a, b) REPLACE (a + 1 AS a)
+        if self.prev_non_trivia() == STAR {
+            loop {
+                self.skip_trivia();
+                if self.at_ident_text(&["exclude"]) {
+                    self.parse_wildcard_exclude();
+                } else if self.at_ident_text(&["replace"]) && self.peek_non_trivia() == LPAREN {
+                    self.parse_wildcard_replace();
+                } else {
+                    break;
+                }
+            }
+        }
+
         // Optional AS alias
         self.skip_trivia();
         if self.at(AS_KW) {
             self.advance();
             self.skip_trivia();
-            if self.at(IDENT) {
-                self.advance();
+            if self.at_name() {
+                self.advance_name();
             }
-        } else if self.at(IDENT) {
-            // Implicit alias (no AS keyword)
+        } else if self.at_ident() {
+            // Implicit alias (no AS keyword; keywords need one)
             self.advance();
         }
 
         self.finish_node();
     }
 
+    /// EXCLUDE (col1, col2) or EXCLUDE col
+    fn parse_wildcard_exclude(&mut self) {
+        self.start_node(WILDCARD_EXCLUDE);
+        self.advance(); // EXCLUDE
+        self.skip_trivia();
+        if self.at(LPAREN) {
+            self.parse_column_list();
+        } else {
+            self.expect_ident();
+        }
+        self.finish_node();
+    }
+
+    /// REPLACE (expr AS col, ...)
+    fn parse_wildcard_replace(&mut self) {
+        self.start_node(WILDCARD_REPLACE);
+        self.advance(); // REPLACE
+        self.expect(LPAREN);
+
+        loop {
+            self.parse_select_item();
+
+            self.skip_trivia();
+            if self.at(COMMA) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        self.expect(RPAREN);
+        self.finish_node();
+    }
+
     fn parse_from_clause(&mut self) {
         self.start_node(FROM_CLAUSE);
 
         self.expect(FROM_KW);
 
-        // Parse table references (could be identifier or template)
+        // Parse table references (could be identifier or template),
+        // each optionally followed by JOIN clauses
         loop {
             self.parse_table_ref();
 
             self.skip_trivia();
+            while self.at_join_keyword() {
+                self.parse_join_clause();
+                self.skip_trivia();
+            }
+
             if self.at(COMMA) {
                 self.advance();
             } else {
@@ -270,10 +726,10 @@ impl<'a> Parser<'a> {
         self.start_node(TABLE_REF);
         self.skip_trivia();
 
-        if self.at(IDENT) {
+        if self.at_name() {
             // Use builder checkpoint for proper lookahead
             let checkpoint = self.builder.checkpoint();
-            self.advance(); // Consume IDENT
+            self.advance_name(); // Consume IDENT
             self.skip_trivia();
 
             if self.at(LPAREN) {
@@ -285,7 +741,7 @@ impl<'a> Parser<'a> {
                 // Could be schema.table or namespace.func()
                 self.advance(); // Consume DOT
                 self.skip_trivia();
-                self.expect(IDENT); // Consume second IDENT
+                self.expect_ident(); // Consume second IDENT
                 self.skip_trivia();
 
                 if self.at(LPAREN) {
@@ -297,6 +753,9 @@ impl<'a> Parser<'a> {
                 // else: just a qualified table name (schema.table), already consumed
             }
             // else: simple identifier, already consumed
+        } else if self.at(LPAREN) {
+            // Derived table: (SELECT ...) alias
+            self.parse_subquery();
         } else {
             self.error("Expected table reference".to_string());
         }
@@ -306,8 +765,8 @@ impl<'a> Parser<'a> {
         if self.at(AS_KW) {
             self.advance();
             self.skip_trivia();
-            self.expect(IDENT);
-        } else if self.at(IDENT) && !self.at_keyword_that_ends_table_ref() {
+            self.expect_ident();
+        } else if self.at_ident() && !self.at_keyword_that_ends_table_ref() {
             // Implicit alias (no AS keyword)
             // Only consume if it's not a keyword that would end the table ref
             self.advance();
@@ -316,148 +775,672 @@ impl<'a> Parser<'a> {
         self.finish_node();
     }
 
-    fn parse_where_clause(&mut self) {
-        self.start_node(WHERE_CLAUSE);
-        self.expect(WHERE_KW);
-        self.parse_expression();
-        self.finish_node();
-    }
-
-    fn parse_group_by_clause(&mut self) {
-        self.start_node(GROUP_BY_CLAUSE);
-        self.expect(GROUP_KW);
-        self.expect(BY_KW);
-
-        // Parse comma-separated column list
-        loop {
-            self.parse_expression();
+    fn parse_join_clause(&mut self) {
+        self.start_node(JOIN_CLAUSE);
 
+        // Join type: [INNER] JOIN, {LEFT|RIGHT|FULL} [OUTER] JOIN, CROSS JOIN
+        let is_cross = self.at(CROSS_KW);
+        if self.at_any(&[LEFT_KW, RIGHT_KW, FULL_KW]) {
+            self.advance();
             self.skip_trivia();
-            if self.at(COMMA) {
+            if self.at(OUTER_KW) {
                 self.advance();
-            } else {
-                break;
             }
+            self.expect(JOIN_KW);
+        } else if self.at_any(&[INNER_KW, CROSS_KW]) {
+            self.advance();
+            self.expect(JOIN_KW);
+        } else {
+            self.expect(JOIN_KW);
+        }
+
+        // Right-hand side table
+        self.parse_table_ref();
+
+        // Join condition
+        self.skip_trivia();
+        if self.at_any(&[ON_KW, USING_KW]) {
+            self.parse_join_condition();
+        } else if !is_cross {
+            self.error("Expected ON or USING after JOIN".to_string());
         }
 
         self.finish_node();
     }
 
-    fn parse_expression(&mut self) {
-        self.start_node(EXPRESSION);
-        self.skip_trivia();
+    fn parse_join_condition(&mut self) {
+        self.start_node(JOIN_CONDITION);
 
-        self.parse_or_expr();
+        if self.at(ON_KW) {
+            self.advance();
+            self.parse_expression();
+        } else {
+            self.expect(USING_KW);
+            self.parse_column_list();
+        }
 
         self.finish_node();
     }
 
-    fn parse_or_expr(&mut self) {
-        self.parse_and_expr();
+    fn parse_column_list(&mut self) {
+        self.start_node(COLUMN_LIST);
+        self.expect(LPAREN);
+
+        loop {
+            self.expect_ident();
 
-        while self.at(OR_KW) {
-            self.start_node(BINARY_EXPR);
-            self.advance();
             self.skip_trivia();
-            self.parse_and_expr();
-            self.finish_node();
+            if self.at(COMMA) {
+                self.advance();
+            } else {
+                break;
+            }
         }
+
+        self.expect(RPAREN);
+        self.finish_node();
     }
 
-    fn parse_and_expr(&mut self) {
-        self.parse_comparison_expr();
+    fn parse_where_clause(&mut self) {
+        self.start_node(WHERE_CLAUSE);
+        self.expect(WHERE_KW);
+        self.parse_expression();
+        self.finish_node();
+    }
+
+    fn parse_group_by_clause(&mut self) {
+        self.start_node(GROUP_BY_CLAUSE);
+        self.expect(GROUP_KW);
+        self.expect(BY_KW);
+
+        // Parse comma-separated column list
+        self.parse_expression_list();
+
+        self.finish_node();
+    }
+
+    fn parse_having_clause(&mut self) {
+        self.start_node(HAVING_CLAUSE);
+        self.expect(HAVING_KW);
+        self.parse_expression();
+        self.finish_node();
+    }
+
+    fn parse_qualify_clause(&mut self) {
+        self.start_node(QUALIFY_CLAUSE);
+        self.expect(QUALIFY_KW);
+        self.parse_expression();
+        self.finish_node();
+    }
+
+    fn parse_order_by_clause(&mut self) {
+        self.start_node(ORDER_BY_CLAUSE);
+        self.expect(ORDER_KW);
+        self.expect(BY_KW);
+
+        loop {
+            self.parse_order_by_item();
 
-        while self.at(AND_KW) {
-            self.start_node(BINARY_EXPR);
-            self.advance();
             self.skip_trivia();
-            self.parse_comparison_expr();
-            self.finish_node();
+            if self.at(COMMA) {
+                self.advance();
+            } else {
+                break;
+            }
         }
+
+        self.finish_node();
     }
 
-    fn parse_comparison_expr(&mut self) {
-        self.parse_additive_expr();
+    fn parse_order_by_item(&mut self) {
+        self.start_node(ORDER_BY_ITEM);
+        self.parse_expression();
 
-        while self.at_any(&[EQ, NE, LT, GT, LE, GE]) {
-            self.start_node(BINARY_EXPR);
+        // Optional ASC/DESC
+        self.skip_trivia();
+        if self.at_any(&[ASC_KW, DESC_KW]) {
+            self.advance();
+        }
+
+        // Optional NULLS FIRST/LAST (FIRST and LAST are not reserved)
+        self.skip_trivia();
+        if self.at(NULLS_KW) {
             self.advance();
             self.skip_trivia();
-            self.parse_additive_expr();
+            if self.at_ident_text(&["FIRST", "LAST"]) {
+                self.advance();
+            } else {
+                self.error("Expected FIRST or LAST after NULLS".to_string());
+            }
+        }
+
+        self.finish_node();
+    }
+
+    fn parse_limit_clause(&mut self) {
+        self.start_node(LIMIT_CLAUSE);
+        self.expect(LIMIT_KW);
+
+        // LIMIT ALL means no limit
+        self.skip_trivia();
+        if self.at(ALL_KW) {
+            self.advance();
+        } else {
+            self.parse_expression();
+        }
+
+        self.finish_node();
+    }
+
+    fn parse_offset_clause(&mut self) {
+        self.start_node(OFFSET_CLAUSE);
+        self.expect(OFFSET_KW);
+        self.parse_expression();
+
+        // Optional ROWS (standard SQL noise word)
+        self.skip_trivia();
+        if self.at_ident_text(&["ROW", "ROWS"]) {
+            self.advance();
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse `OVER (window spec)` or `OVER window_name`
+    fn parse_over_clause(&mut self) {
+        self.start_node(OVER_CLAUSE);
+        self.expect(OVER_KW);
+
+        self.skip_trivia();
+        if self.at_name() {
+            self.advance_name();
+        } else {
+            self.parse_window_spec();
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse `( [base_window] [PARTITION BY ...] [ORDER BY ...] [frame] )`
+    fn parse_window_spec(&mut self) {
+        self.start_node(WINDOW_SPEC);
+        self.expect(LPAREN);
+
+        // Named window this spec extends: OVER (w ORDER BY ts)
+        self.skip_trivia();
+        if self.at_ident() && !self.at_ident_text(FRAME_UNITS) {
+            self.advance();
+        }
+
+        self.skip_trivia();
+        if self.at(PARTITION_KW) {
+            self.start_node(PARTITION_BY_CLAUSE);
+            self.advance();
+            self.expect(BY_KW);
+            self.parse_expression_list();
             self.finish_node();
         }
+
+        self.skip_trivia();
+        if self.at(ORDER_KW) {
+            self.parse_order_by_clause();
+        }
+
+        self.skip_trivia();
+        if self.at_ident_text(FRAME_UNITS) {
+            self.parse_window_frame();
+        }
+
+        self.expect(RPAREN);
+        self.finish_node();
     }
 
-    fn parse_additive_expr(&mut self) {
-        self.parse_multiplicative_expr();
+    /// Parse `{ROWS|RANGE|GROUPS} {bound | BETWEEN bound AND bound}`
+    fn parse_window_frame(&mut self) {
+        self.start_node(WINDOW_FRAME);
+        self.advance(); // ROWS, RANGE or GROUPS
 
-        while self.at_any(&[PLUS, MINUS]) {
-            self.start_node(BINARY_EXPR);
+        self.skip_trivia();
+        if self.at(BETWEEN_KW) {
             self.advance();
+            self.parse_frame_bound();
+            self.expect(AND_KW);
+            self.parse_frame_bound();
+        } else {
+            self.parse_frame_bound();
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse UNBOUNDED PRECEDING|FOLLOWING, CURRENT ROW or expr PRECEDING|FOLLOWING
+    fn parse_frame_bound(&mut self) {
+        self.skip_trivia();
+        self.start_node(FRAME_BOUND);
+
+        if self.at_ident_text(&["CURRENT"]) {
+            self.advance();
+            self.skip_trivia();
+            if self.at_ident_text(&["ROW"]) {
+                self.advance();
+            } else {
+                self.error("Expected ROW after CURRENT".to_string());
+            }
+        } else {
+            if self.at_ident_text(&["UNBOUNDED"]) {
+                self.advance();
+            } else {
+                // Offset expression; binds tighter than the AND between bounds
+                self.parse_expr_bp(PREDICATE_BP + 1);
+            }
+
             self.skip_trivia();
-            self.parse_multiplicative_expr();
+            if self.at_ident_text(&["PRECEDING", "FOLLOWING"]) {
+                self.advance();
+            } else {
+                self.error("Expected PRECEDING or FOLLOWING in window frame".to_string());
+            }
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse `WINDOW w AS (spec), ...`
+    fn parse_window_clause(&mut self) {
+        self.start_node(WINDOW_CLAUSE);
+        self.expect(WINDOW_KW);
+
+        loop {
+            self.skip_trivia();
+            self.start_node(WINDOW_DEF);
+            self.expect_ident();
+            self.expect(AS_KW);
+            self.parse_window_spec();
             self.finish_node();
+
+            self.skip_trivia();
+            if self.at(COMMA) {
+                self.advance();
+            } else {
+                break;
+            }
         }
+
+        self.finish_node();
     }
 
-    fn parse_multiplicative_expr(&mut self) {
-        self.parse_primary_expr();
+    /// Parse a comma-separated list of expressions
+    fn parse_expression_list(&mut self) {
+        loop {
+            self.parse_expression();
 
-        while self.at_any(&[STAR, DIVIDE]) {
-            self.start_node(BINARY_EXPR);
-            self.advance();
             self.skip_trivia();
-            self.parse_primary_expr();
+            if self.at(COMMA) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parse a complete expression, wrapped in an EXPRESSION node
+    fn parse_expression(&mut self) {
+        self.skip_trivia();
+        self.start_node(EXPRESSION);
+        self.parse_expr_bp(0);
+        self.finish_node();
+    }
+
+    /// Pratt parser: parse an expression whose operators bind at least as
+    /// tightly as `min_bp`. Infix and postfix nodes are started at a
+    /// checkpoint so that they wrap their left operand. Trivia is only
+    /// consumed once an operator continues the expression, so nodes end at
+    /// their last token.
+    fn parse_expr_bp(&mut self, min_bp: u8) {
+        self.skip_trivia();
+        let checkpoint = self.builder.checkpoint();
+        self.parse_prefix_expr();
+
+        loop {
+            let next = self.nth_non_trivia(0);
+            let negated = next == NOT_KW;
+            let op = if negated { self.nth_non_trivia(1) } else { next };
+
+            match op {
+                // expr::type
+                COLON_COLON if !negated => {
+                    if CAST_BP < min_bp {
+                        break;
+                    }
+                    self.skip_trivia();
+                    self.start_node_at(checkpoint, CAST_EXPR);
+                    self.advance();
+                    self.parse_type_name(false);
+                    self.finish_node();
+                }
+                IS_KW if !negated => {
+                    if IS_BP < min_bp {
+                        break;
+                    }
+                    self.skip_trivia();
+                    self.parse_is_expr(checkpoint);
+                }
+                IN_KW | BETWEEN_KW | LIKE_KW | ILIKE_KW => {
+                    if PREDICATE_BP < min_bp {
+                        break;
+                    }
+                    self.skip_trivia();
+                    match op {
+                        IN_KW => self.parse_in_expr(checkpoint),
+                        BETWEEN_KW => self.parse_between_expr(checkpoint),
+                        _ => self.parse_like_expr(checkpoint),
+                    }
+                }
+                _ if !negated => {
+                    let (left_bp, right_bp) = match infix_binding_power(op) {
+                        Some(bp) => bp,
+                        None => break,
+                    };
+                    if left_bp < min_bp {
+                        break;
+                    }
+                    self.skip_trivia();
+                    self.start_node_at(checkpoint, BINARY_EXPR);
+                    self.advance();
+                    self.parse_expr_bp(right_bp);
+                    self.finish_node();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Parse prefix operators (NOT, unary minus/plus) and primary expressions
+    fn parse_prefix_expr(&mut self) {
+        if self.at(NOT_KW) && self.peek_non_trivia() != EXISTS_KW {
+            self.start_node(UNARY_EXPR);
+            self.advance();
+            self.parse_expr_bp(NOT_BP);
             self.finish_node();
+        } else if self.at_any(&[MINUS, PLUS]) {
+            self.start_node(UNARY_EXPR);
+            self.advance();
+            self.parse_expr_bp(UNARY_BP);
+            self.finish_node();
+        } else {
+            self.parse_primary_expr();
         }
     }
 
     fn parse_primary_expr(&mut self) {
         self.skip_trivia();
 
-        if self.at(LPAREN) {
-            // Parenthesized expression or function call
-            let _checkpoint = self.pos;
+        if self.at(LPAREN) && self.peek_is_query() {
+            // Scalar subquery: (SELECT ...)
+            self.parse_subquery();
+        } else if self.at(LPAREN) {
+            // Parenthesized expression
+            self.start_node(PAREN_EXPR);
+            self.advance();
+            self.parse_expr_bp(0);
+            self.expect(RPAREN);
+            self.finish_node();
+        } else if self.at_any(&[EXISTS_KW, NOT_KW]) {
+            // [NOT] EXISTS (SELECT ...) - a NOT only gets here before EXISTS
+            self.start_node(EXISTS_EXPR);
+            if self.at(NOT_KW) {
+                self.advance();
+            }
+            self.expect(EXISTS_KW);
+            self.parse_subquery();
+            self.finish_node();
+        } else if self.at(CASE_KW) {
+            self.parse_case_expr();
+        } else if self.at(CAST_KW)
+            || (self.at_ident_text(&["TRY_CAST"]) && self.peek_non_trivia() == LPAREN)
+        {
+            self.parse_cast_expr();
+        } else if self.at_ident_text(&["DATE", "TIME", "TIMESTAMP", "TIMESTAMPTZ"])
+            && self.peek_non_trivia() == STRING
+        {
+            // Typed literal: DATE '2024-01-01'
+            self.start_node(TYPED_LITERAL);
+            self.advance();
+            self.expect(STRING);
+            self.finish_node();
+        } else if self.at_ident_text(&["INTERVAL"])
+            && matches!(self.peek_non_trivia(), STRING | NUMBER)
+        {
+            // INTERVAL '30 minutes' or INTERVAL 30 MINUTE
+            self.start_node(TYPED_LITERAL);
             self.advance();
             self.skip_trivia();
+            self.advance();
+            if self.nth_ident_text(0, INTERVAL_UNITS) {
+                self.skip_trivia();
+                self.advance();
+            }
+            self.finish_node();
+        } else if self.at_name() {
+            // Also LEFT(str, n) / RIGHT(str, n), which share the join keywords
+            self.parse_name_expr();
+        } else if self.current().is_literal() {
+            self.start_node(LITERAL);
+            self.advance();
+            self.finish_node();
+        } else if self.at(STAR) {
+            // Wildcard: SELECT * or COUNT(*)
+            self.advance();
+        } else {
+            self.error(format!("Expected expression, found {:?}", self.current()));
+        }
+    }
 
-            // Check if it's a function call (preceded by identifier)
-            // For now, just parse as grouped expression
-            self.parse_expression();
+    /// Parse a column reference (col, t.col, t.*) or a function call (f(), ns.f())
+    fn parse_name_expr(&mut self) {
+        let checkpoint = self.builder.checkpoint();
+        self.advance_name(); // consume first IDENT
+
+        while self.nth_non_trivia(0) == DOT {
             self.skip_trivia();
-            self.expect(RPAREN);
-        } else if self.at(IDENT) {
-            // Could be column reference, qualified name, or function call
-            let checkpoint = self.builder.checkpoint();
-            self.advance(); // consume first IDENT
+            self.advance(); // consume DOT
             self.skip_trivia();
 
-            if self.at(LPAREN) {
-                // Simple function call: func()
-                self.start_node_at(checkpoint, FUNCTION_CALL);
-                self.parse_arg_list();
+            if self.at(STAR) {
+                // Qualified wildcard: t.*
+                self.advance();
+                self.start_node_at(checkpoint, COLUMN_REF);
                 self.finish_node();
-            } else if self.at(DOT) {
-                // Could be table.column or namespace.func()
-                self.advance(); // consume DOT
-                self.skip_trivia();
-                self.expect(IDENT); // consume second IDENT
-                self.skip_trivia();
+                return;
+            }
+            self.expect_ident();
+        }
 
-                if self.at(LPAREN) {
-                    // Namespaced function call: smelt.ref()
-                    self.start_node_at(checkpoint, FUNCTION_CALL);
-                    self.parse_arg_list();
-                    self.finish_node();
-                }
-                // else: just a qualified name (table.column), no extra node needed
+        if self.nth_non_trivia(0) == LPAREN {
+            // Function call: func() or namespace.func() like smelt.ref(),
+            // optionally a window function call: func() OVER (...)
+            self.skip_trivia();
+            self.start_node_at(checkpoint, FUNCTION_CALL);
+            self.parse_arg_list();
+            if self.nth_non_trivia(0) == OVER_KW {
+                self.skip_trivia();
+                self.parse_over_clause();
             }
-            // else: just an identifier, no extra node needed
-        } else if self.current().is_literal() || self.at(STAR) {
+            self.finish_node();
+        } else {
+            self.start_node_at(checkpoint, COLUMN_REF);
+            self.finish_node();
+        }
+    }
+
+    /// Parse `[NOT] IN (values)` or `[NOT] IN (query)`, wrapping the
+    /// already-parsed left operand
+    fn parse_in_expr(&mut self, checkpoint: rowan::Checkpoint) {
+        self.start_node_at(checkpoint, IN_EXPR);
+
+        if self.at(NOT_KW) {
             self.advance();
+        }
+        self.expect(IN_KW);
+
+        self.skip_trivia();
+        if self.at(LPAREN) && self.peek_is_query() {
+            self.parse_subquery();
         } else {
-            self.error(format!("Expected expression, found {:?}", self.current()));
+            self.expect(LPAREN);
+            self.parse_expression_list();
+            self.expect(RPAREN);
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse `IS [NOT] {NULL | TRUE | FALSE | DISTINCT FROM expr}`
+    fn parse_is_expr(&mut self, checkpoint: rowan::Checkpoint) {
+        self.start_node_at(checkpoint, IS_EXPR);
+        self.expect(IS_KW);
+
+        self.skip_trivia();
+        if self.at(NOT_KW) {
+            self.advance();
+            self.skip_trivia();
+        }
+
+        if self.at_any(&[NULL_KW, TRUE_KW, FALSE_KW]) {
+            self.advance();
+        } else if self.at(DISTINCT_KW) {
+            self.advance();
+            self.expect(FROM_KW);
+            self.parse_expr_bp(IS_BP + 1);
+        } else {
+            self.error("Expected NULL, TRUE, FALSE or DISTINCT FROM after IS".to_string());
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse `[NOT] BETWEEN [SYMMETRIC] low AND high`
+    fn parse_between_expr(&mut self, checkpoint: rowan::Checkpoint) {
+        self.start_node_at(checkpoint, BETWEEN_EXPR);
+
+        if self.at(NOT_KW) {
+            self.advance();
+        }
+        self.expect(BETWEEN_KW);
+
+        self.skip_trivia();
+        if self.at_ident_text(&["SYMMETRIC"]) {
+            self.advance();
+        }
+
+        // The bounds bind tighter than AND, which separates them
+        self.parse_expr_bp(PREDICATE_BP + 1);
+        self.expect(AND_KW);
+        self.parse_expr_bp(PREDICATE_BP + 1);
+
+        self.finish_node();
+    }
+
+    /// Parse `[NOT] LIKE|ILIKE pattern [ESCAPE char]`
+    fn parse_like_expr(&mut self, checkpoint: rowan::Checkpoint) {
+        self.start_node_at(checkpoint, LIKE_EXPR);
+
+        if self.at(NOT_KW) {
+            self.advance();
+            self.skip_trivia();
         }
+        if self.at_any(&[LIKE_KW, ILIKE_KW]) {
+            self.advance();
+        }
+
+        self.parse_expr_bp(PREDICATE_BP + 1);
+
+        if self.nth_ident_text(0, &["ESCAPE"]) {
+            self.skip_trivia();
+            self.advance();
+            self.parse_expr_bp(PREDICATE_BP + 1);
+        }
+
+        self.finish_node();
+    }
+
+    /// Parse `CASE [operand] WHEN ... THEN ... [ELSE ...] END`
+    fn parse_case_expr(&mut self) {
+        self.start_node(CASE_EXPR);
+        self.expect(CASE_KW);
+
+        // Simple CASE compares an operand: CASE status WHEN 'a' THEN ...
+        if self.nth_non_trivia(0) != WHEN_KW {
+            self.parse_expr_bp(0);
+        }
+
+        if self.nth_non_trivia(0) != WHEN_KW {
+            self.error("Expected WHEN in CASE expression".to_string());
+        }
+        while self.nth_non_trivia(0) == WHEN_KW {
+            self.skip_trivia();
+            self.start_node(CASE_WHEN);
+            self.advance();
+            self.parse_expr_bp(0);
+            self.expect(THEN_KW);
+            self.parse_expr_bp(0);
+            self.finish_node();
+        }
+
+        if self.nth_non_trivia(0) == ELSE_KW {
+            self.skip_trivia();
+            self.start_node(CASE_ELSE);
+            self.advance();
+            self.parse_expr_bp(0);
+            self.finish_node();
+        }
+
+        self.expect(END_KW);
+        self.finish_node();
+    }
+
+    /// Parse `CAST(expr AS type)` or `TRY_CAST(expr AS type)`
+    fn parse_cast_expr(&mut self) {
+        self.start_node(CAST_EXPR);
+        self.advance(); // CAST or TRY_CAST
+        self.expect(LPAREN);
+        self.parse_expr_bp(0);
+        self.expect(AS_KW);
+        self.parse_type_name(true);
+        self.expect(RPAREN);
+        self.finish_node();
+    }
+
+    /// Parse a type name such as INTEGER, VARCHAR(255) or DECIMAL(10, 2).
+    /// Multi-word names (DOUBLE PRECISION) are only allowed inside CAST(...),
+    /// since after `::` a following identifier is the column alias.
+    fn parse_type_name(&mut self, multi_word: bool) {
+        self.skip_trivia();
+        self.start_node(TYPE_NAME);
+        self.expect(IDENT);
+
+        while multi_word && self.nth_non_trivia(0) == IDENT {
+            self.skip_trivia();
+            self.advance();
+        }
+
+        // Optional precision/scale: DECIMAL(10, 2)
+        if self.nth_non_trivia(0) == LPAREN {
+            self.skip_trivia();
+            self.advance();
+            loop {
+                self.expect(NUMBER);
+                self.skip_trivia();
+                if self.at(COMMA) {
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            self.expect(RPAREN);
+        }
+
+        self.finish_node();
     }
 
     fn parse_arg_list(&mut self) {
@@ -470,7 +1453,8 @@ impl<'a> Parser<'a> {
                 self.parse_argument();
 
                 self.skip_trivia();
-                if self.at(COMMA) {
+                // EXTRACT(HOUR FROM ts) separates arguments with FROM
+                if self.at_any(&[COMMA, FROM_KW]) {
                     self.advance();
                     self.skip_trivia();
                 } else {
@@ -479,6 +1463,12 @@ impl<'a> Parser<'a> {
             }
         }
 
+        // Ordered aggregate: FIRST(country ORDER BY event_time)
+        self.skip_trivia();
+        if self.at(ORDER_KW) {
+            self.parse_order_by_clause();
+        }
+
         self.expect(RPAREN);
         self.finish_node();
     }
@@ -486,50 +1476,1026 @@ impl<'a> Parser<'a> {
     fn parse_argument(&mut self) {
         self.skip_trivia();
 
-        // Check for named parameter: IDENT => expression
-        if self.at(IDENT) {
-            // Look ahead to check for ARROW
-            let checkpoint = self.builder.checkpoint();
+        if self.at_distinct_modifier() {
+            // Aggregate modifier: COUNT(DISTINCT x)
+            self.advance();
+            self.parse_expression();
+        } else if (self.at(IDENT) || self.current().is_keyword()) && self.peek_non_trivia() == ARROW {
+            // Named parameter: name => expression (names like `limit` may be keywords)
+            self.start_node(NAMED_PARAM);
             self.advance(); // consume IDENT
             self.skip_trivia();
+            self.advance(); // consume ARROW
+            self.parse_expression();
+            self.finish_node();
+        } else {
+            self.parse_expression();
+        }
+    }
+}
 
-            if self.at(ARROW) {
-                // It's a named parameter
-                self.start_node_at(checkpoint, NAMED_PARAM);
-                self.advance(); // consume ARROW
-                self.skip_trivia();
-                self.parse_expression();
-                self.finish_node();
-            } else {
-                // Not a named parameter, need to parse the rest as expression
-                // The IDENT is already consumed, continue parsing expression from here
-                self.skip_trivia();
+// Binding powers for the expression parser, loosest first
+const NOT_BP: u8 = 5;
+const IS_BP: u8 = 7;
+const PREDICATE_BP: u8 = 11; // [NOT] IN / BETWEEN / LIKE / ILIKE
+const UNARY_BP: u8 = 19;
+const CAST_BP: u8 = 21;
+
+/// Left and right binding power of a binary operator
+fn infix_binding_power(kind: SyntaxKind) -> Option<(u8, u8)> {
+    let bp = match kind {
+        OR_KW => (1, 2),
+        AND_KW => (3, 4),
+        EQ | NE | LT | GT | LE | GE => (9, 10),
+        CONCAT => (13, 14),
+        PLUS | MINUS => (15, 16),
+        STAR | DIVIDE | PERCENT => (17, 18),
+        _ => return None,
+    };
+    Some(bp)
+}
 
-                // Handle cases where IDENT might be followed by operators or function calls
-                if self.at(LPAREN) {
-                    // Function call - wrap in FUNCTION_CALL
-                    self.start_node_at(checkpoint, FUNCTION_CALL);
-                    self.parse_arg_list();
-                    self.finish_node();
-                } else if self.at(DOT) {
-                    // Qualified name or namespaced function
-                    self.advance();
-                    self.skip_trivia();
-                    self.expect(IDENT);
-                    self.skip_trivia();
+/// Window frame units (not reserved words)
+const FRAME_UNITS: &[&str] = &["ROWS", "RANGE", "GROUPS"];
+
+/// Units accepted after `INTERVAL n`
+const INTERVAL_UNITS: &[&str] = &[
+    "YEAR", "YEARS", "MONTH", "MONTHS", "WEEK", "WEEKS", "DAY", "DAYS", "HOUR", "HOURS",
+    "MINUTE", "MINUTES", "SECOND", "SECONDS", "MILLISECOND", "MILLISECONDS",
+    "MICROSECOND", "MICROSECONDS",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{File, JoinType, SetOperator};
+
+    fn parse_ok(input: &str) -> File {
+        let parse = parse(input);
+        assert!(parse.errors.is_empty(), "unexpected errors: {:?}", parse.errors);
+        File::cast(parse.syntax()).unwrap()
+    }
 
-                    if self.at(LPAREN) {
-                        // Namespaced function call
-                        self.start_node_at(checkpoint, FUNCTION_CALL);
-                        self.parse_arg_list();
-                        self.finish_node();
-                    }
-                }
-                // Otherwise, the IDENT alone is the expression (already consumed)
-            }
-        } else {
-            // Not starting with IDENT, parse as regular expression
-            self.parse_expression();
+    #[test]
+    fn test_join_types() {
+        let file = parse_ok(
+            "SELECT * FROM a \
+             JOIN b ON a.id = b.id \
+             INNER JOIN c ON a.id = c.id \
+             LEFT JOIN d ON a.id = d.id \
+             RIGHT OUTER JOIN e ON a.id = e.id \
+             FULL JOIN f USING (id) \
+             CROSS JOIN g",
+        );
+        let from = file.select_stmt().unwrap().from_clause().unwrap();
+
+        let types: Vec<_> = from.joins().map(|j| j.join_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                JoinType::Inner,
+                JoinType::Inner,
+                JoinType::Left,
+                JoinType::Right,
+                JoinType::Full,
+                JoinType::Cross,
+            ]
+        );
+        assert_eq!(from.table_refs().count(), 7);
+    }
+
+    #[test]
+    fn test_join_conditions() {
+        let file = parse_ok(
+            "SELECT * FROM a JOIN b ON a.id = b.id AND a.x > 1 LEFT JOIN c USING (id, day)",
+        );
+        let joins: Vec<_> = file.select_stmt().unwrap().from_clause().unwrap().joins().collect();
+
+        let on = joins[0].condition().unwrap();
+        assert_eq!(on.on_expr().unwrap().text().trim(), "a.id = b.id AND a.x > 1");
+        assert!(on.using_columns().is_empty());
+
+        let using = joins[1].condition().unwrap();
+        assert!(using.on_expr().is_none());
+        assert_eq!(using.using_columns(), vec!["id", "day"]);
+    }
+
+    #[test]
+    fn test_join_with_refs_and_aliases() {
+        let file = parse_ok(
+            "SELECT u.user_id, e.event_id\n\
+             FROM smelt.ref('users') u\n\
+             LEFT JOIN smelt.ref('events') AS e ON u.user_id = e.user_id\n\
+             WHERE e.event_id > 0",
+        );
+
+        let refs: Vec<_> = file.refs().filter_map(|r| r.model_name()).collect();
+        assert_eq!(refs, vec!["users", "events"]);
+
+        let select = file.select_stmt().unwrap();
+        let aliases: Vec<_> = select
+            .from_clause()
+            .unwrap()
+            .table_refs()
+            .map(|t| t.alias())
+            .collect();
+        assert_eq!(aliases, vec![Some("u".to_string()), Some("e".to_string())]);
+        assert!(select.where_clause().is_some());
+    }
+
+    /// Names of the column references and aliases of a query's select list
+    fn select_names(file: &File) -> (Vec<String>, Vec<Option<String>>) {
+        let select = file.select_stmt().unwrap();
+        let columns = select.column_refs().iter().map(|c| c.name().to_string()).collect();
+        let aliases = select.select_list().unwrap().items().map(|i| i.alias()).collect();
+        (columns, aliases)
+    }
+
+    #[test]
+    fn test_join_keywords_as_names() {
+        let file = parse_ok(
+            "SELECT t.left, right AS inner, full \
+             FROM cross AS outer LEFT JOIN t ON outer.left = t.right",
+        );
+        let (columns, aliases) = select_names(&file);
+        assert_eq!(columns, vec!["left", "right", "full", "left", "right"]);
+        assert_eq!(aliases, vec![None, Some("inner".to_string()), None]);
+
+        let from = file.select_stmt().unwrap().from_clause().unwrap();
+        let tables: Vec<_> = from.table_refs().map(|t| (t.table_name(), t.alias())).collect();
+        assert_eq!(
+            tables,
+            vec![
+                (Some("cross".to_string()), Some("outer".to_string())),
+                (Some("t".to_string()), None),
+            ]
+        );
+        assert_eq!(from.joins().next().unwrap().join_type(), JoinType::Left);
+    }
+
+    #[test]
+    fn test_join_missing_condition() {
+        let parse = parse("SELECT * FROM a JOIN b WHERE a.id = 1");
+        assert_eq!(parse.errors.len(), 1);
+        assert!(parse.errors[0].message.contains("Expected ON or USING"));
+    }
+
+    #[test]
+    fn test_with_clause() {
+        let file = parse_ok(
+            "WITH events AS (\n\
+               SELECT user_id, event_time FROM smelt.ref('raw_events')\n\
+             ),\n\
+             daily(user_id, events) AS (SELECT user_id, COUNT(*) FROM events GROUP BY user_id)\n\
+             SELECT user_id, events FROM daily",
+        );
+
+        let with_clause = file.with_clause().unwrap();
+        assert!(!with_clause.is_recursive());
+
+        let ctes: Vec<_> = with_clause.ctes().collect();
+        assert_eq!(ctes.len(), 2);
+        assert_eq!(ctes[0].name().as_deref(), Some("events"));
+        assert!(ctes[0].column_names().is_empty());
+        assert_eq!(ctes[1].name().as_deref(), Some("daily"));
+        assert_eq!(ctes[1].column_names(), vec!["user_id", "events"]);
+
+        // The main SELECT is the one after the WITH clause
+        let select = file.select_stmt().unwrap();
+        let table = select.from_clause().unwrap().table_refs().next().unwrap();
+        assert_eq!(table.table_name().as_deref(), Some("daily"));
+
+        // CTE names resolve from the main query and from later CTEs
+        assert!(select.find_cte("daily").is_some());
+        let daily_body = ctes[1].select_stmt().unwrap();
+        assert_eq!(daily_body.find_cte("events").unwrap().name().as_deref(), Some("events"));
+        assert!(select.find_cte("missing").is_none());
+
+        // Refs inside CTE bodies are still found
+        let refs: Vec<_> = file.refs().filter_map(|r| r.model_name()).collect();
+        assert_eq!(refs, vec!["raw_events"]);
+    }
+
+    #[test]
+    fn test_recursive_cte() {
+        let file = parse_ok(
+            "WITH RECURSIVE numbers(n) AS (\n\
+               SELECT 1\n\
+               UNION ALL\n\
+               SELECT n + 1 FROM numbers WHERE n < 10\n\
+             )\n\
+             SELECT n FROM numbers",
+        );
+
+        let with_clause = file.with_clause().unwrap();
+        assert!(with_clause.is_recursive());
+
+        let cte = with_clause.ctes().next().unwrap();
+        assert_eq!(cte.column_names(), vec!["n"]);
+
+        // The anchor member defines the CTE's columns
+        let anchor = cte.select_stmt().unwrap();
+        assert!(anchor.from_clause().is_none());
+    }
+
+    #[test]
+    fn test_recursive_as_name() {
+        let file = parse_ok(
+            "WITH recursive AS (SELECT 1 AS recursive) \
+             SELECT r.recursive FROM recursive AS r",
+        );
+        let with = file.with_clause().unwrap();
+        assert!(!with.is_recursive());
+        assert_eq!(with.ctes().next().unwrap().name().as_deref(), Some("recursive"));
+        let (columns, _) = select_names(&file);
+        assert_eq!(columns, vec!["recursive"]);
+
+        let file = parse_ok("WITH RECURSIVE recursive (n) AS (SELECT 1) SELECT n FROM recursive");
+        let with = file.with_clause().unwrap();
+        assert!(with.is_recursive());
+        assert_eq!(with.ctes().next().unwrap().name().as_deref(), Some("recursive"));
+    }
+
+    #[test]
+    fn test_cte_missing_query() {
+        let parse = parse("WITH x AS (1) SELECT * FROM x");
+        assert!(!parse.errors.is_empty());
+        assert!(parse.errors[0].message.contains("Expected query"));
+    }
+
+    #[test]
+    fn test_left_function_is_not_a_join() {
+        let file = parse_ok("SELECT LEFT(name, 3) AS prefix FROM users");
+        let item = file.select_stmt().unwrap().select_list().unwrap().items().next().unwrap();
+        let func = item.expression().unwrap().as_function_call().unwrap();
+        assert_eq!(func.name().as_deref(), Some("LEFT"));
+    }
+    #[test]
+    fn test_aggregate_clauses() {
+        let file = parse_ok(
+            "SELECT DISTINCT customer_id, COUNT(DISTINCT order_id) AS orders\n\
+             FROM orders\n\
+             WHERE status = 'complete'\n\
+             GROUP BY customer_id\n\
+             HAVING COUNT(*) > 1",
+        );
+        let select = file.select_stmt().unwrap();
+        assert!(select.is_distinct());
+        assert!(select.where_clause().unwrap().expression().is_some());
+        assert_eq!(select.group_by_clause().unwrap().expressions().count(), 1);
+        assert!(select.having_clause().is_some());
+
+        let items: Vec<_> = select.select_list().unwrap().items().collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].alias().as_deref(), Some("orders"));
+    }
+
+    #[test]
+    fn test_order_by_and_limit() {
+        let file = parse_ok(
+            "SELECT id, created_at FROM events \
+             ORDER BY created_at DESC NULLS LAST, id \
+             LIMIT 10 OFFSET 20",
+        );
+        let select = file.select_stmt().unwrap();
+
+        let items: Vec<_> = select.order_by_clause().unwrap().items().collect();
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_descending());
+        assert_eq!(items[0].nulls_first(), Some(false));
+        assert!(!items[1].is_descending());
+        assert_eq!(items[1].nulls_first(), None);
+
+        assert_eq!(select.limit_clause().unwrap().count(), Some(10));
+        assert_eq!(select.offset_clause().unwrap().count(), Some(20));
+    }
+
+    #[test]
+    fn test_offset_before_limit() {
+        let file = parse_ok("SELECT id FROM events OFFSET 5 ROWS LIMIT ALL");
+        let select = file.select_stmt().unwrap();
+        assert_eq!(select.offset_clause().unwrap().count(), Some(5));
+        assert!(select.limit_clause().unwrap().expression().is_none());
+    }
+
+    #[test]
+    fn test_qualify_and_distinct_on() {
+        let file = parse_ok(
+            "SELECT DISTINCT ON (user_id) user_id, ts FROM sessions \
+             QUALIFY ts > 0 \
+             ORDER BY user_id",
+        );
+        let select = file.select_stmt().unwrap();
+        assert!(select.is_distinct());
+        assert!(select.qualify_clause().unwrap().expression().is_some());
+        assert!(select.order_by_clause().is_some());
+    }
+
+    #[test]
+    fn test_order_by_on_union() {
+        let parse = parse("SELECT a FROM x UNION ALL SELECT a FROM y ORDER BY a LIMIT 5");
+        assert!(parse.errors.is_empty());
+        let compound = parse
+            .syntax()
+            .children()
+            .find_map(crate::ast::CompoundSelect::cast)
+            .unwrap();
+        assert_eq!(compound.select_stmts().count(), 2);
+        assert!(compound.order_by_clause().is_some());
+        assert_eq!(compound.limit_clause().unwrap().count(), Some(5));
+        // The modifiers belong to the compound, not the last operand
+        assert!(compound.select_stmts().all(|s| s.order_by_clause().is_none()));
+    }
+
+    #[test]
+    fn test_nulls_and_distinct_as_names() {
+        let file = parse_ok(
+            "SELECT distinct, nulls, t.distinct AS nulls, COUNT(distinct) \
+             FROM t ORDER BY nulls DESC NULLS LAST",
+        );
+        let (columns, aliases) = select_names(&file);
+        assert_eq!(columns, vec!["distinct", "nulls", "distinct", "distinct", "nulls"]);
+        assert_eq!(aliases[2].as_deref(), Some("nulls"));
+        let select = file.select_stmt().unwrap();
+        assert!(!select.is_distinct());
+        let item = select.order_by_clause().unwrap().items().next().unwrap();
+        assert_eq!(item.nulls_first(), Some(false));
+
+        // Still modifiers when followed by an expression
+        let file = parse_ok("SELECT DISTINCT nulls, COUNT(DISTINCT distinct) FROM t");
+        assert!(file.select_stmt().unwrap().is_distinct());
+        let (columns, _) = select_names(&file);
+        assert_eq!(columns, vec!["nulls", "distinct"]);
+    }
+
+    #[test]
+    fn test_nulls_requires_first_or_last() {
+        let parse = parse("SELECT a FROM x ORDER BY a NULLS");
+        assert!(parse
+            .errors
+            .iter()
+            .any(|e| e.message.contains("Expected FIRST or LAST")));
+    }
+    #[test]
+    fn test_set_operations() {
+        let parse = parse(
+            "SELECT a FROM x UNION SELECT a FROM y \
+             INTERSECT ALL SELECT a FROM z \
+             EXCEPT SELECT a FROM w",
+        );
+        assert!(parse.errors.is_empty(), "unexpected errors: {:?}", parse.errors);
+        let compound = parse
+            .syntax()
+            .children()
+            .find_map(crate::ast::CompoundSelect::cast)
+            .unwrap();
+
+        assert_eq!(compound.select_stmts().count(), 4);
+        assert_eq!(
+            compound.operators(),
+            vec![
+                (SetOperator::Union, false),
+                (SetOperator::Intersect, true),
+                (SetOperator::Except, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parenthesized_set_operands() {
+        let compound = |file: &File| {
+            file.syntax()
+                .children()
+                .find_map(crate::ast::CompoundSelect::cast)
+                .unwrap()
+        };
+        let first_columns = |compound: &crate::ast::CompoundSelect| {
+            compound
+                .select_stmts()
+                .map(|s| s.column_refs()[0].name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let file = parse_ok("SELECT a FROM t UNION (SELECT b FROM u)");
+        assert_eq!(first_columns(&compound(&file)), vec!["a", "b"]);
+
+        // Each operand keeps its own ORDER BY / LIMIT; the last ones apply to the whole
+        let file = parse_ok(
+            "(SELECT a FROM t) UNION ALL (SELECT b FROM u ORDER BY b LIMIT 1) ORDER BY a",
+        );
+        let query = compound(&file);
+        assert_eq!(first_columns(&query), vec!["a", "b"]);
+        assert_eq!(query.operators(), vec![(SetOperator::Union, true)]);
+        assert!(query.order_by_clause().is_some());
+        assert!(query.limit_clause().is_none());
+        assert_eq!(file.select_stmt().unwrap().column_refs()[0].name(), "a");
+
+        // Nested operands, as a derived table
+        let file = parse_ok(
+            "SELECT * FROM (((SELECT a FROM t) UNION SELECT b FROM u) EXCEPT SELECT c FROM v) AS x",
+        );
+        assert_eq!(file.select_stmts().count(), 4);
+
+        // A query in parentheses on its own
+        let file = parse_ok("(SELECT a FROM t)");
+        assert_eq!(file.select_stmt().unwrap().column_refs()[0].name(), "a");
+    }
+
+    #[test]
+    fn test_derived_table() {
+        let file = parse_ok(
+            "SELECT s.id FROM (SELECT id FROM smelt.ref('users') ORDER BY id LIMIT 10) AS s \
+             JOIN (SELECT id FROM a UNION ALL SELECT id FROM b) t ON s.id = t.id",
+        );
+        let from = file.select_stmt().unwrap().from_clause().unwrap();
+        let table_refs: Vec<_> = from.table_refs().collect();
+
+        assert_eq!(table_refs[0].table_name(), None);
+        assert_eq!(table_refs[0].alias().as_deref(), Some("s"));
+        let inner = table_refs[0].subquery().unwrap().select_stmt().unwrap();
+        assert_eq!(inner.limit_clause().unwrap().count(), Some(10));
+
+        assert_eq!(table_refs[1].alias().as_deref(), Some("t"));
+        assert!(table_refs[1].subquery().unwrap().compound_select().is_some());
+
+        let refs: Vec<_> = file.refs().filter_map(|r| r.model_name()).collect();
+        assert_eq!(refs, vec!["users"]);
+    }
+
+    #[test]
+    fn test_expression_subqueries() {
+        let parse = parse(
+            "SELECT id, (SELECT MAX(ts) FROM events) AS latest FROM users \
+             WHERE id IN (SELECT user_id FROM smelt.ref('orders')) \
+             AND status NOT IN ('deleted', 'banned') \
+             AND NOT EXISTS (SELECT 1 FROM smelt.ref('bans') b WHERE b.id = users.id)",
+        );
+        assert!(parse.errors.is_empty(), "unexpected errors: {:?}", parse.errors);
+        let file = File::cast(parse.syntax()).unwrap();
+
+        let in_exprs: Vec<_> = parse
+            .syntax()
+            .descendants()
+            .filter_map(crate::ast::InExpr::cast)
+            .collect();
+        assert_eq!(in_exprs.len(), 2);
+        assert!(in_exprs[0].subquery().is_some());
+        assert!(!in_exprs[0].is_negated());
+        assert!(in_exprs[1].is_negated());
+        assert_eq!(in_exprs[1].values().count(), 2);
+
+        let exists = parse
+            .syntax()
+            .descendants()
+            .find_map(crate::ast::ExistsExpr::cast)
+            .unwrap();
+        assert!(exists.is_negated());
+        assert!(exists.subquery().unwrap().select_stmt().is_some());
+
+        let refs: Vec<_> = file.refs().filter_map(|r| r.model_name()).collect();
+        assert_eq!(refs, vec!["orders", "bans"]);
+
+        // The scalar subquery needs an alias to name its column
+        let items: Vec<_> = file.select_stmt().unwrap().select_list().unwrap().items().collect();
+        assert_eq!(items[1].column_name().as_deref(), Some("latest"));
+    }
+
+    #[test]
+    fn test_subquery_missing_query() {
+        let parse = parse("SELECT * FROM (1) x");
+        assert!(parse.errors.iter().any(|e| e.message.contains("Expected query in subquery")));
+    }
+    /// Parse a single expression via `SELECT <expr>` and return it
+    fn parse_expr(input: &str) -> crate::ast::Expr {
+        let file = parse_ok(&format!("SELECT {}", input));
+        let item = file.select_stmt().unwrap().select_list().unwrap().items().next().unwrap();
+        item.expression().unwrap()
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        // a + (b * c)
+        let expr = parse_expr("a + b * c");
+        let add = expr.as_binary_expr().unwrap();
+        assert_eq!(add.op(), Some(PLUS));
+        assert_eq!(add.lhs().unwrap().text(), "a");
+        assert_eq!(add.rhs().unwrap().as_binary_expr().unwrap().op(), Some(STAR));
+
+        // (a OR (b AND c))
+        let expr = parse_expr("a = 1 OR b = 2 AND c = 3");
+        let or = expr.as_binary_expr().unwrap();
+        assert_eq!(or.op(), Some(OR_KW));
+        assert_eq!(or.rhs().unwrap().as_binary_expr().unwrap().op(), Some(AND_KW));
+
+        // Parentheses override precedence; % and || are operators
+        let expr = parse_expr("(a + b) % 7 || 'x'");
+        let concat = expr.as_binary_expr().unwrap();
+        assert_eq!(concat.op(), Some(CONCAT));
+        let modulo = concat.lhs().unwrap().as_binary_expr().unwrap();
+        assert_eq!(modulo.op(), Some(PERCENT));
+        assert_eq!(modulo.lhs().unwrap().as_binary_expr().unwrap().op(), Some(PLUS));
+    }
+
+    #[test]
+    fn test_unary_expressions() {
+        let expr = parse_expr("-amount * 2");
+        let mul = expr.as_binary_expr().unwrap();
+        assert_eq!(mul.lhs().unwrap().as_unary_expr().unwrap().op(), Some(MINUS));
+
+        // NOT binds looser than comparison: NOT (a = b)
+        let expr = parse_expr("NOT a = b");
+        let not = expr.as_unary_expr().unwrap();
+        assert_eq!(not.op(), Some(NOT_KW));
+        assert!(not.operand().unwrap().as_binary_expr().is_some());
+    }
+
+    #[test]
+    fn test_case_expression() {
+        let expr = parse_expr(
+            "CASE WHEN amount > 100 THEN 'large' WHEN amount > 10 THEN 'medium' ELSE 'small' END",
+        );
+        let case = expr.as_case_expr().unwrap();
+        assert!(case.operand().is_none());
+        let whens: Vec<_> = case.whens().collect();
+        assert_eq!(whens.len(), 2);
+        assert_eq!(whens[0].condition().unwrap().text(), "amount > 100");
+        assert_eq!(whens[1].result().unwrap().text(), "'medium'");
+        assert_eq!(case.else_expr().unwrap().text(), "'small'");
+
+        let case = parse_expr("CASE status WHEN 'a' THEN 1 END").as_case_expr().unwrap();
+        assert_eq!(case.operand().unwrap().text(), "status");
+        assert!(case.else_expr().is_none());
+    }
+
+    #[test]
+    fn test_casts() {
+        let cast = parse_expr("CAST(amount AS DECIMAL(10, 2))").as_cast_expr().unwrap();
+        assert_eq!(cast.expression().unwrap().text(), "amount");
+        assert_eq!(cast.type_name().unwrap().name(), "DECIMAL");
+
+        let cast = parse_expr("TRY_CAST(x AS DOUBLE PRECISION)").as_cast_expr().unwrap();
+        assert_eq!(cast.type_name().unwrap().name(), "DOUBLE PRECISION");
+
+        // :: binds tighter than arithmetic, and a following identifier is the alias
+        let file = parse_ok("SELECT a + b::int total FROM t");
+        let item = file.select_stmt().unwrap().select_list().unwrap().items().next().unwrap();
+        assert_eq!(item.column_name().as_deref(), Some("total"));
+        let add = item.expression().unwrap().as_binary_expr().unwrap();
+        let cast = add.rhs().unwrap().as_cast_expr().unwrap();
+        assert_eq!(cast.type_name().unwrap().name(), "INT");
+    }
+
+    #[test]
+    fn test_predicates() {
+        let is = parse_expr("deleted_at IS NOT NULL").as_is_expr().unwrap();
+        assert!(is.is_negated());
+        assert_eq!(is.test(), Some(crate::ast::IsTest::Null));
+
+        let between = parse_expr("ts NOT BETWEEN 1 AND 10 AND flag").as_binary_expr().unwrap();
+        assert_eq!(between.op(), Some(AND_KW));
+        let between = between.lhs().unwrap().as_between_expr().unwrap();
+        assert!(between.is_negated());
+        assert_eq!(between.low().unwrap().text(), "1");
+        assert_eq!(between.high().unwrap().text(), "10");
+
+        let like = parse_expr("name NOT ILIKE 'a%' ESCAPE '!'").as_like_expr().unwrap();
+        assert!(like.is_negated());
+        assert!(like.is_case_insensitive());
+        assert_eq!(like.pattern().unwrap().text(), "'a%'");
+
+        let in_expr = parse_expr("status IN ('a', 'b', 'c')").as_in_expr().unwrap();
+        assert_eq!(in_expr.expression().unwrap().text(), "status");
+        assert_eq!(in_expr.values().count(), 3);
+    }
+
+    #[test]
+    fn test_literals() {
+        use crate::ast::LiteralKind;
+
+        let kinds: Vec<_> = ["'text'", "42", "3.14", "TRUE", "false", "NULL"]
+            .iter()
+            .map(|input| parse_expr(input).as_literal().unwrap().kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LiteralKind::String,
+                LiteralKind::Number,
+                LiteralKind::Number,
+                LiteralKind::Boolean,
+                LiteralKind::Boolean,
+                LiteralKind::Null,
+            ]
+        );
+
+        let date = parse_expr("DATE '2024-01-01'").as_typed_literal().unwrap();
+        assert_eq!(date.type_name().as_deref(), Some("DATE"));
+        assert_eq!(date.value_text().as_deref(), Some("'2024-01-01'"));
+
+        let interval = parse_expr("INTERVAL 30 MINUTE").as_typed_literal().unwrap();
+        assert_eq!(interval.type_name().as_deref(), Some("INTERVAL"));
+        assert_eq!(interval.value_text().as_deref(), Some("30"));
+        assert_eq!(interval.unit().as_deref(), Some("MINUTE"));
+
+        // A column named `date` is still a column
+        assert!(parse_expr("date").as_column_ref().is_some());
+    }
+
+    #[test]
+    fn test_expression_column_refs() {
+        let expr = parse_expr(
+            "CASE WHEN o.amount > 0 THEN o.amount ELSE refund END \
+             + (SELECT MAX(x) FROM t)",
+        );
+        let refs: Vec<_> = expr
+            .column_refs()
+            .iter()
+            .map(|r| (r.qualifier().map(str::to_string), r.name().to_string()))
+            .collect();
+
+        // References inside the subquery are not part of this expression's scope
+        assert_eq!(
+            refs,
+            vec![
+                (Some("o".to_string()), "amount".to_string()),
+                (Some("o".to_string()), "amount".to_string()),
+                (None, "refund".to_string()),
+            ]
+        );
+        assert!(expr.as_column_ref().is_none());
+        assert_eq!(parse_expr("(o.amount)").as_column_ref().unwrap().name(), "amount");
+    }
+
+    #[test]
+    fn test_case_missing_end() {
+        let parse = parse("SELECT CASE WHEN a THEN 1 FROM t");
+        assert!(parse.errors.iter().any(|e| e.message.contains("END_KW")));
+    }
+    #[test]
+    fn test_window_functions() {
+        let file = parse_ok(
+            "SELECT\n\
+               user_id,\n\
+               LAG(event_time) OVER (PARTITION BY user_id ORDER BY event_time) AS prev_event_time,\n\
+               SUM(CASE\n\
+                 WHEN prev_event_time IS NULL OR event_time - prev_event_time > INTERVAL 30 MINUTE\n\
+                 THEN 1 ELSE 0\n\
+               END) OVER (PARTITION BY user_id ORDER BY event_time) AS session_id,\n\
+               ROW_NUMBER() OVER () AS event_id\n\
+             FROM smelt.ref('events')",
+        );
+
+        let windows: Vec<_> = file.window_functions().collect();
+        let names: Vec<_> = windows.iter().filter_map(|f| f.name()).collect();
+        assert_eq!(names, vec!["LAG", "SUM", "ROW_NUMBER"]);
+
+        let spec = windows[0].over_clause().unwrap().window_spec().unwrap();
+        let partition: Vec<_> = spec.partition_by().iter().map(|e| e.text()).collect();
+        assert_eq!(partition, vec!["user_id"]);
+        assert_eq!(spec.order_by_clause().unwrap().items().count(), 1);
+        assert!(spec.frame().is_none());
+
+        let empty = windows[2].over_clause().unwrap().window_spec().unwrap();
+        assert!(empty.partition_by().is_empty());
+        assert!(empty.order_by_clause().is_none());
+
+        // Aliases still name the columns
+        let columns: Vec<_> = file
+            .select_stmt()
+            .unwrap()
+            .select_list()
+            .unwrap()
+            .items()
+            .filter_map(|i| i.column_name())
+            .collect();
+        assert_eq!(columns, vec!["user_id", "prev_event_time", "session_id", "event_id"]);
+    }
+
+    #[test]
+    fn test_window_frames() {
+        use crate::ast::{FrameBound, FrameUnit};
+
+        let file = parse_ok(
+            "SELECT \
+               SUM(x) OVER (ORDER BY ts ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW), \
+               AVG(x) OVER (ORDER BY ts RANGE BETWEEN INTERVAL 1 DAY PRECEDING AND 2 FOLLOWING), \
+               MAX(x) OVER (ORDER BY ts ROWS 3 PRECEDING) \
+             FROM t",
+        );
+        let frames: Vec<_> = file
+            .window_functions()
+            .map(|f| f.over_clause().unwrap().window_spec().unwrap().frame().unwrap())
+            .collect();
+
+        assert_eq!(frames[0].unit(), Some(FrameUnit::Rows));
+        assert_eq!(frames[0].start(), Some(FrameBound::UnboundedPreceding));
+        assert_eq!(frames[0].end(), Some(FrameBound::CurrentRow));
+
+        assert_eq!(frames[1].unit(), Some(FrameUnit::Range));
+        match frames[1].start() {
+            Some(FrameBound::Preceding(offset)) => assert_eq!(offset.text(), "INTERVAL 1 DAY"),
+            other => panic!("unexpected start bound: {:?}", other),
         }
+        assert!(matches!(frames[1].end(), Some(FrameBound::Following(_))));
+
+        // Without BETWEEN the frame ends at the current row
+        assert!(matches!(frames[2].start(), Some(FrameBound::Preceding(_))));
+        assert_eq!(frames[2].end(), Some(FrameBound::CurrentRow));
+    }
+
+    #[test]
+    fn test_named_windows() {
+        let file = parse_ok(
+            "SELECT \
+               RANK() OVER w AS r, \
+               SUM(x) OVER (w ROWS 1 PRECEDING) AS s \
+             FROM t \
+             WINDOW w AS (PARTITION BY a ORDER BY b DESC) \
+             QUALIFY r = 1",
+        );
+        let select = file.select_stmt().unwrap();
+        let window = select.window_clause().unwrap().find("W").unwrap();
+        assert_eq!(window.name().as_deref(), Some("w"));
+        assert_eq!(window.window_spec().unwrap().partition_by().len(), 1);
+        assert!(select.qualify_clause().is_some());
+
+        let calls: Vec<_> = file.window_functions().collect();
+        let over = calls[0].over_clause().unwrap();
+        assert_eq!(over.window_name().as_deref(), Some("w"));
+        assert!(over.window_spec().is_none());
+
+        let spec = calls[1].over_clause().unwrap().window_spec().unwrap();
+        assert_eq!(spec.base_window_name().as_deref(), Some("w"));
+        assert!(spec.frame().is_some());
+    }
+
+    #[test]
+    fn test_window_keywords_as_names() {
+        let file = parse_ok("SELECT partition FROM t");
+        assert_eq!(select_names(&file).0, vec!["partition"]);
+
+        let file = parse_ok("SELECT t.window FROM t");
+        assert_eq!(select_names(&file).0, vec!["window"]);
+
+        let file = parse_ok("SELECT x AS over FROM t");
+        assert_eq!(select_names(&file).1, vec![Some("over".to_string())]);
+
+        // Alongside the window syntax they name
+        let file = parse_ok(
+            "SELECT SUM(over) OVER window AS partition FROM window \
+             WHERE partition > 0 \
+             WINDOW window AS (PARTITION BY partition ORDER BY over)",
+        );
+        let select = file.select_stmt().unwrap();
+        let (columns, aliases) = select_names(&file);
+        assert_eq!(columns, vec!["over", "partition", "partition", "over"]);
+        assert_eq!(aliases, vec![Some("partition".to_string())]);
+        let window = select.window_clause().unwrap().definitions().next().unwrap();
+        assert_eq!(window.name().as_deref(), Some("window"));
+        let table = select.from_clause().unwrap().table_refs().next().unwrap();
+        assert_eq!(table.table_name().as_deref(), Some("window"));
+    }
+
+    #[test]
+    fn test_window_frame_errors() {
+        let parse = parse("SELECT SUM(x) OVER (ORDER BY ts ROWS 3) FROM t");
+        assert!(parse
+            .errors
+            .iter()
+            .any(|e| e.message.contains("Expected PRECEDING or FOLLOWING")));
+    }
+    #[test]
+    fn test_aggregate_argument_forms() {
+        let file = parse_ok(
+            "SELECT FIRST(country ORDER BY event_time) AS country, \
+                    EXTRACT(HOUR FROM MIN(event_time)) AS hour \
+             FROM sessions",
+        );
+        let items: Vec<_> = file.select_stmt().unwrap().select_list().unwrap().items().collect();
+        let first = items[0].expression().unwrap().as_function_call().unwrap();
+        assert_eq!(first.args().count(), 1);
+        let extract = items[1].expression().unwrap().as_function_call().unwrap();
+        assert_eq!(extract.args().count(), 2);
+    }
+    #[test]
+    fn test_quoted_identifiers() {
+        let file = parse_ok(
+            "SELECT \"Order Id\", o.`amount` AS `Total \"USD\"`, \"say \"\"hi\"\"\" \
+             FROM \"Raw Schema\".\"Orders\" o",
+        );
+        let select = file.select_stmt().unwrap();
+        let names: Vec<_> = select
+            .select_list()
+            .unwrap()
+            .items()
+            .filter_map(|i| i.column_name())
+            .collect();
+        assert_eq!(names, vec!["Order Id", "Total \"USD\"", "say \"hi\""]);
+
+        let table_ref = select.from_clause().unwrap().table_refs().next().unwrap();
+        assert_eq!(table_ref.table_name().as_deref(), Some("Raw Schema.Orders"));
+        assert_eq!(table_ref.alias().as_deref(), Some("o"));
+
+        // A double-quoted model name is a quoted identifier but still names the model
+        let file = parse_ok("SELECT * FROM smelt.ref(\"users\")");
+        let refs: Vec<_> = file.refs().filter_map(|r| r.model_name()).collect();
+        assert_eq!(refs, vec!["users"]);
+    }
+
+    #[test]
+    fn test_comments_and_strings_in_queries() {
+        let file = parse_ok(
+            "/* header /* nested */ */\n\
+             SELECT 'it''s' AS quote, $$multi\nline$$ AS body /* trailing */\n\
+             FROM smelt.ref('users')",
+        );
+        let refs: Vec<_> = file.refs().filter_map(|r| r.model_name()).collect();
+        assert_eq!(refs, vec!["users"]);
+
+        let names: Vec<_> = file
+            .select_stmt()
+            .unwrap()
+            .select_list()
+            .unwrap()
+            .items()
+            .filter_map(|i| i.column_name())
+            .collect();
+        assert_eq!(names, vec!["quote", "body"]);
+    }
+
+    #[test]
+    fn test_model_statement_terminator() {
+        let file = parse_ok("SELECT id FROM smelt.ref('users');\n-- trailing comment\n");
+        assert!(file.select_stmt().is_some());
+        assert_eq!(file.statements().count(), 0);
+
+        // A second statement in a model is an error rather than silently ignored
+        let parse = parse("SELECT 1; SELECT 2");
+        assert_eq!(parse.errors.len(), 1);
+        assert_eq!(parse.errors[0].message, "Expected end of model after statement");
+        assert_eq!(parse.syntax().text().to_string(), "SELECT 1; SELECT 2");
+    }
+
+    #[test]
+    fn test_parse_statements() {
+        let input = "-- Create source schema\n\
+                     CREATE SCHEMA IF NOT EXISTS source;\n\
+                     ;\n\
+                     CREATE TABLE source.events AS SELECT 'a;b' AS x;\n\
+                     WITH t AS (SELECT 1 AS id) SELECT id FROM t;\n\
+                     INSERT INTO source.events VALUES ('c')";
+        let parse = parse_statements(input);
+        assert!(parse.errors.is_empty(), "unexpected errors: {:?}", parse.errors);
+        assert_eq!(parse.syntax().text().to_string(), input);
+
+        let file = File::cast(parse.syntax()).unwrap();
+        let statements: Vec<_> = file.statements().collect();
+        assert_eq!(statements.len(), 4);
+
+        let keywords: Vec<_> = statements.iter().filter_map(|s| s.keyword()).collect();
+        assert_eq!(keywords, vec!["CREATE", "CREATE", "WITH", "INSERT"]);
+        assert_eq!(statements[0].text(), "CREATE SCHEMA IF NOT EXISTS source");
+        assert_eq!(statements[1].text(), "CREATE TABLE source.events AS SELECT 'a;b' AS x");
+
+        assert!(!statements[0].is_query());
+        assert!(statements[2].is_query());
+        let select = statements[2].select_stmt().unwrap();
+        assert!(select.find_cte("t").is_some());
+    }
+
+    #[test]
+    fn test_parse_statements_recovery() {
+        // An unparseable tail of a query is reported and skipped up to the `;`
+        let parse = parse_statements("SELECT 1 garbage garbage; CREATE TABLE t (id INT);");
+        assert_eq!(parse.errors.len(), 1);
+        let file = File::cast(parse.syntax()).unwrap();
+        let keywords: Vec<_> = file.statements().filter_map(|s| s.keyword()).collect();
+        assert_eq!(keywords, vec!["SELECT", "CREATE"]);
+    }
+
+    #[test]
+    fn test_ref_named_params() {
+        let file = parse_ok(
+            "SELECT * FROM smelt.ref('events', filter => kind = 'click' AND id > 1, limit => 10, sample => 0.5) e",
+        );
+        let ref_call = file.refs().next().unwrap();
+        let params: Vec<_> = ref_call
+            .named_params()
+            .map(|p| (p.name().unwrap(), p.value().unwrap().text()))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("filter".to_string(), "kind = 'click' AND id > 1".to_string()),
+                ("limit".to_string(), "10".to_string()),
+                ("sample".to_string(), "0.5".to_string()),
+            ]
+        );
+        assert_eq!(ref_call.table_ref().unwrap().alias().as_deref(), Some("e"));
+
+        // Parameters of a ref nested in a filter belong to the nested call
+        let file = parse_ok(
+            "SELECT * FROM smelt.ref('events', filter => id IN (SELECT id FROM smelt.ref('users', limit => 5)))",
+        );
+        let counts: Vec<_> = file.refs().map(|r| r.named_params().count()).collect();
+        assert_eq!(counts, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_source_calls() {
+        let file = parse_ok(
+            "SELECT * FROM smelt.source('raw', 'users') u JOIN smelt.source(\"raw\", 'order''s') o ON u.id = o.user_id",
+        );
+        let sources: Vec<_> = file.sources().collect();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].qualified_name().as_deref(), Some("raw.users"));
+        assert!(sources[0].is_well_formed());
+        assert_eq!(sources[0].table_ref().unwrap().alias().as_deref(), Some("u"));
+        assert_eq!(sources[1].table_name().as_deref(), Some("order's"));
+        assert_eq!(sources[1].schema_name().as_deref(), Some("raw"));
+
+        // smelt.source calls are not refs
+        assert_eq!(file.refs().count(), 0);
+
+        let file = parse_ok("SELECT * FROM smelt.source('raw')");
+        let source = file.sources().next().unwrap();
+        assert_eq!(source.schema_name().as_deref(), Some("raw"));
+        assert_eq!(source.table_name(), None);
+        assert!(!source.is_well_formed());
+    }
+
+    #[test]
+    fn test_wildcard_modifiers() {
+        let file = parse_ok(
+            "SELECT * EXCLUDE (a, \"B\") REPLACE (a + 1 AS c), u.*, x.* EXCLUDE y, id exclude \
+             FROM t, u",
+        );
+        let items: Vec<_> = file
+            .select_stmt()
+            .unwrap()
+            .select_list()
+            .unwrap()
+            .items()
+            .collect();
+        assert_eq!(items.len(), 4);
+
+        let star = items[0].wildcard().unwrap();
+        assert_eq!(star.qualifier(), None);
+        assert_eq!(star.excluded_columns(), vec!["a", "B"]);
+        let replacements = star.replacements();
+        assert_eq!(replacements.len(), 1);
+        assert_eq!(replacements[0].column_name().as_deref(), Some("c"));
+        assert_eq!(items[0].alias(), None);
+
+        assert_eq!(items[1].wildcard().unwrap().qualifier().as_deref(), Some("u"));
+        assert_eq!(items[2].wildcard().unwrap().excluded_columns(), vec!["y"]);
+
+        // EXCLUDE is only a modifier after a wildcard
+        assert!(items[3].wildcard().is_none());
+        assert_eq!(items[3].alias().as_deref(), Some("exclude"));
+    }
+
+    #[test]
+    fn test_select_scope_column_refs() {
+        let file = parse_ok(
+            "SELECT a.id, EXTRACT(YEAR FROM a.created) AS y, (SELECT MAX(b.id) FROM b WHERE b.id = a.id) \
+             FROM smelt.ref('a', filter => kind = 'x') a \
+             JOIN (SELECT id FROM c) c ON c.id = a.id \
+             WHERE a.flag ORDER BY y",
+        );
+        let outer = file.select_stmt().unwrap();
+        let names: Vec<_> = outer
+            .column_refs()
+            .iter()
+            .map(|c| format!("{}.{}", c.qualifier().unwrap_or(""), c.name()))
+            .collect();
+        assert_eq!(
+            names,
+            vec!["a.id", "a.created", "c.id", "a.id", "a.flag", ".y"]
+        );
+
+        // The scalar subquery sees the outer query; the derived table does not
+        let selects: Vec<_> = file.select_stmts().collect();
+        assert_eq!(selects.len(), 3);
+        assert_eq!(selects[1].enclosing_selects().len(), 1);
+        assert!(selects[2].enclosing_selects().is_empty());
+    }
+
+    #[test]
+    fn test_select_scope_function_calls() {
+        let file = parse_ok(
+            "SELECT DISTINCT day, SUM(ABS(x)), COUNT(DISTINCT id), (SELECT MAX(y) FROM b) \
+             FROM smelt.ref('a') WHERE day > CURRENT_DATE - 1",
+        );
+        let outer = file.select_stmt().unwrap();
+        let calls = outer.function_calls();
+        let names: Vec<_> = calls.iter().filter_map(|f| f.name()).collect();
+        assert_eq!(names, vec!["SUM", "ABS", "COUNT"]);
+        assert!(!calls[0].is_distinct());
+        assert!(calls[2].is_distinct());
+        assert_eq!(calls[2].range(), TextRange::new(34.into(), 52.into()));
+
+        let distinct = outer.distinct_range().unwrap();
+        assert_eq!(distinct, TextRange::new(7.into(), 15.into()));
     }
 }
@@ -13,6 +13,48 @@ pub enum SyntaxKind {
     AND_KW,
     OR_KW,
     NOT_KW,
+    JOIN_KW,
+    INNER_KW,
+    LEFT_KW,
+    RIGHT_KW,
+    FULL_KW,
+    OUTER_KW,
+    CROSS_KW,
+    ON_KW,
+    USING_KW,
+    WITH_KW,
+    RECURSIVE_KW,
+    UNION_KW,
+    ALL_KW,
+    HAVING_KW,
+    QUALIFY_KW,
+    ORDER_KW,
+    ASC_KW,
+    DESC_KW,
+    NULLS_KW,
+    LIMIT_KW,
+    OFFSET_KW,
+    DISTINCT_KW,
+    INTERSECT_KW,
+    EXCEPT_KW,
+    IN_KW,
+    EXISTS_KW,
+    CASE_KW,
+    WHEN_KW,
+    THEN_KW,
+    ELSE_KW,
+    END_KW,
+    CAST_KW,
+    IS_KW,
+    NULL_KW,
+    TRUE_KW,
+    FALSE_KW,
+    BETWEEN_KW,
+    LIKE_KW,
+    ILIKE_KW,
+    OVER_KW,
+    PARTITION_KW,
+    WINDOW_KW,
 
     // Operators & punctuation
     LPAREN,   // (
@@ -21,7 +63,7 @@ pub enum SyntaxKind {
     DOT,      // .
     STAR,     // *
     EQ,       // =
-    NE,       // !=
+    NE,       // != or <>
     LT,       // <
     GT,       // >
     LE,       // <=
@@ -30,14 +72,19 @@ pub enum SyntaxKind {
     MINUS,    // -
     MULTIPLY, // * (same as STAR, but in expression context)
     DIVIDE,   // /
+    PERCENT,  // %
+    CONCAT,   // ||
+    COLON_COLON, // ::
+    SEMICOLON, // ;
     ARROW,    // => (named parameter)
 
     // Literals & identifiers
-    STRING,     // 'value' or "value"
-    NUMBER,     // 123, 3.14
-    IDENT,      // column_name, table_name
-    WHITESPACE, // spaces, tabs, newlines
-    COMMENT,    // -- comment
+    STRING,       // 'value', E'escaped\n', $$dollar quoted$$
+    NUMBER,       // 123, 3.14
+    IDENT,        // column_name, table_name
+    QUOTED_IDENT, // "Column Name" or `column name` (Spark)
+    WHITESPACE,   // spaces, tabs, newlines
+    COMMENT,      // -- comment or /* block comment */
 
     // Composite nodes
     FILE,            // Root node
@@ -53,6 +100,44 @@ pub enum SyntaxKind {
     FUNCTION_CALL,   // COUNT(*), SUM(col), ref('model')
     ARG_LIST,        // (arg1, arg2)
     NAMED_PARAM,     // param_name => value
+    JOIN_CLAUSE,     // [INNER|LEFT|RIGHT|FULL|CROSS] JOIN table [ON ...|USING (...)]
+    JOIN_CONDITION,  // ON expression or USING (col1, col2)
+    COLUMN_LIST,     // (col1, col2)
+    WITH_CLAUSE,     // WITH [RECURSIVE] cte1 AS (...), cte2 AS (...)
+    CTE,             // name [(col1, col2)] AS (query)
+    COMPOUND_SELECT, // SELECT ... {UNION|INTERSECT|EXCEPT} [ALL] SELECT ...
+    HAVING_CLAUSE,   // HAVING expression
+    QUALIFY_CLAUSE,  // QUALIFY expression
+    ORDER_BY_CLAUSE, // ORDER BY item1, item2
+    ORDER_BY_ITEM,   // expression [ASC|DESC] [NULLS FIRST|LAST]
+    LIMIT_CLAUSE,    // LIMIT expression
+    OFFSET_CLAUSE,   // OFFSET expression
+    SUBQUERY,        // ( query )
+    IN_EXPR,         // expr [NOT] IN (values | query)
+    EXISTS_EXPR,     // [NOT] EXISTS ( query )
+    COLUMN_REF,      // column or table.column
+    WILDCARD_EXCLUDE, // * EXCLUDE (col1, col2)
+    WILDCARD_REPLACE, // * REPLACE (expr AS col, ...)
+    LITERAL,         // 'text', 42, TRUE, NULL
+    TYPED_LITERAL,   // DATE '2024-01-01', INTERVAL 30 MINUTE
+    PAREN_EXPR,      // ( expression )
+    UNARY_EXPR,      // NOT expr, -expr
+    CASE_EXPR,       // CASE [operand] WHEN ... THEN ... [ELSE ...] END
+    CASE_WHEN,       // WHEN condition THEN result
+    CASE_ELSE,       // ELSE result
+    CAST_EXPR,       // CAST(expr AS type) or expr::type
+    TYPE_NAME,       // INTEGER, DECIMAL(10, 2)
+    IS_EXPR,         // expr IS [NOT] NULL / TRUE / FALSE / DISTINCT FROM expr
+    BETWEEN_EXPR,    // expr [NOT] BETWEEN low AND high
+    LIKE_EXPR,       // expr [NOT] LIKE/ILIKE pattern [ESCAPE char]
+    OVER_CLAUSE,     // OVER (window spec) or OVER window_name
+    WINDOW_SPEC,     // ([base] [PARTITION BY ...] [ORDER BY ...] [frame])
+    PARTITION_BY_CLAUSE, // PARTITION BY expr1, expr2
+    WINDOW_FRAME,    // {ROWS|RANGE|GROUPS} [BETWEEN] bound [AND bound]
+    FRAME_BOUND,     // UNBOUNDED PRECEDING, n PRECEDING, CURRENT ROW, ...
+    WINDOW_CLAUSE,   // WINDOW w AS (...), ...
+    WINDOW_DEF,      // w AS (window spec)
+    STATEMENT,       // One statement of a multi-statement file, up to its `;`
 
     // Error handling
     ERROR, // Invalid syntax
@@ -67,7 +152,77 @@ impl SyntaxKind {
     pub fn is_keyword(&self) -> bool {
         matches!(
             self,
-            SELECT_KW | FROM_KW | WHERE_KW | GROUP_KW | BY_KW | AS_KW | AND_KW | OR_KW | NOT_KW
+            SELECT_KW
+                | FROM_KW
+                | WHERE_KW
+                | GROUP_KW
+                | BY_KW
+                | AS_KW
+                | AND_KW
+                | OR_KW
+                | NOT_KW
+                | JOIN_KW
+                | INNER_KW
+                | LEFT_KW
+                | RIGHT_KW
+                | FULL_KW
+                | OUTER_KW
+                | CROSS_KW
+                | ON_KW
+                | USING_KW
+                | WITH_KW
+                | RECURSIVE_KW
+                | UNION_KW
+                | ALL_KW
+                | HAVING_KW
+                | QUALIFY_KW
+                | ORDER_KW
+                | ASC_KW
+                | DESC_KW
+                | NULLS_KW
+                | LIMIT_KW
+                | OFFSET_KW
+                | DISTINCT_KW
+                | INTERSECT_KW
+                | EXCEPT_KW
+                | IN_KW
+                | EXISTS_KW
+                | CASE_KW
+                | WHEN_KW
+                | THEN_KW
+                | ELSE_KW
+                | END_KW
+                | CAST_KW
+                | IS_KW
+                | NULL_KW
+                | TRUE_KW
+                | FALSE_KW
+                | BETWEEN_KW
+                | LIKE_KW
+                | ILIKE_KW
+                | OVER_KW
+                | PARTITION_KW
+                | WINDOW_KW
+        )
+    }
+
+    /// Keywords that are still accepted as names: columns, tables, aliases
+    /// after AS, and anything after `.`
+    pub fn is_non_reserved_keyword(&self) -> bool {
+        matches!(
+            self,
+            INNER_KW
+                | LEFT_KW
+                | RIGHT_KW
+                | FULL_KW
+                | OUTER_KW
+                | CROSS_KW
+                | RECURSIVE_KW
+                | NULLS_KW
+                | DISTINCT_KW
+                | OVER_KW
+                | PARTITION_KW
+                | WINDOW_KW
         )
     }
 
@@ -75,8 +230,35 @@ impl SyntaxKind {
         matches!(self, WHITESPACE | COMMENT)
     }
 
+    pub fn is_ident(&self) -> bool {
+        matches!(self, IDENT | QUOTED_IDENT)
+    }
+
     pub fn is_literal(&self) -> bool {
-        matches!(self, STRING | NUMBER)
+        matches!(self, STRING | NUMBER | TRUE_KW | FALSE_KW | NULL_KW)
+    }
+
+    /// Node kinds that represent an expression (or a complete expression wrapper)
+    pub fn is_expression(&self) -> bool {
+        matches!(
+            self,
+            EXPRESSION
+                | BINARY_EXPR
+                | FUNCTION_CALL
+                | SUBQUERY
+                | IN_EXPR
+                | EXISTS_EXPR
+                | COLUMN_REF
+                | LITERAL
+                | TYPED_LITERAL
+                | PAREN_EXPR
+                | UNARY_EXPR
+                | CASE_EXPR
+                | CAST_EXPR
+                | IS_EXPR
+                | BETWEEN_EXPR
+                | LIKE_EXPR
+        )
     }
 }
 
@@ -3,4 +3,4 @@ SELECT
     user_id,
     user_name,
     signup_date
-FROM raw.users
+FROM smelt.source('raw', 'users')